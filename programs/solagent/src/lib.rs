@@ -1,8 +1,46 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::solana_program::sysvar::{instructions as instructions_sysvar, slot_hashes};
 use anchor_lang::system_program;
+use anchor_lang::Discriminator;
 
 declare_id!("FDBu2qdatZd7J1TiDTjCbzNtjqwuJi25UPY7qUdBBFNQ");
 
+/// Denominator for fields expressed in basis points
+pub const BPS_DENOMINATOR: u16 = 10_000;
+pub const MAX_PRICE_TIERS: usize = 5;
+pub const MAX_REPUTATION_DISCOUNTS: usize = 4;
+pub const MAX_SLA_PENALTIES: usize = 4;
+pub const MAX_INDEX_KEY_LEN: usize = 32;
+pub const MAX_SERVICE_CATEGORIES: usize = 3;
+pub const MAX_BUNDLE_COMPONENTS: usize = 5;
+pub const MAX_DISPUTE_EVIDENCE: usize = 8;
+pub const MAX_POOL_ARBITERS: usize = 16;
+pub const MAX_ARBITER_PANEL: usize = 5;
+/// Slots between opening a pooled dispute and the slot whose hash draws its panel
+pub const PANEL_DRAW_DELAY_SLOTS: u64 = 4;
+/// Time the protocol authority has to rule a dispute with no arbiter pool before `expire_dispute`
+/// can refund the payer
+pub const PROTOCOL_DISPUTE_WINDOW_SECONDS: i64 = 14 * 24 * 60 * 60;
+pub const MAX_MILESTONES: usize = 5;
+pub const MAX_PAYMENT_CONDITIONS: usize = 5;
+/// Widest staleness window an oracle condition may set; with `at` set, any update published in
+/// `[at, at + max_age_seconds]` can decide it, so the window bounds which price a cranker can pick
+pub const MAX_ORACLE_PRICE_AGE_SECONDS: u32 = 60;
+/// Pyth pull-oracle receiver program (rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ), owner of `PriceUpdateV2` accounts
+pub const PYTH_RECEIVER_ID: Pubkey = Pubkey::new_from_array([
+    12, 183, 250, 187, 82, 247, 166, 72, 187, 91, 49, 125, 154, 1, 139, 144, 87, 203, 2, 71,
+    116, 250, 254, 1, 230, 196, 223, 152, 204, 56, 88, 129,
+]);
+/// Anchor discriminator of Pyth's `PriceUpdateV2` account
+pub const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+/// Prefix of the message a payer signs off-chain to acknowledge delivery of a payment
+pub const DELIVERY_RECEIPT_PREFIX: &[u8] = b"solagent:delivery-receipt:";
+
 /// SolAgent Economy Protocol
 /// Native Solana infrastructure for AI agent identity, reputation,
 /// payments, and marketplace. Makes x402 + ERC-8004 obsolete.
@@ -21,8 +59,9 @@ pub mod solagent {
     // ============================================================
 
     /// Register a new AI agent on-chain with identity metadata
-    pub fn register_agent(
-        ctx: Context<RegisterAgent>,
+    /// The agent's capability index entry accounts are passed as remaining accounts
+    pub fn register_agent<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RegisterAgent<'info>>,
         name: String,
         description: String,
         capabilities: Vec<String>,
@@ -31,6 +70,7 @@ pub mod solagent {
         require!(name.len() <= 32, SolAgentError::NameTooLong);
         require!(description.len() <= 256, SolAgentError::DescriptionTooLong);
         require!(capabilities.len() <= 10, SolAgentError::TooManyCapabilities);
+        let keys = index_keys(&capabilities)?;
 
         let agent = &mut ctx.accounts.agent;
         agent.authority = ctx.accounts.authority.key();
@@ -42,8 +82,15 @@ pub mod solagent {
         agent.total_staked = 0;
         agent.total_earned = 0;
         agent.total_spent = 0;
+        agent.referral_earned = 0;
+        agent.referral_fees_paid = 0;
         agent.services_completed = 0;
         agent.services_requested = 0;
+        agent.sla_orders = 0;
+        agent.sla_met = 0;
+        agent.disputes = 0;
+        agent.disputes_won = 0;
+        agent.disputes_lost = 0;
         agent.feedbacks_received = 0;
         agent.registered_at = Clock::get()?.unix_timestamp;
         agent.is_active = true;
@@ -56,6 +103,71 @@ pub mod solagent {
             timestamp: agent.registered_at,
         });
 
+        reindex(
+            ctx.remaining_accounts,
+            IndexKind::Capability,
+            ctx.accounts.agent.key(),
+            &[],
+            &keys,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+        )
+    }
+
+    /// Replace an agent's capabilities, moving it between capability indexes
+    /// Remaining accounts: index entries for dropped capabilities, then for new ones
+    pub fn update_capabilities<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateCapabilities<'info>>,
+        capabilities: Vec<String>,
+    ) -> Result<()> {
+        require!(capabilities.len() <= 10, SolAgentError::TooManyCapabilities);
+        let new_keys = index_keys(&capabilities)?;
+        let old_keys = index_keys(&ctx.accounts.agent.capabilities)?;
+
+        let agent = &mut ctx.accounts.agent;
+        agent.capabilities = capabilities;
+
+        emit!(AgentCapabilitiesUpdated {
+            agent: agent.key(),
+            capabilities: agent.capabilities.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        reindex(
+            ctx.remaining_accounts,
+            IndexKind::Capability,
+            ctx.accounts.agent.key(),
+            &old_keys,
+            &new_keys,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+        )
+    }
+
+    /// Grow an agent registered under the launch layout to the current one (authority only)
+    /// The appended referral, SLA and dispute counters start at zero
+    pub fn migrate_agent(ctx: Context<MigrateAgent>) -> Result<()> {
+        let agent_info = &ctx.accounts.agent;
+        let space = 8 + Agent::INIT_SPACE;
+        require!(agent_info.data_len() < space, SolAgentError::AgentUpToDate);
+        require!(
+            agent_info.try_borrow_data()?.get(..8) == Some(&Agent::DISCRIMINATOR[..]),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+
+        grow_account(
+            agent_info,
+            space,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(AgentMigrated {
+            agent: agent_info.key(),
+            space: space as u64,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
         Ok(())
     }
 
@@ -101,6 +213,7 @@ pub mod solagent {
     }
 
     /// Submit feedback for an agent after service completion
+    #[allow(clippy::manual_range_contains)]
     pub fn submit_feedback(
         ctx: Context<SubmitFeedback>,
         rating: u8,
@@ -141,13 +254,83 @@ pub mod solagent {
         Ok(())
     }
 
+    /// Attest an agent on behalf of the signing issuer (e.g. an organisation's key)
+    /// Services can require an attestation or list the issuer as an organisation
+    pub fn issue_attestation(ctx: Context<IssueAttestation>, expires_at: i64) -> Result<()> {
+        let attestation = &mut ctx.accounts.attestation;
+        attestation.issuer = ctx.accounts.issuer.key();
+        attestation.subject = ctx.accounts.subject_agent.key();
+        attestation.issued_at = Clock::get()?.unix_timestamp;
+        attestation.expires_at = expires_at;
+        attestation.bump = ctx.bumps.attestation;
+
+        emit!(AttestationIssued {
+            attestation: attestation.key(),
+            issuer: attestation.issuer,
+            subject: attestation.subject,
+            expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Revoke an attestation and refund its rent to the issuer
+    pub fn revoke_attestation(ctx: Context<RevokeAttestation>) -> Result<()> {
+        let attestation = &ctx.accounts.attestation;
+
+        emit!(AttestationRevoked {
+            attestation: attestation.key(),
+            issuer: attestation.issuer,
+            subject: attestation.subject,
+        });
+
+        Ok(())
+    }
+
+    /// Authorise a session key to sign delivery receipts for the agent until `expires_at` (0 = never)
+    pub fn create_session_key(
+        ctx: Context<CreateSessionKey>,
+        key: Pubkey,
+        expires_at: i64,
+    ) -> Result<()> {
+        let session_key = &mut ctx.accounts.session_key;
+        session_key.agent = ctx.accounts.agent.key();
+        session_key.key = key;
+        session_key.created_at = Clock::get()?.unix_timestamp;
+        session_key.expires_at = expires_at;
+        session_key.bump = ctx.bumps.session_key;
+
+        emit!(SessionKeyCreated {
+            session_key: session_key.key(),
+            agent: session_key.agent,
+            key,
+            expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Revoke a session key and refund its rent to the agent authority
+    pub fn revoke_session_key(ctx: Context<RevokeSessionKey>) -> Result<()> {
+        let session_key = &ctx.accounts.session_key;
+
+        emit!(SessionKeyRevoked {
+            session_key: session_key.key(),
+            agent: session_key.agent,
+            key: session_key.key,
+        });
+
+        Ok(())
+    }
+
     // ============================================================
     // AGENT MARKETPLACE & SERVICE REGISTRY
     // ============================================================
 
     /// Register a service on the agent marketplace
-    pub fn create_service(
-        ctx: Context<CreateService>,
+    /// The service's tag index entry accounts are passed as remaining accounts
+    pub fn create_service<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateService<'info>>,
         service_id: String,
         title: String,
         description: String,
@@ -157,6 +340,7 @@ pub mod solagent {
     ) -> Result<()> {
         require!(title.len() <= 64, SolAgentError::TitleTooLong);
         require!(tags.len() <= 5, SolAgentError::TooManyTags);
+        let keys = index_keys(&tags)?;
 
         let service = &mut ctx.accounts.service;
         service.provider = ctx.accounts.agent.key();
@@ -166,9 +350,22 @@ pub mod solagent {
         service.description = description;
         service.price_lamports = price_lamports;
         service.price_model = price_model;
+        service.pricing = None;
+        service.referral_bps = 0;
+        service.access = None;
+        service.sla = None;
+        service.version = 1;
         service.tags = tags;
+        service.categories = Vec::new();
+        service.arbiter_pool = None;
+        service.timeout_policy = TimeoutPolicy::RefundOnTimeout;
         service.total_orders = 0;
+        service.open_orders = 0;
+        service.max_concurrent_orders = 0;
         service.total_revenue = 0;
+        service.sla_orders = 0;
+        service.sla_met = 0;
+        service.unclosed_payments = 0;
         service.avg_rating = 0;
         service.is_active = true;
         service.created_at = Clock::get()?.unix_timestamp;
@@ -182,6 +379,412 @@ pub mod solagent {
             price_model: service.price_model.clone(),
         });
 
+        reindex(
+            ctx.remaining_accounts,
+            IndexKind::Tag,
+            ctx.accounts.service.key(),
+            &[],
+            &keys,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+        )
+    }
+
+    /// Update a service listing's terms
+    /// Price changes only apply to new orders; escrowed payments keep their amount
+    /// When tags change, remaining accounts are the index entries for dropped tags, then for new ones
+    pub fn update_service<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateServiceListing<'info>>,
+        title: Option<String>,
+        description: Option<String>,
+        price_lamports: Option<u64>,
+        price_model: Option<PriceModel>,
+        tags: Option<Vec<String>>,
+    ) -> Result<()> {
+        let service = &mut ctx.accounts.service;
+        let old_terms = service.terms();
+
+        if let Some(title) = title {
+            require!(title.len() <= 64, SolAgentError::TitleTooLong);
+            service.title = title;
+        }
+        if let Some(description) = description {
+            require!(description.len() <= 256, SolAgentError::DescriptionTooLong);
+            service.description = description;
+        }
+        if let Some(price_lamports) = price_lamports {
+            service.price_lamports = price_lamports;
+        }
+        if let Some(price_model) = price_model {
+            service.price_model = price_model;
+        }
+        let mut index_change = None;
+        if let Some(tags) = tags {
+            require!(tags.len() <= 5, SolAgentError::TooManyTags);
+            index_change = Some((index_keys(&service.tags)?, index_keys(&tags)?));
+            service.tags = tags;
+        }
+
+        record_terms_change(service, old_terms)?;
+
+        match index_change {
+            Some((old_keys, new_keys)) => reindex(
+                ctx.remaining_accounts,
+                IndexKind::Tag,
+                ctx.accounts.service.key(),
+                &old_keys,
+                &new_keys,
+                &ctx.accounts.authority,
+                &ctx.accounts.system_program,
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Set or clear a service's volume pricing schedule
+    /// Like price changes, the schedule only applies to new orders and usage escrows
+    pub fn set_pricing_schedule(
+        ctx: Context<UpdateService>,
+        pricing: Option<PricingSchedule>,
+    ) -> Result<()> {
+        if let Some(schedule) = &pricing {
+            schedule.validate()?;
+        }
+
+        let service = &mut ctx.accounts.service;
+        let old_terms = service.terms();
+        service.pricing = pricing;
+        record_terms_change(service, old_terms)?;
+
+        emit!(PricingScheduleUpdated {
+            service: service.key(),
+            pricing: service.pricing.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Set the share of each order paid to the referring agent, in basis points
+    /// Locked into each payment when it is created
+    pub fn set_referral_fee(ctx: Context<UpdateService>, referral_bps: u16) -> Result<()> {
+        require!(
+            referral_bps <= BPS_DENOMINATOR,
+            SolAgentError::InvalidReferralFee
+        );
+
+        let service = &mut ctx.accounts.service;
+        service.referral_bps = referral_bps;
+
+        emit!(ReferralFeeUpdated {
+            service: service.key(),
+            referral_bps,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Set or clear the buyer access rules, enforced wherever a buyer commits funds to the service
+    /// (`pay_for_service`, `open_usage_escrow`, `subscribe`, `place_bid`, `accept_job_bid`)
+    pub fn set_access_rules(
+        ctx: Context<UpdateService>,
+        access: Option<AccessRules>,
+    ) -> Result<()> {
+        if let Some(rules) = &access {
+            require!(rules.list.len() <= 10, SolAgentError::TooManyAccessEntries);
+        }
+
+        let service = &mut ctx.accounts.service;
+        service.access = access;
+
+        emit!(AccessRulesUpdated {
+            service: service.key(),
+            access: service.access.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Limit how many escrowed orders a service can have open at once (0 = unlimited)
+    /// New orders fail fast with `ServiceAtCapacity` instead of waiting out a timeout
+    pub fn set_capacity(ctx: Context<UpdateService>, max_concurrent_orders: u64) -> Result<()> {
+        let service = &mut ctx.accounts.service;
+        service.max_concurrent_orders = max_concurrent_orders;
+
+        emit!(ServiceCapacityUpdated {
+            service: service.key(),
+            max_concurrent_orders,
+            open_orders: service.open_orders,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Choose the arbiter pool that disputes on this service are assigned from (None = protocol authority)
+    pub fn set_arbiter_pool(ctx: Context<SetArbiterPool>) -> Result<()> {
+        let service = &mut ctx.accounts.service;
+        service.arbiter_pool = ctx.accounts.arbiter_pool.as_ref().map(|pool| pool.key());
+
+        emit!(ServiceArbiterPoolSet {
+            service: service.key(),
+            arbiter_pool: service.arbiter_pool,
+        });
+
+        Ok(())
+    }
+
+    /// Place a service in up to `MAX_SERVICE_CATEGORIES` curated categories
+    /// The active `Category` accounts are passed as remaining accounts, in `category_ids` order
+    pub fn set_service_categories<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateService<'info>>,
+        category_ids: Vec<u16>,
+    ) -> Result<()> {
+        require!(
+            category_ids.len() <= MAX_SERVICE_CATEGORIES,
+            SolAgentError::TooManyCategories
+        );
+        require!(
+            ctx.remaining_accounts.len() == category_ids.len(),
+            SolAgentError::InvalidCategory
+        );
+        for (i, (info, id)) in ctx.remaining_accounts.iter().zip(&category_ids).enumerate() {
+            require!(
+                !category_ids[..i].contains(id),
+                SolAgentError::InvalidCategory
+            );
+            let category = Account::<Category>::try_from(info)?;
+            require!(category.id == *id, SolAgentError::InvalidCategory);
+            require!(category.is_active, SolAgentError::CategoryInactive);
+        }
+
+        let service = &mut ctx.accounts.service;
+        service.categories = category_ids;
+
+        emit!(ServiceCategoriesUpdated {
+            service: service.key(),
+            categories: service.categories.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Set or clear a service's SLA terms
+    /// Terms are copied into each payment, so changes only affect new orders
+    pub fn set_sla(ctx: Context<UpdateService>, sla: Option<SlaTerms>) -> Result<()> {
+        if let Some(terms) = &sla {
+            terms.validate()?;
+        }
+
+        let service = &mut ctx.accounts.service;
+        let old_terms = service.terms();
+        service.sla = sla;
+        record_terms_change(service, old_terms)?;
+
+        emit!(SlaUpdated {
+            service: service.key(),
+            sla: service.sla.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Choose what happens to orders the payer never releases: refund on timeout, or release
+    /// to the provider once a delivered order's challenge window passes undisputed
+    pub fn set_timeout_policy(ctx: Context<UpdateService>, policy: TimeoutPolicy) -> Result<()> {
+        if let TimeoutPolicy::ReleaseAfterDelivery { challenge_seconds } = policy {
+            require!(challenge_seconds > 0, SolAgentError::InvalidTimeoutPolicy);
+        }
+
+        let service = &mut ctx.accounts.service;
+        let old_terms = service.terms();
+        service.timeout_policy = policy;
+        record_terms_change(service, old_terms)?;
+
+        emit!(TimeoutPolicyUpdated {
+            service: service.key(),
+            timeout_policy: service.timeout_policy.clone(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Grow a service created under an older, shorter layout to the current one (authority only)
+    /// Fields added since launch are appended to `Service`, so the old data is a prefix and the new
+    /// space decodes as defaults: no pricing schedule, access rules, SLA or arbiter pool, and
+    /// refund on timeout. Every older order still has its payment account, so all count as unclosed
+    pub fn migrate_service(ctx: Context<MigrateService>) -> Result<()> {
+        let service_info = &ctx.accounts.service;
+        let space = 8 + Service::INIT_SPACE;
+        require!(service_info.data_len() < space, SolAgentError::ServiceUpToDate);
+        {
+            let data = service_info.try_borrow_data()?;
+            require!(
+                data.get(..8) == Some(&Service::DISCRIMINATOR[..]),
+                SolAgentError::ServiceMismatch
+            );
+            // `authority` follows the discriminator and `provider` in every layout
+            require!(
+                data.get(40..72) == Some(ctx.accounts.authority.key().as_ref()),
+                SolAgentError::Unauthorized
+            );
+        }
+
+        grow_account(
+            service_info,
+            space,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        let mut service = Service::try_deserialize(&mut &service_info.try_borrow_data()?[..])?;
+        service.version = 1;
+        service.unclosed_payments = service.total_orders;
+        service.try_serialize(&mut &mut service_info.try_borrow_mut_data()?[..])?;
+
+        emit!(ServiceMigrated {
+            service: service_info.key(),
+            space: space as u64,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Pause a service so it stops accepting new orders
+    pub fn pause_service(ctx: Context<SetServiceStatus>) -> Result<()> {
+        let service = &mut ctx.accounts.service;
+        require!(service.is_active, SolAgentError::ServiceInactive);
+        service.is_active = false;
+
+        emit!(ServiceStatusChanged {
+            service: service.key(),
+            is_active: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Resume a paused service
+    pub fn resume_service(ctx: Context<SetServiceStatus>) -> Result<()> {
+        let service = &mut ctx.accounts.service;
+        require!(!service.is_active, SolAgentError::ServiceAlreadyActive);
+        service.is_active = true;
+
+        emit!(ServiceStatusChanged {
+            service: service.key(),
+            is_active: true,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Delist a service and refund its rent to the authority
+    /// Only allowed once every order has finished and its payment account was closed, so that a
+    /// service re-created under the same id can never reuse an existing payment address
+    /// The service's tag index entry accounts are passed as remaining accounts
+    pub fn close_service<'info>(ctx: Context<'_, '_, 'info, 'info, CloseService<'info>>) -> Result<()> {
+        let service = &ctx.accounts.service;
+        require!(service.open_orders == 0, SolAgentError::ServiceHasOpenOrders);
+        require!(
+            service.unclosed_payments == 0,
+            SolAgentError::ServiceHasPayments
+        );
+
+        let keys = index_keys(&service.tags)?;
+        require!(
+            ctx.remaining_accounts.len() == keys.len(),
+            SolAgentError::IndexAccountMismatch
+        );
+        for (index, key) in ctx.remaining_accounts.iter().zip(&keys) {
+            remove_from_index(
+                index,
+                IndexKind::Tag,
+                key,
+                service.key(),
+                &ctx.accounts.authority,
+            )?;
+        }
+
+        emit!(ServiceClosed {
+            service: service.key(),
+            provider: service.provider,
+            total_orders: service.total_orders,
+            total_revenue: service.total_revenue,
+        });
+
+        Ok(())
+    }
+
+    // ============================================================
+    // PROMOTIONS (discount codes)
+    // ============================================================
+
+    /// Create a discount code for a service, redeemable in `pay_for_service`
+    /// `max_redemptions` and `expires_at` of 0 mean unlimited / never expires
+    /// The discount must leave something to pay: under 100%, or under the unit price
+    pub fn create_coupon(
+        ctx: Context<CreateCoupon>,
+        code: String,
+        discount: Discount,
+        max_redemptions: u64,
+        expires_at: i64,
+        allowed_buyers: Vec<Pubkey>,
+    ) -> Result<()> {
+        require!(code.len() <= 32, SolAgentError::CouponCodeTooLong);
+        require!(allowed_buyers.len() <= 10, SolAgentError::TooManyAllowedBuyers);
+        match discount {
+            Discount::Percent { bps } => {
+                require!(bps > 0 && bps < BPS_DENOMINATOR, SolAgentError::InvalidDiscount)
+            }
+            Discount::Fixed { lamports } => require!(
+                lamports > 0 && lamports < ctx.accounts.service.price_lamports,
+                SolAgentError::InvalidDiscount
+            ),
+        }
+
+        let coupon = &mut ctx.accounts.coupon;
+        coupon.service = ctx.accounts.service.key();
+        coupon.authority = ctx.accounts.authority.key();
+        coupon.code = code;
+        coupon.discount = discount;
+        coupon.max_redemptions = max_redemptions;
+        coupon.redemptions = 0;
+        coupon.expires_at = expires_at;
+        coupon.allowed_buyers = allowed_buyers;
+        coupon.total_discounted = 0;
+        coupon.created_at = Clock::get()?.unix_timestamp;
+        coupon.bump = ctx.bumps.coupon;
+
+        emit!(CouponCreated {
+            coupon: coupon.key(),
+            service: coupon.service,
+            code: coupon.code.clone(),
+            discount: coupon.discount.clone(),
+            max_redemptions,
+            expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw a discount code and refund its rent to the service authority
+    pub fn close_coupon(ctx: Context<CloseCoupon>) -> Result<()> {
+        let coupon = &ctx.accounts.coupon;
+
+        emit!(CouponClosed {
+            coupon: coupon.key(),
+            service: coupon.service,
+            redemptions: coupon.redemptions,
+        });
+
         Ok(())
     }
 
@@ -191,15 +794,84 @@ pub mod solagent {
 
     /// Pay for a service with automatic escrow
     /// Funds are locked until service is delivered and confirmed
+    /// `amount` must equal the service quote for `quantity` units, less any coupon discount
     pub fn pay_for_service(
         ctx: Context<PayForService>,
         amount: u64,
+        quantity: u64,
         intent: String,
-        conditions: Vec<String>,
+        conditions: Vec<PaymentCondition>,
         timeout_seconds: i64,
     ) -> Result<()> {
         require!(amount > 0, SolAgentError::ZeroAmount);
         require!(intent.len() <= 256, SolAgentError::IntentTooLong);
+        require!(
+            conditions.len() <= MAX_PAYMENT_CONDITIONS,
+            SolAgentError::TooManyConditions
+        );
+        // A deadline only bounds the other conditions; on its own it would release at once
+        require!(
+            conditions.is_empty()
+                || conditions
+                    .iter()
+                    .any(|condition| !matches!(condition, PaymentCondition::Deadline { .. })),
+            SolAgentError::DeadlineOnlyConditions
+        );
+        require!(
+            conditions.iter().all(|condition| match condition {
+                PaymentCondition::OraclePrice { max_age_seconds, .. } => {
+                    (1..=MAX_ORACLE_PRICE_AGE_SECONDS).contains(max_age_seconds)
+                }
+                _ => true,
+            }),
+            SolAgentError::InvalidOracleCondition
+        );
+        require!(ctx.accounts.service.is_active, SolAgentError::ServiceInactive);
+        require!(
+            ctx.accounts.service.has_capacity(),
+            SolAgentError::ServiceAtCapacity
+        );
+        // Releasing after delivery would pay the provider without the conditions being checked
+        require!(
+            conditions.is_empty()
+                || ctx.accounts.service.timeout_policy == TimeoutPolicy::RefundOnTimeout,
+            SolAgentError::ConditionsWithAutoRelease
+        );
+
+        let quoted = ctx
+            .accounts
+            .service
+            .quote(quantity, ctx.accounts.payer_agent.reputation_score)?;
+        let now = Clock::get()?.unix_timestamp;
+        let payer_key = ctx.accounts.payer_agent.key();
+
+        ctx.accounts.service.check_access(
+            &ctx.accounts.payer_agent,
+            ctx.accounts.buyer_attestation.as_deref(),
+            now,
+        )?;
+
+        let referrer = ctx.accounts.referrer_agent.as_ref().map(|r| r.key());
+        if let Some(referrer) = referrer {
+            require!(
+                referrer != payer_key && referrer != ctx.accounts.service.provider,
+                SolAgentError::InvalidReferrer
+            );
+        }
+
+        let mut coupon_discount = 0;
+        if let Some(coupon) = &mut ctx.accounts.coupon {
+            coupon.check_redeemable(&payer_key, now)?;
+            coupon_discount = coupon.discount_on(quoted);
+            // The service price may have dropped below a fixed discount since
+            require!(coupon_discount < quoted, SolAgentError::InvalidDiscount);
+            coupon.redemptions += 1;
+            coupon.total_discounted += coupon_discount;
+        }
+        require!(
+            amount == quoted - coupon_discount,
+            SolAgentError::AmountMismatch
+        );
 
         // Transfer SOL to escrow PDA
         system_program::transfer(
@@ -218,15 +890,42 @@ pub mod solagent {
         payment.receiver = ctx.accounts.receiver_agent.key();
         payment.service = ctx.accounts.service.key();
         payment.amount = amount;
+        payment.quantity = quantity;
         payment.intent = intent;
         payment.conditions = conditions;
+        payment.referrer = referrer;
+        payment.referral_bps = if referrer.is_some() {
+            ctx.accounts.service.referral_bps
+        } else {
+            0
+        };
+        payment.sla = ctx.accounts.service.sla.clone();
+        payment.timeout_policy = ctx.accounts.service.timeout_policy.clone();
+        payment.service_version = ctx.accounts.service.version;
+        payment.service_price = ctx.accounts.service.price_lamports;
+        payment.acknowledged_at = 0;
+        payment.delivered_at = 0;
         payment.status = PaymentStatus::Escrowed;
-        payment.created_at = Clock::get()?.unix_timestamp;
+        payment.created_at = now;
         payment.timeout_at = payment.created_at + timeout_seconds;
         payment.completed_at = 0;
+        payment.milestones = Vec::new();
+        payment.released_amount = 0;
         payment.bump = ctx.bumps.payment;
         payment.escrow_bump = ctx.bumps.escrow;
 
+        if let Some(coupon) = &ctx.accounts.coupon {
+            emit!(CouponRedeemed {
+                coupon: coupon.key(),
+                service: coupon.service,
+                payment: payment.key(),
+                buyer: payer_key,
+                discount: coupon_discount,
+                amount,
+                redemptions: coupon.redemptions,
+            });
+        }
+
         // Update agent stats
         let payer = &mut ctx.accounts.payer_agent;
         payer.services_requested += 1;
@@ -234,6 +933,8 @@ pub mod solagent {
 
         let service = &mut ctx.accounts.service;
         service.total_orders += 1;
+        service.open_orders += 1;
+        service.unclosed_payments += 1;
 
         emit!(PaymentCreated {
             payment: payment.key(),
@@ -246,46 +947,79 @@ pub mod solagent {
         Ok(())
     }
 
-    /// Release escrowed payment after service delivery
-    /// Called by the payer agent to confirm satisfaction
-    pub fn release_payment(ctx: Context<ReleasePayment>) -> Result<()> {
+    /// Provider acknowledges an escrowed order, stopping the SLA acknowledgement clock
+    pub fn acknowledge_payment(ctx: Context<ProviderPaymentAction>) -> Result<()> {
         let payment = &mut ctx.accounts.payment;
         require!(
             payment.status == PaymentStatus::Escrowed,
             SolAgentError::PaymentNotEscrowed
         );
+        require!(payment.acknowledged_at == 0, SolAgentError::AlreadyAcknowledged);
 
-        // Transfer from escrow to receiver
-        let amount = payment.amount;
-        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= amount;
-        **ctx
-            .accounts
-            .receiver_authority
-            .to_account_info()
-            .try_borrow_mut_lamports()? += amount;
-
-        payment.status = PaymentStatus::Released;
-        payment.completed_at = Clock::get()?.unix_timestamp;
+        payment.acknowledged_at = Clock::get()?.unix_timestamp;
 
-        // Update receiver agent stats
-        let receiver = &mut ctx.accounts.receiver_agent;
-        receiver.services_completed += 1;
-        receiver.total_earned += amount;
+        emit!(PaymentAcknowledged {
+            payment: payment.key(),
+            receiver: payment.receiver,
+            timestamp: payment.acknowledged_at,
+        });
 
-        // Update service revenue
-        let service = &mut ctx.accounts.service;
-        service.total_revenue += amount;
+        Ok(())
+    }
+
+    /// Provider marks an escrowed order as delivered, stopping the SLA delivery clock
+    /// Under `ReleaseAfterDelivery` this also starts the payer's challenge window
+    pub fn mark_delivered(ctx: Context<ProviderPaymentAction>) -> Result<()> {
+        let payment = &mut ctx.accounts.payment;
+        require!(
+            payment.status == PaymentStatus::Escrowed,
+            SolAgentError::PaymentNotEscrowed
+        );
+        require!(payment.delivered_at == 0, SolAgentError::AlreadyDelivered);
+
+        // Past the timeout the payer can already refund; a late delivery mustn't take that away
+        let now = Clock::get()?.unix_timestamp;
+        require!(now <= payment.timeout_at, SolAgentError::DeliveryAfterTimeout);
+        if payment.acknowledged_at == 0 {
+            payment.acknowledged_at = now;
+        }
+        payment.delivered_at = now;
 
-        emit!(PaymentReleased {
+        emit!(PaymentDelivered {
             payment: payment.key(),
             receiver: payment.receiver,
-            amount,
-            latency_ms: ((payment.completed_at - payment.created_at) * 1000) as u64,
+            timestamp: now,
+            auto_release_at: payment.auto_release_at(),
         });
 
         Ok(())
     }
 
+    /// Release escrowed payment after service delivery
+    /// Called by the payer agent to confirm satisfaction
+    pub fn release_payment(ctx: Context<ReleasePayment>) -> Result<()> {
+        require!(
+            ctx.accounts.payment.status == PaymentStatus::Escrowed,
+            SolAgentError::PaymentNotEscrowed
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let accounts = &mut *ctx.accounts;
+        // The payer's own wallet takes any SLA penalty refund
+        release_to_provider(
+            &mut accounts.payment,
+            &accounts.escrow,
+            &mut accounts.payer_agent,
+            &accounts.authority,
+            &mut accounts.receiver_agent,
+            &accounts.receiver_authority,
+            &mut accounts.service,
+            accounts.referrer_agent.as_mut(),
+            accounts.referrer_authority.as_ref(),
+            now,
+        )
+    }
+
     /// Refund escrowed payment (timeout or dispute)
     pub fn refund_payment(ctx: Context<RefundPayment>) -> Result<()> {
         let payment = &mut ctx.accounts.payment;
@@ -294,8 +1028,14 @@ pub mod solagent {
             SolAgentError::PaymentNotEscrowed
         );
 
+        // Delivered orders under `ReleaseAfterDelivery` can only be contested through a dispute
+        require!(
+            payment.auto_release_at().is_none(),
+            SolAgentError::DeliveredReleasePending
+        );
+
         let now = Clock::get()?.unix_timestamp;
-        let is_timeout = now > payment.timeout_at;
+        let is_timeout = now > payment.timeout_at || payment.milestone_overdue(now);
         let is_payer = ctx.accounts.authority.key() == ctx.accounts.payer_agent.authority;
 
         require!(
@@ -303,8 +1043,8 @@ pub mod solagent {
             SolAgentError::RefundNotAllowed
         );
 
-        // Return from escrow to payer
-        let amount = payment.amount;
+        // Return the unreleased remainder from escrow to payer
+        let amount = payment.remaining();
         **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= amount;
         **ctx
             .accounts
@@ -315,6 +1055,9 @@ pub mod solagent {
         payment.status = PaymentStatus::Refunded;
         payment.completed_at = now;
 
+        let service = &mut ctx.accounts.service;
+        service.open_orders = service.open_orders.saturating_sub(1);
+
         emit!(PaymentRefunded {
             payment: payment.key(),
             payer: payment.payer,
@@ -329,6 +1072,471 @@ pub mod solagent {
         Ok(())
     }
 
+    /// Release an escrowed payment on a delivery receipt signed off-chain by the payer (receiver only)
+    /// The receipt is checked through an Ed25519 sig-verify instruction right before this one
+    pub fn release_with_receipt(ctx: Context<ProviderRelease>) -> Result<()> {
+        let payment = &ctx.accounts.payment;
+        require!(
+            payment.status == PaymentStatus::Escrowed,
+            SolAgentError::PaymentNotEscrowed
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut signers = vec![ctx.accounts.payer_agent.authority];
+        if let Some(session_key) = &ctx.accounts.session_key {
+            require!(
+                session_key.agent == payment.payer && session_key.is_valid_at(now),
+                SolAgentError::InvalidSessionKey
+            );
+            signers.push(session_key.key);
+        }
+        let signer = verify_delivery_receipt(&ctx.accounts.instructions, &payment.key())?;
+        require!(signers.contains(&signer), SolAgentError::InvalidReceipt);
+
+        emit!(ReceiptRedeemed {
+            payment: payment.key(),
+            signer,
+        });
+        let accounts = &mut *ctx.accounts;
+        release_to_provider(
+            &mut accounts.payment,
+            &accounts.escrow,
+            &mut accounts.payer_agent,
+            &accounts.payer_authority,
+            &mut accounts.receiver_agent,
+            &accounts.receiver_authority,
+            &mut accounts.service,
+            accounts.referrer_agent.as_mut(),
+            accounts.referrer_authority.as_ref(),
+            now,
+        )
+    }
+
+    /// Release a delivered order to the provider once its challenge window passes undisputed
+    /// Only for payments under `TimeoutPolicy::ReleaseAfterDelivery` without release conditions
+    pub fn release_on_timeout(ctx: Context<ReleaseOnTimeout>) -> Result<()> {
+        let payment = &ctx.accounts.payment;
+        require!(
+            payment.status == PaymentStatus::Escrowed,
+            SolAgentError::PaymentNotEscrowed
+        );
+        require!(
+            payment.milestones.is_empty(),
+            SolAgentError::MilestonesWithAutoRelease
+        );
+        require!(
+            payment.conditions.is_empty(),
+            SolAgentError::ConditionsWithAutoRelease
+        );
+        let release_at = payment
+            .auto_release_at()
+            .ok_or(SolAgentError::NoAutoRelease)?;
+        let now = Clock::get()?.unix_timestamp;
+        require!(now > release_at, SolAgentError::ChallengeWindowOpen);
+
+        let accounts = &mut *ctx.accounts;
+        release_to_provider(
+            &mut accounts.payment,
+            &accounts.escrow,
+            &mut accounts.payer_agent,
+            &accounts.payer_authority,
+            &mut accounts.receiver_agent,
+            &accounts.receiver_authority,
+            &mut accounts.service,
+            accounts.referrer_agent.as_mut(),
+            accounts.referrer_authority.as_ref(),
+            now,
+        )
+    }
+
+    /// Split an escrowed payment into milestones with their own amounts and deadlines (payer only, once)
+    /// Amounts must add up to the payment; a missed deadline lets anyone refund the unreleased remainder
+    /// Only before the provider acknowledges, and not for SLA orders, which are held to their SLA instead,
+    /// or orders that release to the provider after delivery
+    pub fn set_milestones(ctx: Context<SetMilestones>, milestones: Vec<MilestoneSpec>) -> Result<()> {
+        let payment = &mut ctx.accounts.payment;
+        require!(
+            payment.status == PaymentStatus::Escrowed,
+            SolAgentError::PaymentNotEscrowed
+        );
+        require!(
+            payment.milestones.is_empty() && payment.released_amount == 0,
+            SolAgentError::InvalidMilestones
+        );
+        require!(payment.acknowledged_at == 0, SolAgentError::AlreadyAcknowledged);
+        require!(payment.sla.is_none(), SolAgentError::MilestonesWithSla);
+        require!(
+            payment.timeout_policy == TimeoutPolicy::RefundOnTimeout,
+            SolAgentError::MilestonesWithAutoRelease
+        );
+        require!(
+            !milestones.is_empty()
+                && milestones.len() <= MAX_MILESTONES
+                && milestones.iter().all(|m| m.amount > 0)
+                && milestones.iter().map(|m| m.amount).sum::<u64>() == payment.amount
+                && MilestoneSpec::deadlines_valid(
+                    &milestones,
+                    Clock::get()?.unix_timestamp,
+                    payment.timeout_at
+                ),
+            SolAgentError::InvalidMilestones
+        );
+
+        payment.milestones = milestones
+            .iter()
+            .map(|m| Milestone {
+                amount: m.amount,
+                deadline: m.deadline,
+                released_at: 0,
+            })
+            .collect();
+
+        emit!(MilestonesSet {
+            payment: payment.key(),
+            milestones,
+        });
+
+        Ok(())
+    }
+
+    /// Release one milestone of an escrowed payment to the receiver (payer only)
+    /// Releasing the last milestone completes the payment
+    pub fn release_milestone(ctx: Context<ReleasePayment>, index: u8) -> Result<()> {
+        let payment = &mut ctx.accounts.payment;
+        require!(
+            payment.status == PaymentStatus::Escrowed,
+            SolAgentError::PaymentNotEscrowed
+        );
+        let now = Clock::get()?.unix_timestamp;
+        let milestone = payment
+            .milestones
+            .get_mut(index as usize)
+            .ok_or(SolAgentError::InvalidMilestones)?;
+        require!(milestone.released_at == 0, SolAgentError::MilestoneReleased);
+        milestone.released_at = now;
+        let amount = milestone.amount;
+
+        let referral_share = pay_referral(
+            payment,
+            &ctx.accounts.escrow,
+            ctx.accounts.referrer_agent.as_mut(),
+            ctx.accounts.referrer_authority.as_ref(),
+            amount,
+        )?;
+        let receiver_amount = amount - referral_share;
+        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= receiver_amount;
+        **ctx
+            .accounts
+            .receiver_authority
+            .to_account_info()
+            .try_borrow_mut_lamports()? += receiver_amount;
+        payment.released_amount += amount;
+
+        let receiver = &mut ctx.accounts.receiver_agent;
+        receiver.total_earned += receiver_amount;
+        receiver.referral_fees_paid += referral_share;
+        let service = &mut ctx.accounts.service;
+        service.total_revenue += amount;
+
+        emit!(MilestoneReleased {
+            payment: payment.key(),
+            index,
+            amount,
+            remaining: payment.remaining(),
+        });
+
+        if payment.remaining() == 0 {
+            payment.status = PaymentStatus::Released;
+            payment.completed_at = now;
+            receiver.services_completed += 1;
+            service.open_orders = service.open_orders.saturating_sub(1);
+
+            emit!(PaymentReleased {
+                payment: payment.key(),
+                receiver: payment.receiver,
+                amount: payment.amount,
+                latency_ms: ((payment.completed_at - payment.created_at) * 1000) as u64,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Settle an escrowed or disputed payment at a split both parties agreed to (payer and receiver sign)
+    /// Only the unreleased remainder is split; the referral share and any bundle components' shares come
+    /// out of the receiver's part. Settling a disputed payment resolves its dispute (pass the `Dispute`);
+    /// fee shares already paid to voters stay with them. The payment and escrow are closed to the payer
+    /// Remaining accounts (bundles): each component's provider `Agent` then its authority wallet
+    pub fn settle_payment<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettlePayment<'info>>,
+        payer_amount: u64,
+    ) -> Result<()> {
+        let payment = &mut ctx.accounts.payment;
+        let now = Clock::get()?.unix_timestamp;
+        let disputed = payment.status == PaymentStatus::Disputed;
+        let remaining = match payment.status {
+            PaymentStatus::Escrowed => payment.remaining(),
+            PaymentStatus::Disputed => {
+                let dispute = ctx
+                    .accounts
+                    .dispute
+                    .as_ref()
+                    .ok_or(SolAgentError::DisputeNotOpen)?;
+                dispute.amount - dispute.fee_paid
+            }
+            _ => return err!(SolAgentError::PaymentNotEscrowed),
+        };
+        require!(payer_amount <= remaining, SolAgentError::InvalidSettlement);
+        let settled_amount = remaining - payer_amount;
+
+        let referral_share = pay_referral(
+            payment,
+            &ctx.accounts.escrow,
+            ctx.accounts.referrer_agent.as_mut(),
+            ctx.accounts.referrer_authority.as_ref(),
+            settled_amount,
+        )?;
+        let components_share = match listing_bundle_components(&ctx.accounts.listing)? {
+            Some(components) => pay_bundle_components(
+                &components,
+                ctx.remaining_accounts,
+                &ctx.accounts.escrow,
+                settled_amount,
+            )?,
+            None => 0,
+        };
+        let receiver_amount = settled_amount - referral_share - components_share;
+        let payment_key = payment.key();
+        let escrow_seeds: &[&[u8]] = &[b"escrow", payment_key.as_ref(), &[payment.escrow_bump]];
+        let escrow = &ctx.accounts.escrow;
+        let system_program = ctx.accounts.system_program.to_account_info();
+        let payer_authority = ctx.accounts.payer_authority.to_account_info();
+        transfer_from_vault(escrow, &payer_authority, &system_program, escrow_seeds, payer_amount)?;
+        transfer_from_vault(
+            escrow,
+            &ctx.accounts.receiver_authority.to_account_info(),
+            &system_program,
+            escrow_seeds,
+            receiver_amount,
+        )?;
+        // Close the escrow: anything left over belongs to the payer
+        let leftover = escrow.lamports();
+        transfer_from_vault(escrow, &payer_authority, &system_program, escrow_seeds, leftover)?;
+
+        payment.status = PaymentStatus::Settled;
+        payment.completed_at = now;
+        if let Some(dispute) = ctx
+            .accounts
+            .dispute
+            .as_mut()
+            .filter(|_| disputed)
+        {
+            dispute.payer_amount = payer_amount;
+            dispute.receiver_amount = settled_amount;
+            dispute.status = DisputeStatus::Resolved;
+            dispute.resolved_at = now;
+
+            emit!(DisputeResolved {
+                dispute: dispute.key(),
+                payment: payment.key(),
+                payer_amount,
+                receiver_amount: settled_amount,
+                appealed: dispute.appealed_by.is_some(),
+            });
+        }
+        release_order_slot(&ctx.accounts.listing, settled_amount)?;
+        release_payment_record(&ctx.accounts.listing)?;
+
+        let payer = &mut ctx.accounts.payer_agent;
+        payer.total_spent = payer.total_spent.saturating_sub(payer_amount);
+        let receiver = &mut ctx.accounts.receiver_agent;
+        receiver.total_earned += receiver_amount;
+        receiver.referral_fees_paid += referral_share;
+        if settled_amount > 0 {
+            receiver.services_completed += 1;
+        }
+
+        emit!(PaymentSettled {
+            payment: payment.key(),
+            payer_amount,
+            receiver_amount,
+            referral_share,
+        });
+        emit!(PaymentClosed {
+            payment: payment.key(),
+            payer: payment.payer,
+            service: payment.service,
+        });
+
+        Ok(())
+    }
+
+    /// Release or refund a conditional payment once its conditions decide it (permissionless)
+    /// Witness accounts for oracle, validator and attestation conditions follow in condition order
+    pub fn execute_conditional_release<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteConditionalRelease<'info>>,
+        preimages: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        let payment = &mut ctx.accounts.payment;
+        require!(
+            payment.status == PaymentStatus::Escrowed,
+            SolAgentError::PaymentNotEscrowed
+        );
+        require!(!payment.conditions.is_empty(), SolAgentError::NoConditions);
+
+        let now = Clock::get()?.unix_timestamp;
+        let release = evaluate_conditions(payment, ctx.remaining_accounts, &preimages, now)?
+            .ok_or(SolAgentError::ConditionsPending)?;
+
+        if release {
+            let accounts = &mut *ctx.accounts;
+            return release_to_provider(
+                &mut accounts.payment,
+                &accounts.escrow,
+                &mut accounts.payer_agent,
+                &accounts.payer_authority,
+                &mut accounts.receiver_agent,
+                &accounts.receiver_authority,
+                &mut accounts.service,
+                accounts.referrer_agent.as_mut(),
+                accounts.referrer_authority.as_ref(),
+                now,
+            );
+        }
+
+        let amount = payment.remaining();
+        **ctx.accounts.escrow.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.payer_authority.try_borrow_mut_lamports()? += amount;
+
+        payment.status = PaymentStatus::Refunded;
+        payment.completed_at = now;
+        let service = &mut ctx.accounts.service;
+        service.open_orders = service.open_orders.saturating_sub(1);
+
+        emit!(PaymentRefunded {
+            payment: payment.key(),
+            payer: payment.payer,
+            amount,
+            reason: "conditions_failed".to_string(),
+        });
+
+        Ok(())
+    }
+
+    /// Claim a hashlocked payment by revealing the preimages of its `HashPreimage` conditions, in
+    /// condition order (receiver only). Any other conditions must hold too; each preimage is emitted
+    /// for the payer
+    pub fn claim_with_preimage<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimWithPreimage<'info>>,
+        preimages: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        let payment = &ctx.accounts.payment;
+        require!(
+            payment.status == PaymentStatus::Escrowed,
+            SolAgentError::PaymentNotEscrowed
+        );
+        let hashes: Vec<[u8; 32]> = payment
+            .conditions
+            .iter()
+            .filter_map(|condition| match condition {
+                PaymentCondition::HashPreimage { hash } => Some(*hash),
+                _ => None,
+            })
+            .collect();
+        require!(
+            !hashes.is_empty()
+                && hashes.len() == preimages.len()
+                && hashes
+                    .iter()
+                    .zip(&preimages)
+                    .all(|(hash, preimage)| hashv(&[preimage]).to_bytes() == *hash),
+            SolAgentError::PreimageMismatch
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            evaluate_conditions(payment, ctx.remaining_accounts, &preimages, now)? == Some(true),
+            SolAgentError::ConditionsPending
+        );
+
+        for (hash, preimage) in hashes.into_iter().zip(preimages) {
+            emit!(PreimageRevealed {
+                payment: payment.key(),
+                hash,
+                preimage,
+            });
+        }
+        let accounts = &mut *ctx.accounts;
+        release_to_provider(
+            &mut accounts.payment,
+            &accounts.escrow,
+            &mut accounts.payer_agent,
+            &accounts.payer_authority,
+            &mut accounts.receiver_agent,
+            &accounts.receiver_authority,
+            &mut accounts.service,
+            accounts.referrer_agent.as_mut(),
+            accounts.referrer_authority.as_ref(),
+            now,
+        )
+    }
+
+    /// Close a released, refunded or settled payment and refund its rent to the payer
+    /// Services and bundles can only be closed once all of their payments are
+    pub fn close_payment(ctx: Context<ClosePayment>) -> Result<()> {
+        let payment = &ctx.accounts.payment;
+        require!(
+            matches!(
+                payment.status,
+                PaymentStatus::Released | PaymentStatus::Refunded | PaymentStatus::Settled
+            ),
+            SolAgentError::PaymentNotFinished
+        );
+        release_payment_record(&ctx.accounts.listing)?;
+
+        emit!(PaymentClosed {
+            payment: payment.key(),
+            payer: payment.payer,
+            service: payment.service,
+        });
+
+        Ok(())
+    }
+
+    /// Grow a payment created under the launch layout to the current one
+    /// Anyone can pay for it, so either party can unstick an order placed before the upgrade. The
+    /// appended space decodes as defaults: no typed conditions, referrer, SLA or milestones, and
+    /// refund on timeout. Launch orders were always for a single unit
+    pub fn migrate_payment(ctx: Context<MigratePayment>) -> Result<()> {
+        let payment_info = &ctx.accounts.payment;
+        let space = 8 + Payment::INIT_SPACE;
+        require!(payment_info.data_len() < space, SolAgentError::PaymentUpToDate);
+        require!(
+            payment_info.try_borrow_data()?.get(..8) == Some(&Payment::DISCRIMINATOR[..]),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+
+        grow_account(
+            payment_info,
+            space,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        let mut payment = Payment::try_deserialize(&mut &payment_info.try_borrow_data()?[..])?;
+        payment.quantity = 1;
+        payment.service_price = payment.amount;
+        payment.try_serialize(&mut &mut payment_info.try_borrow_mut_data()?[..])?;
+
+        emit!(PaymentMigrated {
+            payment: payment_info.key(),
+            space: space as u64,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
     /// Create a streaming payment (pay-per-second)
     pub fn create_stream(
         ctx: Context<CreateStream>,
@@ -442,431 +1650,6271 @@ pub mod solagent {
     }
 
     // ============================================================
-    // PROTOCOL STATS
+    // METERED BILLING (PriceModel::PerRequest / PerToken)
     // ============================================================
 
-    /// Initialize global protocol state
-    pub fn initialize_protocol(ctx: Context<InitializeProtocol>) -> Result<()> {
-        let protocol = &mut ctx.accounts.protocol;
-        protocol.authority = ctx.accounts.authority.key();
-        protocol.total_agents = 0;
-        protocol.total_services = 0;
-        protocol.total_payments = 0;
-        protocol.total_volume = 0;
-        protocol.total_staked = 0;
-        protocol.fee_bps = 10; // 0.1%
-        protocol.treasury = ctx.accounts.authority.key();
-        protocol.bump = ctx.bumps.protocol;
+    /// Pre-fund a usage escrow for a metered service
+    /// The per-unit price is locked in at funding time
+    pub fn open_usage_escrow(
+        ctx: Context<OpenUsageEscrow>,
+        deposit: u64,
+        max_units_per_report: u64,
+        challenge_window_seconds: i64,
+    ) -> Result<()> {
+        let service = &ctx.accounts.service;
+        require!(service.is_active, SolAgentError::ServiceInactive);
+        require!(
+            matches!(
+                service.price_model,
+                PriceModel::PerRequest | PriceModel::PerToken
+            ),
+            SolAgentError::PriceModelMismatch
+        );
+        require!(deposit > 0, SolAgentError::ZeroAmount);
+        service.check_access(
+            &ctx.accounts.payer_agent,
+            ctx.accounts.buyer_attestation.as_deref(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        require!(challenge_window_seconds >= 0, SolAgentError::InvalidDuration);
 
-        emit!(ProtocolInitialized {
-            authority: protocol.authority,
-            timestamp: Clock::get()?.unix_timestamp,
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer_authority.to_account_info(),
+                    to: ctx.accounts.usage_vault.to_account_info(),
+                },
+            ),
+            deposit,
+        )?;
+
+        let usage = &mut ctx.accounts.usage_escrow;
+        usage.payer = ctx.accounts.payer_agent.key();
+        usage.payer_authority = ctx.accounts.payer_authority.key();
+        usage.service = service.key();
+        usage.provider = service.provider;
+        usage.price_per_unit = service.price_lamports;
+        usage.pricing = service.pricing.clone();
+        usage.discount_bps = service
+            .pricing
+            .as_ref()
+            .map_or(0, |p| p.discount_bps(ctx.accounts.payer_agent.reputation_score));
+        usage.deposited = deposit;
+        usage.drawn = 0;
+        usage.pending = 0;
+        usage.units_billed = 0;
+        usage.units_reported = 0;
+        usage.max_units_per_report = max_units_per_report;
+        usage.challenge_window_seconds = challenge_window_seconds;
+        usage.report_count = 0;
+        usage.open_reports = 0;
+        usage.created_at = Clock::get()?.unix_timestamp;
+        usage.bump = ctx.bumps.usage_escrow;
+        usage.vault_bump = ctx.bumps.usage_vault;
+
+        emit!(UsageEscrowOpened {
+            usage_escrow: usage.key(),
+            payer: usage.payer,
+            service: usage.service,
+            price_per_unit: usage.price_per_unit,
+            deposit,
         });
 
         Ok(())
     }
-}
-
-// ============================================================
-// ACCOUNTS
-// ============================================================
 
-#[derive(Accounts)]
-pub struct InitializeProtocol<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Protocol::INIT_SPACE,
-        seeds = [b"protocol"],
-        bump
-    )]
-    pub protocol: Account<'info, Protocol>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+    /// Add more funds to an existing usage escrow
+    pub fn top_up_usage_escrow(ctx: Context<TopUpUsageEscrow>, amount: u64) -> Result<()> {
+        require!(amount > 0, SolAgentError::ZeroAmount);
 
-#[derive(Accounts)]
-#[instruction(name: String)]
-pub struct RegisterAgent<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Agent::INIT_SPACE,
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer_authority.to_account_info(),
+                    to: ctx.accounts.usage_vault.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let usage = &mut ctx.accounts.usage_escrow;
+        usage.deposited += amount;
+
+        emit!(UsageEscrowFunded {
+            usage_escrow: usage.key(),
+            amount,
+            available: usage.available(),
+        });
+
+        Ok(())
+    }
+
+    /// Cap the number of units a single usage report may bill (0 = no cap)
+    pub fn set_usage_cap(ctx: Context<SetUsageCap>, max_units_per_report: u64) -> Result<()> {
+        ctx.accounts.usage_escrow.max_units_per_report = max_units_per_report;
+        Ok(())
+    }
+
+    /// Provider reports usage against a usage escrow
+    /// Co-signed reports settle immediately; others can be contested until the window closes
+    pub fn submit_usage_report(ctx: Context<SubmitUsageReport>, units: u64) -> Result<()> {
+        require!(units > 0, SolAgentError::InvalidQuantity);
+
+        let usage = &ctx.accounts.usage_escrow;
+        require!(
+            usage.max_units_per_report == 0 || units <= usage.max_units_per_report,
+            SolAgentError::UsageCapExceeded
+        );
+        // Tiers count from the units already reported against this escrow
+        let amount = price_units(
+            usage.price_per_unit,
+            usage.pricing.as_ref(),
+            usage.units_reported,
+            units,
+            usage.discount_bps,
+        )?;
+        require!(amount <= usage.available(), SolAgentError::InsufficientEscrow);
+
+        let now = Clock::get()?.unix_timestamp;
+        let co_signed = ctx.accounts.payer_authority.is_some();
+
+        let report = &mut ctx.accounts.usage_report;
+        report.usage_escrow = usage.key();
+        report.index = usage.report_count;
+        report.units = units;
+        report.amount = amount;
+        report.submitted_at = now;
+        report.claimable_at = now + usage.challenge_window_seconds;
+        report.co_signed = co_signed;
+        report.status = UsageReportStatus::Pending;
+        report.bump = ctx.bumps.usage_report;
+
+        let usage = &mut ctx.accounts.usage_escrow;
+        usage.report_count += 1;
+        usage.open_reports += 1;
+        usage.units_reported += units;
+        usage.pending += amount;
+
+        emit!(UsageReported {
+            usage_escrow: usage.key(),
+            report: report.key(),
+            units,
+            amount,
+            co_signed,
+        });
+
+        if co_signed {
+            settle_usage_report(
+                usage,
+                report,
+                &mut ctx.accounts.payer_agent,
+                &mut ctx.accounts.provider_agent,
+                &mut ctx.accounts.service,
+                &ctx.accounts.usage_vault,
+                &ctx.accounts.provider_authority.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Payer contests a pending usage report before its challenge window closes
+    pub fn contest_usage_report(ctx: Context<ContestUsageReport>) -> Result<()> {
+        let report = &mut ctx.accounts.usage_report;
+        require!(
+            report.status == UsageReportStatus::Pending,
+            SolAgentError::UsageReportNotPending
+        );
+        require!(
+            Clock::get()?.unix_timestamp < report.claimable_at,
+            SolAgentError::ChallengeWindowClosed
+        );
+
+        report.status = UsageReportStatus::Contested;
+
+        // Contested units do not count towards volume tiers
+        let usage = &mut ctx.accounts.usage_escrow;
+        usage.pending -= report.amount;
+        usage.units_reported -= report.units;
+
+        emit!(UsageContested {
+            usage_escrow: usage.key(),
+            report: report.key(),
+            amount: report.amount,
+        });
+
+        Ok(())
+    }
+
+    /// Provider claims an uncontested usage report once its challenge window has passed
+    pub fn claim_usage_report(ctx: Context<ClaimUsageReport>) -> Result<()> {
+        let report = &ctx.accounts.usage_report;
+        require!(
+            report.status == UsageReportStatus::Pending,
+            SolAgentError::UsageReportNotPending
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= report.claimable_at,
+            SolAgentError::ChallengeWindowOpen
+        );
+
+        settle_usage_report(
+            &mut ctx.accounts.usage_escrow,
+            &mut ctx.accounts.usage_report,
+            &mut ctx.accounts.payer_agent,
+            &mut ctx.accounts.provider_agent,
+            &mut ctx.accounts.service,
+            &ctx.accounts.usage_vault,
+            &ctx.accounts.provider_authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )
+    }
+
+    /// Payer resolves a contested usage report by accepting `units` of it (0 drops it), typically
+    /// after agreeing with the provider off-chain; the report is closed and its rent returned
+    pub fn resolve_usage_report(ctx: Context<ResolveUsageReport>, units: u64) -> Result<()> {
+        let report = &ctx.accounts.usage_report;
+        require!(
+            report.status == UsageReportStatus::Contested,
+            SolAgentError::UsageReportNotContested
+        );
+        require!(units <= report.units, SolAgentError::InvalidQuantity);
+
+        let usage = &ctx.accounts.usage_escrow;
+        let amount = price_units(
+            usage.price_per_unit,
+            usage.pricing.as_ref(),
+            usage.units_reported,
+            units,
+            usage.discount_bps,
+        )?;
+        require!(amount <= usage.available(), SolAgentError::InsufficientEscrow);
+
+        emit!(UsageReportResolved {
+            usage_escrow: usage.key(),
+            report: report.key(),
+            units,
+            amount,
+        });
+
+        let usage = &mut ctx.accounts.usage_escrow;
+        usage.open_reports -= 1;
+        if units > 0 {
+            let report = &mut ctx.accounts.usage_report;
+            report.units = units;
+            report.amount = amount;
+            usage.units_reported += units;
+            usage.pending += amount;
+            settle_usage_report(
+                usage,
+                report,
+                &mut ctx.accounts.payer_agent,
+                &mut ctx.accounts.provider_agent,
+                &mut ctx.accounts.service,
+                &ctx.accounts.usage_vault,
+                &ctx.accounts.provider_authority,
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Close a settled usage report, returning its rent to the provider (permissionless)
+    pub fn close_usage_report(ctx: Context<CloseUsageReport>) -> Result<()> {
+        let report = &ctx.accounts.usage_report;
+        require!(
+            report.status == UsageReportStatus::Settled,
+            SolAgentError::UsageReportNotSettled
+        );
+        ctx.accounts.usage_escrow.open_reports -= 1;
+
+        emit!(UsageReportClosed {
+            usage_escrow: report.usage_escrow,
+            report: report.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Close a usage escrow and refund the unused balance to the payer
+    /// Every report must be settled or resolved and closed first, so a reopened escrow starts clean
+    pub fn close_usage_escrow(ctx: Context<CloseUsageEscrow>) -> Result<()> {
+        let usage = &ctx.accounts.usage_escrow;
+        require!(usage.pending == 0, SolAgentError::UsageReportsPending);
+        require!(usage.open_reports == 0, SolAgentError::UsageReportsOpen);
+
+        let refunded = usage.available();
+        if refunded > 0 {
+            transfer_from_vault(
+                &ctx.accounts.usage_vault,
+                &ctx.accounts.payer_authority.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                &[b"usage_vault", usage.key().as_ref(), &[usage.vault_bump]],
+                refunded,
+            )?;
+        }
+
+        emit!(UsageEscrowClosed {
+            usage_escrow: usage.key(),
+            units_billed: usage.units_billed,
+            drawn: usage.drawn,
+            refunded,
+        });
+
+        Ok(())
+    }
+
+    // ============================================================
+    // SUBSCRIPTIONS (PriceModel::Subscription)
+    // ============================================================
+
+    /// Subscribe to a service, pre-funding a balance that is charged every period
+    /// The first period is charged immediately
+    pub fn subscribe(
+        ctx: Context<Subscribe>,
+        amount_per_period: u64,
+        period_seconds: i64,
+        deposit: u64,
+    ) -> Result<()> {
+        let service = &ctx.accounts.service;
+        require!(service.is_active, SolAgentError::ServiceInactive);
+        let PriceModel::Subscription {
+            period_seconds: service_period,
+        } = service.price_model
+        else {
+            return err!(SolAgentError::PriceModelMismatch);
+        };
+        // The subscriber authorises exactly the listed terms
+        require!(
+            amount_per_period == service.price_lamports,
+            SolAgentError::AmountMismatch
+        );
+        require!(
+            period_seconds == service_period && period_seconds > 0,
+            SolAgentError::InvalidDuration
+        );
+        require!(
+            deposit >= amount_per_period,
+            SolAgentError::InsufficientDeposit
+        );
+        service.check_access(
+            &ctx.accounts.subscriber_agent,
+            ctx.accounts.buyer_attestation.as_deref(),
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.subscriber_authority.to_account_info(),
+                    to: ctx.accounts.subscription_vault.to_account_info(),
+                },
+            ),
+            deposit,
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.subscriber = ctx.accounts.subscriber_agent.key();
+        subscription.subscriber_authority = ctx.accounts.subscriber_authority.key();
+        subscription.service = service.key();
+        subscription.provider = service.provider;
+        subscription.provider_authority = service.authority;
+        subscription.amount_per_period = amount_per_period;
+        subscription.period_seconds = period_seconds;
+        subscription.deposited = deposit;
+        subscription.charged = 0;
+        subscription.periods_charged = 0;
+        subscription.paid_until = now;
+        subscription.status = SubscriptionStatus::Active;
+        subscription.created_at = now;
+        subscription.bump = ctx.bumps.subscription;
+        subscription.vault_bump = ctx.bumps.subscription_vault;
+
+        emit!(Subscribed {
+            subscription: subscription.key(),
+            subscriber: subscription.subscriber,
+            service: subscription.service,
+            amount_per_period,
+            period_seconds,
+        });
+
+        charge_subscription_period(
+            subscription,
+            &mut ctx.accounts.subscriber_agent,
+            &mut ctx.accounts.provider_agent,
+            &mut ctx.accounts.service,
+            &ctx.accounts.subscription_vault,
+            &ctx.accounts.provider_authority,
+            &ctx.accounts.system_program.to_account_info(),
+            now,
+        )
+    }
+
+    /// Add funds to a subscription balance, reactivating it if it had lapsed
+    /// and the new balance covers at least one period
+    pub fn top_up_subscription(ctx: Context<TopUpSubscription>, amount: u64) -> Result<()> {
+        require!(amount > 0, SolAgentError::ZeroAmount);
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.subscriber_authority.to_account_info(),
+                    to: ctx.accounts.subscription_vault.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.deposited += amount;
+        if subscription.status == SubscriptionStatus::Lapsed
+            && subscription.balance() >= subscription.amount_per_period
+        {
+            subscription.status = SubscriptionStatus::Active;
+        }
+
+        emit!(SubscriptionFunded {
+            subscription: subscription.key(),
+            amount,
+            balance: subscription.balance(),
+        });
+
+        Ok(())
+    }
+
+    /// Charge the next period of a subscription (permissionless crank)
+    /// Marks the subscription as lapsed if the balance cannot cover the period
+    pub fn charge_subscription(ctx: Context<ChargeSubscription>) -> Result<()> {
+        let subscription = &ctx.accounts.subscription;
+        require!(
+            subscription.status == SubscriptionStatus::Active,
+            SolAgentError::SubscriptionInactive
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= subscription.paid_until, SolAgentError::SubscriptionNotDue);
+
+        charge_subscription_period(
+            &mut ctx.accounts.subscription,
+            &mut ctx.accounts.subscriber_agent,
+            &mut ctx.accounts.provider_agent,
+            &mut ctx.accounts.service,
+            &ctx.accounts.subscription_vault,
+            &ctx.accounts.provider_authority,
+            &ctx.accounts.system_program.to_account_info(),
+            now,
+        )
+    }
+
+    /// Cancel a subscription, refunding the unused balance and closing the account
+    pub fn cancel_subscription(ctx: Context<CancelSubscription>) -> Result<()> {
+        let subscription = &ctx.accounts.subscription;
+        let refunded = subscription.balance();
+        if refunded > 0 {
+            transfer_from_vault(
+                &ctx.accounts.subscription_vault,
+                &ctx.accounts.subscriber_authority.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                &[
+                    b"subscription_vault",
+                    subscription.key().as_ref(),
+                    &[subscription.vault_bump],
+                ],
+                refunded,
+            )?;
+        }
+
+        emit!(SubscriptionCancelled {
+            subscription: subscription.key(),
+            subscriber: subscription.subscriber,
+            periods_charged: subscription.periods_charged,
+            refunded,
+        });
+
+        Ok(())
+    }
+
+    /// Fail unless the subscriber currently holds a paid-up subscription to the service
+    /// Intended for CPI and transaction simulation by service backends
+    pub fn verify_subscription(ctx: Context<VerifySubscription>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            ctx.accounts.subscription.is_active_at(now),
+            SolAgentError::SubscriptionInactive
+        );
+        Ok(())
+    }
+
+    // ============================================================
+    // AUCTIONS (PriceModel::Auction)
+    // ============================================================
+
+    /// Open an auction on an Auction-priced service
+    /// One auction per service can be open at a time
+    pub fn open_auction(
+        ctx: Context<OpenAuction>,
+        reserve_price: u64,
+        duration_seconds: i64,
+        delivery_timeout_seconds: i64,
+    ) -> Result<()> {
+        let service = &ctx.accounts.service;
+        require!(service.is_active, SolAgentError::ServiceInactive);
+        require!(
+            service.price_model == PriceModel::Auction,
+            SolAgentError::PriceModelMismatch
+        );
+        require!(reserve_price > 0, SolAgentError::ZeroAmount);
+        require!(duration_seconds > 0, SolAgentError::InvalidDuration);
+        require!(delivery_timeout_seconds > 0, SolAgentError::InvalidDuration);
+        // Checked when opening so that settlement can never be blocked
+        require!(service.has_capacity(), SolAgentError::ServiceAtCapacity);
+
+        let auction = &mut ctx.accounts.auction;
+        auction.service = service.key();
+        auction.provider = service.provider;
+        auction.authority = ctx.accounts.authority.key();
+        auction.reserve_price = reserve_price;
+        auction.highest_bid = 0;
+        auction.highest_bidder = Pubkey::default();
+        auction.highest_bidder_authority = Pubkey::default();
+        auction.intent = String::new();
+        auction.bid_count = 0;
+        auction.delivery_timeout_seconds = delivery_timeout_seconds;
+        auction.created_at = Clock::get()?.unix_timestamp;
+        auction.end_at = auction.created_at + duration_seconds;
+        auction.status = AuctionStatus::Open;
+        auction.payment = Pubkey::default();
+        auction.bump = ctx.bumps.auction;
+        auction.vault_bump = ctx.bumps.auction_vault;
+
+        emit!(AuctionOpened {
+            auction: auction.key(),
+            service: auction.service,
+            reserve_price,
+            end_at: auction.end_at,
+        });
+
+        Ok(())
+    }
+
+    /// Place an escrowed bid; the previous highest bidder is refunded automatically
+    pub fn place_bid(ctx: Context<PlaceBid>, amount: u64, intent: String) -> Result<()> {
+        require!(intent.len() <= 256, SolAgentError::IntentTooLong);
+
+        let auction = &ctx.accounts.auction;
+        require!(
+            auction.status == AuctionStatus::Open,
+            SolAgentError::AuctionNotOpen
+        );
+        require!(
+            Clock::get()?.unix_timestamp < auction.end_at,
+            SolAgentError::AuctionEnded
+        );
+        require!(
+            ctx.accounts.bidder_agent.key() != auction.provider,
+            SolAgentError::Unauthorized
+        );
+        ctx.accounts.service.check_access(
+            &ctx.accounts.bidder_agent,
+            ctx.accounts.buyer_attestation.as_deref(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        require!(
+            amount >= auction.reserve_price && amount > auction.highest_bid,
+            SolAgentError::BidTooLow
+        );
+
+        // Escrow the new bid
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.bidder_authority.to_account_info(),
+                    to: ctx.accounts.auction_vault.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        // Refund the outbid bidder
+        let previous_bidder = auction.highest_bidder;
+        let refunded = auction.highest_bid;
+        if refunded > 0 {
+            let previous_authority = ctx
+                .accounts
+                .previous_bidder_authority
+                .as_ref()
+                .ok_or(SolAgentError::PreviousBidderMissing)?;
+            require!(
+                previous_authority.key() == auction.highest_bidder_authority,
+                SolAgentError::PreviousBidderMissing
+            );
+            let auction_key = auction.key();
+            transfer_from_vault(
+                &ctx.accounts.auction_vault,
+                previous_authority,
+                &ctx.accounts.system_program.to_account_info(),
+                &[b"auction_vault", auction_key.as_ref(), &[auction.vault_bump]],
+                refunded,
+            )?;
+        }
+
+        let auction = &mut ctx.accounts.auction;
+        auction.highest_bid = amount;
+        auction.highest_bidder = ctx.accounts.bidder_agent.key();
+        auction.highest_bidder_authority = ctx.accounts.bidder_authority.key();
+        auction.intent = intent;
+        auction.bid_count += 1;
+
+        emit!(BidPlaced {
+            auction: auction.key(),
+            bidder: auction.highest_bidder,
+            amount,
+            previous_bidder,
+            refunded,
+        });
+
+        Ok(())
+    }
+
+    /// Settle an ended auction, moving the winning bid into a normal payment escrow
+    /// Permissionless: the caller only pays rent for the new payment account
+    pub fn settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
+        let auction = &ctx.accounts.auction;
+        require!(
+            auction.status == AuctionStatus::Open,
+            SolAgentError::AuctionNotOpen
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= auction.end_at, SolAgentError::AuctionNotEnded);
+        require!(auction.bid_count > 0, SolAgentError::NoBids);
+
+        let amount = auction.highest_bid;
+        let auction_key = auction.key();
+        transfer_from_vault(
+            &ctx.accounts.auction_vault,
+            &ctx.accounts.escrow,
+            &ctx.accounts.system_program.to_account_info(),
+            &[b"auction_vault", auction_key.as_ref(), &[auction.vault_bump]],
+            amount,
+        )?;
+
+        let payment = &mut ctx.accounts.payment;
+        payment.payer = auction.highest_bidder;
+        payment.receiver = auction.provider;
+        payment.service = auction.service;
+        payment.amount = amount;
+        payment.quantity = 1;
+        payment.intent = auction.intent.clone();
+        payment.conditions = Vec::new();
+        payment.referrer = None;
+        payment.referral_bps = 0;
+        payment.sla = None;
+        payment.service_version = ctx.accounts.service.version;
+        payment.service_price = ctx.accounts.service.price_lamports;
+        payment.acknowledged_at = 0;
+        payment.delivered_at = 0;
+        payment.status = PaymentStatus::Escrowed;
+        payment.created_at = now;
+        payment.timeout_at = now + auction.delivery_timeout_seconds;
+        payment.completed_at = 0;
+        payment.milestones = Vec::new();
+        payment.released_amount = 0;
+        payment.timeout_policy = TimeoutPolicy::RefundOnTimeout;
+        payment.bump = ctx.bumps.payment;
+        payment.escrow_bump = ctx.bumps.escrow;
+
+        let winner = &mut ctx.accounts.winner_agent;
+        winner.services_requested += 1;
+        winner.total_spent += amount;
+
+        let service = &mut ctx.accounts.service;
+        service.total_orders += 1;
+        service.open_orders += 1;
+        service.unclosed_payments += 1;
+
+        let auction = &mut ctx.accounts.auction;
+        auction.status = AuctionStatus::Settled;
+        auction.payment = payment.key();
+
+        emit!(AuctionSettled {
+            auction: auction.key(),
+            payment: payment.key(),
+            winner: payment.payer,
+            amount,
+        });
+
+        emit!(PaymentCreated {
+            payment: payment.key(),
+            payer: payment.payer,
+            receiver: payment.receiver,
+            amount,
+            intent: payment.intent.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Cancel an auction that has not received any bids and refund its rent
+    pub fn cancel_auction(ctx: Context<CancelAuction>) -> Result<()> {
+        let auction = &ctx.accounts.auction;
+        require!(
+            auction.status == AuctionStatus::Open,
+            SolAgentError::AuctionNotOpen
+        );
+        require!(auction.bid_count == 0, SolAgentError::AuctionHasBids);
+
+        emit!(AuctionCancelled {
+            auction: auction.key(),
+            service: auction.service,
+        });
+
+        Ok(())
+    }
+
+    /// Close a settled auction and refund its rent; the winning bid lives on in its payment
+    pub fn close_auction(ctx: Context<CancelAuction>) -> Result<()> {
+        let auction = &ctx.accounts.auction;
+        require!(
+            auction.status == AuctionStatus::Settled,
+            SolAgentError::AuctionNotSettled
+        );
+
+        emit!(AuctionClosed {
+            auction: auction.key(),
+            service: auction.service,
+            payment: auction.payment,
+        });
+
+        Ok(())
+    }
+
+    // ============================================================
+    // JOB BOARD (reverse auctions / request-for-quote)
+    // ============================================================
+
+    /// Post a job describing what the buyer needs, with a budget cap and bid deadline
+    pub fn post_job(
+        ctx: Context<PostJob>,
+        job_id: String,
+        intent: String,
+        budget: u64,
+        required_capabilities: Vec<String>,
+        deadline: i64,
+    ) -> Result<()> {
+        require!(job_id.len() <= 32, SolAgentError::NameTooLong);
+        require!(intent.len() <= 256, SolAgentError::IntentTooLong);
+        require!(budget > 0, SolAgentError::ZeroAmount);
+        require!(
+            required_capabilities.len() <= 10,
+            SolAgentError::TooManyCapabilities
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(deadline > now, SolAgentError::InvalidDuration);
+
+        let job = &mut ctx.accounts.job;
+        job.buyer = ctx.accounts.buyer_agent.key();
+        job.authority = ctx.accounts.authority.key();
+        job.job_id = job_id;
+        job.intent = intent;
+        job.budget = budget;
+        job.required_capabilities = required_capabilities;
+        job.deadline = deadline;
+        job.status = JobStatus::Open;
+        job.bid_count = 0;
+        job.accepted_bid = Pubkey::default();
+        job.payment = Pubkey::default();
+        job.created_at = now;
+        job.bump = ctx.bumps.job;
+
+        emit!(JobPosted {
+            job: job.key(),
+            buyer: job.buyer,
+            budget,
+            deadline,
+            intent: job.intent.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Quote a price for an open job through one of the provider's services
+    pub fn submit_job_bid(
+        ctx: Context<SubmitJobBid>,
+        price: u64,
+        delivery_seconds: i64,
+        message: String,
+    ) -> Result<()> {
+        require!(message.len() <= 256, SolAgentError::DescriptionTooLong);
+        require!(price > 0, SolAgentError::ZeroAmount);
+        require!(delivery_seconds > 0, SolAgentError::InvalidDuration);
+
+        let job = &ctx.accounts.job;
+        require!(job.status == JobStatus::Open, SolAgentError::JobNotOpen);
+        require!(
+            Clock::get()?.unix_timestamp < job.deadline,
+            SolAgentError::JobDeadlinePassed
+        );
+        require!(price <= job.budget, SolAgentError::BidExceedsBudget);
+        require!(ctx.accounts.service.is_active, SolAgentError::ServiceInactive);
+
+        let provider = &ctx.accounts.provider_agent;
+        require!(provider.key() != job.buyer, SolAgentError::Unauthorized);
+        require!(
+            job.required_capabilities
+                .iter()
+                .all(|cap| provider.capabilities.contains(cap)),
+            SolAgentError::MissingCapability
+        );
+
+        let bid = &mut ctx.accounts.job_bid;
+        bid.job = job.key();
+        bid.provider = provider.key();
+        bid.authority = ctx.accounts.authority.key();
+        bid.service = ctx.accounts.service.key();
+        bid.price = price;
+        bid.delivery_seconds = delivery_seconds;
+        bid.message = message;
+        bid.submitted_at = Clock::get()?.unix_timestamp;
+        bid.bump = ctx.bumps.job_bid;
+
+        let job = &mut ctx.accounts.job;
+        job.bid_count += 1;
+
+        emit!(JobBidSubmitted {
+            job: job.key(),
+            bid: bid.key(),
+            provider: bid.provider,
+            price,
+            delivery_seconds,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw a job bid and reclaim its rent
+    pub fn withdraw_job_bid(ctx: Context<WithdrawJobBid>) -> Result<()> {
+        let bid = &ctx.accounts.job_bid;
+        ctx.accounts.job.bid_count -= 1;
+
+        emit!(JobBidWithdrawn {
+            job: bid.job,
+            bid: bid.key(),
+            provider: bid.provider,
+        });
+
+        Ok(())
+    }
+
+    /// Close a bid on a job that is no longer open, returning rent to the bidder
+    /// Permissionless so an awarded or cancelled job can always be cleaned up
+    pub fn close_job_bid(ctx: Context<CloseJobBid>) -> Result<()> {
+        require!(
+            ctx.accounts.job.status != JobStatus::Open,
+            SolAgentError::JobStillOpen
+        );
+        let bid = &ctx.accounts.job_bid;
+        ctx.accounts.job.bid_count -= 1;
+
+        emit!(JobBidWithdrawn {
+            job: bid.job,
+            bid: bid.key(),
+            provider: bid.provider,
+        });
+
+        Ok(())
+    }
+
+    /// Accept a bid, escrowing its price as a normal payment against the bid's service
+    pub fn accept_job_bid(ctx: Context<AcceptJobBid>) -> Result<()> {
+        let job = &ctx.accounts.job;
+        require!(job.status == JobStatus::Open, SolAgentError::JobNotOpen);
+        require!(ctx.accounts.service.is_active, SolAgentError::ServiceInactive);
+        require!(
+            ctx.accounts.service.has_capacity(),
+            SolAgentError::ServiceAtCapacity
+        );
+        ctx.accounts.service.check_access(
+            &ctx.accounts.buyer_agent,
+            ctx.accounts.buyer_attestation.as_deref(),
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        let bid = &ctx.accounts.job_bid;
+        let amount = bid.price;
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.escrow.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let payment = &mut ctx.accounts.payment;
+        payment.payer = job.buyer;
+        payment.receiver = bid.provider;
+        payment.service = bid.service;
+        payment.amount = amount;
+        payment.quantity = 1;
+        payment.intent = job.intent.clone();
+        payment.conditions = Vec::new();
+        payment.referrer = None;
+        payment.referral_bps = 0;
+        payment.sla = None;
+        payment.service_version = ctx.accounts.service.version;
+        payment.service_price = ctx.accounts.service.price_lamports;
+        payment.acknowledged_at = 0;
+        payment.delivered_at = 0;
+        payment.status = PaymentStatus::Escrowed;
+        payment.created_at = now;
+        payment.timeout_at = now + bid.delivery_seconds;
+        payment.completed_at = 0;
+        payment.milestones = Vec::new();
+        payment.released_amount = 0;
+        payment.timeout_policy = TimeoutPolicy::RefundOnTimeout;
+        payment.bump = ctx.bumps.payment;
+        payment.escrow_bump = ctx.bumps.escrow;
+
+        let buyer = &mut ctx.accounts.buyer_agent;
+        buyer.services_requested += 1;
+        buyer.total_spent += amount;
+
+        let service = &mut ctx.accounts.service;
+        service.total_orders += 1;
+        service.open_orders += 1;
+        service.unclosed_payments += 1;
+
+        let job = &mut ctx.accounts.job;
+        job.status = JobStatus::Awarded;
+        job.accepted_bid = bid.key();
+        job.payment = payment.key();
+
+        emit!(JobAwarded {
+            job: job.key(),
+            bid: bid.key(),
+            payment: payment.key(),
+            provider: bid.provider,
+            amount,
+        });
+
+        emit!(PaymentCreated {
+            payment: payment.key(),
+            payer: payment.payer,
+            receiver: payment.receiver,
+            amount,
+            intent: payment.intent.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Cancel an open job; it can be closed once its bids are gone
+    pub fn cancel_job(ctx: Context<CancelJob>) -> Result<()> {
+        let job = &mut ctx.accounts.job;
+        require!(job.status == JobStatus::Open, SolAgentError::JobNotOpen);
+        job.status = JobStatus::Cancelled;
+
+        emit!(JobCancelled {
+            job: job.key(),
+            buyer: job.buyer,
+        });
+
+        Ok(())
+    }
+
+    /// Close an awarded or cancelled job with no bids left and reclaim its rent
+    pub fn close_job(ctx: Context<CloseJob>) -> Result<()> {
+        let job = &ctx.accounts.job;
+        require!(job.status != JobStatus::Open, SolAgentError::JobStillOpen);
+        require!(job.bid_count == 0, SolAgentError::JobHasBids);
+
+        emit!(JobClosed {
+            job: job.key(),
+            buyer: job.buyer,
+        });
+
+        Ok(())
+    }
+
+    // ============================================================
+    // SERVICE BUNDLES (composite offerings with revenue shares)
+    // ============================================================
+
+    /// List a bundle of other agents' services; the orchestrator keeps whatever the shares leave
+    /// Remaining accounts: each component's `Service` then its provider `Agent`, in order, followed
+    /// by each component provider's authority as a signer, so no provider is bundled without consent
+    pub fn create_bundle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateBundle<'info>>,
+        bundle_id: String,
+        title: String,
+        description: String,
+        price_lamports: u64,
+        components: Vec<BundleComponent>,
+    ) -> Result<()> {
+        require!(title.len() <= 64, SolAgentError::TitleTooLong);
+        require!(description.len() <= 256, SolAgentError::DescriptionTooLong);
+        require!(price_lamports > 0, SolAgentError::ZeroAmount);
+        require!(
+            !components.is_empty() && components.len() <= MAX_BUNDLE_COMPONENTS,
+            SolAgentError::InvalidBundle
+        );
+        let orchestrator = ctx.accounts.agent.key();
+        let mut total_bps: u64 = 0;
+        for (i, component) in components.iter().enumerate() {
+            require!(
+                component.share_bps > 0
+                    && component.provider != orchestrator
+                    && !components[..i]
+                        .iter()
+                        .any(|c| c.service == component.service || c.provider == component.provider),
+                SolAgentError::InvalidBundle
+            );
+            total_bps += component.share_bps as u64;
+        }
+        require!(
+            total_bps <= BPS_DENOMINATOR as u64,
+            SolAgentError::InvalidBundle
+        );
+        require!(
+            ctx.remaining_accounts.len() == components.len() * 3,
+            SolAgentError::BundleComponentMismatch
+        );
+        let (listings, signers) = ctx.remaining_accounts.split_at(components.len() * 2);
+        let (avg_rating, component_reputation) = component_ratings(&components, listings, true)?;
+        for (pair, signer) in listings.chunks(2).zip(signers) {
+            let provider = Account::<Agent>::try_from(&pair[1])?;
+            require!(
+                signer.is_signer && signer.key() == provider.authority,
+                SolAgentError::ComponentProviderNotSigner
+            );
+        }
+
+        let bundle = &mut ctx.accounts.bundle;
+        bundle.orchestrator = orchestrator;
+        bundle.authority = ctx.accounts.authority.key();
+        bundle.bundle_id = bundle_id;
+        bundle.title = title;
+        bundle.description = description;
+        bundle.price_lamports = price_lamports;
+        bundle.components = components;
+        bundle.total_orders = 0;
+        bundle.open_orders = 0;
+        bundle.unclosed_payments = 0;
+        bundle.total_revenue = 0;
+        bundle.avg_rating = avg_rating;
+        bundle.component_reputation = component_reputation;
+        bundle.is_active = true;
+        bundle.created_at = Clock::get()?.unix_timestamp;
+        bundle.bump = ctx.bumps.bundle;
+
+        emit!(BundleCreated {
+            bundle: bundle.key(),
+            orchestrator,
+            price: price_lamports,
+            components: bundle.components.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Pay for a bundle into a single escrow, recorded as a normal `Payment` against the bundle
+    pub fn pay_for_bundle(
+        ctx: Context<PayForBundle>,
+        amount: u64,
+        intent: String,
+        timeout_seconds: i64,
+    ) -> Result<()> {
+        require!(intent.len() <= 256, SolAgentError::IntentTooLong);
+        let bundle = &ctx.accounts.bundle;
+        require!(bundle.is_active, SolAgentError::ServiceInactive);
+        require!(amount == bundle.price_lamports, SolAgentError::AmountMismatch);
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer_authority.to_account_info(),
+                    to: ctx.accounts.escrow.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let payment = &mut ctx.accounts.payment;
+        payment.payer = ctx.accounts.payer_agent.key();
+        payment.receiver = bundle.orchestrator;
+        payment.service = bundle.key();
+        payment.amount = amount;
+        payment.quantity = 1;
+        payment.intent = intent;
+        payment.conditions = Vec::new();
+        payment.referrer = None;
+        payment.referral_bps = 0;
+        payment.sla = None;
+        payment.service_version = 0;
+        payment.service_price = bundle.price_lamports;
+        payment.acknowledged_at = 0;
+        payment.delivered_at = 0;
+        payment.status = PaymentStatus::Escrowed;
+        payment.created_at = now;
+        payment.timeout_at = now + timeout_seconds;
+        payment.completed_at = 0;
+        payment.milestones = Vec::new();
+        payment.released_amount = 0;
+        payment.timeout_policy = TimeoutPolicy::RefundOnTimeout;
+        payment.bump = ctx.bumps.payment;
+        payment.escrow_bump = ctx.bumps.escrow;
+
+        let payer = &mut ctx.accounts.payer_agent;
+        payer.services_requested += 1;
+        payer.total_spent += amount;
+
+        let bundle = &mut ctx.accounts.bundle;
+        bundle.total_orders += 1;
+        bundle.open_orders += 1;
+        bundle.unclosed_payments += 1;
+
+        emit!(PaymentCreated {
+            payment: payment.key(),
+            payer: payment.payer,
+            receiver: payment.receiver,
+            amount,
+            intent: payment.intent.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Release a bundle payment, splitting the escrow between component providers and the orchestrator
+    /// Remaining accounts: each component's provider `Agent` then its authority wallet, in order
+    pub fn release_bundle_payment<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReleaseBundlePayment<'info>>,
+    ) -> Result<()> {
+        let payment = &mut ctx.accounts.payment;
+        require!(
+            payment.status == PaymentStatus::Escrowed,
+            SolAgentError::PaymentNotEscrowed
+        );
+        let components = &ctx.accounts.bundle.components;
+        let amount = payment.amount;
+        let escrow = ctx.accounts.escrow.to_account_info();
+        let paid_out = pay_bundle_components(components, ctx.remaining_accounts, &escrow, amount)?;
+
+        let orchestrator_share = amount - paid_out;
+        **escrow.try_borrow_mut_lamports()? -= orchestrator_share;
+        **ctx
+            .accounts
+            .orchestrator_authority
+            .to_account_info()
+            .try_borrow_mut_lamports()? += orchestrator_share;
+
+        payment.status = PaymentStatus::Released;
+        payment.completed_at = Clock::get()?.unix_timestamp;
+
+        let orchestrator = &mut ctx.accounts.orchestrator_agent;
+        orchestrator.services_completed += 1;
+        orchestrator.total_earned += orchestrator_share;
+
+        let bundle = &mut ctx.accounts.bundle;
+        bundle.total_revenue += amount;
+        bundle.open_orders = bundle.open_orders.saturating_sub(1);
+
+        emit!(BundlePaymentReleased {
+            payment: payment.key(),
+            bundle: bundle.key(),
+            amount,
+            components_share: paid_out,
+            orchestrator_share,
+        });
+
+        Ok(())
+    }
+
+    /// Refund a bundle payment to the payer (payer any time, anyone after timeout)
+    pub fn refund_bundle_payment(ctx: Context<RefundBundlePayment>) -> Result<()> {
+        let payment = &mut ctx.accounts.payment;
+        require!(
+            payment.status == PaymentStatus::Escrowed,
+            SolAgentError::PaymentNotEscrowed
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let is_timeout = now > payment.timeout_at;
+        let is_payer = ctx.accounts.authority.key() == ctx.accounts.payer_agent.authority;
+        require!(is_timeout || is_payer, SolAgentError::RefundNotAllowed);
+
+        let amount = payment.amount;
+        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx
+            .accounts
+            .payer_authority
+            .to_account_info()
+            .try_borrow_mut_lamports()? += amount;
+
+        payment.status = PaymentStatus::Refunded;
+        payment.completed_at = now;
+
+        let bundle = &mut ctx.accounts.bundle;
+        bundle.open_orders = bundle.open_orders.saturating_sub(1);
+
+        emit!(PaymentRefunded {
+            payment: payment.key(),
+            payer: payment.payer,
+            amount,
+            reason: if is_timeout {
+                "timeout".to_string()
+            } else {
+                "payer_cancelled".to_string()
+            },
+        });
+
+        Ok(())
+    }
+
+    /// Recompute a bundle's rating from its components (permissionless)
+    /// Remaining accounts: each component's `Service` then its provider `Agent`, in order
+    pub fn refresh_bundle_rating<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RefreshBundleRating<'info>>,
+    ) -> Result<()> {
+        let bundle = &mut ctx.accounts.bundle;
+        let (avg_rating, component_reputation) =
+            component_ratings(&bundle.components, ctx.remaining_accounts, false)?;
+        bundle.avg_rating = avg_rating;
+        bundle.component_reputation = component_reputation;
+
+        emit!(BundleRatingUpdated {
+            bundle: bundle.key(),
+            avg_rating,
+            component_reputation,
+        });
+
+        Ok(())
+    }
+
+    /// Pause a bundle so it stops accepting new orders
+    pub fn pause_bundle(ctx: Context<SetBundleStatus>) -> Result<()> {
+        let bundle = &mut ctx.accounts.bundle;
+        require!(bundle.is_active, SolAgentError::ServiceInactive);
+        bundle.is_active = false;
+
+        emit!(BundleStatusChanged {
+            bundle: bundle.key(),
+            is_active: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Resume a paused bundle
+    pub fn resume_bundle(ctx: Context<SetBundleStatus>) -> Result<()> {
+        let bundle = &mut ctx.accounts.bundle;
+        require!(!bundle.is_active, SolAgentError::ServiceAlreadyActive);
+        bundle.is_active = true;
+
+        emit!(BundleStatusChanged {
+            bundle: bundle.key(),
+            is_active: true,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Delist a bundle once it has no open orders or payment accounts, refunding rent to the authority
+    pub fn close_bundle(ctx: Context<CloseBundle>) -> Result<()> {
+        let bundle = &ctx.accounts.bundle;
+        require!(bundle.open_orders == 0, SolAgentError::ServiceHasOpenOrders);
+        require!(
+            bundle.unclosed_payments == 0,
+            SolAgentError::ServiceHasPayments
+        );
+
+        emit!(BundleClosed {
+            bundle: bundle.key(),
+            total_orders: bundle.total_orders,
+            total_revenue: bundle.total_revenue,
+        });
+
+        Ok(())
+    }
+
+    // ============================================================
+    // DISPUTES
+    // ============================================================
+
+    /// Dispute an escrowed payment as its payer or receiver; freezes release and timeout refunds
+    /// Services with an arbiter pool get a panel drawn from it later by `draw_dispute_panel`, others the
+    /// protocol authority
+    pub fn open_dispute(ctx: Context<OpenDispute>, reason: String) -> Result<()> {
+        require!(reason.len() <= 256, SolAgentError::DescriptionTooLong);
+        let payment = &mut ctx.accounts.payment;
+        require!(
+            payment.status == PaymentStatus::Escrowed,
+            SolAgentError::PaymentNotEscrowed
+        );
+        let opened_by = ctx.accounts.agent.key();
+        require!(
+            opened_by == payment.payer || opened_by == payment.receiver,
+            SolAgentError::NotPaymentParty
+        );
+        // Under `ReleaseAfterDelivery` the payer can only object within the challenge window
+        if let Some(release_at) = payment.auto_release_at().filter(|_| opened_by == payment.payer) {
+            require!(
+                Clock::get()?.unix_timestamp <= release_at,
+                SolAgentError::ChallengeWindowClosed
+            );
+        }
+        payment.status = PaymentStatus::Disputed;
+
+        let dispute = &mut ctx.accounts.dispute;
+        match listing_arbiter_pool(&ctx.accounts.listing)? {
+            Some(pool_key) => {
+                let pool = ctx
+                    .accounts
+                    .arbiter_pool
+                    .as_ref()
+                    .ok_or(SolAgentError::ArbiterPoolMismatch)?;
+                require_keys_eq!(pool.key(), pool_key, SolAgentError::ArbiterPoolMismatch);
+                require!(
+                    pool.arbiters.len() >= pool.panel_size as usize,
+                    SolAgentError::NotEnoughArbiters
+                );
+                // Commit to a slot whose hash nobody knows yet; the panel is drawn from it
+                dispute.pool = Some(pool_key);
+                dispute.arbiters = Vec::new();
+                dispute.draw_slot = Clock::get()?.slot + PANEL_DRAW_DELAY_SLOTS;
+                dispute.appeal_authority = pool.authority;
+                dispute.fee = bps_share(payment.remaining(), pool.fee_bps);
+                dispute.appeal_window_seconds = pool.appeal_window_seconds;
+                dispute.slash_bps = pool.slash_bps;
+                // Set once the panel is drawn
+                dispute.vote_deadline = 0;
+            }
+            None => {
+                let authority = ctx.accounts.protocol.authority;
+                dispute.pool = None;
+                dispute.arbiters = vec![authority];
+                dispute.draw_slot = 0;
+                dispute.appeal_authority = authority;
+                dispute.fee = 0;
+                dispute.appeal_window_seconds = 0;
+                dispute.slash_bps = 0;
+                dispute.vote_deadline = Clock::get()?.unix_timestamp + PROTOCOL_DISPUTE_WINDOW_SECONDS;
+            }
+        }
+        dispute.payment = payment.key();
+        dispute.payer = payment.payer;
+        dispute.receiver = payment.receiver;
+        dispute.opened_by = opened_by;
+        dispute.votes = Vec::new();
+        dispute.amount = payment.remaining();
+        dispute.fee_paid = 0;
+        dispute.reason = reason;
+        dispute.evidence = Vec::new();
+        dispute.status = DisputeStatus::Open;
+        dispute.payer_amount = 0;
+        dispute.receiver_amount = 0;
+        dispute.appealed_by = None;
+        dispute.opened_at = Clock::get()?.unix_timestamp;
+        dispute.ruled_at = 0;
+        dispute.resolved_at = 0;
+        dispute.bump = ctx.bumps.dispute;
+        dispute.released_arbiters = 0;
+
+        emit!(DisputeOpened {
+            dispute: dispute.key(),
+            payment: dispute.payment,
+            opened_by,
+            arbiters: dispute.arbiters.clone(),
+            reason: dispute.reason.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Draw a pooled dispute's panel from the hash of its committed slot (permissionless)
+    /// Arbiters whose wallet is the payer's or receiver's authority are skipped; if too few remain
+    /// the pool authority arbitrates alone. If the committed slot has aged out of SlotHashes a new
+    /// one is committed instead
+    /// Remaining accounts: every `Arbiter` in the pool, in pool order
+    pub fn draw_dispute_panel<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DrawDisputePanel<'info>>,
+    ) -> Result<()> {
+        let dispute = &mut ctx.accounts.dispute;
+        require!(
+            dispute.status == DisputeStatus::Open
+                && dispute.pool.is_some()
+                && dispute.arbiters.is_empty(),
+            SolAgentError::PanelAlreadyDrawn
+        );
+        let clock = Clock::get()?;
+        require!(clock.slot > dispute.draw_slot, SolAgentError::PanelDrawPending);
+        let Some(slot_hash) = committed_slot_hash(&ctx.accounts.slot_hashes, dispute.draw_slot)?
+        else {
+            dispute.draw_slot = clock.slot + PANEL_DRAW_DELAY_SLOTS;
+            emit!(DisputePanelRecommitted {
+                dispute: dispute.key(),
+                draw_slot: dispute.draw_slot,
+            });
+            return Ok(());
+        };
+
+        let pool = &ctx.accounts.arbiter_pool;
+        require!(
+            ctx.remaining_accounts.len() == pool.arbiters.len(),
+            SolAgentError::NotAssignedArbiter
+        );
+        let parties = [
+            ctx.accounts.payer_agent.authority,
+            ctx.accounts.receiver_agent.authority,
+        ];
+        let mut arbiters = Vec::with_capacity(pool.arbiters.len());
+        let mut eligible = Vec::with_capacity(pool.arbiters.len());
+        for (key, info) in pool.arbiters.iter().zip(ctx.remaining_accounts) {
+            let arbiter = Account::<Arbiter>::try_from(info)?;
+            require_keys_eq!(arbiter.key(), *key, SolAgentError::NotAssignedArbiter);
+            if !parties.contains(&arbiter.authority) {
+                eligible.push(arbiter.key());
+            }
+            arbiters.push(arbiter);
+        }
+
+        if eligible.len() < pool.panel_size as usize {
+            dispute.pool = None;
+            dispute.arbiters = vec![pool.authority];
+            dispute.appeal_window_seconds = 0;
+        } else {
+            let seed = hashv(&[&slot_hash, dispute.key().as_ref()]).to_bytes();
+            dispute.arbiters = draw_panel(&eligible, pool.panel_size as usize, seed);
+            for arbiter in arbiters
+                .iter_mut()
+                .filter(|arbiter| dispute.arbiters.contains(&arbiter.key()))
+            {
+                arbiter.open_cases += 1;
+                arbiter.exit(&crate::ID)?;
+            }
+        }
+        dispute.vote_deadline = clock.unix_timestamp + pool.vote_window_seconds;
+
+        emit!(DisputePanelDrawn {
+            dispute: dispute.key(),
+            arbiters: dispute.arbiters.clone(),
+            vote_deadline: dispute.vote_deadline,
+        });
+
+        Ok(())
+    }
+
+    /// Attach evidence (content hash + URI) to an open dispute as the payer or receiver
+    pub fn submit_evidence(ctx: Context<SubmitEvidence>, hash: [u8; 32], uri: String) -> Result<()> {
+        require!(uri.len() <= 128, SolAgentError::EvidenceUriTooLong);
+        let dispute = &mut ctx.accounts.dispute;
+        require!(
+            dispute.status == DisputeStatus::Open,
+            SolAgentError::DisputeNotOpen
+        );
+        let submitter = ctx.accounts.agent.key();
+        require!(
+            submitter == dispute.payer || submitter == dispute.receiver,
+            SolAgentError::NotPaymentParty
+        );
+        require!(
+            dispute
+                .evidence
+                .iter()
+                .filter(|evidence| evidence.submitter == submitter)
+                .count()
+                < MAX_DISPUTE_EVIDENCE,
+            SolAgentError::EvidenceLimitReached
+        );
+
+        let submitted_at = Clock::get()?.unix_timestamp;
+        dispute.evidence.push(Evidence {
+            submitter,
+            hash,
+            uri: uri.clone(),
+            submitted_at,
+        });
+
+        emit!(EvidenceSubmitted {
+            dispute: dispute.key(),
+            submitter,
+            hash,
+            uri,
+            timestamp: submitted_at,
+        });
+
+        Ok(())
+    }
+
+    /// Cast an arbiter's ruling (the payer's share of the escrow after fees) and collect the fee share
+    /// Once a majority of the panel has ruled the median split stands, paid out now or after the appeal window
+    /// Remaining accounts (bundle payments, when this vote pays out): each component's provider `Agent` then its authority wallet
+    pub fn resolve_dispute<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ResolveDispute<'info>>,
+        payer_amount: u64,
+    ) -> Result<()> {
+        let dispute = &mut ctx.accounts.dispute;
+        require!(
+            dispute.status == DisputeStatus::Open,
+            SolAgentError::DisputeNotOpen
+        );
+        require!(
+            payer_amount <= dispute.amount - dispute.fee,
+            SolAgentError::InvalidDisputeSplit
+        );
+        let voter = match (dispute.pool, ctx.accounts.arbiter_account.as_ref()) {
+            (None, _) => ctx.accounts.arbiter.key(),
+            (Some(pool), Some(arbiter)) => {
+                require!(
+                    arbiter.pool == pool && arbiter.authority == ctx.accounts.arbiter.key(),
+                    SolAgentError::NotAssignedArbiter
+                );
+                arbiter.key()
+            }
+            (Some(_), None) => return err!(SolAgentError::NotAssignedArbiter),
+        };
+        require!(
+            dispute.arbiters.contains(&voter),
+            SolAgentError::NotAssignedArbiter
+        );
+        require!(
+            !dispute.votes.iter().any(|vote| vote.arbiter == voter),
+            SolAgentError::AlreadyVoted
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            dispute.vote_deadline == 0 || now <= dispute.vote_deadline,
+            SolAgentError::VoteWindowClosed
+        );
+        let fee_share = dispute.fee / dispute.arbiters.len() as u64;
+        **ctx.accounts.escrow.to_account_info().try_borrow_mut_lamports()? -= fee_share;
+        **ctx.accounts.arbiter.to_account_info().try_borrow_mut_lamports()? += fee_share;
+        dispute.fee_paid += fee_share;
+        dispute.votes.push(ArbiterVote {
+            arbiter: voter,
+            payer_amount,
+        });
+        if let Some(arbiter) = ctx.accounts.arbiter_account.as_mut() {
+            arbiter.cases_ruled += 1;
+            arbiter.fees_earned += fee_share;
+            arbiter.locked_until = arbiter
+                .locked_until
+                .max(now + dispute.appeal_window_seconds);
+        }
+
+        emit!(ArbiterVoted {
+            dispute: dispute.key(),
+            arbiter: voter,
+            payer_amount,
+            fee: fee_share,
+        });
+
+        if dispute.votes.len() * 2 <= dispute.arbiters.len() {
+            return Ok(());
+        }
+        let ruling = median_ruling(&dispute.votes);
+        dispute.payer_amount = ruling;
+        dispute.receiver_amount = dispute.amount - dispute.fee - ruling;
+        dispute.ruled_at = now;
+        dispute.status = DisputeStatus::Ruled;
+
+        emit!(DisputeRuled {
+            dispute: dispute.key(),
+            payer_amount: dispute.payer_amount,
+            receiver_amount: dispute.receiver_amount,
+            appeal_deadline: now + dispute.appeal_window_seconds,
+        });
+
+        if dispute.appeal_window_seconds == 0 {
+            pay_out_dispute(
+                dispute,
+                &mut ctx.accounts.payment,
+                &mut ctx.accounts.payer_agent,
+                &mut ctx.accounts.receiver_agent,
+                &ctx.accounts.escrow,
+                &ctx.accounts.payer_authority,
+                &ctx.accounts.receiver_authority,
+                ctx.accounts.referrer_agent.as_mut(),
+                ctx.accounts.referrer_authority.as_ref(),
+                &ctx.accounts.listing,
+                ctx.remaining_accounts,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Appeal a panel ruling inside its appeal window as the payer or receiver
+    /// Remaining accounts: the voting `Arbiter` accounts, in vote order; their stake stays locked until the appeal is decided
+    pub fn appeal_dispute<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AppealDispute<'info>>,
+    ) -> Result<()> {
+        let dispute = &mut ctx.accounts.dispute;
+        require!(
+            dispute.status == DisputeStatus::Ruled,
+            SolAgentError::DisputeNotOpen
+        );
+        require!(
+            Clock::get()?.unix_timestamp < dispute.ruled_at + dispute.appeal_window_seconds,
+            SolAgentError::AppealWindowClosed
+        );
+        let appellant = ctx.accounts.agent.key();
+        require!(
+            appellant == dispute.payer || appellant == dispute.receiver,
+            SolAgentError::NotPaymentParty
+        );
+        require!(
+            ctx.remaining_accounts.len() == dispute.votes.len(),
+            SolAgentError::NotAssignedArbiter
+        );
+        for (vote, info) in dispute.votes.iter().zip(ctx.remaining_accounts) {
+            let mut arbiter = Account::<Arbiter>::try_from(info)?;
+            require_keys_eq!(arbiter.key(), vote.arbiter, SolAgentError::NotAssignedArbiter);
+            arbiter.open_appeals += 1;
+            arbiter.exit(&crate::ID)?;
+        }
+
+        dispute.status = DisputeStatus::Appealed;
+        dispute.appealed_by = Some(appellant);
+
+        emit!(DisputeAppealed {
+            dispute: dispute.key(),
+            appellant,
+        });
+
+        Ok(())
+    }
+
+    /// Decide an appeal as the pool authority; panel members whose ruling is overturned are slashed
+    /// Remaining accounts: the voting `Arbiter` accounts, in vote order, then for bundle payments each
+    /// component's provider `Agent` and its authority wallet
+    pub fn resolve_appeal<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ResolveAppeal<'info>>,
+        payer_amount: u64,
+    ) -> Result<()> {
+        let dispute = &mut ctx.accounts.dispute;
+        require!(
+            dispute.status == DisputeStatus::Appealed,
+            SolAgentError::DisputeNotOpen
+        );
+        require!(
+            payer_amount <= dispute.amount - dispute.fee,
+            SolAgentError::InvalidDisputeSplit
+        );
+        require!(
+            ctx.remaining_accounts.len() >= dispute.votes.len(),
+            SolAgentError::NotAssignedArbiter
+        );
+        let (voters, component_accounts) = ctx.remaining_accounts.split_at(dispute.votes.len());
+
+        let overturned = payer_amount != dispute.payer_amount;
+        let treasury = ctx.accounts.treasury.to_account_info();
+        for (vote, info) in dispute.votes.iter().zip(voters) {
+            let mut arbiter = Account::<Arbiter>::try_from(info)?;
+            require_keys_eq!(arbiter.key(), vote.arbiter, SolAgentError::NotAssignedArbiter);
+            arbiter.open_appeals = arbiter.open_appeals.saturating_sub(1);
+            if overturned && vote.payer_amount != payer_amount {
+                arbiter.cases_overturned += 1;
+                slash_arbiter(&mut arbiter, &treasury, dispute.key(), dispute.slash_bps)?;
+            }
+            arbiter.exit(&crate::ID)?;
+        }
+
+        emit!(DisputeAppealResolved {
+            dispute: dispute.key(),
+            panel_payer_amount: dispute.payer_amount,
+            payer_amount,
+            overturned,
+        });
+
+        dispute.payer_amount = payer_amount;
+        dispute.receiver_amount = dispute.amount - dispute.fee - payer_amount;
+        pay_out_dispute(
+            dispute,
+            &mut ctx.accounts.payment,
+            &mut ctx.accounts.payer_agent,
+            &mut ctx.accounts.receiver_agent,
+            &ctx.accounts.escrow,
+            &ctx.accounts.payer_authority,
+            &ctx.accounts.receiver_authority,
+            ctx.accounts.referrer_agent.as_mut(),
+            ctx.accounts.referrer_authority.as_ref(),
+            &ctx.accounts.listing,
+            component_accounts,
+        )
+    }
+
+    /// Pay out a panel ruling once its appeal window has passed unappealed (permissionless)
+    /// Remaining accounts (bundle payments): each component's provider `Agent` then its authority wallet
+    pub fn finalize_dispute<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FinalizeDispute<'info>>,
+    ) -> Result<()> {
+        let dispute = &mut ctx.accounts.dispute;
+        require!(
+            dispute.status == DisputeStatus::Ruled,
+            SolAgentError::DisputeNotOpen
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= dispute.ruled_at + dispute.appeal_window_seconds,
+            SolAgentError::AppealWindowOpen
+        );
+
+        pay_out_dispute(
+            dispute,
+            &mut ctx.accounts.payment,
+            &mut ctx.accounts.payer_agent,
+            &mut ctx.accounts.receiver_agent,
+            &ctx.accounts.escrow,
+            &ctx.accounts.payer_authority,
+            &ctx.accounts.receiver_authority,
+            ctx.accounts.referrer_agent.as_mut(),
+            ctx.accounts.referrer_authority.as_ref(),
+            &ctx.accounts.listing,
+            ctx.remaining_accounts,
+        )
+    }
+
+    /// Refund a dispute whose panel missed its vote deadline without a majority (permissionless)
+    /// Panel members who never voted are slashed; fee shares already paid stay with the voters
+    /// Remaining accounts (pooled disputes): every panel `Arbiter`, in panel order
+    pub fn expire_dispute<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExpireDispute<'info>>,
+    ) -> Result<()> {
+        let dispute = &mut ctx.accounts.dispute;
+        require!(
+            dispute.status == DisputeStatus::Open,
+            SolAgentError::DisputeNotOpen
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            dispute.vote_deadline > 0 && now > dispute.vote_deadline,
+            SolAgentError::VoteWindowOpen
+        );
+
+        let mut slashed_arbiters = 0;
+        if dispute.pool.is_some() {
+            require!(
+                ctx.remaining_accounts.len() == dispute.arbiters.len(),
+                SolAgentError::NotAssignedArbiter
+            );
+            let treasury = ctx.accounts.treasury.to_account_info();
+            for (key, info) in dispute.arbiters.iter().zip(ctx.remaining_accounts) {
+                let mut arbiter = Account::<Arbiter>::try_from(info)?;
+                require_keys_eq!(arbiter.key(), *key, SolAgentError::NotAssignedArbiter);
+                if !dispute.votes.iter().any(|vote| vote.arbiter == *key) {
+                    slash_arbiter(&mut arbiter, &treasury, dispute.key(), dispute.slash_bps)?;
+                    slashed_arbiters += 1;
+                }
+                arbiter.open_cases = arbiter.open_cases.saturating_sub(1);
+                arbiter.exit(&crate::ID)?;
+            }
+            dispute.released_arbiters = u8::MAX;
+        }
+
+        let refund = dispute.amount - dispute.fee_paid;
+        **ctx.accounts.escrow.try_borrow_mut_lamports()? -= refund;
+        **ctx.accounts.payer_authority.try_borrow_mut_lamports()? += refund;
+
+        let payment = &mut ctx.accounts.payment;
+        payment.status = PaymentStatus::Refunded;
+        payment.completed_at = now;
+        dispute.status = DisputeStatus::Expired;
+        dispute.resolved_at = now;
+        release_order_slot(&ctx.accounts.listing, 0)?;
+        let payer = &mut ctx.accounts.payer_agent;
+        payer.total_spent = payer.total_spent.saturating_sub(refund);
+
+        emit!(DisputeExpired {
+            dispute: dispute.key(),
+            payment: payment.key(),
+            refunded: refund,
+            slashed_arbiters,
+        });
+
+        Ok(())
+    }
+
+    /// Release a panel member's stake lock once its dispute is resolved or expired (permissionless)
+    pub fn release_arbiter_case(ctx: Context<ReleaseArbiterCase>) -> Result<()> {
+        let dispute = &mut ctx.accounts.dispute;
+        require!(
+            matches!(dispute.status, DisputeStatus::Resolved | DisputeStatus::Expired),
+            SolAgentError::DisputeNotFinished
+        );
+        let arbiter = &mut ctx.accounts.arbiter;
+        let index = dispute
+            .arbiters
+            .iter()
+            .position(|key| *key == arbiter.key())
+            .ok_or(SolAgentError::NotAssignedArbiter)?;
+        require!(
+            dispute.released_arbiters & (1 << index) == 0,
+            SolAgentError::ArbiterAlreadyReleased
+        );
+        dispute.released_arbiters |= 1 << index;
+        arbiter.open_cases = arbiter.open_cases.saturating_sub(1);
+
+        emit!(ArbiterReleased {
+            arbiter: arbiter.key(),
+            dispute: dispute.key(),
+            open_cases: arbiter.open_cases,
+        });
+
+        Ok(())
+    }
+
+    // ============================================================
+    // ARBITER REGISTRY
+    // ============================================================
+
+    /// Create an arbiter pool services can route their disputes to (protocol authority only)
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_arbiter_pool(
+        ctx: Context<CreateArbiterPool>,
+        pool_id: u16,
+        name: String,
+        min_stake: u64,
+        fee_bps: u16,
+        panel_size: u8,
+        appeal_window_seconds: i64,
+        slash_bps: u16,
+        vote_window_seconds: i64,
+    ) -> Result<()> {
+        require!(name.len() <= 32, SolAgentError::NameTooLong);
+        require!(
+            panel_size % 2 == 1
+                && panel_size as usize <= MAX_ARBITER_PANEL
+                && fee_bps < BPS_DENOMINATOR
+                && slash_bps <= BPS_DENOMINATOR
+                && appeal_window_seconds >= 0
+                && vote_window_seconds > 0,
+            SolAgentError::InvalidArbiterPool
+        );
+
+        let pool = &mut ctx.accounts.arbiter_pool;
+        pool.pool_id = pool_id;
+        pool.authority = ctx.accounts.authority.key();
+        pool.name = name;
+        pool.min_stake = min_stake;
+        pool.fee_bps = fee_bps;
+        pool.panel_size = panel_size;
+        pool.appeal_window_seconds = appeal_window_seconds;
+        pool.slash_bps = slash_bps;
+        pool.arbiters = Vec::new();
+        pool.created_at = Clock::get()?.unix_timestamp;
+        pool.bump = ctx.bumps.arbiter_pool;
+        pool.vote_window_seconds = vote_window_seconds;
+
+        emit!(ArbiterPoolCreated {
+            arbiter_pool: pool.key(),
+            pool_id,
+            min_stake,
+            fee_bps,
+            panel_size,
+            vote_window_seconds,
+        });
+
+        Ok(())
+    }
+
+    /// Join an arbiter pool by staking at least its minimum; any wallet (agent or human) can join
+    pub fn register_arbiter(ctx: Context<RegisterArbiter>, stake: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.arbiter_pool;
+        require!(stake >= pool.min_stake, SolAgentError::InsufficientArbiterStake);
+        require!(
+            pool.arbiters.len() < MAX_POOL_ARBITERS,
+            SolAgentError::ArbiterPoolFull
+        );
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.arbiter.to_account_info(),
+                },
+            ),
+            stake,
+        )?;
+
+        let arbiter = &mut ctx.accounts.arbiter;
+        arbiter.authority = ctx.accounts.authority.key();
+        arbiter.pool = pool.key();
+        arbiter.stake = stake;
+        arbiter.cases_ruled = 0;
+        arbiter.cases_overturned = 0;
+        arbiter.fees_earned = 0;
+        arbiter.total_slashed = 0;
+        arbiter.open_appeals = 0;
+        arbiter.locked_until = 0;
+        arbiter.registered_at = Clock::get()?.unix_timestamp;
+        arbiter.bump = ctx.bumps.arbiter;
+        arbiter.open_cases = 0;
+        pool.arbiters.push(arbiter.key());
+
+        emit!(ArbiterRegistered {
+            arbiter: arbiter.key(),
+            arbiter_pool: pool.key(),
+            authority: arbiter.authority,
+            stake,
+        });
+
+        Ok(())
+    }
+
+    /// Add stake to an arbiter account, e.g. to recover from a slash
+    pub fn add_arbiter_stake(ctx: Context<AddArbiterStake>, amount: u64) -> Result<()> {
+        require!(amount > 0, SolAgentError::ZeroAmount);
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.arbiter.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let arbiter = &mut ctx.accounts.arbiter;
+        arbiter.stake += amount;
+
+        emit!(ArbiterStakeAdded {
+            arbiter: arbiter.key(),
+            amount,
+            stake: arbiter.stake,
+        });
+
+        Ok(())
+    }
+
+    /// Leave the pool and reclaim stake once no ruling is within its appeal window or under appeal
+    pub fn withdraw_arbiter(ctx: Context<WithdrawArbiter>) -> Result<()> {
+        let arbiter = &ctx.accounts.arbiter;
+        require!(
+            arbiter.open_cases == 0
+                && arbiter.open_appeals == 0
+                && Clock::get()?.unix_timestamp >= arbiter.locked_until,
+            SolAgentError::ArbiterStakeLocked
+        );
+
+        let key = arbiter.key();
+        ctx.accounts.arbiter_pool.arbiters.retain(|a| *a != key);
+
+        emit!(ArbiterWithdrawn {
+            arbiter: key,
+            arbiter_pool: arbiter.pool,
+            stake: arbiter.stake,
+        });
+
+        Ok(())
+    }
+
+    // ============================================================
+    // PROTOCOL STATS
+    // ============================================================
+
+    /// Initialize global protocol state
+    pub fn initialize_protocol(ctx: Context<InitializeProtocol>) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol;
+        protocol.authority = ctx.accounts.authority.key();
+        protocol.total_agents = 0;
+        protocol.total_services = 0;
+        protocol.total_payments = 0;
+        protocol.total_volume = 0;
+        protocol.total_staked = 0;
+        protocol.fee_bps = 10; // 0.1%
+        protocol.treasury = ctx.accounts.authority.key();
+        protocol.bump = ctx.bumps.protocol;
+
+        emit!(ProtocolInitialized {
+            authority: protocol.authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Add a category to the curated service taxonomy (protocol authority only)
+    pub fn create_category(
+        ctx: Context<CreateCategory>,
+        id: u16,
+        name: String,
+        description: String,
+    ) -> Result<()> {
+        require!(name.len() <= 32, SolAgentError::NameTooLong);
+        require!(description.len() <= 128, SolAgentError::DescriptionTooLong);
+
+        let category = &mut ctx.accounts.category;
+        category.id = id;
+        category.name = name;
+        category.description = description;
+        category.is_active = true;
+        category.created_at = Clock::get()?.unix_timestamp;
+        category.bump = ctx.bumps.category;
+
+        emit!(CategoryCreated {
+            category: category.key(),
+            id,
+            name: category.name.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Rename, redescribe or retire a category (protocol authority only)
+    /// Retired categories stay on services that already use them but can't be newly chosen
+    pub fn update_category(
+        ctx: Context<UpdateCategory>,
+        name: Option<String>,
+        description: Option<String>,
+        is_active: Option<bool>,
+    ) -> Result<()> {
+        let category = &mut ctx.accounts.category;
+        if let Some(name) = name {
+            require!(name.len() <= 32, SolAgentError::NameTooLong);
+            category.name = name;
+        }
+        if let Some(description) = description {
+            require!(description.len() <= 128, SolAgentError::DescriptionTooLong);
+            category.description = description;
+        }
+        if let Some(is_active) = is_active {
+            category.is_active = is_active;
+        }
+
+        emit!(CategoryUpdated {
+            category: category.key(),
+            id: category.id,
+            name: category.name.clone(),
+            is_active: category.is_active,
+        });
+
+        Ok(())
+    }
+}
+
+/// Pay a pending usage report out of the usage vault and record it on all parties
+#[allow(clippy::too_many_arguments)]
+fn settle_usage_report<'info>(
+    usage: &mut Account<'info, UsageEscrow>,
+    report: &mut Account<'info, UsageReport>,
+    payer_agent: &mut Account<'info, Agent>,
+    provider_agent: &mut Account<'info, Agent>,
+    service: &mut Account<'info, Service>,
+    usage_vault: &AccountInfo<'info>,
+    provider_authority: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let amount = report.amount;
+    let usage_key = usage.key();
+    transfer_from_vault(
+        usage_vault,
+        provider_authority,
+        system_program,
+        &[b"usage_vault", usage_key.as_ref(), &[usage.vault_bump]],
+        amount,
+    )?;
+
+    report.status = UsageReportStatus::Settled;
+
+    usage.pending -= amount;
+    usage.drawn += amount;
+    usage.units_billed += report.units;
+
+    payer_agent.total_spent += amount;
+    provider_agent.total_earned += amount;
+    service.total_revenue += amount;
+
+    emit!(UsageSettled {
+        usage_escrow: usage.key(),
+        report: report.key(),
+        units: report.units,
+        amount,
+    });
+
+    Ok(())
+}
+
+/// Refund the SLA penalty on `amount` for a late delivery to the payer's wallet
+/// Returns the refund and whether the SLA was met (`None` without an SLA)
+fn refund_sla_penalty<'info>(
+    payment: &Account<'info, Payment>,
+    amount: u64,
+    escrow: &AccountInfo<'info>,
+    payer_wallet: &AccountInfo<'info>,
+    payer_agent: &mut Account<'info, Agent>,
+    now: i64,
+) -> Result<(u64, Option<bool>)> {
+    let Some(sla) = payment.sla.as_ref() else {
+        return Ok((0, None));
+    };
+    let delivered_at = if payment.delivered_at > 0 {
+        payment.delivered_at
+    } else {
+        now
+    };
+    let late_seconds = sla.late_seconds(payment.created_at, payment.acknowledged_at, delivered_at);
+    if late_seconds <= 0 {
+        return Ok((0, Some(true)));
+    }
+
+    let refund = bps_share(amount, sla.penalty_bps(late_seconds));
+    if refund > 0 {
+        **escrow.try_borrow_mut_lamports()? -= refund;
+        **payer_wallet.try_borrow_mut_lamports()? += refund;
+        payer_agent.total_spent -= refund;
+    }
+    emit!(SlaPenaltyApplied {
+        payment: payment.key(),
+        late_seconds,
+        refunded: refund,
+    });
+    Ok((refund, Some(false)))
+}
+
+/// The message a payer (or its session key) signs to acknowledge delivery of `payment`
+/// Bound to this program's id so a receipt can't be replayed against another deployment
+pub fn delivery_receipt_message(payment: &Pubkey) -> Vec<u8> {
+    [DELIVERY_RECEIPT_PREFIX, crate::ID.as_ref(), payment.as_ref()].concat()
+}
+
+/// Check the instruction before this one verifies a delivery receipt for `payment`; returns its signer
+fn verify_delivery_receipt(instructions: &AccountInfo, payment: &Pubkey) -> Result<Pubkey> {
+    let current = load_current_index_checked(instructions)?;
+    require!(current > 0, SolAgentError::InvalidReceipt);
+    let ix = load_instruction_at_checked(current as usize - 1, instructions)?;
+    require_keys_eq!(ix.program_id, ed25519_program::ID, SolAgentError::InvalidReceipt);
+
+    let (signer, message) = parse_ed25519_instruction(&ix.data)?;
+    require!(
+        message == delivery_receipt_message(payment),
+        SolAgentError::InvalidReceipt
+    );
+    Ok(signer)
+}
+
+/// Read the public key and message of a single-signature Ed25519 sig-verify instruction
+/// Offsets must point into the instruction itself, so the precompile checked this exact data
+pub fn parse_ed25519_instruction(data: &[u8]) -> Result<(Pubkey, &[u8])> {
+    // num_signatures, padding, then signature / public key / message offsets (u16 each)
+    require!(data.len() >= 16 && data[0] == 1, SolAgentError::InvalidReceipt);
+    let read = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let (signature_ix, public_key_offset, public_key_ix) = (read(4), read(6), read(8));
+    let (message_offset, message_size, message_ix) = (read(10), read(12), read(14));
+    require!(
+        signature_ix == u16::MAX && public_key_ix == u16::MAX && message_ix == u16::MAX,
+        SolAgentError::InvalidReceipt
+    );
+
+    let public_key = data
+        .get(public_key_offset as usize..public_key_offset as usize + 32)
+        .ok_or(SolAgentError::InvalidReceipt)?;
+    let message = data
+        .get(message_offset as usize..message_offset as usize + message_size as usize)
+        .ok_or(SolAgentError::InvalidReceipt)?;
+    Ok((Pubkey::try_from(public_key).unwrap(), message))
+}
+
+/// Pay a referred order's referral share of `amount` out of escrow; returns the share
+fn pay_referral<'info>(
+    payment: &Account<'info, Payment>,
+    escrow: &AccountInfo<'info>,
+    referrer_agent: Option<&mut Account<'info, Agent>>,
+    referrer_authority: Option<&AccountInfo<'info>>,
+    amount: u64,
+) -> Result<u64> {
+    let Some(referrer) = payment.referrer else {
+        return Ok(0);
+    };
+    let (Some(referrer_agent), Some(referrer_authority)) = (referrer_agent, referrer_authority)
+    else {
+        return err!(SolAgentError::ReferrerMissing);
+    };
+    require!(
+        referrer_agent.key() == referrer && referrer_authority.key() == referrer_agent.authority,
+        SolAgentError::ReferrerMissing
+    );
+
+    let share = bps_share(amount, payment.referral_bps);
+    if share > 0 {
+        **escrow.try_borrow_mut_lamports()? -= share;
+        **referrer_authority.try_borrow_mut_lamports()? += share;
+    }
+    referrer_agent.referral_earned += share;
+
+    emit!(ReferralPaid {
+        payment: payment.key(),
+        referrer,
+        receiver: payment.receiver,
+        amount: share,
+    });
+    Ok(share)
+}
+
+/// Release the rest of an escrowed payment to its provider, applying SLA penalties and referrals
+/// Every full release (payer, receipt, timeout and conditional) goes through here
+#[allow(clippy::too_many_arguments)]
+fn release_to_provider<'info>(
+    payment: &mut Account<'info, Payment>,
+    escrow: &AccountInfo<'info>,
+    payer_agent: &mut Account<'info, Agent>,
+    payer_authority: &AccountInfo<'info>,
+    receiver: &mut Account<'info, Agent>,
+    receiver_authority: &AccountInfo<'info>,
+    service: &mut Account<'info, Service>,
+    referrer_agent: Option<&mut Account<'info, Agent>>,
+    referrer_authority: Option<&AccountInfo<'info>>,
+    now: i64,
+) -> Result<()> {
+    let amount = payment.remaining();
+    let (sla_refund, sla_met) =
+        refund_sla_penalty(payment, amount, escrow, payer_authority, payer_agent, now)?;
+    let amount = amount - sla_refund;
+    let referral_share = pay_referral(payment, escrow, referrer_agent, referrer_authority, amount)?;
+    let receiver_amount = amount - referral_share;
+    **escrow.try_borrow_mut_lamports()? -= receiver_amount;
+    **receiver_authority.try_borrow_mut_lamports()? += receiver_amount;
+
+    payment.status = PaymentStatus::Released;
+    payment.completed_at = now;
+    payment.released_amount = payment.amount;
+    for milestone in payment.milestones.iter_mut().filter(|m| m.released_at == 0) {
+        milestone.released_at = now;
+    }
+
+    receiver.services_completed += 1;
+    receiver.total_earned += receiver_amount;
+    receiver.referral_fees_paid += referral_share;
+    service.total_revenue += amount;
+    service.open_orders = service.open_orders.saturating_sub(1);
+    if let Some(met) = sla_met {
+        receiver.sla_orders += 1;
+        service.sla_orders += 1;
+        if met {
+            receiver.sla_met += 1;
+            service.sla_met += 1;
+        }
+    }
+
+    emit!(PaymentReleased {
+        payment: payment.key(),
+        receiver: payment.receiver,
+        amount,
+        latency_ms: ((payment.completed_at - payment.created_at) * 1000) as u64,
+    });
+
+    Ok(())
+}
+
+/// Evaluate a payment's conditions: `Some(false)` once a deadline has passed or a price check
+/// at `at` failed, otherwise `Some(true)` once all hold and `None` while pending
+pub fn evaluate_conditions<'info>(
+    payment: &Payment,
+    witnesses: &'info [AccountInfo<'info>],
+    preimages: &[Vec<u8>],
+    now: i64,
+) -> Result<Option<bool>> {
+    let mut witnesses = witnesses.iter();
+    let mut preimages = preimages.iter();
+    let mut pending = false;
+    let mut failed = false;
+
+    for condition in &payment.conditions {
+        let met = match condition {
+            PaymentCondition::Deadline { at } => {
+                failed |= now > *at;
+                true
+            }
+            PaymentCondition::OraclePrice {
+                feed_id,
+                price,
+                exponent,
+                above,
+                at,
+                max_age_seconds,
+                max_conf_bps,
+            } => {
+                let feed = witnesses.next().ok_or(SolAgentError::ConditionWitnessMissing)?;
+                let (update_feed_id, update) = read_price_update(feed)?;
+                require!(
+                    update_feed_id == *feed_id && update.exponent == *exponent,
+                    SolAgentError::InvalidOracle
+                );
+                require!(
+                    update.confidence_within(*max_conf_bps),
+                    SolAgentError::OracleConfidenceTooWide
+                );
+                let crossed = if *above {
+                    update.price > *price
+                } else {
+                    update.price < *price
+                };
+
+                if *at == 0 {
+                    // Holds as soon as a fresh price crosses the threshold
+                    require!(
+                        now - update.publish_time <= *max_age_seconds as i64,
+                        SolAgentError::StaleOracle
+                    );
+                    crossed
+                } else if update.publish_time < *at {
+                    false
+                } else {
+                    // Decided by the price published at (or just after) `at`
+                    require!(
+                        update.publish_time - at <= *max_age_seconds as i64,
+                        SolAgentError::StaleOracle
+                    );
+                    failed |= !crossed;
+                    crossed
+                }
+            }
+            PaymentCondition::HashPreimage { hash } => preimages
+                .next()
+                .is_some_and(|preimage| hashv(&[preimage]).to_bytes() == *hash),
+            PaymentCondition::ValidatorSignature { validator } => {
+                let signer = witnesses.next().ok_or(SolAgentError::ConditionWitnessMissing)?;
+                require_keys_eq!(signer.key(), *validator, SolAgentError::ConditionWitnessMismatch);
+                signer.is_signer
+            }
+            PaymentCondition::AttestationPresent { issuer } => {
+                let info = witnesses.next().ok_or(SolAgentError::ConditionWitnessMissing)?;
+                let attestation = Account::<Attestation>::try_from(info)?;
+                require!(
+                    attestation.issuer == *issuer && attestation.subject == payment.receiver,
+                    SolAgentError::ConditionWitnessMismatch
+                );
+                attestation.is_valid_at(now)
+            }
+            PaymentCondition::NotBefore { at } => now >= *at,
+        };
+        pending |= !met;
+    }
+
+    Ok(match (pending, failed) {
+        (_, true) => Some(false),
+        (false, false) => Some(true),
+        (true, false) => None,
+    })
+}
+
+/// Read the feed id and price from a fully verified Pyth `PriceUpdateV2` account
+fn read_price_update(info: &AccountInfo) -> Result<([u8; 32], PythPrice)> {
+    require_keys_eq!(*info.owner, PYTH_RECEIVER_ID, SolAgentError::InvalidOracle);
+    let data = info.try_borrow_data()?;
+    parse_price_update(&data)
+}
+
+/// Parse a `PriceUpdateV2`: discriminator, write authority, verification level, price message
+/// Partially verified updates (`Partial { num_signatures }`, tag 0) are rejected
+pub fn parse_price_update(data: &[u8]) -> Result<([u8; 32], PythPrice)> {
+    require!(
+        data.get(..8) == Some(&PRICE_UPDATE_V2_DISCRIMINATOR[..]) && data.get(40) == Some(&1),
+        SolAgentError::InvalidOracle
+    );
+    // PriceFeedMessage: feed_id, price, conf, exponent, publish_time, ...
+    let message = data.get(41..101).ok_or(SolAgentError::InvalidOracle)?;
+    let feed_id: [u8; 32] = message[..32].try_into().unwrap();
+    Ok((
+        feed_id,
+        PythPrice {
+            price: i64::from_le_bytes(message[32..40].try_into().unwrap()),
+            conf: u64::from_le_bytes(message[40..48].try_into().unwrap()),
+            exponent: i32::from_le_bytes(message[48..52].try_into().unwrap()),
+            publish_time: i64::from_le_bytes(message[52..60].try_into().unwrap()),
+        },
+    ))
+}
+
+/// Share-weighted average of the components' service ratings and provider reputations
+/// `accounts` holds each component's `Service` followed by its provider `Agent`
+fn component_ratings<'info>(
+    components: &[BundleComponent],
+    accounts: &'info [AccountInfo<'info>],
+    require_active: bool,
+) -> Result<(u8, u64)> {
+    require!(
+        accounts.len() == components.len() * 2,
+        SolAgentError::BundleComponentMismatch
+    );
+
+    let mut weight: u128 = 0;
+    let mut rating: u128 = 0;
+    let mut reputation: u128 = 0;
+    for (component, pair) in components.iter().zip(accounts.chunks(2)) {
+        let service = Account::<Service>::try_from(&pair[0])?;
+        let provider = Account::<Agent>::try_from(&pair[1])?;
+        require!(
+            service.key() == component.service
+                && service.provider == component.provider
+                && provider.key() == component.provider,
+            SolAgentError::BundleComponentMismatch
+        );
+        if require_active {
+            require!(service.is_active, SolAgentError::ServiceInactive);
+        }
+
+        let share = component.share_bps as u128;
+        weight += share;
+        rating += service.avg_rating as u128 * share;
+        reputation += provider.reputation_score as u128 * share;
+    }
+    if weight == 0 {
+        return Ok((0, 0));
+    }
+    Ok(((rating / weight) as u8, (reputation / weight) as u64))
+}
+
+/// Count a resolved dispute towards an agent's outcome stats
+fn record_dispute_outcome(agent: &mut Agent, awarded: u64, amount: u64) {
+    agent.disputes += 1;
+    if awarded == amount {
+        agent.disputes_won += 1;
+    } else if awarded == 0 {
+        agent.disputes_lost += 1;
+    }
+}
+
+/// Pay a dispute's final split out of escrow and settle the payment
+/// Fee shares of panel members who never ruled go back to the payer. The receiver's award is
+/// paid like a release: SLA penalty back to the payer, then the referral share, then (for
+/// bundles) each component's share, with the rest to the receiver
+/// `component_accounts` holds each bundle component's provider `Agent` then its authority wallet
+#[allow(clippy::too_many_arguments)]
+fn pay_out_dispute<'info>(
+    dispute: &mut Account<'info, Dispute>,
+    payment: &mut Account<'info, Payment>,
+    payer_agent: &mut Account<'info, Agent>,
+    receiver_agent: &mut Account<'info, Agent>,
+    escrow: &AccountInfo<'info>,
+    payer_authority: &AccountInfo<'info>,
+    receiver_authority: &AccountInfo<'info>,
+    referrer_agent: Option<&mut Account<'info, Agent>>,
+    referrer_authority: Option<&AccountInfo<'info>>,
+    listing: &AccountInfo<'info>,
+    component_accounts: &'info [AccountInfo<'info>],
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let mut awarded = dispute.receiver_amount;
+    let (mut referral_share, mut components_share) = (0, 0);
+    if awarded > 0 {
+        let (sla_refund, _) =
+            refund_sla_penalty(payment, awarded, escrow, payer_authority, payer_agent, now)?;
+        awarded -= sla_refund;
+        referral_share = pay_referral(payment, escrow, referrer_agent, referrer_authority, awarded)?;
+        if let Some(components) = listing_bundle_components(listing)? {
+            components_share =
+                pay_bundle_components(&components, component_accounts, escrow, awarded)?;
+        }
+    }
+
+    let payer_amount = dispute.payer_amount + dispute.fee - dispute.fee_paid;
+    let receiver_amount = awarded - referral_share - components_share;
+    **escrow.try_borrow_mut_lamports()? -= payer_amount + receiver_amount;
+    **payer_authority.try_borrow_mut_lamports()? += payer_amount;
+    **receiver_authority.try_borrow_mut_lamports()? += receiver_amount;
+
+    payment.status = if awarded > 0 {
+        PaymentStatus::Released
+    } else {
+        PaymentStatus::Refunded
+    };
+    payment.completed_at = now;
+    dispute.status = DisputeStatus::Resolved;
+    dispute.resolved_at = now;
+    release_order_slot(listing, awarded)?;
+
+    // Any SLA penalty counts towards the payer's award
+    let payer_awarded = dispute.amount - dispute.fee_paid - awarded;
+    let total = payer_awarded + awarded;
+    payer_agent.total_spent = payer_agent.total_spent.saturating_sub(payer_amount);
+    record_dispute_outcome(payer_agent, payer_awarded, total);
+    receiver_agent.total_earned += receiver_amount;
+    receiver_agent.referral_fees_paid += referral_share;
+    if awarded > 0 {
+        receiver_agent.services_completed += 1;
+    }
+    record_dispute_outcome(receiver_agent, awarded, total);
+
+    emit!(DisputeResolved {
+        dispute: dispute.key(),
+        payment: payment.key(),
+        payer_amount: payer_awarded,
+        receiver_amount: awarded,
+        appealed: dispute.appealed_by.is_some(),
+    });
+
+    Ok(())
+}
+
+/// Pay each bundle component its share of `amount` out of escrow; returns the total paid
+/// `accounts` holds each component's provider `Agent` then its authority wallet, in order
+fn pay_bundle_components<'info>(
+    components: &[BundleComponent],
+    accounts: &'info [AccountInfo<'info>],
+    escrow: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<u64> {
+    require!(
+        accounts.len() == components.len() * 2,
+        SolAgentError::BundleComponentMismatch
+    );
+
+    let mut paid_out = 0;
+    for (component, accounts) in components.iter().zip(accounts.chunks(2)) {
+        let mut provider = Account::<Agent>::try_from(&accounts[0])?;
+        require_keys_eq!(
+            provider.key(),
+            component.provider,
+            SolAgentError::BundleComponentMismatch
+        );
+        require_keys_eq!(
+            accounts[1].key(),
+            provider.authority,
+            SolAgentError::BundleComponentMismatch
+        );
+
+        let share = bps_share(amount, component.share_bps);
+        **escrow.try_borrow_mut_lamports()? -= share;
+        **accounts[1].try_borrow_mut_lamports()? += share;
+        provider.total_earned += share;
+        provider.services_completed += 1;
+        provider.exit(&crate::ID)?;
+        paid_out += share;
+    }
+    Ok(paid_out)
+}
+
+/// The components of a `Bundle` listing; `None` for a `Service`
+fn listing_bundle_components(listing: &AccountInfo) -> Result<Option<Vec<BundleComponent>>> {
+    require_keys_eq!(*listing.owner, crate::ID, SolAgentError::ServiceMismatch);
+    let data = listing.try_borrow_data()?;
+    Ok(Bundle::try_deserialize(&mut &data[..])
+        .ok()
+        .map(|bundle| bundle.components))
+}
+
+/// The arbiter pool a `Service` routes disputes to; bundles always use the protocol authority
+fn listing_arbiter_pool(listing: &AccountInfo) -> Result<Option<Pubkey>> {
+    require_keys_eq!(*listing.owner, crate::ID, SolAgentError::ServiceMismatch);
+    let data = listing.try_borrow_data()?;
+    Ok(Service::try_deserialize(&mut &data[..])
+        .ok()
+        .and_then(|service| service.arbiter_pool))
+}
+
+/// Hash of the first slot at or after `target` in SlotHashes, `None` once `target` has aged out
+fn committed_slot_hash(slot_hashes: &AccountInfo, target: u64) -> Result<Option<[u8; 32]>> {
+    // SlotHashes layout: u64 entry count, then (slot: u64, hash: [u8; 32]) entries, newest first
+    let data = slot_hashes.try_borrow_data()?;
+    let count = data
+        .get(..8)
+        .map_or(0, |count| u64::from_le_bytes(count.try_into().unwrap()) as usize);
+    let mut hash = None;
+    for entry in data[8..].chunks_exact(40).take(count) {
+        let slot = u64::from_le_bytes(entry[..8].try_into().unwrap());
+        if slot < target {
+            // Skipped slots leave gaps; the first hash after `target` stands in for it
+            return hash.map(Some).ok_or(error!(SolAgentError::PanelDrawPending));
+        }
+        hash = Some(entry[8..].try_into().unwrap());
+        if slot == target {
+            return Ok(hash);
+        }
+    }
+    Ok(None)
+}
+
+/// Pick `size` distinct arbiters by a partial Fisher-Yates shuffle keyed on `seed`
+pub fn draw_panel(arbiters: &[Pubkey], size: usize, seed: [u8; 32]) -> Vec<Pubkey> {
+    let mut candidates = arbiters.to_vec();
+    (0..size)
+        .map(|i| {
+            let roll = hashv(&[&seed, &[i as u8]]).to_bytes();
+            let offset = u64::from_le_bytes(roll[..8].try_into().unwrap())
+                % (candidates.len() - i) as u64;
+            candidates.swap(i, i + offset as usize);
+            candidates[i]
+        })
+        .collect()
+}
+
+/// Move `slash_bps` of an arbiter's stake to the treasury
+fn slash_arbiter<'info>(
+    arbiter: &mut Account<'info, Arbiter>,
+    treasury: &AccountInfo<'info>,
+    dispute: Pubkey,
+    slash_bps: u16,
+) -> Result<()> {
+    let slashed = bps_share(arbiter.stake, slash_bps);
+    **arbiter.to_account_info().try_borrow_mut_lamports()? -= slashed;
+    **treasury.try_borrow_mut_lamports()? += slashed;
+    arbiter.stake -= slashed;
+    arbiter.total_slashed += slashed;
+
+    emit!(ArbiterSlashed {
+        arbiter: arbiter.key(),
+        dispute,
+        amount: slashed,
+        remaining_stake: arbiter.stake,
+    });
+    Ok(())
+}
+
+/// Median of the payer amounts voted so far (lower median for an even count)
+pub fn median_ruling(votes: &[ArbiterVote]) -> u64 {
+    let mut amounts: Vec<u64> = votes.iter().map(|vote| vote.payer_amount).collect();
+    amounts.sort_unstable();
+    amounts[(amounts.len() - 1) / 2]
+}
+
+/// Free the open-order slot a payment held on its `Service` or `Bundle` and credit it `revenue`
+fn release_order_slot(listing: &AccountInfo, revenue: u64) -> Result<()> {
+    require_keys_eq!(*listing.owner, crate::ID, SolAgentError::ServiceMismatch);
+    let mut data = listing.try_borrow_mut_data()?;
+    if let Ok(mut service) = Service::try_deserialize(&mut &data[..]) {
+        service.open_orders = service.open_orders.saturating_sub(1);
+        service.total_revenue += revenue;
+        service.try_serialize(&mut &mut data[..])?;
+    } else {
+        let mut bundle = Bundle::try_deserialize(&mut &data[..])?;
+        bundle.open_orders = bundle.open_orders.saturating_sub(1);
+        bundle.total_revenue += revenue;
+        bundle.try_serialize(&mut &mut data[..])?;
+    }
+    Ok(())
+}
+
+/// Drop a closed payment from its `Service` or `Bundle`'s count of payment accounts
+fn release_payment_record(listing: &AccountInfo) -> Result<()> {
+    require_keys_eq!(*listing.owner, crate::ID, SolAgentError::ServiceMismatch);
+    let mut data = listing.try_borrow_mut_data()?;
+    if let Ok(mut service) = Service::try_deserialize(&mut &data[..]) {
+        service.unclosed_payments = service.unclosed_payments.saturating_sub(1);
+        service.try_serialize(&mut &mut data[..])?;
+    } else {
+        let mut bundle = Bundle::try_deserialize(&mut &data[..])?;
+        bundle.unclosed_payments = bundle.unclosed_payments.saturating_sub(1);
+        bundle.try_serialize(&mut &mut data[..])?;
+    }
+    Ok(())
+}
+
+/// Normalise tags or capabilities into discovery index keys (trimmed, lowercase, deduplicated)
+/// Blank values are left out of the indexes rather than rejected
+pub fn index_keys(values: &[String]) -> Result<Vec<String>> {
+    let mut keys: Vec<String> = Vec::with_capacity(values.len());
+    for value in values {
+        let key = value.trim().to_ascii_lowercase();
+        if key.is_empty() {
+            continue;
+        }
+        require!(key.len() <= MAX_INDEX_KEY_LEN, SolAgentError::InvalidIndexKey);
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    Ok(keys)
+}
+
+/// Move `entry` between discovery indexes as its keys change from `old` to `new`
+/// `indexes` holds the index accounts for removed keys, then for added keys
+fn reindex<'info>(
+    indexes: &'info [AccountInfo<'info>],
+    kind: IndexKind,
+    entry: Pubkey,
+    old: &[String],
+    new: &[String],
+    authority: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let removed: Vec<&String> = old.iter().filter(|k| !new.contains(k)).collect();
+    let added: Vec<&String> = new.iter().filter(|k| !old.contains(k)).collect();
+    require!(
+        indexes.len() == removed.len() + added.len(),
+        SolAgentError::IndexAccountMismatch
+    );
+
+    let (removed_indexes, added_indexes) = indexes.split_at(removed.len());
+    for (index, key) in removed_indexes.iter().zip(removed) {
+        remove_from_index(index, kind, key, entry, authority)?;
+    }
+    for (index, key) in added_indexes.iter().zip(added) {
+        add_to_index(index, kind, key, entry, authority, system_program)?;
+    }
+    Ok(())
+}
+
+/// Grow a program account to `space` bytes, zero-filled, with `payer` covering the extra rent
+fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    space: usize,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let top_up = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(account.lamports());
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    account.realloc(space, true)?;
+    Ok(())
+}
+
+/// Move lamports out of a system-owned vault PDA with a system transfer signed by its `seeds`
+/// The program doesn't own these vaults, so it can't debit them directly
+fn transfer_from_vault<'info>(
+    vault: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+    amount: u64,
+) -> Result<()> {
+    system_program::transfer(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            system_program::Transfer {
+                from: vault.clone(),
+                to: to.clone(),
+            },
+            &[seeds],
+        ),
+        amount,
+    )
+}
+
+/// Check `index` is the entry PDA for `kind`/`key`/`entry` and return its bump
+fn index_bump(index: &AccountInfo, kind: IndexKind, key: &str, entry: &Pubkey) -> Result<u8> {
+    let (expected, bump) = Pubkey::find_program_address(
+        &[kind.seed(), key.as_bytes(), entry.as_ref()],
+        &crate::ID,
+    );
+    require_keys_eq!(index.key(), expected, SolAgentError::IndexAccountMismatch);
+    Ok(bump)
+}
+
+/// Create the index entry listing `entry` under `key`; `payer` covers its rent
+fn add_to_index<'info>(
+    index: &AccountInfo<'info>,
+    kind: IndexKind,
+    key: &str,
+    entry: Pubkey,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let bump = index_bump(index, kind, key, &entry)?;
+    let space = 8 + IndexEntry::INIT_SPACE;
+    let signer_seeds: &[&[u8]] = &[kind.seed(), key.as_bytes(), entry.as_ref(), &[bump]];
+    let rent = Rent::get()?.minimum_balance(space);
+    if index.lamports() == 0 {
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: index.clone(),
+                },
+                &[signer_seeds],
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        )?;
+    } else {
+        // Anyone can send lamports to the PDA first, which would make `create_account` fail, so
+        // do what Anchor's `init` does: top up to rent, then allocate and assign it ourselves
+        let top_up = rent.saturating_sub(index.lamports());
+        if top_up > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.clone(),
+                    system_program::Transfer {
+                        from: payer.clone(),
+                        to: index.clone(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+        system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::Allocate {
+                    account_to_allocate: index.clone(),
+                },
+                &[signer_seeds],
+            ),
+            space as u64,
+        )?;
+        system_program::assign(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::Assign {
+                    account_to_assign: index.clone(),
+                },
+                &[signer_seeds],
+            ),
+            &crate::ID,
+        )?;
+    }
+
+    IndexEntry {
+        kind,
+        key: key.to_string(),
+        entry,
+        bump,
+    }
+    .try_serialize(&mut &mut index.try_borrow_mut_data()?[..])
+}
+
+/// Close the index entry listing `entry` under `key`, refunding its rent to `receiver`
+fn remove_from_index<'info>(
+    index: &AccountInfo<'info>,
+    kind: IndexKind,
+    key: &str,
+    entry: Pubkey,
+    receiver: &AccountInfo<'info>,
+) -> Result<()> {
+    index_bump(index, kind, key, &entry)?;
+    // Listings created before indexing existed have nothing to remove
+    if index.owner == &system_program::ID {
+        return Ok(());
+    }
+
+    let lamports = index.lamports();
+    **index.try_borrow_mut_lamports()? -= lamports;
+    **receiver.try_borrow_mut_lamports()? += lamports;
+    index.assign(&system_program::ID);
+    index.realloc(0, false).map_err(Into::into)
+}
+
+/// Bump the service version and log the change if `old_terms` no longer match
+fn record_terms_change(service: &mut Account<Service>, old_terms: ServiceTerms) -> Result<()> {
+    let new_terms = service.terms();
+    if new_terms == old_terms {
+        return Ok(());
+    }
+    service.version += 1;
+
+    emit!(ServiceUpdated {
+        service: service.key(),
+        version: service.version,
+        old_terms,
+        new_terms,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Charge one subscription period from the vault, or mark the subscription lapsed
+#[allow(clippy::too_many_arguments)]
+fn charge_subscription_period<'info>(
+    subscription: &mut Account<'info, Subscription>,
+    subscriber_agent: &mut Account<'info, Agent>,
+    provider_agent: &mut Account<'info, Agent>,
+    service: &mut Account<'info, Service>,
+    subscription_vault: &AccountInfo<'info>,
+    provider_authority: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    now: i64,
+) -> Result<()> {
+    let amount = subscription.amount_per_period;
+    if subscription.balance() < amount {
+        subscription.status = SubscriptionStatus::Lapsed;
+        emit!(SubscriptionLapsed {
+            subscription: subscription.key(),
+            subscriber: subscription.subscriber,
+            balance: subscription.balance(),
+        });
+        return Ok(());
+    }
+
+    let subscription_key = subscription.key();
+    transfer_from_vault(
+        subscription_vault,
+        provider_authority,
+        system_program,
+        &[b"subscription_vault", subscription_key.as_ref(), &[subscription.vault_bump]],
+        amount,
+    )?;
+
+    // A period missed while unpaid is not billed retroactively
+    subscription.paid_until = subscription.paid_until.max(now) + subscription.period_seconds;
+    subscription.charged += amount;
+    subscription.periods_charged += 1;
+
+    subscriber_agent.total_spent += amount;
+    provider_agent.total_earned += amount;
+    service.total_revenue += amount;
+
+    emit!(SubscriptionCharged {
+        subscription: subscription.key(),
+        amount,
+        period: subscription.periods_charged,
+        paid_until: subscription.paid_until,
+    });
+
+    Ok(())
+}
+
+// ============================================================
+// ACCOUNTS
+// ============================================================
+
+#[derive(Accounts)]
+pub struct InitializeProtocol<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Protocol::INIT_SPACE,
+        seeds = [b"protocol"],
+        bump
+    )]
+    pub protocol: Account<'info, Protocol>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(id: u16)]
+pub struct CreateCategory<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Category::INIT_SPACE,
+        seeds = [b"category", id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub category: Account<'info, Category>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol.bump,
+        has_one = authority @ SolAgentError::Unauthorized
+    )]
+    pub protocol: Account<'info, Protocol>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateCategory<'info> {
+    #[account(mut)]
+    pub category: Account<'info, Category>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol.bump,
+        has_one = authority @ SolAgentError::Unauthorized
+    )]
+    pub protocol: Account<'info, Protocol>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct RegisterAgent<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Agent::INIT_SPACE,
+        seeds = [b"agent", authority.key().as_ref()],
+        bump
+    )]
+    pub agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateCapabilities<'info> {
+    #[account(
+        mut,
+        seeds = [b"agent", authority.key().as_ref()],
+        bump = agent.bump,
+        has_one = authority @ SolAgentError::Unauthorized
+    )]
+    pub agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAgent<'info> {
+    /// CHECK: an `Agent` in the launch layout that `Account` can't decode; the seeds tie it to
+    /// `authority` and the discriminator is checked in `migrate_agent`
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"agent", authority.key().as_ref()],
+        bump
+    )]
+    pub agent: AccountInfo<'info>,
+    /// Pays for the added space
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StakeReputation<'info> {
+    #[account(
+        mut,
+        seeds = [b"agent", authority.key().as_ref()],
+        bump = agent.bump,
+        has_one = authority
+    )]
+    pub agent: Account<'info, Agent>,
+    /// CHECK: vault PDA to hold staked SOL
+    #[account(
+        mut,
+        seeds = [b"vault", agent.key().as_ref()],
+        bump
+    )]
+    pub vault: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitFeedback<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Feedback::INIT_SPACE,
+        seeds = [b"feedback", from_agent.key().as_ref(), to_agent.key().as_ref()],
+        bump
+    )]
+    pub feedback: Account<'info, Feedback>,
+    pub from_agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub to_agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct IssueAttestation<'info> {
+    #[account(
+        init,
+        payer = issuer,
+        space = 8 + Attestation::INIT_SPACE,
+        seeds = [b"attestation", issuer.key().as_ref(), subject_agent.key().as_ref()],
+        bump
+    )]
+    pub attestation: Account<'info, Attestation>,
+    pub subject_agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeAttestation<'info> {
+    #[account(
+        mut,
+        has_one = issuer @ SolAgentError::Unauthorized,
+        close = issuer
+    )]
+    pub attestation: Account<'info, Attestation>,
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(key: Pubkey)]
+pub struct CreateSessionKey<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + SessionKey::INIT_SPACE,
+        seeds = [b"session_key", agent.key().as_ref(), key.as_ref()],
+        bump
+    )]
+    pub session_key: Account<'info, SessionKey>,
+    #[account(has_one = authority @ SolAgentError::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSessionKey<'info> {
+    #[account(
+        mut,
+        has_one = agent @ SolAgentError::Unauthorized,
+        close = authority
+    )]
+    pub session_key: Account<'info, SessionKey>,
+    #[account(has_one = authority @ SolAgentError::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(service_id: String)]
+pub struct CreateService<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Service::INIT_SPACE,
+        seeds = [b"service", agent.key().as_ref(), service_id.as_bytes()],
+        bump
+    )]
+    pub service: Account<'info, Service>,
+    #[account(
+        seeds = [b"agent", authority.key().as_ref()],
+        bump = agent.bump,
+        has_one = authority
+    )]
+    pub agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateService<'info> {
+    #[account(mut, has_one = authority @ SolAgentError::Unauthorized)]
+    pub service: Account<'info, Service>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateService<'info> {
+    /// CHECK: a `Service` in an older layout that `Account` can't decode; discriminator and
+    /// authority are checked in `migrate_service`
+    #[account(mut, owner = crate::ID @ SolAgentError::ServiceMismatch)]
+    pub service: AccountInfo<'info>,
+    /// Pays for the added space
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetArbiterPool<'info> {
+    #[account(mut, has_one = authority @ SolAgentError::Unauthorized)]
+    pub service: Account<'info, Service>,
+    pub arbiter_pool: Option<Account<'info, ArbiterPool>>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateServiceListing<'info> {
+    #[account(mut, has_one = authority @ SolAgentError::Unauthorized)]
+    pub service: Account<'info, Service>,
+    /// Pays for (or is refunded) tag index space when tags change
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetServiceStatus<'info> {
+    #[account(mut, has_one = authority @ SolAgentError::Unauthorized)]
+    pub service: Account<'info, Service>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseService<'info> {
+    #[account(
+        mut,
+        has_one = authority @ SolAgentError::Unauthorized,
+        close = authority
+    )]
+    pub service: Account<'info, Service>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(code: String)]
+pub struct CreateCoupon<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Coupon::INIT_SPACE,
+        seeds = [b"coupon", service.key().as_ref(), code.as_bytes()],
+        bump
+    )]
+    pub coupon: Account<'info, Coupon>,
+    #[account(has_one = authority @ SolAgentError::Unauthorized)]
+    pub service: Account<'info, Service>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseCoupon<'info> {
+    #[account(
+        mut,
+        has_one = authority @ SolAgentError::Unauthorized,
+        close = authority
+    )]
+    pub coupon: Account<'info, Coupon>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PayForService<'info> {
+    #[account(
+        init,
+        payer = payer_authority,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [b"payment", payer_agent.key().as_ref(), service.key().as_ref(), &service.total_orders.to_le_bytes()],
+        bump
+    )]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA to hold payment
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"agent", payer_authority.key().as_ref()],
+        bump = payer_agent.bump
+    )]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(address = service.provider @ SolAgentError::ProviderMismatch)]
+    pub receiver_agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub service: Account<'info, Service>,
+    #[account(mut, has_one = service @ SolAgentError::ServiceMismatch)]
+    pub coupon: Option<Account<'info, Coupon>>,
+    pub referrer_agent: Option<Account<'info, Agent>>,
+    #[account(
+        constraint = buyer_attestation.subject == payer_agent.key() @ SolAgentError::AttestationInvalid
+    )]
+    pub buyer_attestation: Option<Account<'info, Attestation>>,
+    #[account(mut)]
+    pub payer_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleasePayment<'info> {
+    #[account(
+        mut,
+        constraint = payment.payer == payer_agent.key() @ SolAgentError::Unauthorized,
+    )]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump = payment.escrow_bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, has_one = authority @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut, address = payment.receiver @ SolAgentError::ProviderMismatch)]
+    pub receiver_agent: Account<'info, Agent>,
+    #[account(
+        mut,
+        constraint = payment.service == service.key() @ SolAgentError::ServiceMismatch,
+    )]
+    pub service: Account<'info, Service>,
+    /// CHECK: receiver wallet, checked against `receiver_agent.authority`
+    #[account(mut, address = receiver_agent.authority @ SolAgentError::ProviderMismatch)]
+    pub receiver_authority: AccountInfo<'info>,
+    #[account(mut)]
+    pub referrer_agent: Option<Account<'info, Agent>>,
+    /// CHECK: referrer wallet, checked against `referrer_agent.authority`
+    #[account(mut)]
+    pub referrer_authority: Option<AccountInfo<'info>>,
+    /// Payer wallet; receives any SLA penalty refund
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettlePayment<'info> {
+    #[account(mut, close = payer_authority)]
+    pub payment: Account<'info, Payment>,
+    /// The payment's open dispute; required when settling a disputed payment
+    #[account(mut, has_one = payment @ SolAgentError::Unauthorized)]
+    pub dispute: Option<Account<'info, Dispute>>,
+    /// CHECK: escrow PDA
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump = payment.escrow_bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, address = payment.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut, address = payment.receiver @ SolAgentError::ProviderMismatch)]
+    pub receiver_agent: Account<'info, Agent>,
+    /// CHECK: the `Service` or `Bundle` the payment was for; deserialized in `release_order_slot`
+    #[account(mut, address = payment.service @ SolAgentError::ServiceMismatch)]
+    pub listing: AccountInfo<'info>,
+    #[account(mut)]
+    pub referrer_agent: Option<Account<'info, Agent>>,
+    /// CHECK: referrer wallet, checked against `referrer_agent.authority`
+    #[account(mut)]
+    pub referrer_authority: Option<AccountInfo<'info>>,
+    #[account(mut, address = payer_agent.authority @ SolAgentError::Unauthorized)]
+    pub payer_authority: Signer<'info>,
+    #[account(mut, address = receiver_agent.authority @ SolAgentError::ProviderMismatch)]
+    pub receiver_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProviderRelease<'info> {
+    #[account(mut)]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump = payment.escrow_bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, address = payment.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut, address = payment.receiver @ SolAgentError::ProviderMismatch)]
+    pub receiver_agent: Account<'info, Agent>,
+    #[account(mut, address = payment.service @ SolAgentError::ServiceMismatch)]
+    pub service: Account<'info, Service>,
+    /// CHECK: payer wallet, checked against `payer_agent.authority`; receives any SLA penalty refund
+    #[account(mut, address = payer_agent.authority @ SolAgentError::Unauthorized)]
+    pub payer_authority: AccountInfo<'info>,
+    #[account(mut)]
+    pub referrer_agent: Option<Account<'info, Agent>>,
+    /// CHECK: referrer wallet, checked against `referrer_agent.authority`
+    #[account(mut)]
+    pub referrer_authority: Option<AccountInfo<'info>>,
+    /// Payer session key that signed the receipt, if not the payer authority itself
+    pub session_key: Option<Account<'info, SessionKey>>,
+    /// CHECK: instructions sysvar, read for `release_with_receipt`'s Ed25519 sig-verify instruction
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: AccountInfo<'info>,
+    #[account(mut, address = receiver_agent.authority @ SolAgentError::ProviderMismatch)]
+    pub receiver_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteConditionalRelease<'info> {
+    #[account(mut)]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump = payment.escrow_bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, address = payment.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut, address = payment.receiver @ SolAgentError::ProviderMismatch)]
+    pub receiver_agent: Account<'info, Agent>,
+    #[account(mut, address = payment.service @ SolAgentError::ServiceMismatch)]
+    pub service: Account<'info, Service>,
+    /// CHECK: payer wallet, checked against `payer_agent.authority`; receives refunds and SLA penalties
+    #[account(mut, address = payer_agent.authority @ SolAgentError::Unauthorized)]
+    pub payer_authority: AccountInfo<'info>,
+    /// CHECK: receiver wallet, checked against `receiver_agent.authority`
+    #[account(mut, address = receiver_agent.authority @ SolAgentError::ProviderMismatch)]
+    pub receiver_authority: AccountInfo<'info>,
+    #[account(mut)]
+    pub referrer_agent: Option<Account<'info, Agent>>,
+    /// CHECK: referrer wallet, checked against `referrer_agent.authority`
+    #[account(mut)]
+    pub referrer_authority: Option<AccountInfo<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseOnTimeout<'info> {
+    #[account(mut)]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump = payment.escrow_bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, address = payment.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut, address = payment.receiver @ SolAgentError::ProviderMismatch)]
+    pub receiver_agent: Account<'info, Agent>,
+    #[account(mut, address = payment.service @ SolAgentError::ServiceMismatch)]
+    pub service: Account<'info, Service>,
+    /// CHECK: payer wallet, checked against `payer_agent.authority`; receives any SLA penalty refund
+    #[account(mut, address = payer_agent.authority @ SolAgentError::Unauthorized)]
+    pub payer_authority: AccountInfo<'info>,
+    #[account(mut)]
+    pub referrer_agent: Option<Account<'info, Agent>>,
+    /// CHECK: referrer wallet, checked against `referrer_agent.authority`
+    #[account(mut)]
+    pub referrer_authority: Option<AccountInfo<'info>>,
+    #[account(mut, address = receiver_agent.authority @ SolAgentError::ProviderMismatch)]
+    pub receiver_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWithPreimage<'info> {
+    #[account(mut)]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump = payment.escrow_bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, address = payment.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut, address = payment.receiver @ SolAgentError::ProviderMismatch)]
+    pub receiver_agent: Account<'info, Agent>,
+    #[account(mut, address = payment.service @ SolAgentError::ServiceMismatch)]
+    pub service: Account<'info, Service>,
+    /// CHECK: payer wallet, checked against `payer_agent.authority`; receives any SLA penalty refund
+    #[account(mut, address = payer_agent.authority @ SolAgentError::Unauthorized)]
+    pub payer_authority: AccountInfo<'info>,
+    #[account(mut)]
+    pub referrer_agent: Option<Account<'info, Agent>>,
+    /// CHECK: referrer wallet, checked against `referrer_agent.authority`
+    #[account(mut)]
+    pub referrer_authority: Option<AccountInfo<'info>>,
+    #[account(mut, address = receiver_agent.authority @ SolAgentError::ProviderMismatch)]
+    pub receiver_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMilestones<'info> {
+    #[account(
+        mut,
+        constraint = payment.payer == payer_agent.key() @ SolAgentError::Unauthorized,
+        constraint = payment.service == service.key() @ SolAgentError::ServiceMismatch,
+    )]
+    pub payment: Account<'info, Payment>,
+    #[account(has_one = authority @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    /// Milestones are only supported on service orders, not bundles
+    pub service: Account<'info, Service>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProviderPaymentAction<'info> {
+    #[account(
+        mut,
+        constraint = payment.receiver == receiver_agent.key() @ SolAgentError::Unauthorized,
+    )]
+    pub payment: Account<'info, Payment>,
+    #[account(has_one = authority @ SolAgentError::Unauthorized)]
+    pub receiver_agent: Account<'info, Agent>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefundPayment<'info> {
+    #[account(mut)]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump = payment.escrow_bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(address = payment.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(
+        mut,
+        constraint = payment.service == service.key() @ SolAgentError::ServiceMismatch,
+    )]
+    pub service: Account<'info, Service>,
+    /// CHECK: payer wallet for refund, checked against `payer_agent.authority`
+    #[account(mut, address = payer_agent.authority @ SolAgentError::Unauthorized)]
+    pub payer_authority: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePayment<'info> {
+    #[account(mut, close = payer_authority)]
+    pub payment: Account<'info, Payment>,
+    #[account(address = payment.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    /// CHECK: the payment's `Service` or `Bundle`, decoded in `release_payment_record`
+    #[account(mut, address = payment.service @ SolAgentError::ServiceMismatch)]
+    pub listing: AccountInfo<'info>,
+    #[account(mut, address = payer_agent.authority @ SolAgentError::Unauthorized)]
+    pub payer_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePayment<'info> {
+    /// CHECK: a `Payment` in the launch layout that `Account` can't decode; discriminator checked
+    /// in `migrate_payment`
+    #[account(mut, owner = crate::ID)]
+    pub payment: AccountInfo<'info>,
+    /// Pays for the added space
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateStream<'info> {
+    #[account(
+        init,
+        payer = payer_authority,
+        space = 8 + Stream::INIT_SPACE,
+        seeds = [b"stream", payer_agent.key().as_ref(), receiver_agent.key().as_ref()],
+        bump
+    )]
+    pub stream: Account<'info, Stream>,
+    /// CHECK: vault for stream deposits
+    #[account(
+        mut,
+        seeds = [b"stream_vault", stream.key().as_ref()],
+        bump
+    )]
+    pub stream_vault: AccountInfo<'info>,
+    pub payer_agent: Account<'info, Agent>,
+    pub receiver_agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub payer_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawStream<'info> {
+    #[account(mut)]
+    pub stream: Account<'info, Stream>,
+    /// CHECK: stream vault PDA
+    #[account(
+        mut,
+        seeds = [b"stream_vault", stream.key().as_ref()],
+        bump = stream.vault_bump
+    )]
+    pub stream_vault: AccountInfo<'info>,
+    #[account(mut, address = stream.receiver @ SolAgentError::ProviderMismatch)]
+    pub receiver_agent: Account<'info, Agent>,
+    /// CHECK: receiver wallet, checked against `receiver_agent.authority`
+    #[account(mut, address = receiver_agent.authority @ SolAgentError::ProviderMismatch)]
+    pub receiver_authority: AccountInfo<'info>,
+    #[account(address = stream.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    /// CHECK: payer wallet for refunds, checked against `payer_agent.authority`
+    #[account(mut, address = payer_agent.authority @ SolAgentError::Unauthorized)]
+    pub payer_authority: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenUsageEscrow<'info> {
+    #[account(
+        init,
+        payer = payer_authority,
+        space = 8 + UsageEscrow::INIT_SPACE,
+        seeds = [b"usage", payer_agent.key().as_ref(), service.key().as_ref()],
+        bump
+    )]
+    pub usage_escrow: Account<'info, UsageEscrow>,
+    /// CHECK: vault PDA holding pre-funded usage balance
+    #[account(
+        mut,
+        seeds = [b"usage_vault", usage_escrow.key().as_ref()],
+        bump
+    )]
+    pub usage_vault: AccountInfo<'info>,
+    #[account(
+        seeds = [b"agent", payer_authority.key().as_ref()],
+        bump = payer_agent.bump
+    )]
+    pub payer_agent: Account<'info, Agent>,
+    pub service: Account<'info, Service>,
+    #[account(
+        constraint = buyer_attestation.subject == payer_agent.key() @ SolAgentError::AttestationInvalid
+    )]
+    pub buyer_attestation: Option<Account<'info, Attestation>>,
+    #[account(mut)]
+    pub payer_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TopUpUsageEscrow<'info> {
+    #[account(mut, has_one = payer_authority @ SolAgentError::Unauthorized)]
+    pub usage_escrow: Account<'info, UsageEscrow>,
+    /// CHECK: usage vault PDA
+    #[account(
+        mut,
+        seeds = [b"usage_vault", usage_escrow.key().as_ref()],
+        bump = usage_escrow.vault_bump
+    )]
+    pub usage_vault: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetUsageCap<'info> {
+    #[account(mut, has_one = payer_authority @ SolAgentError::Unauthorized)]
+    pub usage_escrow: Account<'info, UsageEscrow>,
+    pub payer_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitUsageReport<'info> {
+    #[account(mut)]
+    pub usage_escrow: Account<'info, UsageEscrow>,
+    /// CHECK: usage vault PDA
+    #[account(
+        mut,
+        seeds = [b"usage_vault", usage_escrow.key().as_ref()],
+        bump = usage_escrow.vault_bump
+    )]
+    pub usage_vault: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = provider_authority,
+        space = 8 + UsageReport::INIT_SPACE,
+        seeds = [b"usage_report", usage_escrow.key().as_ref(), &usage_escrow.report_count.to_le_bytes()],
+        bump
+    )]
+    pub usage_report: Account<'info, UsageReport>,
+    #[account(
+        mut,
+        address = usage_escrow.service @ SolAgentError::ServiceMismatch,
+        constraint = service.authority == provider_authority.key() @ SolAgentError::Unauthorized,
+    )]
+    pub service: Account<'info, Service>,
+    #[account(mut, address = usage_escrow.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut, address = usage_escrow.provider @ SolAgentError::ProviderMismatch)]
+    pub provider_agent: Account<'info, Agent>,
+    /// Optional payer co-signature; co-signed reports skip the challenge window
+    #[account(address = usage_escrow.payer_authority @ SolAgentError::Unauthorized)]
+    pub payer_authority: Option<Signer<'info>>,
+    #[account(mut)]
+    pub provider_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ContestUsageReport<'info> {
+    #[account(mut, has_one = payer_authority @ SolAgentError::Unauthorized)]
+    pub usage_escrow: Account<'info, UsageEscrow>,
+    #[account(mut, has_one = usage_escrow)]
+    pub usage_report: Account<'info, UsageReport>,
+    pub payer_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimUsageReport<'info> {
+    #[account(mut)]
+    pub usage_escrow: Account<'info, UsageEscrow>,
+    /// CHECK: usage vault PDA
+    #[account(
+        mut,
+        seeds = [b"usage_vault", usage_escrow.key().as_ref()],
+        bump = usage_escrow.vault_bump
+    )]
+    pub usage_vault: AccountInfo<'info>,
+    #[account(mut, has_one = usage_escrow)]
+    pub usage_report: Account<'info, UsageReport>,
+    #[account(
+        mut,
+        address = usage_escrow.service @ SolAgentError::ServiceMismatch,
+        constraint = service.authority == provider_authority.key() @ SolAgentError::Unauthorized,
+    )]
+    pub service: Account<'info, Service>,
+    #[account(mut, address = usage_escrow.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut, address = usage_escrow.provider @ SolAgentError::ProviderMismatch)]
+    pub provider_agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub provider_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveUsageReport<'info> {
+    #[account(mut, has_one = payer_authority @ SolAgentError::Unauthorized)]
+    pub usage_escrow: Account<'info, UsageEscrow>,
+    /// CHECK: usage vault PDA
+    #[account(
+        mut,
+        seeds = [b"usage_vault", usage_escrow.key().as_ref()],
+        bump = usage_escrow.vault_bump
+    )]
+    pub usage_vault: AccountInfo<'info>,
+    #[account(mut, has_one = usage_escrow, close = provider_authority)]
+    pub usage_report: Account<'info, UsageReport>,
+    #[account(mut, address = usage_escrow.service @ SolAgentError::ServiceMismatch)]
+    pub service: Account<'info, Service>,
+    #[account(mut, address = usage_escrow.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut, address = usage_escrow.provider @ SolAgentError::ProviderMismatch)]
+    pub provider_agent: Account<'info, Agent>,
+    /// CHECK: provider wallet, paid the report's rent; checked against `service.authority`
+    #[account(mut, address = service.authority @ SolAgentError::Unauthorized)]
+    pub provider_authority: AccountInfo<'info>,
+    pub payer_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseUsageReport<'info> {
+    #[account(mut)]
+    pub usage_escrow: Account<'info, UsageEscrow>,
+    #[account(mut, has_one = usage_escrow, close = provider_authority)]
+    pub usage_report: Account<'info, UsageReport>,
+    #[account(address = usage_escrow.service @ SolAgentError::ServiceMismatch)]
+    pub service: Account<'info, Service>,
+    /// CHECK: provider wallet, paid the report's rent; checked against `service.authority`
+    #[account(mut, address = service.authority @ SolAgentError::Unauthorized)]
+    pub provider_authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseUsageEscrow<'info> {
+    #[account(
+        mut,
+        has_one = payer_authority @ SolAgentError::Unauthorized,
+        close = payer_authority
+    )]
+    pub usage_escrow: Account<'info, UsageEscrow>,
+    /// CHECK: usage vault PDA
+    #[account(
+        mut,
+        seeds = [b"usage_vault", usage_escrow.key().as_ref()],
+        bump = usage_escrow.vault_bump
+    )]
+    pub usage_vault: AccountInfo<'info>,
+    #[account(mut)]
+    pub payer_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Subscribe<'info> {
+    #[account(
+        init,
+        payer = subscriber_authority,
+        space = 8 + Subscription::INIT_SPACE,
+        seeds = [b"subscription", subscriber_agent.key().as_ref(), service.key().as_ref()],
+        bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+    /// CHECK: vault PDA holding the pre-funded subscription balance
+    #[account(
+        mut,
+        seeds = [b"subscription_vault", subscription.key().as_ref()],
+        bump
+    )]
+    pub subscription_vault: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"agent", subscriber_authority.key().as_ref()],
+        bump = subscriber_agent.bump
+    )]
+    pub subscriber_agent: Account<'info, Agent>,
+    #[account(mut, address = service.provider @ SolAgentError::ProviderMismatch)]
+    pub provider_agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub service: Account<'info, Service>,
+    /// CHECK: provider wallet, must be the service authority
+    #[account(mut, address = service.authority @ SolAgentError::Unauthorized)]
+    pub provider_authority: AccountInfo<'info>,
+    #[account(
+        constraint = buyer_attestation.subject == subscriber_agent.key() @ SolAgentError::AttestationInvalid
+    )]
+    pub buyer_attestation: Option<Account<'info, Attestation>>,
+    #[account(mut)]
+    pub subscriber_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TopUpSubscription<'info> {
+    #[account(mut, has_one = subscriber_authority @ SolAgentError::Unauthorized)]
+    pub subscription: Account<'info, Subscription>,
+    /// CHECK: subscription vault PDA
+    #[account(
+        mut,
+        seeds = [b"subscription_vault", subscription.key().as_ref()],
+        bump = subscription.vault_bump
+    )]
+    pub subscription_vault: AccountInfo<'info>,
+    #[account(mut)]
+    pub subscriber_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ChargeSubscription<'info> {
+    #[account(mut)]
+    pub subscription: Account<'info, Subscription>,
+    /// CHECK: subscription vault PDA
+    #[account(
+        mut,
+        seeds = [b"subscription_vault", subscription.key().as_ref()],
+        bump = subscription.vault_bump
+    )]
+    pub subscription_vault: AccountInfo<'info>,
+    #[account(mut, address = subscription.subscriber @ SolAgentError::Unauthorized)]
+    pub subscriber_agent: Account<'info, Agent>,
+    #[account(mut, address = subscription.provider @ SolAgentError::ProviderMismatch)]
+    pub provider_agent: Account<'info, Agent>,
+    #[account(mut, address = subscription.service @ SolAgentError::ServiceMismatch)]
+    pub service: Account<'info, Service>,
+    /// CHECK: provider wallet recorded at subscribe time
+    #[account(mut, address = subscription.provider_authority @ SolAgentError::Unauthorized)]
+    pub provider_authority: AccountInfo<'info>,
+    pub cranker: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelSubscription<'info> {
+    #[account(
+        mut,
+        has_one = subscriber_authority @ SolAgentError::Unauthorized,
+        close = subscriber_authority
+    )]
+    pub subscription: Account<'info, Subscription>,
+    /// CHECK: subscription vault PDA
+    #[account(
+        mut,
+        seeds = [b"subscription_vault", subscription.key().as_ref()],
+        bump = subscription.vault_bump
+    )]
+    pub subscription_vault: AccountInfo<'info>,
+    #[account(mut)]
+    pub subscriber_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VerifySubscription<'info> {
+    #[account(
+        seeds = [b"subscription", subscriber_agent.key().as_ref(), service.key().as_ref()],
+        bump = subscription.bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+    pub subscriber_agent: Account<'info, Agent>,
+    pub service: Account<'info, Service>,
+}
+
+#[derive(Accounts)]
+pub struct OpenAuction<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Auction::INIT_SPACE,
+        seeds = [b"auction", service.key().as_ref(), &service.total_orders.to_le_bytes()],
+        bump
+    )]
+    pub auction: Account<'info, Auction>,
+    /// CHECK: vault PDA holding the current highest bid
+    #[account(
+        seeds = [b"auction_vault", auction.key().as_ref()],
+        bump
+    )]
+    pub auction_vault: AccountInfo<'info>,
+    #[account(has_one = authority @ SolAgentError::Unauthorized)]
+    pub service: Account<'info, Service>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceBid<'info> {
+    #[account(mut)]
+    pub auction: Account<'info, Auction>,
+    /// CHECK: auction vault PDA
+    #[account(
+        mut,
+        seeds = [b"auction_vault", auction.key().as_ref()],
+        bump = auction.vault_bump
+    )]
+    pub auction_vault: AccountInfo<'info>,
+    #[account(
+        seeds = [b"agent", bidder_authority.key().as_ref()],
+        bump = bidder_agent.bump
+    )]
+    pub bidder_agent: Account<'info, Agent>,
+    #[account(address = auction.service @ SolAgentError::ServiceMismatch)]
+    pub service: Account<'info, Service>,
+    #[account(
+        constraint = buyer_attestation.subject == bidder_agent.key() @ SolAgentError::AttestationInvalid
+    )]
+    pub buyer_attestation: Option<Account<'info, Attestation>>,
+    /// CHECK: outbid bidder's wallet, required once the auction has a bid
+    #[account(mut)]
+    pub previous_bidder_authority: Option<AccountInfo<'info>>,
+    #[account(mut)]
+    pub bidder_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleAuction<'info> {
+    #[account(mut)]
+    pub auction: Account<'info, Auction>,
+    /// CHECK: auction vault PDA
+    #[account(
+        mut,
+        seeds = [b"auction_vault", auction.key().as_ref()],
+        bump = auction.vault_bump
+    )]
+    pub auction_vault: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = settler,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [b"payment", winner_agent.key().as_ref(), service.key().as_ref(), &service.total_orders.to_le_bytes()],
+        bump
+    )]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA to hold payment
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, address = auction.highest_bidder @ SolAgentError::Unauthorized)]
+    pub winner_agent: Account<'info, Agent>,
+    #[account(mut, address = auction.service @ SolAgentError::ServiceMismatch)]
+    pub service: Account<'info, Service>,
+    #[account(mut)]
+    pub settler: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAuction<'info> {
+    #[account(
+        mut,
+        has_one = authority @ SolAgentError::Unauthorized,
+        close = authority
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(job_id: String)]
+pub struct PostJob<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Job::INIT_SPACE,
+        seeds = [b"job", buyer_agent.key().as_ref(), job_id.as_bytes()],
+        bump
+    )]
+    pub job: Account<'info, Job>,
+    #[account(
+        seeds = [b"agent", authority.key().as_ref()],
+        bump = buyer_agent.bump,
+        has_one = authority
+    )]
+    pub buyer_agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitJobBid<'info> {
+    #[account(mut)]
+    pub job: Account<'info, Job>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + JobBid::INIT_SPACE,
+        seeds = [b"job_bid", job.key().as_ref(), provider_agent.key().as_ref()],
+        bump
+    )]
+    pub job_bid: Account<'info, JobBid>,
+    #[account(
+        seeds = [b"agent", authority.key().as_ref()],
+        bump = provider_agent.bump,
+        has_one = authority
+    )]
+    pub provider_agent: Account<'info, Agent>,
+    #[account(constraint = service.provider == provider_agent.key() @ SolAgentError::ProviderMismatch)]
+    pub service: Account<'info, Service>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawJobBid<'info> {
+    #[account(mut, address = job_bid.job @ SolAgentError::Unauthorized)]
+    pub job: Account<'info, Job>,
+    #[account(
+        mut,
+        has_one = authority @ SolAgentError::Unauthorized,
+        close = authority
+    )]
+    pub job_bid: Account<'info, JobBid>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseJobBid<'info> {
+    #[account(mut)]
+    pub job: Account<'info, Job>,
+    #[account(
+        mut,
+        has_one = job @ SolAgentError::Unauthorized,
+        has_one = authority @ SolAgentError::Unauthorized,
+        close = authority
+    )]
+    pub job_bid: Account<'info, JobBid>,
+    /// CHECK: the bidder's wallet, receives the bid rent
+    #[account(mut)]
+    pub authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptJobBid<'info> {
+    #[account(mut, has_one = authority @ SolAgentError::Unauthorized)]
+    pub job: Account<'info, Job>,
+    #[account(has_one = job @ SolAgentError::Unauthorized)]
+    pub job_bid: Account<'info, JobBid>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [b"payment", buyer_agent.key().as_ref(), service.key().as_ref(), &service.total_orders.to_le_bytes()],
+        bump
+    )]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA to hold payment
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, address = job.buyer @ SolAgentError::Unauthorized)]
+    pub buyer_agent: Account<'info, Agent>,
+    #[account(mut, address = job_bid.service @ SolAgentError::ServiceMismatch)]
+    pub service: Account<'info, Service>,
+    #[account(
+        constraint = buyer_attestation.subject == buyer_agent.key() @ SolAgentError::AttestationInvalid
+    )]
+    pub buyer_attestation: Option<Account<'info, Attestation>>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelJob<'info> {
+    #[account(mut, has_one = authority @ SolAgentError::Unauthorized)]
+    pub job: Account<'info, Job>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseJob<'info> {
+    #[account(
+        mut,
+        has_one = authority @ SolAgentError::Unauthorized,
+        close = authority
+    )]
+    pub job: Account<'info, Job>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(bundle_id: String)]
+pub struct CreateBundle<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Bundle::INIT_SPACE,
+        seeds = [b"bundle", agent.key().as_ref(), bundle_id.as_bytes()],
+        bump
+    )]
+    pub bundle: Account<'info, Bundle>,
+    #[account(
         seeds = [b"agent", authority.key().as_ref()],
+        bump = agent.bump,
+        has_one = authority
+    )]
+    pub agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PayForBundle<'info> {
+    #[account(
+        init,
+        payer = payer_authority,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [b"payment", payer_agent.key().as_ref(), bundle.key().as_ref(), &bundle.total_orders.to_le_bytes()],
+        bump
+    )]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA to hold payment
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"agent", payer_authority.key().as_ref()],
+        bump = payer_agent.bump
+    )]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub bundle: Account<'info, Bundle>,
+    #[account(mut)]
+    pub payer_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseBundlePayment<'info> {
+    #[account(
+        mut,
+        constraint = payment.payer == payer_agent.key() @ SolAgentError::Unauthorized,
+        constraint = payment.service == bundle.key() @ SolAgentError::ServiceMismatch,
+    )]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump = payment.escrow_bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(has_one = authority @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub bundle: Account<'info, Bundle>,
+    #[account(mut, address = bundle.orchestrator @ SolAgentError::ProviderMismatch)]
+    pub orchestrator_agent: Account<'info, Agent>,
+    /// CHECK: orchestrator wallet, checked against `orchestrator_agent.authority`
+    #[account(mut, address = orchestrator_agent.authority @ SolAgentError::ProviderMismatch)]
+    pub orchestrator_authority: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefundBundlePayment<'info> {
+    #[account(
+        mut,
+        constraint = payment.service == bundle.key() @ SolAgentError::ServiceMismatch,
+    )]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump = payment.escrow_bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(address = payment.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut)]
+    pub bundle: Account<'info, Bundle>,
+    /// CHECK: payer wallet, checked against `payer_agent.authority`
+    #[account(mut, address = payer_agent.authority @ SolAgentError::Unauthorized)]
+    pub payer_authority: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefreshBundleRating<'info> {
+    #[account(mut)]
+    pub bundle: Account<'info, Bundle>,
+}
+
+#[derive(Accounts)]
+pub struct SetBundleStatus<'info> {
+    #[account(mut, has_one = authority @ SolAgentError::Unauthorized)]
+    pub bundle: Account<'info, Bundle>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBundle<'info> {
+    #[account(
+        mut,
+        has_one = authority @ SolAgentError::Unauthorized,
+        close = authority
+    )]
+    pub bundle: Account<'info, Bundle>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenDispute<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Dispute::INIT_SPACE,
+        seeds = [b"dispute", payment.key().as_ref()],
+        bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+    #[account(mut)]
+    pub payment: Account<'info, Payment>,
+    /// Payer or receiver agent of the payment
+    #[account(has_one = authority @ SolAgentError::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    /// CHECK: the `Service` or `Bundle` the payment was for; read in `listing_arbiter_pool`
+    #[account(address = payment.service @ SolAgentError::ServiceMismatch)]
+    pub listing: AccountInfo<'info>,
+    /// Required when the service has an arbiter pool
+    pub arbiter_pool: Option<Account<'info, ArbiterPool>>,
+    #[account(seeds = [b"protocol"], bump = protocol.bump)]
+    pub protocol: Account<'info, Protocol>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DrawDisputePanel<'info> {
+    #[account(mut)]
+    pub dispute: Account<'info, Dispute>,
+    #[account(
+        constraint = dispute.pool == Some(arbiter_pool.key()) @ SolAgentError::ArbiterPoolMismatch
+    )]
+    pub arbiter_pool: Account<'info, ArbiterPool>,
+    #[account(address = dispute.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(address = dispute.receiver @ SolAgentError::ProviderMismatch)]
+    pub receiver_agent: Account<'info, Agent>,
+    /// CHECK: SlotHashes sysvar, read for panel selection
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitEvidence<'info> {
+    #[account(mut)]
+    pub dispute: Account<'info, Dispute>,
+    /// Payer or receiver agent of the disputed payment
+    #[account(has_one = authority @ SolAgentError::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveDispute<'info> {
+    #[account(mut, has_one = payment @ SolAgentError::Unauthorized)]
+    pub dispute: Account<'info, Dispute>,
+    #[account(mut)]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump = payment.escrow_bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, address = payment.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut, address = payment.receiver @ SolAgentError::ProviderMismatch)]
+    pub receiver_agent: Account<'info, Agent>,
+    /// CHECK: payer wallet, checked against `payer_agent.authority`
+    #[account(mut, address = payer_agent.authority @ SolAgentError::Unauthorized)]
+    pub payer_authority: AccountInfo<'info>,
+    /// CHECK: receiver wallet, checked against `receiver_agent.authority`
+    #[account(mut, address = receiver_agent.authority @ SolAgentError::ProviderMismatch)]
+    pub receiver_authority: AccountInfo<'info>,
+    /// CHECK: the `Service` or `Bundle` the payment was for; deserialized in `release_order_slot`
+    #[account(mut, address = payment.service @ SolAgentError::ServiceMismatch)]
+    pub listing: AccountInfo<'info>,
+    /// The voter's `Arbiter` account; required for pooled disputes
+    #[account(mut)]
+    pub arbiter_account: Option<Account<'info, Arbiter>>,
+    /// Panel member's wallet (the protocol authority for unpooled disputes); receives the fee share
+    #[account(mut)]
+    pub arbiter: Signer<'info>,
+    /// Referrer of a referred payment; receives its share of the receiver's award
+    #[account(mut)]
+    pub referrer_agent: Option<Account<'info, Agent>>,
+    /// CHECK: referrer wallet, checked against `referrer_agent.authority`
+    #[account(mut)]
+    pub referrer_authority: Option<AccountInfo<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct AppealDispute<'info> {
+    #[account(mut)]
+    pub dispute: Account<'info, Dispute>,
+    /// Payer or receiver agent of the disputed payment
+    #[account(has_one = authority @ SolAgentError::Unauthorized)]
+    pub agent: Account<'info, Agent>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveAppeal<'info> {
+    #[account(
+        mut,
+        has_one = payment @ SolAgentError::Unauthorized,
+        constraint = dispute.appeal_authority == authority.key() @ SolAgentError::Unauthorized,
+    )]
+    pub dispute: Account<'info, Dispute>,
+    #[account(mut)]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump = payment.escrow_bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, address = payment.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut, address = payment.receiver @ SolAgentError::ProviderMismatch)]
+    pub receiver_agent: Account<'info, Agent>,
+    /// CHECK: payer wallet, checked against `payer_agent.authority`
+    #[account(mut, address = payer_agent.authority @ SolAgentError::Unauthorized)]
+    pub payer_authority: AccountInfo<'info>,
+    /// CHECK: receiver wallet, checked against `receiver_agent.authority`
+    #[account(mut, address = receiver_agent.authority @ SolAgentError::ProviderMismatch)]
+    pub receiver_authority: AccountInfo<'info>,
+    /// CHECK: the `Service` the payment was for; deserialized in `release_order_slot`
+    #[account(mut, address = payment.service @ SolAgentError::ServiceMismatch)]
+    pub listing: AccountInfo<'info>,
+    #[account(seeds = [b"protocol"], bump = protocol.bump)]
+    pub protocol: Account<'info, Protocol>,
+    /// CHECK: protocol treasury, receives slashed stake
+    #[account(mut, address = protocol.treasury @ SolAgentError::Unauthorized)]
+    pub treasury: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+    /// Referrer of a referred payment; receives its share of the receiver's award
+    #[account(mut)]
+    pub referrer_agent: Option<Account<'info, Agent>>,
+    /// CHECK: referrer wallet, checked against `referrer_agent.authority`
+    #[account(mut)]
+    pub referrer_authority: Option<AccountInfo<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeDispute<'info> {
+    #[account(mut, has_one = payment @ SolAgentError::Unauthorized)]
+    pub dispute: Account<'info, Dispute>,
+    #[account(mut)]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump = payment.escrow_bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, address = payment.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    #[account(mut, address = payment.receiver @ SolAgentError::ProviderMismatch)]
+    pub receiver_agent: Account<'info, Agent>,
+    /// CHECK: payer wallet, checked against `payer_agent.authority`
+    #[account(mut, address = payer_agent.authority @ SolAgentError::Unauthorized)]
+    pub payer_authority: AccountInfo<'info>,
+    /// CHECK: receiver wallet, checked against `receiver_agent.authority`
+    #[account(mut, address = receiver_agent.authority @ SolAgentError::ProviderMismatch)]
+    pub receiver_authority: AccountInfo<'info>,
+    /// CHECK: the `Service` the payment was for; deserialized in `release_order_slot`
+    #[account(mut, address = payment.service @ SolAgentError::ServiceMismatch)]
+    pub listing: AccountInfo<'info>,
+    /// Referrer of a referred payment; receives its share of the receiver's award
+    #[account(mut)]
+    pub referrer_agent: Option<Account<'info, Agent>>,
+    /// CHECK: referrer wallet, checked against `referrer_agent.authority`
+    #[account(mut)]
+    pub referrer_authority: Option<AccountInfo<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireDispute<'info> {
+    #[account(mut, has_one = payment @ SolAgentError::Unauthorized)]
+    pub dispute: Account<'info, Dispute>,
+    #[account(mut)]
+    pub payment: Account<'info, Payment>,
+    /// CHECK: escrow PDA
+    #[account(
+        mut,
+        seeds = [b"escrow", payment.key().as_ref()],
+        bump = payment.escrow_bump
+    )]
+    pub escrow: AccountInfo<'info>,
+    #[account(mut, address = payment.payer @ SolAgentError::Unauthorized)]
+    pub payer_agent: Account<'info, Agent>,
+    /// CHECK: payer wallet, checked against `payer_agent.authority`
+    #[account(mut, address = payer_agent.authority @ SolAgentError::Unauthorized)]
+    pub payer_authority: AccountInfo<'info>,
+    /// CHECK: the `Service` or `Bundle` the payment was for; deserialized in `release_order_slot`
+    #[account(mut, address = payment.service @ SolAgentError::ServiceMismatch)]
+    pub listing: AccountInfo<'info>,
+    #[account(seeds = [b"protocol"], bump = protocol.bump)]
+    pub protocol: Account<'info, Protocol>,
+    /// CHECK: protocol treasury, receives slashed stake
+    #[account(mut, address = protocol.treasury @ SolAgentError::Unauthorized)]
+    pub treasury: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseArbiterCase<'info> {
+    #[account(mut)]
+    pub dispute: Account<'info, Dispute>,
+    #[account(mut)]
+    pub arbiter: Account<'info, Arbiter>,
+}
+
+#[derive(Accounts)]
+#[instruction(pool_id: u16)]
+pub struct CreateArbiterPool<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ArbiterPool::INIT_SPACE,
+        seeds = [b"arbiter_pool", pool_id.to_le_bytes().as_ref()],
         bump
     )]
-    pub agent: Account<'info, Agent>,
+    pub arbiter_pool: Account<'info, ArbiterPool>,
+    #[account(
+        seeds = [b"protocol"],
+        bump = protocol.bump,
+        has_one = authority @ SolAgentError::Unauthorized
+    )]
+    pub protocol: Account<'info, Protocol>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterArbiter<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Arbiter::INIT_SPACE,
+        seeds = [b"arbiter", arbiter_pool.key().as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub arbiter: Account<'info, Arbiter>,
+    #[account(mut)]
+    pub arbiter_pool: Account<'info, ArbiterPool>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddArbiterStake<'info> {
+    #[account(mut, has_one = authority @ SolAgentError::Unauthorized)]
+    pub arbiter: Account<'info, Arbiter>,
     #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
-#[derive(Accounts)]
-pub struct StakeReputation<'info> {
-    #[account(
-        mut,
-        seeds = [b"agent", authority.key().as_ref()],
-        bump = agent.bump,
-        has_one = authority
-    )]
-    pub agent: Account<'info, Agent>,
-    /// CHECK: vault PDA to hold staked SOL
-    #[account(
-        mut,
-        seeds = [b"vault", agent.key().as_ref()],
-        bump
-    )]
-    pub vault: AccountInfo<'info>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
+#[derive(Accounts)]
+pub struct WithdrawArbiter<'info> {
+    #[account(
+        mut,
+        has_one = authority @ SolAgentError::Unauthorized,
+        close = authority
+    )]
+    pub arbiter: Account<'info, Arbiter>,
+    #[account(mut, address = arbiter.pool @ SolAgentError::ArbiterPoolMismatch)]
+    pub arbiter_pool: Account<'info, ArbiterPool>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+// ============================================================
+// STATE
+// ============================================================
+
+#[account]
+#[derive(InitSpace)]
+pub struct Protocol {
+    pub authority: Pubkey,
+    pub total_agents: u64,
+    pub total_services: u64,
+    pub total_payments: u64,
+    pub total_volume: u64,
+    pub total_staked: u64,
+    pub fee_bps: u16,
+    pub treasury: Pubkey,
+    pub bump: u8,
+}
+
+/// A curated marketplace category; services reference it by `id`
+#[account]
+#[derive(InitSpace)]
+pub struct Category {
+    pub id: u16,
+    #[max_len(32)]
+    pub name: String,
+    #[max_len(128)]
+    pub description: String,
+    pub is_active: bool,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Agent {
+    pub authority: Pubkey,
+    #[max_len(32)]
+    pub name: String,
+    #[max_len(256)]
+    pub description: String,
+    #[max_len(10, 32)]
+    pub capabilities: Vec<String>,
+    #[max_len(128)]
+    pub endpoint: String,
+    pub reputation_score: u64,
+    pub total_staked: u64,
+    pub total_earned: u64,
+    pub total_spent: u64,
+    pub services_completed: u64,
+    pub services_requested: u64,
+    pub feedbacks_received: u64,
+    pub registered_at: i64,
+    pub is_active: bool,
+    pub bump: u8,
+    // Fields below were added after launch. New fields go at the end so older accounts stay a
+    // prefix of this layout; `migrate_agent` grows them and the new space decodes as defaults
+    pub referral_earned: u64,
+    pub referral_fees_paid: u64,
+    pub sla_orders: u64,
+    pub sla_met: u64,
+    /// Resolved disputes this agent was party to
+    pub disputes: u64,
+    /// Of those, disputes where it was awarded the whole escrow
+    pub disputes_won: u64,
+    /// Of those, disputes where it was awarded nothing
+    pub disputes_lost: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Attestation {
+    pub issuer: Pubkey,
+    pub subject: Pubkey,
+    pub issued_at: i64,
+    /// 0 = never expires
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+impl Attestation {
+    /// Whether the attestation has not expired at `now`
+    pub fn is_valid_at(&self, now: i64) -> bool {
+        self.expires_at == 0 || now < self.expires_at
+    }
+}
+
+/// A key an agent authorises to sign delivery receipts on its behalf
+#[account]
+#[derive(InitSpace)]
+pub struct SessionKey {
+    pub agent: Pubkey,
+    pub key: Pubkey,
+    pub created_at: i64,
+    /// 0 = never expires
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+impl SessionKey {
+    /// Whether the session key has not expired at `now`
+    pub fn is_valid_at(&self, now: i64) -> bool {
+        self.expires_at == 0 || now < self.expires_at
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Service {
+    pub provider: Pubkey,
+    pub authority: Pubkey,
+    #[max_len(32)]
+    pub service_id: String,
+    #[max_len(64)]
+    pub title: String,
+    #[max_len(256)]
+    pub description: String,
+    pub price_lamports: u64,
+    pub price_model: PriceModel,
+    #[max_len(5, 32)]
+    pub tags: Vec<String>,
+    pub total_orders: u64,
+    pub total_revenue: u64,
+    pub avg_rating: u8,
+    pub is_active: bool,
+    pub created_at: i64,
+    pub bump: u8,
+    // Fields below were added after launch. New fields go at the end so older accounts stay a
+    // prefix of this layout; `migrate_service` grows them and the new space decodes as defaults
+    pub pricing: Option<PricingSchedule>,
+    pub referral_bps: u16,
+    pub access: Option<AccessRules>,
+    pub open_orders: u64,
+    /// 0 = unlimited
+    pub max_concurrent_orders: u64,
+    pub sla: Option<SlaTerms>,
+    pub sla_orders: u64,
+    pub sla_met: u64,
+    /// Starts at 1; bumped whenever the terms a buyer purchases under change
+    pub version: u64,
+    /// Curated `Category` ids; free-form `tags` remain for long-tail search
+    #[max_len(3)]
+    pub categories: Vec<u16>,
+    /// `ArbiterPool` that disputes on this service's orders are assigned from (None = protocol authority)
+    pub arbiter_pool: Option<Pubkey>,
+    /// Payment accounts created against this service and not yet closed; it can only be closed at 0
+    pub unclosed_payments: u64,
+    /// Copied into each order; see `TimeoutPolicy`
+    pub timeout_policy: TimeoutPolicy,
+}
+
+impl Service {
+    /// Check a buyer against the service's access rules, if it has any
+    pub fn check_access(
+        &self,
+        buyer: &Account<Agent>,
+        attestation: Option<&Attestation>,
+        now: i64,
+    ) -> Result<()> {
+        match &self.access {
+            Some(rules) => rules.check(buyer, attestation, now),
+            None => Ok(()),
+        }
+    }
+
+    pub fn terms(&self) -> ServiceTerms {
+        ServiceTerms {
+            title: self.title.clone(),
+            description: self.description.clone(),
+            price_lamports: self.price_lamports,
+            price_model: self.price_model.clone(),
+            pricing: self.pricing.clone(),
+            sla: self.sla.clone(),
+            timeout_policy: self.timeout_policy.clone(),
+            tags: self.tags.clone(),
+        }
+    }
+
+    /// Whether another escrowed order fits under the concurrency limit
+    pub fn has_capacity(&self) -> bool {
+        self.max_concurrent_orders == 0 || self.open_orders < self.max_concurrent_orders
+    }
+
+    /// Total price in lamports for `quantity` units under this service's price model,
+    /// with the pricing schedule applied for a buyer of the given reputation
+    pub fn quote(&self, quantity: u64, buyer_reputation: u64) -> Result<u64> {
+        require!(quantity > 0, SolAgentError::InvalidQuantity);
+        let discount_bps = self
+            .pricing
+            .as_ref()
+            .map_or(0, |p| p.discount_bps(buyer_reputation));
+        match self.price_model {
+            PriceModel::Fixed => {
+                require!(quantity == 1, SolAgentError::InvalidQuantity);
+                Ok(apply_discount(self.price_lamports, discount_bps))
+            }
+            // Tiers apply to the quantity within a single order
+            PriceModel::PerRequest | PriceModel::PerToken => price_units(
+                self.price_lamports,
+                self.pricing.as_ref(),
+                0,
+                quantity,
+                discount_bps,
+            ),
+            PriceModel::PerSecond | PriceModel::Auction | PriceModel::Subscription { .. } => {
+                err!(SolAgentError::UnsupportedPriceModel)
+            }
+        }
+    }
+}
+
+/// Cost of units `[units_before, units_before + quantity)` at `base_price`,
+/// with any volume tiers and a reputation discount applied
+pub fn price_units(
+    base_price: u64,
+    pricing: Option<&PricingSchedule>,
+    units_before: u64,
+    quantity: u64,
+    discount_bps: u16,
+) -> Result<u64> {
+    let gross = match pricing {
+        Some(schedule) => schedule.tiered_cost(base_price, units_before, quantity),
+        None => base_price.checked_mul(quantity),
+    }
+    .ok_or(SolAgentError::MathOverflow)?;
+    Ok(apply_discount(gross, discount_bps))
+}
+
+/// `bps` basis points of `amount`, rounded down
+pub fn bps_share(amount: u64, bps: u16) -> u64 {
+    (amount as u128 * bps as u128 / BPS_DENOMINATOR as u128) as u64
+}
+
+/// Take `discount_bps` basis points off `amount`, rounding the discount down
+pub fn apply_discount(amount: u64, discount_bps: u16) -> u64 {
+    amount - bps_share(amount, discount_bps)
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Coupon {
+    pub service: Pubkey,
+    pub authority: Pubkey,
+    #[max_len(32)]
+    pub code: String,
+    pub discount: Discount,
+    pub max_redemptions: u64,
+    pub redemptions: u64,
+    pub expires_at: i64,
+    #[max_len(10)]
+    pub allowed_buyers: Vec<Pubkey>,
+    pub total_discounted: u64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Coupon {
+    /// Check expiry, redemption cap and allowlist for `buyer` (an agent PDA)
+    pub fn check_redeemable(&self, buyer: &Pubkey, now: i64) -> Result<()> {
+        require!(
+            self.expires_at == 0 || now < self.expires_at,
+            SolAgentError::CouponExpired
+        );
+        require!(
+            self.max_redemptions == 0 || self.redemptions < self.max_redemptions,
+            SolAgentError::CouponExhausted
+        );
+        require!(
+            self.allowed_buyers.is_empty() || self.allowed_buyers.contains(buyer),
+            SolAgentError::CouponNotAllowed
+        );
+        Ok(())
+    }
+
+    /// Lamports taken off an order quoted at `amount`
+    pub fn discount_on(&self, amount: u64) -> u64 {
+        match self.discount {
+            Discount::Percent { bps } => bps_share(amount, bps),
+            Discount::Fixed { lamports } => lamports.min(amount),
+        }
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Payment {
+    pub payer: Pubkey,
+    pub receiver: Pubkey,
+    /// `Service` the order is for, or the `Bundle` for bundle orders
+    pub service: Pubkey,
+    pub amount: u64,
+    #[max_len(256)]
+    pub intent: String,
+    /// Free-text conditions of launch-era orders; never evaluated, and empty on newer orders
+    #[max_len(5, 64)]
+    pub legacy_conditions: Vec<String>,
+    pub status: PaymentStatus,
+    pub created_at: i64,
+    pub timeout_at: i64,
+    pub completed_at: i64,
+    pub bump: u8,
+    pub escrow_bump: u8,
+    // Fields below were added after launch. New fields go at the end so older accounts stay a
+    // prefix of this layout; `migrate_payment` grows them and the new space decodes as defaults
+    pub quantity: u64,
+    #[max_len(5)]
+    pub conditions: Vec<PaymentCondition>,
+    pub referrer: Option<Pubkey>,
+    pub referral_bps: u16,
+    pub sla: Option<SlaTerms>,
+    /// Service version and listed price at the time the order was placed
+    pub service_version: u64,
+    pub service_price: u64,
+    pub acknowledged_at: i64,
+    pub delivered_at: i64,
+    /// Empty = released all at once
+    #[max_len(5)]
+    pub milestones: Vec<Milestone>,
+    /// Paid out to the receiver so far through `release_milestone`
+    pub released_amount: u64,
+    pub timeout_policy: TimeoutPolicy,
+}
+
+impl Payment {
+    /// Escrowed amount not yet released to the receiver
+    pub fn remaining(&self) -> u64 {
+        self.amount - self.released_amount
+    }
+
+    /// When a delivered order releases to the provider without the payer, if its policy allows
+    pub fn auto_release_at(&self) -> Option<i64> {
+        match self.timeout_policy {
+            TimeoutPolicy::ReleaseAfterDelivery { challenge_seconds } if self.delivered_at > 0 => {
+                Some(self.delivered_at + challenge_seconds)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether an unreleased milestone has passed its deadline at `now`
+    pub fn milestone_overdue(&self, now: i64) -> bool {
+        self.milestones
+            .iter()
+            .any(|m| m.released_at == 0 && m.deadline > 0 && now > m.deadline)
+    }
+}
+
+/// A release condition attached to a payment, evaluated by `execute_conditional_release`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum PaymentCondition {
+    /// The other conditions must all hold by this unix timestamp, or the escrow is refunded
+    Deadline { at: i64 },
+    /// A Pyth feed's price must be above (or below) `price`, in the feed's `exponent`
+    /// With `at` set, the price published at that time decides it; 0 = any fresh price. Whoever
+    /// cranks the release picks which update in `[at, at + max_age_seconds]` to pass, so a price
+    /// hovering near the threshold can go either way within that window
+    OraclePrice {
+        feed_id: [u8; 32],
+        price: i64,
+        exponent: i32,
+        above: bool,
+        at: i64,
+        /// Max seconds between the update's publish time and now (or `at`); 1-`MAX_ORACLE_PRICE_AGE_SECONDS`
+        max_age_seconds: u32,
+        /// Max confidence interval as a share of the price
+        max_conf_bps: u16,
+    },
+    /// A preimage whose sha256 is `hash` must be revealed
+    HashPreimage { hash: [u8; 32] },
+    /// `validator` must co-sign the release
+    ValidatorSignature { validator: Pubkey },
+    /// `issuer` must hold a valid attestation for the receiver agent
+    AttestationPresent { issuer: Pubkey },
+    /// Timelock: holds once this unix timestamp is reached
+    NotBefore { at: i64 },
+}
+
+/// The parts of a Pyth price update that oracle conditions look at
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PythPrice {
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+impl PythPrice {
+    /// Whether the confidence interval is at most `max_conf_bps` of the price
+    pub fn confidence_within(&self, max_conf_bps: u16) -> bool {
+        self.conf as u128 * BPS_DENOMINATOR as u128
+            <= self.price.unsigned_abs() as u128 * max_conf_bps as u128
+    }
+}
+
+/// A milestone as requested in `set_milestones`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct MilestoneSpec {
+    pub amount: u64,
+    /// Unix timestamp; 0 = no deadline
+    pub deadline: i64,
+}
+
+impl MilestoneSpec {
+    /// Deadlines must be in the future, no later than the payment's timeout and non-decreasing;
+    /// once a milestone has no deadline, later ones can't have one either
+    pub fn deadlines_valid(milestones: &[MilestoneSpec], now: i64, timeout_at: i64) -> bool {
+        let mut earliest = now + 1;
+        milestones.iter().all(|m| {
+            if m.deadline == 0 {
+                earliest = i64::MAX;
+                return true;
+            }
+            let valid = m.deadline >= earliest && m.deadline <= timeout_at;
+            earliest = m.deadline;
+            valid
+        })
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct Milestone {
+    pub amount: u64,
+    /// Unix timestamp; 0 = no deadline
+    pub deadline: i64,
+    /// 0 = not released yet
+    pub released_at: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Feedback {
+    pub from_agent: Pubkey,
+    pub to_agent: Pubkey,
+    pub rating: u8,
+    #[max_len(256)]
+    pub comment: String,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Stream {
+    pub payer: Pubkey,
+    pub receiver: Pubkey,
+    pub rate_per_second: u64,
+    pub deposited: u64,
+    pub withdrawn: u64,
+    pub started_at: i64,
+    pub max_end_at: i64,
+    pub last_withdrawn_at: i64,
+    pub is_active: bool,
+    pub bump: u8,
+    pub vault_bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct UsageEscrow {
+    pub payer: Pubkey,
+    pub payer_authority: Pubkey,
+    pub service: Pubkey,
+    pub provider: Pubkey,
+    pub price_per_unit: u64,
+    pub pricing: Option<PricingSchedule>,
+    pub discount_bps: u16,
+    pub deposited: u64,
+    pub drawn: u64,
+    pub pending: u64,
+    pub units_billed: u64,
+    pub units_reported: u64,
+    pub max_units_per_report: u64,
+    pub challenge_window_seconds: i64,
+    pub report_count: u64,
+    pub created_at: i64,
+    pub bump: u8,
+    pub vault_bump: u8,
+    /// Reports not yet closed; the escrow can only be closed at 0
+    pub open_reports: u64,
+}
+
+impl UsageEscrow {
+    /// Lamports not yet drawn or reserved by pending reports
+    pub fn available(&self) -> u64 {
+        self.deposited - self.drawn - self.pending
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct UsageReport {
+    pub usage_escrow: Pubkey,
+    pub index: u64,
+    pub units: u64,
+    pub amount: u64,
+    pub submitted_at: i64,
+    pub claimable_at: i64,
+    pub co_signed: bool,
+    pub status: UsageReportStatus,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Subscription {
+    pub subscriber: Pubkey,
+    pub subscriber_authority: Pubkey,
+    pub service: Pubkey,
+    pub provider: Pubkey,
+    pub provider_authority: Pubkey,
+    pub amount_per_period: u64,
+    pub period_seconds: i64,
+    pub deposited: u64,
+    pub charged: u64,
+    pub periods_charged: u64,
+    pub paid_until: i64,
+    pub status: SubscriptionStatus,
+    pub created_at: i64,
+    pub bump: u8,
+    pub vault_bump: u8,
+}
+
+impl Subscription {
+    /// Lamports left in the vault for future periods
+    pub fn balance(&self) -> u64 {
+        self.deposited - self.charged
+    }
+
+    /// Whether the subscriber has paid for the period covering `now`
+    pub fn is_active_at(&self, now: i64) -> bool {
+        self.status == SubscriptionStatus::Active && now < self.paid_until
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Auction {
+    pub service: Pubkey,
+    pub provider: Pubkey,
+    pub authority: Pubkey,
+    pub reserve_price: u64,
+    pub highest_bid: u64,
+    pub highest_bidder: Pubkey,
+    pub highest_bidder_authority: Pubkey,
+    #[max_len(256)]
+    pub intent: String,
+    pub bid_count: u64,
+    pub delivery_timeout_seconds: i64,
+    pub created_at: i64,
+    pub end_at: i64,
+    pub status: AuctionStatus,
+    pub payment: Pubkey,
+    pub bump: u8,
+    pub vault_bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Job {
+    pub buyer: Pubkey,
+    pub authority: Pubkey,
+    #[max_len(32)]
+    pub job_id: String,
+    #[max_len(256)]
+    pub intent: String,
+    pub budget: u64,
+    #[max_len(10, 32)]
+    pub required_capabilities: Vec<String>,
+    pub deadline: i64,
+    pub status: JobStatus,
+    pub bid_count: u64,
+    pub accepted_bid: Pubkey,
+    pub payment: Pubkey,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct JobBid {
+    pub job: Pubkey,
+    pub provider: Pubkey,
+    pub authority: Pubkey,
+    pub service: Pubkey,
+    pub price: u64,
+    pub delivery_seconds: i64,
+    #[max_len(256)]
+    pub message: String,
+    pub submitted_at: i64,
+    pub bump: u8,
+}
+
+/// A composite listing sold by an orchestrator agent and fulfilled by other agents' services
+#[account]
+#[derive(InitSpace)]
+pub struct Bundle {
+    /// Orchestrator agent; keeps whatever the component shares leave
+    pub orchestrator: Pubkey,
+    pub authority: Pubkey,
+    #[max_len(32)]
+    pub bundle_id: String,
+    #[max_len(64)]
+    pub title: String,
+    #[max_len(256)]
+    pub description: String,
+    pub price_lamports: u64,
+    #[max_len(5)]
+    pub components: Vec<BundleComponent>,
+    pub total_orders: u64,
+    pub open_orders: u64,
+    pub total_revenue: u64,
+    /// Share-weighted average of the component services' ratings
+    pub avg_rating: u8,
+    /// Share-weighted average reputation of the component providers
+    pub component_reputation: u64,
+    pub is_active: bool,
+    pub created_at: i64,
+    pub bump: u8,
+    /// Payment accounts created against this bundle and not yet closed; it can only be closed at 0
+    pub unclosed_payments: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct BundleComponent {
+    pub service: Pubkey,
+    /// Provider agent of `service`; receives `share_bps` of each order
+    pub provider: Pubkey,
+    pub share_bps: u16,
+}
+
+/// A disputed payment and the panel ruling on it
+#[account]
+#[derive(InitSpace)]
+pub struct Dispute {
+    pub payment: Pubkey,
+    pub payer: Pubkey,
+    pub receiver: Pubkey,
+    /// Payer or receiver agent that opened the dispute
+    pub opened_by: Pubkey,
+    /// `ArbiterPool` the panel was drawn from (None = protocol authority arbitrates)
+    pub pool: Option<Pubkey>,
+    /// `Arbiter` accounts on the panel, or the protocol authority's wallet for unpooled disputes
+    #[max_len(5)]
+    pub arbiters: Vec<Pubkey>,
+    #[max_len(5)]
+    pub votes: Vec<ArbiterVote>,
+    /// Decides appeals (the pool authority)
+    pub appeal_authority: Pubkey,
+    /// Escrowed amount under dispute
+    pub amount: u64,
+    /// Arbiter fee taken from the escrow, split evenly across the panel
+    pub fee: u64,
+    pub fee_paid: u64,
+    /// 0 = rulings pay out immediately and cannot be appealed
+    pub appeal_window_seconds: i64,
+    pub slash_bps: u16,
+    #[max_len(256)]
+    pub reason: String,
+    /// Up to `MAX_DISPUTE_EVIDENCE` entries from each party
+    #[max_len(16)]
+    pub evidence: Vec<Evidence>,
+    pub status: DisputeStatus,
+    /// Ruled payer share, excluding fees
+    pub payer_amount: u64,
+    pub receiver_amount: u64,
+    pub appealed_by: Option<Pubkey>,
+    pub opened_at: i64,
+    pub ruled_at: i64,
+    pub resolved_at: i64,
+    pub bump: u8,
+    /// Slot whose hash draws a pooled panel; committed when the dispute opens
+    pub draw_slot: u64,
+    /// Votes close at this time (0 = pooled panel not drawn yet); `expire_dispute` refunds after it
+    /// Disputes without a pool get `PROTOCOL_DISPUTE_WINDOW_SECONDS` from opening
+    pub vote_deadline: i64,
+    /// Bit per panel index: that arbiter's stake lock for this dispute has been released
+    pub released_arbiters: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct Evidence {
+    /// Agent that submitted the evidence
+    pub submitter: Pubkey,
+    /// Hash of the off-chain evidence content
+    pub hash: [u8; 32],
+    #[max_len(128)]
+    pub uri: String,
+    pub submitted_at: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct ArbiterVote {
+    pub arbiter: Pubkey,
+    pub payer_amount: u64,
+}
+
+/// Staked arbiters that disputes on opted-in services are assigned from
+#[account]
+#[derive(InitSpace)]
+pub struct ArbiterPool {
+    pub pool_id: u16,
+    /// Decides appeals against this pool's panels
+    pub authority: Pubkey,
+    #[max_len(32)]
+    pub name: String,
+    pub min_stake: u64,
+    /// Share of the disputed escrow paid to the panel
+    pub fee_bps: u16,
+    /// Arbiters per dispute; odd so a majority exists
+    pub panel_size: u8,
+    pub appeal_window_seconds: i64,
+    /// Share of stake an arbiter loses when its ruling is overturned
+    pub slash_bps: u16,
+    /// Registered `Arbiter` accounts
+    #[max_len(16)]
+    pub arbiters: Vec<Pubkey>,
+    pub created_at: i64,
+    pub bump: u8,
+    /// How long a drawn panel has to reach a majority before the dispute can be expired
+    pub vote_window_seconds: i64,
+}
+
+/// An arbiter's membership of a pool; the account itself holds the stake
+#[account]
+#[derive(InitSpace)]
+pub struct Arbiter {
+    pub authority: Pubkey,
+    pub pool: Pubkey,
+    pub stake: u64,
+    pub cases_ruled: u64,
+    pub cases_overturned: u64,
+    pub fees_earned: u64,
+    pub total_slashed: u64,
+    /// Appeals pending against this arbiter's rulings
+    pub open_appeals: u64,
+    /// Stake cannot be withdrawn before this (end of the latest appeal window)
+    pub locked_until: i64,
+    pub registered_at: i64,
+    pub bump: u8,
+    /// Disputes this arbiter was drawn for that have not been released; stake is locked while > 0
+    pub open_cases: u32,
+}
+
+/// One service listed under a tag, or one agent under a capability
+/// An index is enumerated by filtering these accounts on `kind` and `key`, which sit at a
+/// fixed offset; membership of a given entry is a PDA lookup
+#[account]
+#[derive(InitSpace)]
+pub struct IndexEntry {
+    pub kind: IndexKind,
+    /// Normalised tag or capability (see `index_keys`)
+    #[max_len(MAX_INDEX_KEY_LEN)]
+    pub key: String,
+    pub entry: Pubkey,
+    pub bump: u8,
+}
+
+// ============================================================
+// ENUMS
+// ============================================================
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum IndexKind {
+    Tag,
+    Capability,
+}
+
+impl IndexKind {
+    pub fn seed(&self) -> &'static [u8] {
+        match self {
+            IndexKind::Tag => b"tag_index",
+            IndexKind::Capability => b"capability_index",
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum AccessListMode {
+    Open,
+    Allowlist,
+    Denylist,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum Discount {
+    Percent { bps: u16 },
+    Fixed { lamports: u64 },
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum PaymentStatus {
+    Escrowed,
+    Released,
+    Refunded,
+    Disputed,
+    /// Split between payer and receiver by mutual agreement
+    Settled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum DisputeStatus {
+    /// Waiting for the panel to reach a majority
+    Open,
+    /// Panel has ruled; payout waits for the appeal window to pass
+    Ruled,
+    /// A party appealed; the pool authority rules again
+    Appealed,
+    Resolved,
+    /// Panel missed its vote deadline; the escrow went back to the payer
+    Expired,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum UsageReportStatus {
+    Pending,
+    Settled,
+    Contested,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum SubscriptionStatus {
+    Active,
+    Lapsed,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum AuctionStatus {
+    Open,
+    Settled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum JobStatus {
+    Open,
+    Awarded,
+    Cancelled,
+}
+
+/// Snapshot of what a buyer purchases under, logged on every change
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct ServiceTerms {
+    pub title: String,
+    pub description: String,
+    pub price_lamports: u64,
+    pub price_model: PriceModel,
+    pub pricing: Option<PricingSchedule>,
+    pub sla: Option<SlaTerms>,
+    pub timeout_policy: TimeoutPolicy,
+    pub tags: Vec<String>,
+}
+
+/// What happens to an escrowed order the payer never releases
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum TimeoutPolicy {
+    /// Refundable to the payer once the payment times out
+    RefundOnTimeout,
+    /// Once marked delivered, released to the provider unless the payer disputes within the window
+    ReleaseAfterDelivery { challenge_seconds: i64 },
+}
+
+/// Delivery deadlines and the escrow share refunded to the payer when they are missed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct SlaTerms {
+    /// Seconds from payment to acknowledgement (0 = not tracked)
+    pub max_ack_seconds: i64,
+    pub max_delivery_seconds: i64,
+    /// Ascending by `late_by_seconds`; the last step reached applies
+    #[max_len(4)]
+    pub penalties: Vec<SlaPenalty>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct SlaPenalty {
+    pub late_by_seconds: i64,
+    pub refund_bps: u16,
+}
+
+impl SlaTerms {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.max_ack_seconds >= 0
+                && self.max_delivery_seconds > 0
+                && self.penalties.len() <= MAX_SLA_PENALTIES,
+            SolAgentError::InvalidSla
+        );
+        let mut last: Option<&SlaPenalty> = None;
+        for penalty in &self.penalties {
+            require!(
+                penalty.late_by_seconds >= 0
+                    && penalty.refund_bps <= BPS_DENOMINATOR
+                    && !last.is_some_and(|l| {
+                        penalty.late_by_seconds <= l.late_by_seconds
+                            || penalty.refund_bps < l.refund_bps
+                    }),
+                SolAgentError::InvalidSla
+            );
+            last = Some(penalty);
+        }
+        Ok(())
+    }
+
+    /// How late the provider was against the worse of the two deadlines (<= 0 means on time)
+    pub fn late_seconds(&self, created_at: i64, acknowledged_at: i64, delivered_at: i64) -> i64 {
+        let delivery_late = delivered_at - created_at - self.max_delivery_seconds;
+        if self.max_ack_seconds == 0 {
+            return delivery_late;
+        }
+        // Delivery without a separate acknowledgement counts as acknowledging
+        let acked_at = if acknowledged_at > 0 {
+            acknowledged_at
+        } else {
+            delivered_at
+        };
+        let ack_late = acked_at - created_at - self.max_ack_seconds;
+        delivery_late.max(ack_late)
+    }
+
+    /// Refund share for an order that was `late_seconds` late
+    pub fn penalty_bps(&self, late_seconds: i64) -> u16 {
+        if late_seconds <= 0 {
+            return 0;
+        }
+        self.penalties
+            .iter()
+            .filter(|p| late_seconds >= p.late_by_seconds)
+            .map(|p| p.refund_bps)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Who may buy from a service
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct AccessRules {
+    pub list_mode: AccessListMode,
+    /// Buyer agent PDAs, or organisation keys matched against the buyer's attestation issuer
+    /// Organisation entries only bind buyers that present that organisation's attestation, so a
+    /// denylist can't keep out members who leave it out; use an allowlist or `required_attester`
+    #[max_len(10)]
+    pub list: Vec<Pubkey>,
+    pub min_reputation: u64,
+    pub required_attester: Option<Pubkey>,
+}
+
+impl AccessRules {
+    /// Check a buyer agent (and the attestation it presented) against these rules
+    pub fn check(
+        &self,
+        buyer: &Account<Agent>,
+        attestation: Option<&Attestation>,
+        now: i64,
+    ) -> Result<()> {
+        let attestation = attestation.filter(|a| a.is_valid_at(now));
+
+        require!(
+            buyer.reputation_score >= self.min_reputation,
+            SolAgentError::ReputationTooLow
+        );
+        if let Some(attester) = self.required_attester {
+            require!(
+                attestation.is_some_and(|a| a.issuer == attester),
+                SolAgentError::AttestationRequired
+            );
+        }
+        let listed = self.list.iter().any(|entry| {
+            *entry == buyer.key() || attestation.is_some_and(|a| a.issuer == *entry)
+        });
+        match self.list_mode {
+            AccessListMode::Open => {}
+            AccessListMode::Allowlist => require!(listed, SolAgentError::BuyerNotAllowlisted),
+            AccessListMode::Denylist => require!(!listed, SolAgentError::BuyerDenylisted),
+        }
+        Ok(())
+    }
+}
+
+/// Volume pricing layered on top of `Service.price_lamports`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct PricingSchedule {
+    /// Ascending unit thresholds; units before the first tier use the base price
+    #[max_len(5)]
+    pub tiers: Vec<PriceTier>,
+    /// The largest discount whose reputation threshold the buyer meets applies
+    #[max_len(4)]
+    pub reputation_discounts: Vec<ReputationDiscount>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct PriceTier {
+    pub from_unit: u64,
+    pub price_lamports: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct ReputationDiscount {
+    pub min_reputation: u64,
+    pub discount_bps: u16,
+}
+
+impl PricingSchedule {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.tiers.len() <= MAX_PRICE_TIERS
+                && self.reputation_discounts.len() <= MAX_REPUTATION_DISCOUNTS,
+            SolAgentError::InvalidPricingSchedule
+        );
+        let mut last_from = 0;
+        for tier in &self.tiers {
+            require!(tier.from_unit > last_from, SolAgentError::InvalidPricingSchedule);
+            last_from = tier.from_unit;
+        }
+        for discount in &self.reputation_discounts {
+            require!(
+                discount.discount_bps <= BPS_DENOMINATOR,
+                SolAgentError::InvalidPricingSchedule
+            );
+        }
+        Ok(())
+    }
+
+    /// Discount in basis points for a buyer with the given reputation score
+    pub fn discount_bps(&self, reputation: u64) -> u16 {
+        self.reputation_discounts
+            .iter()
+            .filter(|d| reputation >= d.min_reputation)
+            .map(|d| d.discount_bps)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Undiscounted cost of units `[units_before, units_before + quantity)`
+    pub fn tiered_cost(&self, base_price: u64, units_before: u64, quantity: u64) -> Option<u64> {
+        let end = units_before.checked_add(quantity)?;
+        let mut total: u64 = 0;
+        let mut band_start = 0;
+        let mut band_price = base_price;
+        for tier in self.tiers.iter().map(Some).chain(std::iter::once(None)) {
+            let band_end = tier.map_or(u64::MAX, |t| t.from_unit);
+            let lo = units_before.max(band_start);
+            let hi = end.min(band_end);
+            if hi > lo {
+                total = total.checked_add(band_price.checked_mul(hi - lo)?)?;
+            }
+            if let Some(t) = tier {
+                band_start = t.from_unit;
+                band_price = t.price_lamports;
+            }
+        }
+        Some(total)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum PriceModel {
+    Fixed,          // One-time payment
+    PerRequest,     // Pay per API call
+    PerSecond,      // Streaming payment
+    PerToken,       // Pay per output token
+    Auction,        // Highest bidder wins
+    Subscription { period_seconds: i64 }, // Recurring charge every period
+}
+
+// ============================================================
+// EVENTS
+// ============================================================
+
+#[event]
+pub struct ProtocolInitialized {
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CategoryCreated {
+    pub category: Pubkey,
+    pub id: u16,
+    pub name: String,
+}
+
+#[event]
+pub struct CategoryUpdated {
+    pub category: Pubkey,
+    pub id: u16,
+    pub name: String,
+    pub is_active: bool,
+}
+
+#[event]
+pub struct ServiceCategoriesUpdated {
+    pub service: Pubkey,
+    pub categories: Vec<u16>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AgentRegistered {
+    pub agent: Pubkey,
+    pub authority: Pubkey,
+    pub name: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AgentCapabilitiesUpdated {
+    pub agent: Pubkey,
+    pub capabilities: Vec<String>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AgentMigrated {
+    pub agent: Pubkey,
+    /// Account size after the migration, discriminator included
+    pub space: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReputationStaked {
+    pub agent: Pubkey,
+    pub amount: u64,
+    pub new_score: u64,
+    pub total_staked: u64,
+}
+
+#[event]
+pub struct FeedbackSubmitted {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub rating: u8,
+    pub new_reputation: u64,
+}
+
+#[event]
+pub struct ServiceCreated {
+    pub service: Pubkey,
+    pub provider: Pubkey,
+    pub title: String,
+    pub price: u64,
+    pub price_model: PriceModel,
+}
+
+#[event]
+pub struct AttestationIssued {
+    pub attestation: Pubkey,
+    pub issuer: Pubkey,
+    pub subject: Pubkey,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct SessionKeyCreated {
+    pub session_key: Pubkey,
+    pub agent: Pubkey,
+    pub key: Pubkey,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct SessionKeyRevoked {
+    pub session_key: Pubkey,
+    pub agent: Pubkey,
+    pub key: Pubkey,
+}
+
+#[event]
+pub struct AttestationRevoked {
+    pub attestation: Pubkey,
+    pub issuer: Pubkey,
+    pub subject: Pubkey,
+}
+
+#[event]
+pub struct AccessRulesUpdated {
+    pub service: Pubkey,
+    pub access: Option<AccessRules>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ServiceCapacityUpdated {
+    pub service: Pubkey,
+    pub max_concurrent_orders: u64,
+    pub open_orders: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TimeoutPolicyUpdated {
+    pub service: Pubkey,
+    pub timeout_policy: TimeoutPolicy,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ServiceMigrated {
+    pub service: Pubkey,
+    /// Account size after the migration, discriminator included
+    pub space: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentMigrated {
+    pub payment: Pubkey,
+    /// Account size after the migration, discriminator included
+    pub space: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SlaUpdated {
+    pub service: Pubkey,
+    pub sla: Option<SlaTerms>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PricingScheduleUpdated {
+    pub service: Pubkey,
+    pub pricing: Option<PricingSchedule>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ServiceUpdated {
+    pub service: Pubkey,
+    pub version: u64,
+    pub old_terms: ServiceTerms,
+    pub new_terms: ServiceTerms,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ServiceStatusChanged {
+    pub service: Pubkey,
+    pub is_active: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ServiceClosed {
+    pub service: Pubkey,
+    pub provider: Pubkey,
+    pub total_orders: u64,
+    pub total_revenue: u64,
+}
+
+#[event]
+pub struct CouponCreated {
+    pub coupon: Pubkey,
+    pub service: Pubkey,
+    pub code: String,
+    pub discount: Discount,
+    pub max_redemptions: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct CouponRedeemed {
+    pub coupon: Pubkey,
+    pub service: Pubkey,
+    pub payment: Pubkey,
+    pub buyer: Pubkey,
+    pub discount: u64,
+    pub amount: u64,
+    pub redemptions: u64,
+}
+
+#[event]
+pub struct CouponClosed {
+    pub coupon: Pubkey,
+    pub service: Pubkey,
+    pub redemptions: u64,
+}
+
+#[event]
+pub struct ReferralFeeUpdated {
+    pub service: Pubkey,
+    pub referral_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralPaid {
+    pub payment: Pubkey,
+    pub referrer: Pubkey,
+    pub receiver: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct PaymentAcknowledged {
+    pub payment: Pubkey,
+    pub receiver: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentDelivered {
+    pub payment: Pubkey,
+    pub receiver: Pubkey,
+    pub timestamp: i64,
+    /// End of the payer's challenge window under `ReleaseAfterDelivery`
+    pub auto_release_at: Option<i64>,
+}
+
+#[event]
+pub struct SlaPenaltyApplied {
+    pub payment: Pubkey,
+    pub late_seconds: i64,
+    pub refunded: u64,
+}
+
+#[event]
+pub struct PaymentCreated {
+    pub payment: Pubkey,
+    pub payer: Pubkey,
+    pub receiver: Pubkey,
+    pub amount: u64,
+    pub intent: String,
+}
+
+#[event]
+pub struct PaymentReleased {
+    pub payment: Pubkey,
+    pub receiver: Pubkey,
+    pub amount: u64,
+    pub latency_ms: u64,
+}
+
+#[event]
+pub struct MilestonesSet {
+    pub payment: Pubkey,
+    pub milestones: Vec<MilestoneSpec>,
+}
+
+#[event]
+pub struct MilestoneReleased {
+    pub payment: Pubkey,
+    pub index: u8,
+    pub amount: u64,
+    pub remaining: u64,
+}
+
+#[event]
+pub struct PaymentSettled {
+    pub payment: Pubkey,
+    pub payer_amount: u64,
+    pub receiver_amount: u64,
+    pub referral_share: u64,
+}
+
+#[event]
+pub struct ReceiptRedeemed {
+    pub payment: Pubkey,
+    /// Payer authority or session key that signed the receipt
+    pub signer: Pubkey,
+}
+
+#[event]
+pub struct PreimageRevealed {
+    pub payment: Pubkey,
+    pub hash: [u8; 32],
+    pub preimage: Vec<u8>,
+}
+
+#[event]
+pub struct PaymentRefunded {
+    pub payment: Pubkey,
+    pub payer: Pubkey,
+    pub amount: u64,
+    pub reason: String,
+}
+
+#[event]
+pub struct PaymentClosed {
+    pub payment: Pubkey,
+    pub payer: Pubkey,
+    pub service: Pubkey,
+}
+
+#[event]
+pub struct UsageEscrowOpened {
+    pub usage_escrow: Pubkey,
+    pub payer: Pubkey,
+    pub service: Pubkey,
+    pub price_per_unit: u64,
+    pub deposit: u64,
+}
+
+#[event]
+pub struct UsageEscrowFunded {
+    pub usage_escrow: Pubkey,
+    pub amount: u64,
+    pub available: u64,
+}
+
+#[event]
+pub struct UsageReported {
+    pub usage_escrow: Pubkey,
+    pub report: Pubkey,
+    pub units: u64,
+    pub amount: u64,
+    pub co_signed: bool,
+}
+
+#[event]
+pub struct UsageSettled {
+    pub usage_escrow: Pubkey,
+    pub report: Pubkey,
+    pub units: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct UsageContested {
+    pub usage_escrow: Pubkey,
+    pub report: Pubkey,
+    pub amount: u64,
 }
 
-#[derive(Accounts)]
-pub struct SubmitFeedback<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Feedback::INIT_SPACE,
-        seeds = [b"feedback", from_agent.key().as_ref(), to_agent.key().as_ref()],
-        bump
-    )]
-    pub feedback: Account<'info, Feedback>,
-    pub from_agent: Account<'info, Agent>,
-    #[account(mut)]
-    pub to_agent: Account<'info, Agent>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct UsageReportResolved {
+    pub usage_escrow: Pubkey,
+    pub report: Pubkey,
+    pub units: u64,
+    pub amount: u64,
 }
 
-#[derive(Accounts)]
-#[instruction(service_id: String)]
-pub struct CreateService<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Service::INIT_SPACE,
-        seeds = [b"service", agent.key().as_ref(), service_id.as_bytes()],
-        bump
-    )]
-    pub service: Account<'info, Service>,
-    #[account(
-        seeds = [b"agent", authority.key().as_ref()],
-        bump = agent.bump,
-        has_one = authority
-    )]
-    pub agent: Account<'info, Agent>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct UsageReportClosed {
+    pub usage_escrow: Pubkey,
+    pub report: Pubkey,
 }
 
-#[derive(Accounts)]
-pub struct PayForService<'info> {
-    #[account(
-        init,
-        payer = payer_authority,
-        space = 8 + Payment::INIT_SPACE,
-        seeds = [b"payment", payer_agent.key().as_ref(), service.key().as_ref(), &service.total_orders.to_le_bytes()],
-        bump
-    )]
-    pub payment: Account<'info, Payment>,
-    /// CHECK: escrow PDA to hold payment
-    #[account(
-        mut,
-        seeds = [b"escrow", payment.key().as_ref()],
-        bump
-    )]
-    pub escrow: AccountInfo<'info>,
-    #[account(mut)]
-    pub payer_agent: Account<'info, Agent>,
-    pub receiver_agent: Account<'info, Agent>,
-    #[account(mut)]
-    pub service: Account<'info, Service>,
-    #[account(mut)]
-    pub payer_authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct UsageEscrowClosed {
+    pub usage_escrow: Pubkey,
+    pub units_billed: u64,
+    pub drawn: u64,
+    pub refunded: u64,
 }
 
-#[derive(Accounts)]
-pub struct ReleasePayment<'info> {
-    #[account(
-        mut,
-        constraint = payment.payer == payer_agent.key() @ SolAgentError::Unauthorized,
-    )]
-    pub payment: Account<'info, Payment>,
-    /// CHECK: escrow PDA
-    #[account(mut)]
-    pub escrow: AccountInfo<'info>,
-    #[account(has_one = authority @ SolAgentError::Unauthorized)]
-    pub payer_agent: Account<'info, Agent>,
-    #[account(mut)]
-    pub receiver_agent: Account<'info, Agent>,
-    #[account(mut)]
-    pub service: Account<'info, Service>,
-    /// CHECK: receiver wallet
-    #[account(mut)]
-    pub receiver_authority: AccountInfo<'info>,
-    pub authority: Signer<'info>,
+#[event]
+pub struct Subscribed {
+    pub subscription: Pubkey,
+    pub subscriber: Pubkey,
+    pub service: Pubkey,
+    pub amount_per_period: u64,
+    pub period_seconds: i64,
 }
 
-#[derive(Accounts)]
-pub struct RefundPayment<'info> {
-    #[account(mut)]
-    pub payment: Account<'info, Payment>,
-    /// CHECK: escrow PDA
-    #[account(mut)]
-    pub escrow: AccountInfo<'info>,
-    pub payer_agent: Account<'info, Agent>,
-    /// CHECK: payer wallet for refund
-    #[account(mut)]
-    pub payer_authority: AccountInfo<'info>,
-    pub authority: Signer<'info>,
+#[event]
+pub struct SubscriptionFunded {
+    pub subscription: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
 }
 
-#[derive(Accounts)]
-pub struct CreateStream<'info> {
-    #[account(
-        init,
-        payer = payer_authority,
-        space = 8 + Stream::INIT_SPACE,
-        seeds = [b"stream", payer_agent.key().as_ref(), receiver_agent.key().as_ref()],
-        bump
-    )]
-    pub stream: Account<'info, Stream>,
-    /// CHECK: vault for stream deposits
-    #[account(
-        mut,
-        seeds = [b"stream_vault", stream.key().as_ref()],
-        bump
-    )]
-    pub stream_vault: AccountInfo<'info>,
-    pub payer_agent: Account<'info, Agent>,
-    pub receiver_agent: Account<'info, Agent>,
-    #[account(mut)]
-    pub payer_authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct SubscriptionCharged {
+    pub subscription: Pubkey,
+    pub amount: u64,
+    pub period: u64,
+    pub paid_until: i64,
 }
 
-#[derive(Accounts)]
-pub struct WithdrawStream<'info> {
-    #[account(mut)]
-    pub stream: Account<'info, Stream>,
-    /// CHECK: stream vault PDA
-    #[account(mut)]
-    pub stream_vault: AccountInfo<'info>,
-    #[account(mut)]
-    pub receiver_agent: Account<'info, Agent>,
-    /// CHECK: receiver wallet
-    #[account(mut)]
-    pub receiver_authority: AccountInfo<'info>,
-    /// CHECK: payer wallet for refunds
-    #[account(mut)]
-    pub payer_authority: AccountInfo<'info>,
-    pub authority: Signer<'info>,
+#[event]
+pub struct SubscriptionLapsed {
+    pub subscription: Pubkey,
+    pub subscriber: Pubkey,
+    pub balance: u64,
 }
 
-// ============================================================
-// STATE
-// ============================================================
+#[event]
+pub struct SubscriptionCancelled {
+    pub subscription: Pubkey,
+    pub subscriber: Pubkey,
+    pub periods_charged: u64,
+    pub refunded: u64,
+}
 
-#[account]
-#[derive(InitSpace)]
-pub struct Protocol {
-    pub authority: Pubkey,
-    pub total_agents: u64,
-    pub total_services: u64,
-    pub total_payments: u64,
-    pub total_volume: u64,
-    pub total_staked: u64,
-    pub fee_bps: u16,
-    pub treasury: Pubkey,
-    pub bump: u8,
+#[event]
+pub struct AuctionOpened {
+    pub auction: Pubkey,
+    pub service: Pubkey,
+    pub reserve_price: u64,
+    pub end_at: i64,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Agent {
-    pub authority: Pubkey,
-    #[max_len(32)]
-    pub name: String,
-    #[max_len(256)]
-    pub description: String,
-    #[max_len(10, 32)]
-    pub capabilities: Vec<String>,
-    #[max_len(128)]
-    pub endpoint: String,
-    pub reputation_score: u64,
-    pub total_staked: u64,
-    pub total_earned: u64,
-    pub total_spent: u64,
-    pub services_completed: u64,
-    pub services_requested: u64,
-    pub feedbacks_received: u64,
-    pub registered_at: i64,
+#[event]
+pub struct BidPlaced {
+    pub auction: Pubkey,
+    pub bidder: Pubkey,
+    pub amount: u64,
+    pub previous_bidder: Pubkey,
+    pub refunded: u64,
+}
+
+#[event]
+pub struct AuctionSettled {
+    pub auction: Pubkey,
+    pub payment: Pubkey,
+    pub winner: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct AuctionCancelled {
+    pub auction: Pubkey,
+    pub service: Pubkey,
+}
+
+#[event]
+pub struct AuctionClosed {
+    pub auction: Pubkey,
+    pub service: Pubkey,
+    pub payment: Pubkey,
+}
+
+#[event]
+pub struct JobPosted {
+    pub job: Pubkey,
+    pub buyer: Pubkey,
+    pub budget: u64,
+    pub deadline: i64,
+    pub intent: String,
+}
+
+#[event]
+pub struct JobBidSubmitted {
+    pub job: Pubkey,
+    pub bid: Pubkey,
+    pub provider: Pubkey,
+    pub price: u64,
+    pub delivery_seconds: i64,
+}
+
+#[event]
+pub struct JobBidWithdrawn {
+    pub job: Pubkey,
+    pub bid: Pubkey,
+    pub provider: Pubkey,
+}
+
+#[event]
+pub struct JobAwarded {
+    pub job: Pubkey,
+    pub bid: Pubkey,
+    pub payment: Pubkey,
+    pub provider: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct JobCancelled {
+    pub job: Pubkey,
+    pub buyer: Pubkey,
+}
+
+#[event]
+pub struct JobClosed {
+    pub job: Pubkey,
+    pub buyer: Pubkey,
+}
+
+#[event]
+pub struct BundleCreated {
+    pub bundle: Pubkey,
+    pub orchestrator: Pubkey,
+    pub price: u64,
+    pub components: Vec<BundleComponent>,
+}
+
+#[event]
+pub struct BundlePaymentReleased {
+    pub payment: Pubkey,
+    pub bundle: Pubkey,
+    pub amount: u64,
+    pub components_share: u64,
+    pub orchestrator_share: u64,
+}
+
+#[event]
+pub struct BundleRatingUpdated {
+    pub bundle: Pubkey,
+    pub avg_rating: u8,
+    pub component_reputation: u64,
+}
+
+#[event]
+pub struct BundleStatusChanged {
+    pub bundle: Pubkey,
     pub is_active: bool,
-    pub bump: u8,
+    pub timestamp: i64,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Service {
-    pub provider: Pubkey,
-    pub authority: Pubkey,
-    #[max_len(32)]
-    pub service_id: String,
-    #[max_len(64)]
-    pub title: String,
-    #[max_len(256)]
-    pub description: String,
-    pub price_lamports: u64,
-    pub price_model: PriceModel,
-    #[max_len(5, 32)]
-    pub tags: Vec<String>,
+#[event]
+pub struct BundleClosed {
+    pub bundle: Pubkey,
     pub total_orders: u64,
     pub total_revenue: u64,
-    pub avg_rating: u8,
-    pub is_active: bool,
-    pub created_at: i64,
-    pub bump: u8,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Payment {
-    pub payer: Pubkey,
-    pub receiver: Pubkey,
-    pub service: Pubkey,
-    pub amount: u64,
-    #[max_len(256)]
-    pub intent: String,
-    #[max_len(5, 64)]
-    pub conditions: Vec<String>,
-    pub status: PaymentStatus,
-    pub created_at: i64,
-    pub timeout_at: i64,
-    pub completed_at: i64,
-    pub bump: u8,
-    pub escrow_bump: u8,
+#[event]
+pub struct DisputeOpened {
+    pub dispute: Pubkey,
+    pub payment: Pubkey,
+    pub opened_by: Pubkey,
+    pub arbiters: Vec<Pubkey>,
+    pub reason: String,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Feedback {
-    pub from_agent: Pubkey,
-    pub to_agent: Pubkey,
-    pub rating: u8,
-    #[max_len(256)]
-    pub comment: String,
-    pub timestamp: i64,
-    pub bump: u8,
+#[event]
+pub struct DisputePanelDrawn {
+    pub dispute: Pubkey,
+    pub arbiters: Vec<Pubkey>,
+    pub vote_deadline: i64,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Stream {
-    pub payer: Pubkey,
-    pub receiver: Pubkey,
-    pub rate_per_second: u64,
-    pub deposited: u64,
-    pub withdrawn: u64,
-    pub started_at: i64,
-    pub max_end_at: i64,
-    pub last_withdrawn_at: i64,
-    pub is_active: bool,
-    pub bump: u8,
-    pub vault_bump: u8,
+#[event]
+pub struct DisputePanelRecommitted {
+    pub dispute: Pubkey,
+    pub draw_slot: u64,
 }
 
-// ============================================================
-// ENUMS
-// ============================================================
-
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
-pub enum PaymentStatus {
-    Escrowed,
-    Released,
-    Refunded,
-    Disputed,
+#[event]
+pub struct DisputeExpired {
+    pub dispute: Pubkey,
+    pub payment: Pubkey,
+    pub refunded: u64,
+    pub slashed_arbiters: u8,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
-pub enum PriceModel {
-    Fixed,          // One-time payment
-    PerRequest,     // Pay per API call
-    PerSecond,      // Streaming payment
-    PerToken,       // Pay per output token
-    Auction,        // Highest bidder wins
+#[event]
+pub struct ArbiterReleased {
+    pub arbiter: Pubkey,
+    pub dispute: Pubkey,
+    pub open_cases: u32,
 }
 
-// ============================================================
-// EVENTS
-// ============================================================
-
 #[event]
-pub struct ProtocolInitialized {
-    pub authority: Pubkey,
+pub struct EvidenceSubmitted {
+    pub dispute: Pubkey,
+    pub submitter: Pubkey,
+    pub hash: [u8; 32],
+    pub uri: String,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct AgentRegistered {
-    pub agent: Pubkey,
-    pub authority: Pubkey,
-    pub name: String,
-    pub timestamp: i64,
+pub struct ArbiterVoted {
+    pub dispute: Pubkey,
+    pub arbiter: Pubkey,
+    pub payer_amount: u64,
+    pub fee: u64,
 }
 
 #[event]
-pub struct ReputationStaked {
-    pub agent: Pubkey,
-    pub amount: u64,
-    pub new_score: u64,
-    pub total_staked: u64,
+pub struct DisputeRuled {
+    pub dispute: Pubkey,
+    pub payer_amount: u64,
+    pub receiver_amount: u64,
+    pub appeal_deadline: i64,
 }
 
 #[event]
-pub struct FeedbackSubmitted {
-    pub from: Pubkey,
-    pub to: Pubkey,
-    pub rating: u8,
-    pub new_reputation: u64,
+pub struct DisputeAppealed {
+    pub dispute: Pubkey,
+    pub appellant: Pubkey,
 }
 
 #[event]
-pub struct ServiceCreated {
-    pub service: Pubkey,
-    pub provider: Pubkey,
-    pub title: String,
-    pub price: u64,
-    pub price_model: PriceModel,
+pub struct DisputeAppealResolved {
+    pub dispute: Pubkey,
+    pub panel_payer_amount: u64,
+    pub payer_amount: u64,
+    pub overturned: bool,
 }
 
 #[event]
-pub struct PaymentCreated {
+pub struct DisputeResolved {
+    pub dispute: Pubkey,
     pub payment: Pubkey,
-    pub payer: Pubkey,
-    pub receiver: Pubkey,
-    pub amount: u64,
-    pub intent: String,
+    pub payer_amount: u64,
+    pub receiver_amount: u64,
+    pub appealed: bool,
 }
 
 #[event]
-pub struct PaymentReleased {
-    pub payment: Pubkey,
-    pub receiver: Pubkey,
+pub struct ArbiterPoolCreated {
+    pub arbiter_pool: Pubkey,
+    pub pool_id: u16,
+    pub min_stake: u64,
+    pub fee_bps: u16,
+    pub panel_size: u8,
+    pub vote_window_seconds: i64,
+}
+
+#[event]
+pub struct ArbiterRegistered {
+    pub arbiter: Pubkey,
+    pub arbiter_pool: Pubkey,
+    pub authority: Pubkey,
+    pub stake: u64,
+}
+
+#[event]
+pub struct ArbiterStakeAdded {
+    pub arbiter: Pubkey,
     pub amount: u64,
-    pub latency_ms: u64,
+    pub stake: u64,
 }
 
 #[event]
-pub struct PaymentRefunded {
-    pub payment: Pubkey,
-    pub payer: Pubkey,
+pub struct ArbiterSlashed {
+    pub arbiter: Pubkey,
+    pub dispute: Pubkey,
     pub amount: u64,
-    pub reason: String,
+    pub remaining_stake: u64,
+}
+
+#[event]
+pub struct ArbiterWithdrawn {
+    pub arbiter: Pubkey,
+    pub arbiter_pool: Pubkey,
+    pub stake: u64,
+}
+
+#[event]
+pub struct ServiceArbiterPoolSet {
+    pub service: Pubkey,
+    pub arbiter_pool: Option<Pubkey>,
 }
 
 #[event]
@@ -922,4 +7970,812 @@ pub enum SolAgentError {
     NothingToWithdraw,
     #[msg("Unauthorized")]
     Unauthorized,
+    #[msg("Service is not active")]
+    ServiceInactive,
+    #[msg("Service is already active")]
+    ServiceAlreadyActive,
+    #[msg("Service still has open orders")]
+    ServiceHasOpenOrders,
+    #[msg("Payment does not belong to this service")]
+    ServiceMismatch,
+    #[msg("Receiver agent is not the service provider")]
+    ProviderMismatch,
+    #[msg("Payment amount does not match the service price")]
+    AmountMismatch,
+    #[msg("Invalid quantity for this price model")]
+    InvalidQuantity,
+    #[msg("Price model cannot be paid through pay_for_service")]
+    UnsupportedPriceModel,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Service price model does not support this operation")]
+    PriceModelMismatch,
+    #[msg("Duration must be greater than zero")]
+    InvalidDuration,
+    #[msg("Auction is not open")]
+    AuctionNotOpen,
+    #[msg("Auction has ended")]
+    AuctionEnded,
+    #[msg("Auction has not ended yet")]
+    AuctionNotEnded,
+    #[msg("Bid must meet the reserve and exceed the highest bid")]
+    BidTooLow,
+    #[msg("Outbid bidder's wallet must be provided")]
+    PreviousBidderMissing,
+    #[msg("Auction has no bids")]
+    NoBids,
+    #[msg("Auction already has bids")]
+    AuctionHasBids,
+    #[msg("Usage report exceeds the payer's per-report cap")]
+    UsageCapExceeded,
+    #[msg("Insufficient funds in usage escrow")]
+    InsufficientEscrow,
+    #[msg("Usage report is not pending")]
+    UsageReportNotPending,
+    #[msg("Challenge window has closed")]
+    ChallengeWindowClosed,
+    #[msg("Challenge window is still open")]
+    ChallengeWindowOpen,
+    #[msg("Usage escrow has pending reports")]
+    UsageReportsPending,
+    #[msg("Job is not open")]
+    JobNotOpen,
+    #[msg("Job bidding deadline has passed")]
+    JobDeadlinePassed,
+    #[msg("Bid price exceeds the job budget")]
+    BidExceedsBudget,
+    #[msg("Provider lacks a capability required by the job")]
+    MissingCapability,
+    #[msg("Subscription is not active")]
+    SubscriptionInactive,
+    #[msg("Subscription period is not due yet")]
+    SubscriptionNotDue,
+    #[msg("Pricing tiers must ascend and discounts cannot exceed 100%")]
+    InvalidPricingSchedule,
+    #[msg("Coupon code exceeds 32 characters")]
+    CouponCodeTooLong,
+    #[msg("Too many allowed buyers (max 10)")]
+    TooManyAllowedBuyers,
+    #[msg("Discount must be non-zero and at most 100%")]
+    InvalidDiscount,
+    #[msg("Coupon has expired")]
+    CouponExpired,
+    #[msg("Coupon has no redemptions left")]
+    CouponExhausted,
+    #[msg("Buyer is not allowed to redeem this coupon")]
+    CouponNotAllowed,
+    #[msg("Referral fee cannot exceed 100%")]
+    InvalidReferralFee,
+    #[msg("Referrer cannot be the payer or the provider")]
+    InvalidReferrer,
+    #[msg("Referred payment requires the referrer agent and wallet")]
+    ReferrerMissing,
+    #[msg("Too many access list entries (max 10)")]
+    TooManyAccessEntries,
+    #[msg("Buyer is not on the service allowlist")]
+    BuyerNotAllowlisted,
+    #[msg("Buyer is on the service denylist")]
+    BuyerDenylisted,
+    #[msg("Buyer reputation is below the service minimum")]
+    ReputationTooLow,
+    #[msg("Service requires a valid attestation from its attester")]
+    AttestationRequired,
+    #[msg("Attestation does not belong to the buyer")]
+    AttestationInvalid,
+    #[msg("Service has reached its maximum concurrent orders")]
+    ServiceAtCapacity,
+    #[msg("SLA needs a delivery deadline and ascending penalty steps of at most 100%")]
+    InvalidSla,
+    #[msg("Payment has already been acknowledged")]
+    AlreadyAcknowledged,
+    #[msg("Payment has already been marked delivered")]
+    AlreadyDelivered,
+    #[msg("Tags and capabilities must be at most 32 characters")]
+    InvalidIndexKey,
+    #[msg("Discovery index accounts missing or out of order")]
+    IndexAccountMismatch,
+    #[msg("Too many categories (max 3)")]
+    TooManyCategories,
+    #[msg("Category accounts missing, duplicated or out of order")]
+    InvalidCategory,
+    #[msg("Category has been retired")]
+    CategoryInactive,
+    #[msg("Bundle needs 1-5 distinct third-party components with shares totalling at most 100%")]
+    InvalidBundle,
+    #[msg("Bundle component accounts missing or out of order")]
+    BundleComponentMismatch,
+    #[msg("Only the payment's payer or receiver can do this")]
+    NotPaymentParty,
+    #[msg("Dispute is not open")]
+    DisputeNotOpen,
+    #[msg("Evidence URI exceeds 128 characters")]
+    EvidenceUriTooLong,
+    #[msg("This party already submitted the maximum evidence entries (8)")]
+    EvidenceLimitReached,
+    #[msg("Dispute split exceeds the escrowed amount")]
+    InvalidDisputeSplit,
+    #[msg("Arbiter panel must be odd and 1-5, fee below 100%, slash at most 100% and vote window positive")]
+    InvalidArbiterPool,
+    #[msg("Arbiter pool is full (max 16)")]
+    ArbiterPoolFull,
+    #[msg("Arbiter pool has fewer arbiters than its panel size")]
+    NotEnoughArbiters,
+    #[msg("Stake is below the pool minimum")]
+    InsufficientArbiterStake,
+    #[msg("Arbiter pool does not match the service")]
+    ArbiterPoolMismatch,
+    #[msg("Signer is not on this dispute's panel")]
+    NotAssignedArbiter,
+    #[msg("Arbiter has already voted on this dispute")]
+    AlreadyVoted,
+    #[msg("Appeal window has not passed yet")]
+    AppealWindowOpen,
+    #[msg("Appeal window has closed")]
+    AppealWindowClosed,
+    #[msg("Arbiter stake is locked by an open case, recent ruling or pending appeal")]
+    ArbiterStakeLocked,
+    #[msg("Milestones must be 1-5 non-zero amounts summing to the payment, with future non-decreasing deadlines by the timeout, set once before any release")]
+    InvalidMilestones,
+    #[msg("Milestone has already been released")]
+    MilestoneReleased,
+    #[msg("Settlement refund exceeds the unreleased escrow")]
+    InvalidSettlement,
+    #[msg("At most 5 payment conditions")]
+    TooManyConditions,
+    #[msg("Payment has no conditions")]
+    NoConditions,
+    #[msg("Payment conditions are not decided yet")]
+    ConditionsPending,
+    #[msg("Missing witness account for a payment condition")]
+    ConditionWitnessMissing,
+    #[msg("Witness account does not match the payment condition")]
+    ConditionWitnessMismatch,
+    #[msg("Oracle account is not a matching, fully verified Pyth price update")]
+    InvalidOracle,
+    #[msg("Oracle price update is too old")]
+    StaleOracle,
+    #[msg("Oracle price confidence interval is too wide")]
+    OracleConfidenceTooWide,
+    #[msg("Preimages must match every hashlock on the payment, in order")]
+    PreimageMismatch,
+    #[msg("Session key is expired or belongs to another agent")]
+    InvalidSessionKey,
+    #[msg("Missing or invalid Ed25519-signed delivery receipt")]
+    InvalidReceipt,
+    #[msg("Release-after-delivery challenge window must be positive")]
+    InvalidTimeoutPolicy,
+    #[msg("Payment does not release on timeout, or has not been delivered")]
+    NoAutoRelease,
+    #[msg("Delivered order releases to the provider unless disputed")]
+    DeliveredReleasePending,
+    #[msg("Close the listing's payment accounts first")]
+    ServiceHasPayments,
+    #[msg("Payment has not been released, refunded or settled yet")]
+    PaymentNotFinished,
+    #[msg("Auction has not been settled")]
+    AuctionNotSettled,
+    #[msg("Usage report is not contested")]
+    UsageReportNotContested,
+    #[msg("Usage escrow still has unclosed reports")]
+    UsageReportsOpen,
+    #[msg("Usage report has not been settled")]
+    UsageReportNotSettled,
+    #[msg("Job is still open")]
+    JobStillOpen,
+    #[msg("Job still has bids")]
+    JobHasBids,
+    #[msg("Every component provider must sign the bundle")]
+    ComponentProviderNotSigner,
+    #[msg("Dispute panel has already been drawn")]
+    PanelAlreadyDrawn,
+    #[msg("Panel draw slot has not passed yet")]
+    PanelDrawPending,
+    #[msg("Panel vote deadline has passed")]
+    VoteWindowClosed,
+    #[msg("Panel vote deadline has not passed yet")]
+    VoteWindowOpen,
+    #[msg("Dispute has not been resolved yet")]
+    DisputeNotFinished,
+    #[msg("Arbiter has already been released from this dispute")]
+    ArbiterAlreadyReleased,
+    #[msg("Orders with an SLA are held to it and can't be split into milestones")]
+    MilestonesWithSla,
+    #[msg("Conditions need at least one condition besides a deadline")]
+    DeadlineOnlyConditions,
+    #[msg("Oracle conditions need a max price age of 1-60 seconds")]
+    InvalidOracleCondition,
+    #[msg("Payment timed out before it was marked delivered")]
+    DeliveryAfterTimeout,
+    #[msg("Orders that release to the provider after delivery can't be split into milestones")]
+    MilestonesWithAutoRelease,
+    #[msg("Service already has the current layout")]
+    ServiceUpToDate,
+    #[msg("Payment already has the current layout")]
+    PaymentUpToDate,
+    #[msg("Agent already has the current layout")]
+    AgentUpToDate,
+    #[msg("Orders that release to the provider after delivery can't carry release conditions")]
+    ConditionsWithAutoRelease,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_service() -> Service {
+        Service {
+            provider: Pubkey::default(),
+            authority: Pubkey::default(),
+            service_id: String::new(),
+            title: String::new(),
+            description: String::new(),
+            price_lamports: 0,
+            price_model: PriceModel::Fixed,
+            pricing: None,
+            referral_bps: 0,
+            access: None,
+            sla: None,
+            version: 1,
+            tags: vec![],
+            categories: vec![],
+            arbiter_pool: None,
+            timeout_policy: TimeoutPolicy::RefundOnTimeout,
+            total_orders: 0,
+            open_orders: 0,
+            max_concurrent_orders: 0,
+            total_revenue: 0,
+            sla_orders: 0,
+            sla_met: 0,
+            avg_rating: 0,
+            is_active: true,
+            created_at: 0,
+            bump: 0,
+            unclosed_payments: 0,
+        }
+    }
+
+    #[test]
+    fn launch_layout_service_decodes_once_grown_with_zeroes() {
+        // Launch-era fields only, serialized as `create_service` wrote them then
+        let mut data = Service::DISCRIMINATOR.to_vec();
+        let (provider, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        (provider, authority, "summarize", "Summaries", "Short summaries", 5_000u64)
+            .serialize(&mut data)
+            .unwrap();
+        (PriceModel::PerRequest, vec!["nlp".to_string()], 3u64, 15_000u64, 4u8, true, 100i64, 254u8)
+            .serialize(&mut data)
+            .unwrap();
+        data.resize(8 + Service::INIT_SPACE, 0);
+
+        let service = Service::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(service.authority, authority);
+        assert_eq!(service.tags, vec!["nlp".to_string()]);
+        assert_eq!((service.total_orders, service.total_revenue, service.bump), (3, 15_000, 254));
+        assert!(service.pricing.is_none() && service.access.is_none() && service.sla.is_none());
+        assert!(service.categories.is_empty() && service.arbiter_pool.is_none());
+        assert!(service.timeout_policy == TimeoutPolicy::RefundOnTimeout);
+        assert_eq!((service.version, service.unclosed_payments), (0, 0));
+    }
+
+    #[test]
+    fn launch_layout_agent_decodes_once_grown_with_zeroes() {
+        // Launch-era fields only, serialized as `register_agent` wrote them then
+        let mut data = Agent::DISCRIMINATOR.to_vec();
+        let authority = Pubkey::new_unique();
+        (authority, "summarizer", "Summaries", vec!["nlp".to_string()], "https://a.example")
+            .serialize(&mut data)
+            .unwrap();
+        (60u64, 2_000_000_000u64, 15_000u64, 900u64, 3u64, 1u64, 2u64, 100i64, true, 255u8)
+            .serialize(&mut data)
+            .unwrap();
+        data.resize(8 + Agent::INIT_SPACE, 0);
+
+        let agent = Agent::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(agent.authority, authority);
+        assert_eq!(agent.capabilities, vec!["nlp".to_string()]);
+        assert_eq!((agent.reputation_score, agent.total_spent, agent.services_completed), (60, 900, 3));
+        assert_eq!((agent.feedbacks_received, agent.registered_at, agent.bump), (2, 100, 255));
+        assert_eq!((agent.referral_earned, agent.sla_orders, agent.disputes_lost), (0, 0, 0));
+    }
+
+    #[test]
+    fn launch_layout_payment_decodes_once_grown_with_zeroes() {
+        // Launch-era fields only, serialized as `pay_for_service` wrote them then
+        let mut data = Payment::DISCRIMINATOR.to_vec();
+        let (payer, receiver, service) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        (payer, receiver, service, 5_000u64, "summarize this", vec!["within 1h".to_string()])
+            .serialize(&mut data)
+            .unwrap();
+        (PaymentStatus::Escrowed, 100i64, 3_700i64, 0i64, 253u8, 252u8)
+            .serialize(&mut data)
+            .unwrap();
+        data.resize(8 + Payment::INIT_SPACE, 0);
+
+        let payment = Payment::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!((payment.payer, payment.receiver, payment.amount), (payer, receiver, 5_000));
+        assert_eq!(payment.legacy_conditions, vec!["within 1h".to_string()]);
+        assert!(payment.status == PaymentStatus::Escrowed);
+        assert_eq!((payment.timeout_at, payment.bump, payment.escrow_bump), (3_700, 253, 252));
+        assert!(payment.conditions.is_empty() && payment.milestones.is_empty());
+        assert!(payment.referrer.is_none() && payment.sla.is_none());
+        assert!(payment.timeout_policy == TimeoutPolicy::RefundOnTimeout);
+        assert_eq!((payment.remaining(), payment.auto_release_at()), (5_000, None));
+    }
+
+    fn schedule(tiers: &[(u64, u64)], discounts: &[(u64, u16)]) -> PricingSchedule {
+        PricingSchedule {
+            tiers: tiers
+                .iter()
+                .map(|&(from_unit, price_lamports)| PriceTier {
+                    from_unit,
+                    price_lamports,
+                })
+                .collect(),
+            reputation_discounts: discounts
+                .iter()
+                .map(|&(min_reputation, discount_bps)| ReputationDiscount {
+                    min_reputation,
+                    discount_bps,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn no_tiers_bills_base_price() {
+        let s = schedule(&[], &[]);
+        assert_eq!(s.tiered_cost(10, 0, 7), Some(70));
+        assert_eq!(s.tiered_cost(10, 500, 7), Some(70));
+    }
+
+    #[test]
+    fn tier_boundary_is_inclusive_of_from_unit() {
+        // First 1000 units at 10, then 6
+        let s = schedule(&[(1000, 6)], &[]);
+        assert_eq!(s.tiered_cost(10, 0, 1000), Some(10_000));
+        assert_eq!(s.tiered_cost(10, 0, 1001), Some(10_006));
+        assert_eq!(s.tiered_cost(10, 999, 1), Some(10));
+        assert_eq!(s.tiered_cost(10, 1000, 1), Some(6));
+    }
+
+    #[test]
+    fn order_spanning_several_tiers() {
+        let s = schedule(&[(100, 8), (200, 5)], &[]);
+        // 100 @ 10 + 100 @ 8 + 50 @ 5
+        assert_eq!(s.tiered_cost(10, 0, 250), Some(1000 + 800 + 250));
+        // Starting mid-tier: 50 @ 8 + 10 @ 5
+        assert_eq!(s.tiered_cost(10, 150, 60), Some(400 + 50));
+        // Entirely past the last threshold
+        assert_eq!(s.tiered_cost(10, 5000, 3), Some(15));
+    }
+
+    #[test]
+    fn consecutive_reports_sum_to_single_order() {
+        let s = schedule(&[(100, 8), (200, 5)], &[]);
+        let split = s.tiered_cost(10, 0, 90).unwrap()
+            + s.tiered_cost(10, 90, 30).unwrap()
+            + s.tiered_cost(10, 120, 130).unwrap();
+        assert_eq!(Some(split), s.tiered_cost(10, 0, 250));
+    }
+
+    #[test]
+    fn tiered_cost_overflow_is_none() {
+        let s = schedule(&[(10, u64::MAX)], &[]);
+        assert_eq!(s.tiered_cost(1, 0, 12), None);
+        assert_eq!(s.tiered_cost(1, u64::MAX, 1), None);
+    }
+
+    #[test]
+    fn best_matching_reputation_discount_applies() {
+        let s = schedule(&[], &[(100, 500), (1000, 1500), (500, 1000)]);
+        assert_eq!(s.discount_bps(99), 0);
+        assert_eq!(s.discount_bps(100), 500);
+        assert_eq!(s.discount_bps(999), 1000);
+        assert_eq!(s.discount_bps(1000), 1500);
+    }
+
+    #[test]
+    fn discount_rounds_in_provider_favour() {
+        assert_eq!(apply_discount(1000, 0), 1000);
+        assert_eq!(apply_discount(1000, 1500), 850);
+        assert_eq!(apply_discount(999, 1), 999);
+        assert_eq!(apply_discount(1000, BPS_DENOMINATOR), 0);
+        assert_eq!(apply_discount(u64::MAX, 5000), u64::MAX - u64::MAX / 2);
+    }
+
+    #[test]
+    fn price_units_combines_tiers_and_discount() {
+        let s = schedule(&[(1000, 6)], &[(100, 1000)]);
+        assert_eq!(price_units(10, Some(&s), 0, 1500, 1000).unwrap(), 11_700);
+        assert_eq!(price_units(10, None, 0, 1500, 0).unwrap(), 15_000);
+    }
+
+    #[test]
+    fn validate_rejects_bad_schedules() {
+        assert!(schedule(&[(1, 5), (10, 4)], &[(0, 10_000)]).validate().is_ok());
+        assert!(schedule(&[(0, 5)], &[]).validate().is_err());
+        assert!(schedule(&[(10, 5), (10, 4)], &[]).validate().is_err());
+        assert!(schedule(&[(10, 5), (5, 4)], &[]).validate().is_err());
+        assert!(schedule(&[], &[(1, 10_001)]).validate().is_err());
+        let too_many: Vec<(u64, u64)> = (1..=6).map(|i| (i, 1)).collect();
+        assert!(schedule(&too_many, &[]).validate().is_err());
+    }
+
+    #[test]
+    fn quote_uses_schedule_per_price_model() {
+        let mut service = test_service();
+        service.price_lamports = 10;
+        service.price_model = PriceModel::PerRequest;
+        service.pricing = Some(schedule(&[(1000, 6)], &[(100, 1000)]));
+        assert_eq!(service.quote(1000, 0).unwrap(), 10_000);
+        assert_eq!(service.quote(1001, 0).unwrap(), 10_006);
+        assert_eq!(service.quote(1001, 100).unwrap(), 9_006);
+
+        service.price_model = PriceModel::Fixed;
+        assert_eq!(service.quote(1, 100).unwrap(), 9);
+        assert!(service.quote(2, 100).is_err());
+
+        service.pricing = None;
+        service.price_model = PriceModel::PerToken;
+        assert_eq!(service.quote(1001, 100).unwrap(), 10_010);
+    }
+
+    #[test]
+    fn capacity_zero_means_unlimited() {
+        let mut service = test_service();
+        service.open_orders = 5;
+        assert!(service.has_capacity());
+
+        service.max_concurrent_orders = 6;
+        assert!(service.has_capacity());
+        service.max_concurrent_orders = 5;
+        assert!(!service.has_capacity());
+    }
+
+    #[test]
+    fn terms_cover_only_buyer_facing_fields() {
+        let mut service = test_service();
+        let before = service.terms();
+
+        service.referral_bps = 500;
+        service.max_concurrent_orders = 3;
+        assert!(service.terms() == before);
+
+        service.price_lamports = 10;
+        assert!(service.terms() != before);
+    }
+
+    #[test]
+    fn milestone_deadlines_are_future_ordered_and_within_timeout() {
+        let specs = |deadlines: &[i64]| -> Vec<MilestoneSpec> {
+            deadlines
+                .iter()
+                .map(|&deadline| MilestoneSpec { amount: 1, deadline })
+                .collect()
+        };
+        let valid = |deadlines: &[i64]| MilestoneSpec::deadlines_valid(&specs(deadlines), 100, 500);
+        assert!(valid(&[0, 0]));
+        assert!(valid(&[200, 200, 500]));
+        assert!(valid(&[200, 0]));
+        assert!(!valid(&[100]));
+        assert!(!valid(&[501]));
+        assert!(!valid(&[300, 200]));
+        assert!(!valid(&[0, 200]));
+    }
+
+    #[test]
+    fn index_keys_normalise_and_dedupe() {
+        let keys = index_keys(&["NLP".into(), " gpt4 ".into(), "nlp".into()]).unwrap();
+        assert_eq!(keys, vec!["nlp".to_string(), "gpt4".to_string()]);
+        assert!(index_keys(&["  ".into()]).unwrap().is_empty());
+        assert!(index_keys(&["x".repeat(33)]).is_err());
+        assert!(index_keys(&[]).unwrap().is_empty());
+    }
+
+    fn sla(max_ack: i64, max_delivery: i64, penalties: &[(i64, u16)]) -> SlaTerms {
+        SlaTerms {
+            max_ack_seconds: max_ack,
+            max_delivery_seconds: max_delivery,
+            penalties: penalties
+                .iter()
+                .map(|&(late_by_seconds, refund_bps)| SlaPenalty {
+                    late_by_seconds,
+                    refund_bps,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn sla_validation() {
+        assert!(sla(0, 3600, &[(0, 500), (600, 2000)]).validate().is_ok());
+        assert!(sla(0, 0, &[]).validate().is_err());
+        assert!(sla(-1, 3600, &[]).validate().is_err());
+        assert!(sla(0, 3600, &[(600, 500), (600, 2000)]).validate().is_err());
+        assert!(sla(0, 3600, &[(0, 2000), (600, 500)]).validate().is_err());
+        assert!(sla(0, 3600, &[(0, 10_001)]).validate().is_err());
+        assert!(sla(0, 3600, &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 5)])
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn sla_late_seconds_uses_worse_deadline() {
+        let terms = sla(60, 3600, &[]);
+        assert_eq!(terms.late_seconds(1000, 1030, 4000), -30);
+        assert_eq!(terms.late_seconds(1000, 1100, 4000), 40);
+        assert_eq!(terms.late_seconds(1000, 1030, 4700), 100);
+        // Unacknowledged delivery counts as the acknowledgement
+        assert_eq!(terms.late_seconds(1000, 0, 1500), 440);
+
+        let no_ack = sla(0, 3600, &[]);
+        assert_eq!(no_ack.late_seconds(1000, 0, 4000), -600);
+    }
+
+    #[test]
+    fn sla_penalty_steps() {
+        let terms = sla(0, 3600, &[(0, 500), (600, 2000), (3600, 5000)]);
+        assert_eq!(terms.penalty_bps(0), 0);
+        assert_eq!(terms.penalty_bps(1), 500);
+        assert_eq!(terms.penalty_bps(599), 500);
+        assert_eq!(terms.penalty_bps(600), 2000);
+        assert_eq!(terms.penalty_bps(100_000), 5000);
+        assert_eq!(sla(0, 3600, &[]).penalty_bps(100), 0);
+    }
+
+    #[test]
+    fn draw_panel_picks_distinct_arbiters() {
+        let pool: Vec<Pubkey> = (0..8).map(|_| Pubkey::new_unique()).collect();
+        for byte in 0..32u8 {
+            let panel = draw_panel(&pool, 5, [byte; 32]);
+            assert_eq!(panel.len(), 5);
+            assert!(panel.iter().all(|key| pool.contains(key)));
+            assert!((1..5).all(|i| !panel[..i].contains(&panel[i])));
+            // Same seed, same panel; smaller panels are a prefix of larger ones
+            assert_eq!(draw_panel(&pool, 5, [byte; 32]), panel);
+            assert_eq!(draw_panel(&pool, 3, [byte; 32]), panel[..3]);
+        }
+        assert_eq!(draw_panel(&pool, 8, [0; 32]).len(), 8);
+    }
+
+    #[test]
+    fn median_ruling_takes_lower_middle_vote() {
+        let votes = |amounts: &[u64]| -> Vec<ArbiterVote> {
+            amounts
+                .iter()
+                .map(|&payer_amount| ArbiterVote {
+                    arbiter: Pubkey::default(),
+                    payer_amount,
+                })
+                .collect()
+        };
+        assert_eq!(median_ruling(&votes(&[700])), 700);
+        assert_eq!(median_ruling(&votes(&[900, 100])), 100);
+        assert_eq!(median_ruling(&votes(&[900, 100, 400])), 400);
+    }
+
+    fn test_payment() -> Payment {
+        Payment {
+            payer: Pubkey::default(),
+            receiver: Pubkey::default(),
+            service: Pubkey::default(),
+            amount: 300,
+            intent: String::new(),
+            legacy_conditions: Vec::new(),
+            status: PaymentStatus::Escrowed,
+            created_at: 0,
+            timeout_at: 0,
+            completed_at: 0,
+            bump: 0,
+            escrow_bump: 0,
+            quantity: 1,
+            conditions: Vec::new(),
+            referrer: None,
+            referral_bps: 0,
+            sla: None,
+            service_version: 1,
+            service_price: 300,
+            acknowledged_at: 0,
+            delivered_at: 0,
+            milestones: Vec::new(),
+            released_amount: 0,
+            timeout_policy: TimeoutPolicy::RefundOnTimeout,
+        }
+    }
+
+    #[test]
+    fn milestone_overdue_ignores_released_and_open_ended() {
+        let milestone = |deadline, released_at| Milestone {
+            amount: 100,
+            deadline,
+            released_at,
+        };
+        let mut payment = Payment {
+            milestones: vec![milestone(50, 40), milestone(0, 0), milestone(200, 0)],
+            released_amount: 100,
+            ..test_payment()
+        };
+        assert_eq!(payment.remaining(), 200);
+        assert!(!payment.milestone_overdue(100));
+        assert!(payment.milestone_overdue(201));
+        payment.milestones[2].released_at = 150;
+        assert!(!payment.milestone_overdue(1_000));
+    }
+
+    #[test]
+    fn conditions_release_once_met_and_refund_after_deadline() {
+        let preimage = b"decryption-key".to_vec();
+        let payment = Payment {
+            conditions: vec![
+                PaymentCondition::HashPreimage {
+                    hash: hashv(&[&preimage]).to_bytes(),
+                },
+                PaymentCondition::Deadline { at: 100 },
+            ],
+            ..test_payment()
+        };
+        assert_eq!(evaluate_conditions(&payment, &[], &[], 50).unwrap(), None);
+        assert_eq!(
+            evaluate_conditions(&payment, &[], &[b"wrong".to_vec()], 50).unwrap(),
+            None
+        );
+        assert_eq!(
+            evaluate_conditions(&payment, &[], &[preimage], 50).unwrap(),
+            Some(true)
+        );
+        assert_eq!(evaluate_conditions(&payment, &[], &[], 101).unwrap(), Some(false));
+        let late = [b"decryption-key".to_vec()];
+        assert_eq!(evaluate_conditions(&payment, &[], &late, 101).unwrap(), Some(false));
+    }
+
+    #[test]
+    fn timelock_holds_from_its_timestamp() {
+        let payment = Payment {
+            conditions: vec![
+                PaymentCondition::NotBefore { at: 100 },
+                PaymentCondition::Deadline { at: 200 },
+            ],
+            ..test_payment()
+        };
+        assert_eq!(evaluate_conditions(&payment, &[], &[], 99).unwrap(), None);
+        assert_eq!(evaluate_conditions(&payment, &[], &[], 100).unwrap(), Some(true));
+        assert_eq!(evaluate_conditions(&payment, &[], &[], 201).unwrap(), Some(false));
+    }
+
+    const FEED: [u8; 32] = [7; 32];
+
+    /// A fully verified `PriceUpdateV2` with the given price message
+    fn price_update(price: i64, conf: u64, publish_time: i64) -> Vec<u8> {
+        let mut data = PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&[0; 32]);
+        data.push(1);
+        data.extend_from_slice(&FEED);
+        data.extend_from_slice(&price.to_le_bytes());
+        data.extend_from_slice(&conf.to_le_bytes());
+        data.extend_from_slice(&(-8i32).to_le_bytes());
+        data.extend_from_slice(&publish_time.to_le_bytes());
+        data.extend_from_slice(&[0; 40]);
+        data
+    }
+
+    fn oracle_condition(at: i64) -> Payment {
+        Payment {
+            conditions: vec![PaymentCondition::OraclePrice {
+                feed_id: FEED,
+                price: 150_00000000,
+                exponent: -8,
+                above: true,
+                at,
+                max_age_seconds: 60,
+                max_conf_bps: 100,
+            }],
+            ..test_payment()
+        }
+    }
+
+    /// Evaluate `payment` against a mocked price account owned by `owner`
+    fn evaluate_with_feed(
+        payment: &Payment,
+        mut data: Vec<u8>,
+        owner: Pubkey,
+        now: i64,
+    ) -> Result<Option<bool>> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 1;
+        let feed = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        evaluate_conditions(payment, std::slice::from_ref(&feed), &[], now)
+    }
+
+    #[test]
+    fn pyth_receiver_id_matches_mainnet_program() {
+        assert_eq!(
+            PYTH_RECEIVER_ID.to_string(),
+            "rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ"
+        );
+    }
+
+    #[test]
+    fn oracle_condition_validates_owner_staleness_and_confidence() {
+        let payment = oracle_condition(0);
+        let above = price_update(160_00000000, 10_000000, 1_000);
+        let below = price_update(140_00000000, 10_000000, 1_000);
+        let id = PYTH_RECEIVER_ID;
+
+        assert_eq!(evaluate_with_feed(&payment, above.clone(), id, 1_030).unwrap(), Some(true));
+        assert_eq!(evaluate_with_feed(&payment, below, id, 1_030).unwrap(), None);
+        assert!(evaluate_with_feed(&payment, above.clone(), Pubkey::new_unique(), 1_030).is_err());
+        assert!(evaluate_with_feed(&payment, above.clone(), id, 1_061).is_err());
+        let wide = price_update(160_00000000, 2_00000000, 1_000);
+        assert!(evaluate_with_feed(&payment, wide, id, 1_030).is_err());
+
+        let mut partial = above;
+        partial[40] = 0;
+        assert!(evaluate_with_feed(&payment, partial, id, 1_030).is_err());
+    }
+
+    #[test]
+    fn oracle_condition_at_deadline_is_decided_by_that_price() {
+        let payment = oracle_condition(2_000);
+        let id = PYTH_RECEIVER_ID;
+
+        let early = price_update(160_00000000, 0, 1_990);
+        assert_eq!(evaluate_with_feed(&payment, early, id, 1_995).unwrap(), None);
+        let above = price_update(160_00000000, 0, 2_010);
+        assert_eq!(evaluate_with_feed(&payment, above, id, 5_000).unwrap(), Some(true));
+        let below = price_update(140_00000000, 0, 2_010);
+        assert_eq!(evaluate_with_feed(&payment, below, id, 5_000).unwrap(), Some(false));
+        let late = price_update(160_00000000, 0, 2_100);
+        assert!(evaluate_with_feed(&payment, late, id, 5_000).is_err());
+    }
+
+    /// Ed25519 sig-verify instruction data laid out like `Ed25519Program.createInstructionWithPublicKey`
+    fn ed25519_data(public_key: &Pubkey, message: &[u8], instruction_index: u16) -> Vec<u8> {
+        let mut data = vec![1, 0];
+        for value in [48, instruction_index, 16, instruction_index, 112] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&(message.len() as u16).to_le_bytes());
+        data.extend_from_slice(&instruction_index.to_le_bytes());
+        data.extend_from_slice(public_key.as_ref());
+        data.extend_from_slice(&[0; 64]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn parse_ed25519_instruction_reads_inline_key_and_message() {
+        let signer = Pubkey::new_unique();
+        let message = delivery_receipt_message(&Pubkey::new_unique());
+        let data = ed25519_data(&signer, &message, u16::MAX);
+        let (public_key, signed) = parse_ed25519_instruction(&data).unwrap();
+        assert_eq!(public_key, signer);
+        assert_eq!(signed, &message[..]);
+
+        // Offsets into another instruction would let the checked data differ from what we read
+        assert!(parse_ed25519_instruction(&ed25519_data(&signer, &message, 0)).is_err());
+        assert!(parse_ed25519_instruction(&data[..100]).is_err());
+    }
+
+    #[test]
+    fn delivery_receipt_message_names_program_and_payment() {
+        let payment = Pubkey::new_unique();
+        let message = delivery_receipt_message(&payment);
+        let (prefix, rest) = message.split_at(DELIVERY_RECEIPT_PREFIX.len());
+        assert_eq!(prefix, DELIVERY_RECEIPT_PREFIX);
+        assert_eq!(rest, [crate::ID.as_ref(), payment.as_ref()].concat());
+    }
+
+    #[test]
+    fn auto_release_starts_at_delivery_under_release_policy() {
+        let mut payment = test_payment();
+        payment.delivered_at = 1_000;
+        assert_eq!(payment.auto_release_at(), None);
+
+        payment.timeout_policy = TimeoutPolicy::ReleaseAfterDelivery {
+            challenge_seconds: 3_600,
+        };
+        assert_eq!(payment.auto_release_at(), Some(4_600));
+        payment.delivered_at = 0;
+        assert_eq!(payment.auto_release_at(), None);
+    }
 }
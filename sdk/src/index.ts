@@ -15,6 +15,10 @@ import {
   TransactionSignature,
   GetProgramAccountsFilter,
   MemcmpFilter,
+  AccountMeta,
+  Ed25519Program,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  SYSVAR_SLOT_HASHES_PUBKEY,
 } from "@solana/web3.js";
 
 // ============================================================
@@ -26,6 +30,11 @@ export const SOLAGENT_PROGRAM_ID = new PublicKey(
   "FDBu2qdatZd7J1TiDTjCbzNtjqwuJi25UPY7qUdBBFNQ"
 );
 
+/** Pyth pull-oracle receiver program that owns `PriceUpdateV2` accounts. */
+export const PYTH_RECEIVER_ID = new PublicKey(
+  "rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ"
+);
+
 /** Number of lamports per SOL. */
 const LAMPORTS_PER_SOL = 1_000_000_000;
 
@@ -45,6 +54,25 @@ const SEED_PAYMENT = "payment";
 const SEED_ESCROW = "escrow";
 const SEED_STREAM = "stream";
 const SEED_STREAM_VAULT = "stream_vault";
+const SEED_AUCTION = "auction";
+const SEED_AUCTION_VAULT = "auction_vault";
+const SEED_USAGE = "usage";
+const SEED_USAGE_VAULT = "usage_vault";
+const SEED_USAGE_REPORT = "usage_report";
+const SEED_JOB = "job";
+const SEED_JOB_BID = "job_bid";
+const SEED_SUBSCRIPTION = "subscription";
+const SEED_SUBSCRIPTION_VAULT = "subscription_vault";
+const SEED_COUPON = "coupon";
+const SEED_ATTESTATION = "attestation";
+const SEED_TAG_INDEX = "tag_index";
+const SEED_CAPABILITY_INDEX = "capability_index";
+const SEED_CATEGORY = "category";
+const SEED_BUNDLE = "bundle";
+const SEED_DISPUTE = "dispute";
+const SEED_ARBITER_POOL = "arbiter_pool";
+const SEED_ARBITER = "arbiter";
+const SEED_SESSION_KEY = "session_key";
 
 // ============================================================
 // ENUMS
@@ -62,6 +90,8 @@ export enum PriceModel {
   PerToken = "perToken",
   /** Highest bidder wins. */
   Auction = "auction",
+  /** Recurring charge every billing period. */
+  Subscription = "subscription",
 }
 
 /** Status of an escrowed payment. */
@@ -70,6 +100,63 @@ export enum PaymentStatus {
   Released = "released",
   Refunded = "refunded",
   Disputed = "disputed",
+  /** Split between payer and receiver by mutual agreement. */
+  Settled = "settled",
+}
+
+/** Lifecycle state of a service auction. */
+export enum AuctionStatus {
+  Open = "open",
+  Settled = "settled",
+}
+
+/** Status of a metered usage report. */
+export enum UsageReportStatus {
+  Pending = "pending",
+  Settled = "settled",
+  Contested = "contested",
+}
+
+/** Status of a job posted on the job board. */
+export enum JobStatus {
+  Open = "open",
+  Awarded = "awarded",
+  Cancelled = "cancelled",
+}
+
+/** Status of a recurring subscription. */
+export enum SubscriptionStatus {
+  Active = "active",
+  Lapsed = "lapsed",
+}
+
+/** How a service's access list is interpreted. */
+export enum AccessListMode {
+  /** The list is ignored. */
+  Open = "open",
+  /** Only listed buyers (or members of listed organisations) may pay. */
+  Allowlist = "allowlist",
+  /** Listed buyers (or members of listed organisations) may not pay. */
+  Denylist = "denylist",
+}
+
+/** Which kind of discovery index an account is. */
+export enum IndexKind {
+  Tag = "tag",
+  Capability = "capability",
+}
+
+/** Lifecycle state of a payment dispute. */
+export enum DisputeStatus {
+  /** Waiting for a panel majority. */
+  Open = "open",
+  /** Ruled; payout waits for the appeal window. */
+  Ruled = "ruled",
+  /** Appealed to the pool authority. */
+  Appealed = "appealed",
+  Resolved = "resolved",
+  /** Panel missed its vote deadline; the payer was refunded. */
+  Expired = "expired",
 }
 
 // ============================================================
@@ -106,6 +193,18 @@ export interface AgentAccount {
   registeredAt: BN;
   isActive: boolean;
   bump: number;
+  referralEarned: BN;
+  referralFeesPaid: BN;
+  /** Released orders that carried an SLA. */
+  slaOrders: BN;
+  /** Of those, orders delivered on time. */
+  slaMet: BN;
+  /** Resolved disputes this agent was party to. */
+  disputes: BN;
+  /** Of those, disputes where it was awarded the whole escrow. */
+  disputesWon: BN;
+  /** Of those, disputes where it was awarded nothing. */
+  disputesLost: BN;
 }
 
 /** An agent-to-agent feedback record. */
@@ -134,6 +233,24 @@ export interface ServiceAccount {
   isActive: boolean;
   createdAt: BN;
   bump: number;
+  pricing: PricingSchedule | null;
+  referralBps: number;
+  access: AccessRules | null;
+  openOrders: BN;
+  /** 0 = unlimited. */
+  maxConcurrentOrders: BN;
+  sla: SlaTerms | null;
+  slaOrders: BN;
+  slaMet: BN;
+  /** Starts at 1; bumped whenever buyer-facing terms change. */
+  version: BN;
+  /** Curated category ids (see `getAllCategories`). */
+  categories: number[];
+  /** Arbiter pool disputes are assigned from (null = protocol authority). */
+  arbiterPool: PublicKey | null;
+  /** Payment accounts not yet closed; the service can only be closed at 0. */
+  unclosedPayments: BN;
+  timeoutPolicy: TimeoutPolicy;
 }
 
 /** An escrowed payment for a service. */
@@ -143,13 +260,33 @@ export interface PaymentAccount {
   service: PublicKey;
   amount: BN;
   intent: string;
-  conditions: string[];
+  /** Free-text conditions of orders placed before typed conditions; never evaluated. */
+  legacyConditions: string[];
   status: Record<string, object>;
   createdAt: BN;
   timeoutAt: BN;
   completedAt: BN;
   bump: number;
   escrowBump: number;
+  quantity: BN;
+  conditions: PaymentCondition[];
+  referrer: PublicKey | null;
+  referralBps: number;
+  sla: SlaTerms | null;
+  /** Service version the order was placed against. */
+  serviceVersion: BN;
+  /** Service list price when the order was placed. */
+  servicePrice: BN;
+  /** 0 until the provider acknowledges the order. */
+  acknowledgedAt: BN;
+  /** 0 until the provider marks the order delivered. */
+  deliveredAt: BN;
+  /** Empty when the payment is released all at once. */
+  milestones: Milestone[];
+  /** Lamports released to the receiver through milestones so far. */
+  releasedAmount: BN;
+  /** Copied from the service when the order was placed. */
+  timeoutPolicy: TimeoutPolicy;
 }
 
 /** A streaming payment channel between two agents. */
@@ -167,6 +304,408 @@ export interface StreamAccount {
   vaultBump: number;
 }
 
+/** An auction on an Auction-priced service. */
+export interface AuctionAccount {
+  service: PublicKey;
+  provider: PublicKey;
+  authority: PublicKey;
+  reservePrice: BN;
+  highestBid: BN;
+  highestBidder: PublicKey;
+  highestBidderAuthority: PublicKey;
+  intent: string;
+  bidCount: BN;
+  deliveryTimeoutSeconds: BN;
+  createdAt: BN;
+  endAt: BN;
+  status: Record<string, object>;
+  payment: PublicKey;
+  bump: number;
+  vaultBump: number;
+}
+
+/** A pre-funded escrow that a metered service draws usage charges from. */
+export interface UsageEscrowAccount {
+  payer: PublicKey;
+  payerAuthority: PublicKey;
+  service: PublicKey;
+  provider: PublicKey;
+  pricePerUnit: BN;
+  pricing: PricingSchedule | null;
+  discountBps: number;
+  deposited: BN;
+  drawn: BN;
+  pending: BN;
+  unitsBilled: BN;
+  unitsReported: BN;
+  maxUnitsPerReport: BN;
+  challengeWindowSeconds: BN;
+  reportCount: BN;
+  createdAt: BN;
+  bump: number;
+  vaultBump: number;
+  /** Reports not yet closed; the escrow can only be closed at 0. */
+  openReports: BN;
+}
+
+/** A provider-submitted usage report against a usage escrow. */
+export interface UsageReportAccount {
+  usageEscrow: PublicKey;
+  index: BN;
+  units: BN;
+  amount: BN;
+  submittedAt: BN;
+  claimableAt: BN;
+  coSigned: boolean;
+  status: Record<string, object>;
+  bump: number;
+}
+
+/** A buyer's request-for-quote on the job board. */
+export interface JobAccount {
+  buyer: PublicKey;
+  authority: PublicKey;
+  jobId: string;
+  intent: string;
+  budget: BN;
+  requiredCapabilities: string[];
+  deadline: BN;
+  status: Record<string, object>;
+  bidCount: BN;
+  acceptedBid: PublicKey;
+  payment: PublicKey;
+  createdAt: BN;
+  bump: number;
+}
+
+/** A provider's quote for a job. */
+export interface JobBidAccount {
+  job: PublicKey;
+  provider: PublicKey;
+  authority: PublicKey;
+  service: PublicKey;
+  price: BN;
+  deliverySeconds: BN;
+  message: string;
+  submittedAt: BN;
+  bump: number;
+}
+
+/** A buyer's recurring subscription to a service. */
+export interface SubscriptionAccount {
+  subscriber: PublicKey;
+  subscriberAuthority: PublicKey;
+  service: PublicKey;
+  provider: PublicKey;
+  providerAuthority: PublicKey;
+  amountPerPeriod: BN;
+  periodSeconds: BN;
+  deposited: BN;
+  charged: BN;
+  periodsCharged: BN;
+  paidUntil: BN;
+  status: Record<string, object>;
+  createdAt: BN;
+  bump: number;
+  vaultBump: number;
+}
+
+/** A volume tier: units from `fromUnit` onwards cost `priceLamports` each. */
+export interface PriceTier {
+  fromUnit: BN;
+  priceLamports: BN;
+}
+
+/** A discount for buyers whose reputation score is at least `minReputation`. */
+export interface ReputationDiscount {
+  minReputation: BN;
+  discountBps: number;
+}
+
+/** Optional volume pricing layered on top of a service's base price. */
+export interface PricingSchedule {
+  tiers: PriceTier[];
+  reputationDiscounts: ReputationDiscount[];
+}
+
+/** A discount code for a service. */
+export interface CouponAccount {
+  service: PublicKey;
+  authority: PublicKey;
+  code: string;
+  discount: Record<string, { bps?: number; lamports?: BN }>;
+  maxRedemptions: BN;
+  redemptions: BN;
+  expiresAt: BN;
+  allowedBuyers: PublicKey[];
+  totalDiscounted: BN;
+  createdAt: BN;
+  bump: number;
+}
+
+/** An issuer's attestation about an agent (e.g. organisation membership). */
+export interface AttestationAccount {
+  issuer: PublicKey;
+  subject: PublicKey;
+  issuedAt: BN;
+  expiresAt: BN;
+  bump: number;
+}
+
+/** Buyer access rules, checked whenever a buyer orders, subscribes, bids or opens a usage escrow. */
+export interface AccessRules {
+  listMode: Record<string, object>;
+  /** Buyer agent PDAs, or organisation keys matched against attestation issuers. */
+  list: PublicKey[];
+  minReputation: BN;
+  requiredAttester: PublicKey | null;
+}
+
+/** A late-delivery step: orders at least `lateBySeconds` late refund `refundBps`. */
+export interface SlaPenalty {
+  lateBySeconds: BN;
+  refundBps: number;
+}
+
+/**
+ * What happens to an escrowed order the payer never releases (Anchor enum
+ * encoding). Under `releaseAfterDelivery` a delivered order goes to the
+ * provider unless the payer disputes within `challengeSeconds`.
+ */
+export type TimeoutPolicy =
+  | { refundOnTimeout: Record<string, never> }
+  | { releaseAfterDelivery: { challengeSeconds: BN } };
+
+/** Delivery deadlines a service commits to, copied into each payment. */
+export interface SlaTerms {
+  /** Seconds from payment to acknowledgement (0 = not tracked). */
+  maxAckSeconds: BN;
+  maxDeliverySeconds: BN;
+  penalties: SlaPenalty[];
+}
+
+/** The buyer-facing terms of a service, as logged in `ServiceUpdated`. */
+export interface ServiceTerms {
+  title: string;
+  description: string;
+  priceLamports: BN;
+  priceModel: Record<string, object>;
+  pricing: PricingSchedule | null;
+  sla: SlaTerms | null;
+  timeoutPolicy: TimeoutPolicy;
+  tags: string[];
+}
+
+/** One service listed under a tag, or one agent under a capability. */
+export interface IndexEntryAccount {
+  kind: Record<string, object>;
+  /** Normalised key (see `indexKeys`). */
+  key: string;
+  /** Service PDA for tag indexes, agent PDA for capability indexes. */
+  entry: PublicKey;
+  bump: number;
+}
+
+/** A curated marketplace category maintained by the protocol authority. */
+export interface CategoryAccount {
+  id: number;
+  name: string;
+  description: string;
+  /** Retired categories can't be newly assigned to services. */
+  isActive: boolean;
+  createdAt: BN;
+  bump: number;
+}
+
+/** One service inside a bundle and the share of each order its provider earns. */
+export interface BundleComponent {
+  service: PublicKey;
+  /** Provider agent of `service`. */
+  provider: PublicKey;
+  shareBps: number;
+}
+
+/** A composite listing sold by an orchestrator agent. */
+export interface BundleAccount {
+  /** Orchestrator agent; keeps whatever the component shares leave. */
+  orchestrator: PublicKey;
+  authority: PublicKey;
+  bundleId: string;
+  title: string;
+  description: string;
+  priceLamports: BN;
+  components: BundleComponent[];
+  totalOrders: BN;
+  openOrders: BN;
+  totalRevenue: BN;
+  /** Share-weighted average of the component services' ratings. */
+  avgRating: number;
+  /** Share-weighted average reputation of the component providers. */
+  componentReputation: BN;
+  isActive: boolean;
+  createdAt: BN;
+  bump: number;
+  /** Payment accounts not yet closed; the bundle can only be closed at 0. */
+  unclosedPayments: BN;
+}
+
+/** A piece of dispute evidence: a content hash plus where to fetch it. */
+export interface Evidence {
+  /** Agent that submitted the evidence. */
+  submitter: PublicKey;
+  hash: number[];
+  uri: string;
+  submittedAt: BN;
+}
+
+/** A vote cast by a panel member: the payer's share of the escrow after fees. */
+export interface ArbiterVote {
+  arbiter: PublicKey;
+  payerAmount: BN;
+}
+
+/** A disputed payment and the panel ruling on it. */
+export interface DisputeAccount {
+  payment: PublicKey;
+  payer: PublicKey;
+  receiver: PublicKey;
+  openedBy: PublicKey;
+  /** Arbiter pool the panel was drawn from (null = protocol authority). */
+  pool: PublicKey | null;
+  /** `Arbiter` accounts on the panel, or the protocol authority's wallet. */
+  arbiters: PublicKey[];
+  votes: ArbiterVote[];
+  /** Decides appeals (the pool authority). */
+  appealAuthority: PublicKey;
+  amount: BN;
+  /** Arbiter fee taken from the escrow, split evenly across the panel. */
+  fee: BN;
+  feePaid: BN;
+  /** 0 = rulings pay out immediately and cannot be appealed. */
+  appealWindowSeconds: BN;
+  slashBps: number;
+  reason: string;
+  evidence: Evidence[];
+  status: Record<string, object>;
+  /** Ruled payer share, excluding fees. */
+  payerAmount: BN;
+  receiverAmount: BN;
+  appealedBy: PublicKey | null;
+  openedAt: BN;
+  ruledAt: BN;
+  resolvedAt: BN;
+  bump: number;
+  /** Slot whose hash draws a pooled panel. */
+  drawSlot: BN;
+  /**
+   * Votes close at this time (0 = pooled panel not drawn yet); disputes
+   * without a pool give the protocol authority 14 days from opening.
+   */
+  voteDeadline: BN;
+  /** Bit per panel index: that arbiter's stake lock has been released. */
+  releasedArbiters: number;
+}
+
+/** Staked arbiters that disputes on opted-in services are assigned from. */
+export interface ArbiterPoolAccount {
+  poolId: number;
+  /** Decides appeals against this pool's panels. */
+  authority: PublicKey;
+  name: string;
+  minStake: BN;
+  feeBps: number;
+  panelSize: number;
+  appealWindowSeconds: BN;
+  slashBps: number;
+  arbiters: PublicKey[];
+  createdAt: BN;
+  bump: number;
+  /** How long a drawn panel has to reach a majority. */
+  voteWindowSeconds: BN;
+}
+
+/** An arbiter's membership of a pool; the account holds the stake. */
+export interface ArbiterAccount {
+  authority: PublicKey;
+  pool: PublicKey;
+  stake: BN;
+  casesRuled: BN;
+  casesOverturned: BN;
+  feesEarned: BN;
+  totalSlashed: BN;
+  /** Appeals pending against this arbiter's rulings. */
+  openAppeals: BN;
+  /** Stake can't be withdrawn before this. */
+  lockedUntil: BN;
+  registeredAt: BN;
+  bump: number;
+  /** Unreleased disputes this arbiter was drawn for; stake is locked while > 0. */
+  openCases: number;
+}
+
+/** A milestone as requested in `setMilestones`. */
+export interface MilestoneSpec {
+  amount: BN;
+  /** Unix timestamp (0 = no deadline). */
+  deadline: BN;
+}
+
+/** One stage of a milestone payment. */
+export interface Milestone {
+  amount: BN;
+  /** Unix timestamp (0 = no deadline). */
+  deadline: BN;
+  /** 0 until the milestone is released. */
+  releasedAt: BN;
+}
+
+/**
+ * A release condition attached to a payment (Anchor enum encoding).
+ * `hash` and `feedId` are 32-byte arrays. Oracle conditions read a fully
+ * verified Pyth `PriceUpdateV2` account; with `at` set (unix seconds) the
+ * price published at that time decides them, otherwise any fresh price.
+ * `maxAgeSeconds` must be 1-60: with `at` set, whoever cranks the release
+ * picks which update in `[at, at + maxAgeSeconds]` to pass, so a price near
+ * the threshold can go either way within that window.
+ * A `deadline` only bounds the others, so a payment needs at least one
+ * condition besides it; `notBefore` is a timelock.
+ */
+export type PaymentCondition =
+  | { deadline: { at: BN } }
+  | {
+      oraclePrice: {
+        feedId: number[];
+        price: BN;
+        exponent: number;
+        above: boolean;
+        at: BN;
+        maxAgeSeconds: number;
+        maxConfBps: number;
+      };
+    }
+  | { hashPreimage: { hash: number[] } }
+  | { validatorSignature: { validator: PublicKey } }
+  | { attestationPresent: { issuer: PublicKey } }
+  | { notBefore: { at: BN } };
+
+/** A key an agent authorises to sign delivery receipts on its behalf. */
+export interface SessionKeyAccount {
+  agent: PublicKey;
+  key: PublicKey;
+  createdAt: BN;
+  /** 0 = never expires. */
+  expiresAt: BN;
+  bump: number;
+}
+
+/** A payer's off-chain signature acknowledging delivery of a payment. */
+export interface DeliveryReceipt {
+  /** Payer authority or one of its session keys. */
+  signer: PublicKey;
+  /** Ed25519 signature over `deliveryReceiptMessage(paymentPda)`. */
+  signature: Uint8Array;
+}
+
 // ============================================================
 // EVENT TYPES
 // ============================================================
@@ -210,6 +749,30 @@ export interface ServiceCreatedEvent {
   priceModel: Record<string, object>;
 }
 
+/** Emitted when a service's terms change; `version` is the new version. */
+export interface ServiceUpdatedEvent {
+  service: PublicKey;
+  version: BN;
+  oldTerms: ServiceTerms;
+  newTerms: ServiceTerms;
+  timestamp: BN;
+}
+
+/** Emitted when a service is paused or resumed. */
+export interface ServiceStatusChangedEvent {
+  service: PublicKey;
+  isActive: boolean;
+  timestamp: BN;
+}
+
+/** Emitted when a service is delisted and its account closed. */
+export interface ServiceClosedEvent {
+  service: PublicKey;
+  provider: PublicKey;
+  totalOrders: BN;
+  totalRevenue: BN;
+}
+
 /** Emitted when a payment is created (escrowed). */
 export interface PaymentCreatedEvent {
   payment: PublicKey;
@@ -227,6 +790,20 @@ export interface PaymentReleasedEvent {
   latencyMs: BN;
 }
 
+/** Emitted when a finished payment account is closed. */
+export interface PaymentClosedEvent {
+  payment: PublicKey;
+  payer: PublicKey;
+  service: PublicKey;
+}
+
+/** Emitted when a launch-layout payment is grown to the current layout. */
+export interface PaymentMigratedEvent {
+  payment: PublicKey;
+  space: BN;
+  timestamp: BN;
+}
+
 /** Emitted when an escrowed payment is refunded to the payer. */
 export interface PaymentRefundedEvent {
   payment: PublicKey;
@@ -252,1251 +829,8083 @@ export interface StreamWithdrawnEvent {
   isActive: boolean;
 }
 
-// ============================================================
-// QUERY / FILTER TYPES
-// ============================================================
+/** Emitted when an auction is opened on a service. */
+export interface AuctionOpenedEvent {
+  auction: PublicKey;
+  service: PublicKey;
+  reservePrice: BN;
+  endAt: BN;
+}
 
-/** Filter options for searching services on the marketplace. */
-export interface FindServicesFilter {
-  /** Filter by a specific tag (exact match). */
-  tag?: string;
-  /** Filter by provider agent PDA. */
-  provider?: PublicKey;
-  /** Only return services from agents with at least this reputation score. */
-  minReputation?: number;
-  /** Only return active services. Defaults to true. */
-  activeOnly?: boolean;
+/** Emitted for every accepted bid. */
+export interface BidPlacedEvent {
+  auction: PublicKey;
+  bidder: PublicKey;
+  amount: BN;
+  previousBidder: PublicKey;
+  refunded: BN;
 }
 
-/** Options for creating a payment and immediately releasing it. */
-export interface PayAndReleaseOptions {
-  /** The service PDA to pay for. */
-  servicePda: PublicKey;
-  /** Amount in SOL. */
-  amountSol: number;
-  /** Freeform intent describing what is being paid for. */
-  intent: string;
-  /** Optional conditions that must be met. */
-  conditions?: string[];
-  /** Timeout in seconds before the escrowed payment can be refunded. Default: 3600 (1 hour). */
-  timeoutSeconds?: number;
-  /** The receiver agent's authority public key (wallet that receives SOL). */
-  receiverAuthority: PublicKey;
+/** Emitted when an auction's winning bid is moved into a payment escrow. */
+export interface AuctionSettledEvent {
+  auction: PublicKey;
+  payment: PublicKey;
+  winner: PublicKey;
+  amount: BN;
 }
 
-/** Options for creating and funding a stream in one call. */
-export interface CreateAndFundStreamOptions {
-  /** The receiver agent PDA. */
-  receiverAgentPda: PublicKey;
-  /** Rate in SOL per second. */
-  ratePerSecondSol: number;
-  /** Maximum duration of the stream in seconds. */
-  maxDurationSeconds: number;
-  /** Deposit amount in SOL. If omitted, defaults to rate * maxDuration. */
-  depositSol?: number;
+/** Emitted when a settled auction is closed. */
+export interface AuctionClosedEvent {
+  auction: PublicKey;
+  service: PublicKey;
+  payment: PublicKey;
 }
 
-// ============================================================
-// MINIMAL IDL
-// ============================================================
+/** Emitted when an auction without bids is cancelled. */
+export interface AuctionCancelledEvent {
+  auction: PublicKey;
+  service: PublicKey;
+}
 
-/**
- * Minimal Anchor IDL for the SolAgent program.
- *
- * This IDL is embedded so the SDK can work without requiring the consumer to
- * generate or fetch the IDL separately. It covers all 10 instructions, all 6
- * account types, and all events.
- */
-const SOLAGENT_IDL: Idl = {
-  version: "0.1.0",
-  name: "solagent",
-  instructions: [
-    {
-      name: "initializeProtocol",
-      accounts: [
-        { name: "protocol", isMut: true, isSigner: false },
-        { name: "authority", isMut: true, isSigner: true },
-        { name: "systemProgram", isMut: false, isSigner: false },
-      ],
-      args: [],
-    },
+/** Emitted when a payer pre-funds a usage escrow. */
+export interface UsageEscrowOpenedEvent {
+  usageEscrow: PublicKey;
+  payer: PublicKey;
+  service: PublicKey;
+  pricePerUnit: BN;
+  deposit: BN;
+}
+
+/** Emitted when a usage escrow is topped up. */
+export interface UsageEscrowFundedEvent {
+  usageEscrow: PublicKey;
+  amount: BN;
+  available: BN;
+}
+
+/** Emitted when a provider submits a usage report. */
+export interface UsageReportedEvent {
+  usageEscrow: PublicKey;
+  report: PublicKey;
+  units: BN;
+  amount: BN;
+  coSigned: boolean;
+}
+
+/** Emitted when a usage report is paid out to the provider. */
+export interface UsageSettledEvent {
+  usageEscrow: PublicKey;
+  report: PublicKey;
+  units: BN;
+  amount: BN;
+}
+
+/** Emitted when the payer contests a pending usage report. */
+export interface UsageContestedEvent {
+  usageEscrow: PublicKey;
+  report: PublicKey;
+  amount: BN;
+}
+
+/** Emitted when the payer resolves a contested usage report. */
+export interface UsageReportResolvedEvent {
+  usageEscrow: PublicKey;
+  report: PublicKey;
+  /** Units the payer accepted (0 = dropped). */
+  units: BN;
+  amount: BN;
+}
+
+/** Emitted when a settled usage report account is closed. */
+export interface UsageReportClosedEvent {
+  usageEscrow: PublicKey;
+  report: PublicKey;
+}
+
+/** Emitted when a usage escrow is closed and its balance refunded. */
+export interface UsageEscrowClosedEvent {
+  usageEscrow: PublicKey;
+  unitsBilled: BN;
+  drawn: BN;
+  refunded: BN;
+}
+
+/** Emitted when a buyer posts a job. */
+export interface JobPostedEvent {
+  job: PublicKey;
+  buyer: PublicKey;
+  budget: BN;
+  deadline: BN;
+  intent: string;
+}
+
+/** Emitted when a provider bids on a job. */
+export interface JobBidSubmittedEvent {
+  job: PublicKey;
+  bid: PublicKey;
+  provider: PublicKey;
+  price: BN;
+  deliverySeconds: BN;
+}
+
+/** Emitted when a provider withdraws a job bid. */
+export interface JobBidWithdrawnEvent {
+  job: PublicKey;
+  bid: PublicKey;
+  provider: PublicKey;
+}
+
+/** Emitted when a buyer accepts a bid and funds its payment escrow. */
+export interface JobAwardedEvent {
+  job: PublicKey;
+  bid: PublicKey;
+  payment: PublicKey;
+  provider: PublicKey;
+  amount: BN;
+}
+
+/** Emitted when a buyer cancels an open job. */
+export interface JobCancelledEvent {
+  job: PublicKey;
+  buyer: PublicKey;
+}
+
+/** Emitted when an awarded or cancelled job is closed. */
+export interface JobClosedEvent {
+  job: PublicKey;
+  buyer: PublicKey;
+}
+
+/** Emitted when an agent subscribes to a service. */
+export interface SubscribedEvent {
+  subscription: PublicKey;
+  subscriber: PublicKey;
+  service: PublicKey;
+  amountPerPeriod: BN;
+  periodSeconds: BN;
+}
+
+/** Emitted when a subscription balance is topped up. */
+export interface SubscriptionFundedEvent {
+  subscription: PublicKey;
+  amount: BN;
+  balance: BN;
+}
+
+/** Emitted when a subscription period is charged to the provider. */
+export interface SubscriptionChargedEvent {
+  subscription: PublicKey;
+  amount: BN;
+  period: BN;
+  paidUntil: BN;
+}
+
+/** Emitted when a subscription lapses for lack of funds. */
+export interface SubscriptionLapsedEvent {
+  subscription: PublicKey;
+  subscriber: PublicKey;
+  balance: BN;
+}
+
+/** Emitted when a subscriber cancels and closes a subscription. */
+export interface SubscriptionCancelledEvent {
+  subscription: PublicKey;
+  subscriber: PublicKey;
+  periodsCharged: BN;
+  refunded: BN;
+}
+
+/** Emitted when a service's pricing schedule is set or cleared. */
+export interface PricingScheduleUpdatedEvent {
+  service: PublicKey;
+  pricing: PricingSchedule | null;
+  timestamp: BN;
+}
+
+/** Emitted when a service authority creates a coupon. */
+export interface CouponCreatedEvent {
+  coupon: PublicKey;
+  service: PublicKey;
+  code: string;
+  discount: Record<string, object>;
+  maxRedemptions: BN;
+  expiresAt: BN;
+}
+
+/** Emitted when a coupon is redeemed in `payForService`. */
+export interface CouponRedeemedEvent {
+  coupon: PublicKey;
+  service: PublicKey;
+  payment: PublicKey;
+  buyer: PublicKey;
+  discount: BN;
+  amount: BN;
+  redemptions: BN;
+}
+
+/** Emitted when a coupon is withdrawn. */
+export interface CouponClosedEvent {
+  coupon: PublicKey;
+  service: PublicKey;
+  redemptions: BN;
+}
+
+/** Emitted when a service changes its referral fee. */
+export interface ReferralFeeUpdatedEvent {
+  service: PublicKey;
+  referralBps: number;
+  timestamp: BN;
+}
+
+/** Emitted when a referrer's share is paid out on release. */
+export interface ReferralPaidEvent {
+  payment: PublicKey;
+  referrer: PublicKey;
+  receiver: PublicKey;
+  amount: BN;
+}
+
+/** Emitted when an issuer attests an agent. */
+export interface AttestationIssuedEvent {
+  attestation: PublicKey;
+  issuer: PublicKey;
+  subject: PublicKey;
+  expiresAt: BN;
+}
+
+/** Emitted when an issuer revokes an attestation. */
+export interface AttestationRevokedEvent {
+  attestation: PublicKey;
+  issuer: PublicKey;
+  subject: PublicKey;
+}
+
+/** Emitted when a service's access rules are set or cleared. */
+export interface AccessRulesUpdatedEvent {
+  service: PublicKey;
+  access: AccessRules | null;
+  timestamp: BN;
+}
+
+/** Emitted when a service changes its concurrent order limit. */
+export interface ServiceCapacityUpdatedEvent {
+  service: PublicKey;
+  maxConcurrentOrders: BN;
+  openOrders: BN;
+  timestamp: BN;
+}
+
+/** Emitted when a service's timeout policy changes. */
+export interface TimeoutPolicyUpdatedEvent {
+  service: PublicKey;
+  timeoutPolicy: TimeoutPolicy;
+  timestamp: BN;
+}
+
+/** Emitted when a launch-layout service is grown to the current layout. */
+export interface ServiceMigratedEvent {
+  service: PublicKey;
+  space: BN;
+  timestamp: BN;
+}
+
+/** Emitted when a service's SLA terms are set or cleared. */
+export interface SlaUpdatedEvent {
+  service: PublicKey;
+  sla: SlaTerms | null;
+  timestamp: BN;
+}
+
+/** Emitted when a provider acknowledges an order. */
+export interface PaymentAcknowledgedEvent {
+  payment: PublicKey;
+  receiver: PublicKey;
+  timestamp: BN;
+}
+
+/** Emitted when a provider marks an order delivered. */
+export interface PaymentDeliveredEvent {
+  payment: PublicKey;
+  receiver: PublicKey;
+  timestamp: BN;
+  /** End of the payer's challenge window under `releaseAfterDelivery`. */
+  autoReleaseAt: BN | null;
+}
+
+/** Emitted when a late order's SLA penalty is refunded on release. */
+export interface SlaPenaltyAppliedEvent {
+  payment: PublicKey;
+  lateSeconds: BN;
+  refunded: BN;
+}
+
+/** Emitted when an agent replaces its capabilities. */
+export interface AgentCapabilitiesUpdatedEvent {
+  agent: PublicKey;
+  capabilities: string[];
+  timestamp: BN;
+}
+
+/** Emitted when a launch-layout agent is grown to the current layout. */
+export interface AgentMigratedEvent {
+  agent: PublicKey;
+  space: BN;
+  timestamp: BN;
+}
+
+/** Emitted when the protocol authority adds a category. */
+export interface CategoryCreatedEvent {
+  category: PublicKey;
+  id: number;
+  name: string;
+}
+
+/** Emitted when a category is renamed, redescribed or retired. */
+export interface CategoryUpdatedEvent {
+  category: PublicKey;
+  id: number;
+  name: string;
+  isActive: boolean;
+}
+
+/** Emitted when a service's categories change. */
+export interface ServiceCategoriesUpdatedEvent {
+  service: PublicKey;
+  categories: number[];
+  timestamp: BN;
+}
+
+/** Emitted when an orchestrator lists a bundle. */
+export interface BundleCreatedEvent {
+  bundle: PublicKey;
+  orchestrator: PublicKey;
+  price: BN;
+  components: BundleComponent[];
+}
+
+/** Emitted when a bundle payment is split between its providers. */
+export interface BundlePaymentReleasedEvent {
+  payment: PublicKey;
+  bundle: PublicKey;
+  amount: BN;
+  componentsShare: BN;
+  orchestratorShare: BN;
+}
+
+/** Emitted when a bundle's rating is recomputed from its components. */
+export interface BundleRatingUpdatedEvent {
+  bundle: PublicKey;
+  avgRating: number;
+  componentReputation: BN;
+}
+
+/** Emitted when a bundle is paused or resumed. */
+export interface BundleStatusChangedEvent {
+  bundle: PublicKey;
+  isActive: boolean;
+  timestamp: BN;
+}
+
+/** Emitted when a bundle is delisted. */
+export interface BundleClosedEvent {
+  bundle: PublicKey;
+  totalOrders: BN;
+  totalRevenue: BN;
+}
+
+/** Emitted when a payer or receiver disputes an escrowed payment. */
+export interface DisputeOpenedEvent {
+  dispute: PublicKey;
+  payment: PublicKey;
+  openedBy: PublicKey;
+  arbiters: PublicKey[];
+  reason: string;
+}
+
+/** Emitted when a pooled dispute's panel is drawn. */
+export interface DisputePanelDrawnEvent {
+  dispute: PublicKey;
+  arbiters: PublicKey[];
+  voteDeadline: BN;
+}
+
+/** Emitted when a panel's draw slot aged out and a new one was committed. */
+export interface DisputePanelRecommittedEvent {
+  dispute: PublicKey;
+  drawSlot: BN;
+}
+
+/** Emitted when a panel misses its vote deadline and the payer is refunded. */
+export interface DisputeExpiredEvent {
+  dispute: PublicKey;
+  payment: PublicKey;
+  refunded: BN;
+  slashedArbiters: number;
+}
+
+/** Emitted when an arbiter's stake lock for a finished dispute is released. */
+export interface ArbiterReleasedEvent {
+  arbiter: PublicKey;
+  dispute: PublicKey;
+  openCases: number;
+}
+
+/** Emitted when evidence is attached to a dispute. */
+export interface EvidenceSubmittedEvent {
+  dispute: PublicKey;
+  submitter: PublicKey;
+  hash: number[];
+  uri: string;
+  timestamp: BN;
+}
+
+/** Emitted when a panel member votes. */
+export interface ArbiterVotedEvent {
+  dispute: PublicKey;
+  arbiter: PublicKey;
+  payerAmount: BN;
+  fee: BN;
+}
+
+/** Emitted when a panel majority has ruled. */
+export interface DisputeRuledEvent {
+  dispute: PublicKey;
+  payerAmount: BN;
+  receiverAmount: BN;
+  appealDeadline: BN;
+}
+
+/** Emitted when a party appeals a panel ruling. */
+export interface DisputeAppealedEvent {
+  dispute: PublicKey;
+  appellant: PublicKey;
+}
+
+/** Emitted when the pool authority decides an appeal. */
+export interface DisputeAppealResolvedEvent {
+  dispute: PublicKey;
+  panelPayerAmount: BN;
+  payerAmount: BN;
+  overturned: boolean;
+}
+
+/** Emitted when a disputed escrow is paid out. */
+export interface DisputeResolvedEvent {
+  dispute: PublicKey;
+  payment: PublicKey;
+  payerAmount: BN;
+  receiverAmount: BN;
+  appealed: boolean;
+}
+
+/** Emitted when the protocol authority creates an arbiter pool. */
+export interface ArbiterPoolCreatedEvent {
+  arbiterPool: PublicKey;
+  poolId: number;
+  minStake: BN;
+  feeBps: number;
+  panelSize: number;
+  voteWindowSeconds: BN;
+}
+
+/** Emitted when a wallet stakes into an arbiter pool. */
+export interface ArbiterRegisteredEvent {
+  arbiter: PublicKey;
+  arbiterPool: PublicKey;
+  authority: PublicKey;
+  stake: BN;
+}
+
+/** Emitted when an arbiter tops up its stake. */
+export interface ArbiterStakeAddedEvent {
+  arbiter: PublicKey;
+  amount: BN;
+  stake: BN;
+}
+
+/** Emitted when an overturned arbiter loses stake. */
+export interface ArbiterSlashedEvent {
+  arbiter: PublicKey;
+  dispute: PublicKey;
+  amount: BN;
+  remainingStake: BN;
+}
+
+/** Emitted when an arbiter leaves its pool. */
+export interface ArbiterWithdrawnEvent {
+  arbiter: PublicKey;
+  arbiterPool: PublicKey;
+  stake: BN;
+}
+
+/** Emitted when a service changes its arbiter pool. */
+export interface ServiceArbiterPoolSetEvent {
+  service: PublicKey;
+  arbiterPool: PublicKey | null;
+}
+
+/** Emitted when a payer splits an escrowed payment into milestones. */
+export interface MilestonesSetEvent {
+  payment: PublicKey;
+  milestones: MilestoneSpec[];
+}
+
+/** Emitted when a single milestone is released to the receiver. */
+export interface MilestoneReleasedEvent {
+  payment: PublicKey;
+  index: number;
+  amount: BN;
+  remaining: BN;
+}
+
+/** Emitted when payer and receiver settle an escrow at an agreed split. */
+export interface PaymentSettledEvent {
+  payment: PublicKey;
+  payerAmount: BN;
+  receiverAmount: BN;
+  referralShare: BN;
+}
+
+/** Emitted for each preimage a receiver reveals to claim a hashlocked payment. */
+export interface PreimageRevealedEvent {
+  payment: PublicKey;
+  hash: number[];
+  preimage: Buffer;
+}
+
+/** Emitted when an agent authorises a session key. */
+export interface SessionKeyCreatedEvent {
+  sessionKey: PublicKey;
+  agent: PublicKey;
+  key: PublicKey;
+  expiresAt: BN;
+}
+
+/** Emitted when a session key is revoked. */
+export interface SessionKeyRevokedEvent {
+  sessionKey: PublicKey;
+  agent: PublicKey;
+  key: PublicKey;
+}
+
+/** Emitted when a provider releases a payment with a signed delivery receipt. */
+export interface ReceiptRedeemedEvent {
+  payment: PublicKey;
+  signer: PublicKey;
+}
+
+// ============================================================
+// QUERY / FILTER TYPES
+// ============================================================
+
+/** Filter options for searching services on the marketplace. */
+export interface FindServicesFilter {
+  /** Filter by a specific tag (exact match). */
+  tag?: string;
+  /** Filter by curated category id. */
+  category?: number;
+  /** Filter by provider agent PDA. */
+  provider?: PublicKey;
+  /** Only return services from agents with at least this reputation score. */
+  minReputation?: number;
+  /** Only return active services. Defaults to true. */
+  activeOnly?: boolean;
+}
+
+/** Options for creating a payment and immediately releasing it. */
+export interface PayAndReleaseOptions {
+  /** The service PDA to pay for. */
+  servicePda: PublicKey;
+  /** Amount in SOL. Must equal the service price times `quantity`. */
+  amountSol: number;
+  /** Units being purchased (requests / tokens). Default: 1. */
+  quantity?: number;
+  /** Freeform intent describing what is being paid for. */
+  intent: string;
+  /** Optional conditions that must be met. */
+  conditions?: PaymentCondition[];
+  /** Timeout in seconds before the escrowed payment can be refunded. Default: 3600 (1 hour). */
+  timeoutSeconds?: number;
+  /** The receiver agent's authority public key (wallet that receives SOL). */
+  receiverAuthority: PublicKey;
+}
+
+/** Options for creating and funding a stream in one call. */
+export interface CreateAndFundStreamOptions {
+  /** The receiver agent PDA. */
+  receiverAgentPda: PublicKey;
+  /** Rate in SOL per second. */
+  ratePerSecondSol: number;
+  /** Maximum duration of the stream in seconds. */
+  maxDurationSeconds: number;
+  /** Deposit amount in SOL. If omitted, defaults to rate * maxDuration. */
+  depositSol?: number;
+}
+
+/** Fields to change on an existing service. Omitted fields are left as-is. */
+export interface UpdateServiceOptions {
+  /** New display title (max 64 chars). */
+  title?: string;
+  /** New description (max 256 chars). */
+  description?: string;
+  /** New price in SOL. Only applies to orders placed after the update. */
+  priceSol?: number;
+  /** New pricing model. */
+  priceModel?: PriceModel;
+  /** Billing period, required when switching to Subscription pricing. */
+  subscriptionPeriodSeconds?: number;
+  /** New tag list (max 5 items). */
+  tags?: string[];
+}
+
+/** Parameters for a new coupon. Exactly one of `percent` / `fixedSol` is required. */
+export interface CreateCouponOptions {
+  /** Percentage off the quoted price (0 < percent < 100). */
+  percent?: number;
+  /** Fixed amount off the quoted price, in SOL (below the unit price). */
+  fixedSol?: number;
+  /** Maximum redemptions (default: 0 = unlimited). */
+  maxRedemptions?: number;
+  /** Unix timestamp after which the coupon is invalid (default: 0 = never). */
+  expiresAt?: number;
+  /** Buyer agent PDAs allowed to redeem (default: anyone, max 10). */
+  allowedBuyers?: PublicKey[];
+}
+
+/** Parameters for `setAccessRules`. */
+export interface AccessRulesOptions {
+  /** How `list` is applied (default: Open). */
+  listMode?: AccessListMode;
+  /** Buyer agent PDAs or organisation keys (max 10). */
+  list?: PublicKey[];
+  /** Minimum buyer reputation score (default: 0). */
+  minReputation?: number;
+  /** Issuer whose attestation every buyer must present. */
+  requiredAttester?: PublicKey;
+}
+
+// ============================================================
+// MINIMAL IDL
+// ============================================================
+
+/**
+ * Minimal Anchor IDL for the SolAgent program.
+ *
+ * This IDL is embedded so the SDK can work without requiring the consumer to
+ * generate or fetch the IDL separately. It covers all instructions, all
+ * account types, and all events.
+ */
+const SOLAGENT_IDL: Idl = {
+  version: "0.1.0",
+  name: "solagent",
+  instructions: [
+    {
+      name: "initializeProtocol",
+      accounts: [
+        { name: "protocol", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "registerAgent",
+      accounts: [
+        { name: "agent", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "name", type: "string" },
+        { name: "description", type: "string" },
+        { name: "capabilities", type: { vec: "string" } },
+        { name: "endpoint", type: "string" },
+      ],
+    },
+    {
+      name: "stakeReputation",
+      accounts: [
+        { name: "agent", isMut: true, isSigner: false },
+        { name: "vault", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [{ name: "amount", type: "u64" }],
+    },
+    {
+      name: "submitFeedback",
+      accounts: [
+        { name: "feedback", isMut: true, isSigner: false },
+        { name: "fromAgent", isMut: false, isSigner: false },
+        { name: "toAgent", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "rating", type: "u8" },
+        { name: "comment", type: "string" },
+      ],
+    },
+    {
+      name: "createService",
+      accounts: [
+        { name: "service", isMut: true, isSigner: false },
+        { name: "agent", isMut: false, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "serviceId", type: "string" },
+        { name: "title", type: "string" },
+        { name: "description", type: "string" },
+        { name: "priceLamports", type: "u64" },
+        {
+          name: "priceModel",
+          type: {
+            defined: "PriceModel",
+          },
+        },
+        { name: "tags", type: { vec: "string" } },
+      ],
+    },
+    {
+      name: "updateService",
+      accounts: [
+        { name: "service", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "title", type: { option: "string" } },
+        { name: "description", type: { option: "string" } },
+        { name: "priceLamports", type: { option: "u64" } },
+        {
+          name: "priceModel",
+          type: { option: { defined: "PriceModel" } },
+        },
+        { name: "tags", type: { option: { vec: "string" } } },
+      ],
+    },
+    {
+      name: "pauseService",
+      accounts: [
+        { name: "service", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "resumeService",
+      accounts: [
+        { name: "service", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "closeService",
+      accounts: [
+        { name: "service", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "payForService",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "receiverAgent", isMut: false, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        {
+          name: "coupon",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "referrerAgent",
+          isMut: false,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "buyerAttestation",
+          isMut: false,
+          isSigner: false,
+          isOptional: true,
+        },
+        { name: "payerAuthority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "amount", type: "u64" },
+        { name: "quantity", type: "u64" },
+        { name: "intent", type: "string" },
+        {
+          name: "conditions",
+          type: { vec: { defined: "PaymentCondition" } },
+        },
+        { name: "timeoutSeconds", type: "i64" },
+      ],
+    },
+    {
+      name: "releasePayment",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "receiverAgent", isMut: true, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        { name: "receiverAuthority", isMut: true, isSigner: false },
+        {
+          name: "referrerAgent",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "referrerAuthority",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        { name: "authority", isMut: true, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "refundPayment",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: false, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "createStream",
+      accounts: [
+        { name: "stream", isMut: true, isSigner: false },
+        { name: "streamVault", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: false, isSigner: false },
+        { name: "receiverAgent", isMut: false, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "ratePerSecond", type: "u64" },
+        { name: "maxDurationSeconds", type: "u64" },
+        { name: "depositAmount", type: "u64" },
+      ],
+    },
+    {
+      name: "withdrawStream",
+      accounts: [
+        { name: "stream", isMut: true, isSigner: false },
+        { name: "streamVault", isMut: true, isSigner: false },
+        { name: "receiverAgent", isMut: true, isSigner: false },
+        { name: "receiverAuthority", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: false, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "openAuction",
+      accounts: [
+        { name: "auction", isMut: true, isSigner: false },
+        { name: "auctionVault", isMut: false, isSigner: false },
+        { name: "service", isMut: false, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "reservePrice", type: "u64" },
+        { name: "durationSeconds", type: "i64" },
+        { name: "deliveryTimeoutSeconds", type: "i64" },
+      ],
+    },
+    {
+      name: "placeBid",
+      accounts: [
+        { name: "auction", isMut: true, isSigner: false },
+        { name: "auctionVault", isMut: true, isSigner: false },
+        { name: "bidderAgent", isMut: false, isSigner: false },
+        { name: "service", isMut: false, isSigner: false },
+        {
+          name: "buyerAttestation",
+          isMut: false,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "previousBidderAuthority",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        { name: "bidderAuthority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "amount", type: "u64" },
+        { name: "intent", type: "string" },
+      ],
+    },
+    {
+      name: "settleAuction",
+      accounts: [
+        { name: "auction", isMut: true, isSigner: false },
+        { name: "auctionVault", isMut: true, isSigner: false },
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "winnerAgent", isMut: true, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        { name: "settler", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "cancelAuction",
+      accounts: [
+        { name: "auction", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "closeAuction",
+      accounts: [
+        { name: "auction", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "openUsageEscrow",
+      accounts: [
+        { name: "usageEscrow", isMut: true, isSigner: false },
+        { name: "usageVault", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: false, isSigner: false },
+        { name: "service", isMut: false, isSigner: false },
+        {
+          name: "buyerAttestation",
+          isMut: false,
+          isSigner: false,
+          isOptional: true,
+        },
+        { name: "payerAuthority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "deposit", type: "u64" },
+        { name: "maxUnitsPerReport", type: "u64" },
+        { name: "challengeWindowSeconds", type: "i64" },
+      ],
+    },
+    {
+      name: "topUpUsageEscrow",
+      accounts: [
+        { name: "usageEscrow", isMut: true, isSigner: false },
+        { name: "usageVault", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [{ name: "amount", type: "u64" }],
+    },
+    {
+      name: "setUsageCap",
+      accounts: [
+        { name: "usageEscrow", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: false, isSigner: true },
+      ],
+      args: [{ name: "maxUnitsPerReport", type: "u64" }],
+    },
+    {
+      name: "submitUsageReport",
+      accounts: [
+        { name: "usageEscrow", isMut: true, isSigner: false },
+        { name: "usageVault", isMut: true, isSigner: false },
+        { name: "usageReport", isMut: true, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "providerAgent", isMut: true, isSigner: false },
+        {
+          name: "payerAuthority",
+          isMut: false,
+          isSigner: true,
+          isOptional: true,
+        },
+        { name: "providerAuthority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [{ name: "units", type: "u64" }],
+    },
+    {
+      name: "contestUsageReport",
+      accounts: [
+        { name: "usageEscrow", isMut: true, isSigner: false },
+        { name: "usageReport", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: false, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "claimUsageReport",
+      accounts: [
+        { name: "usageEscrow", isMut: true, isSigner: false },
+        { name: "usageVault", isMut: true, isSigner: false },
+        { name: "usageReport", isMut: true, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "providerAgent", isMut: true, isSigner: false },
+        { name: "providerAuthority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "resolveUsageReport",
+      accounts: [
+        { name: "usageEscrow", isMut: true, isSigner: false },
+        { name: "usageVault", isMut: true, isSigner: false },
+        { name: "usageReport", isMut: true, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "providerAgent", isMut: true, isSigner: false },
+        { name: "providerAuthority", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: false, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [{ name: "units", type: "u64" }],
+    },
+    {
+      name: "closeUsageReport",
+      accounts: [
+        { name: "usageEscrow", isMut: true, isSigner: false },
+        { name: "usageReport", isMut: true, isSigner: false },
+        { name: "service", isMut: false, isSigner: false },
+        { name: "providerAuthority", isMut: true, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "closeUsageEscrow",
+      accounts: [
+        { name: "usageEscrow", isMut: true, isSigner: false },
+        { name: "usageVault", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "postJob",
+      accounts: [
+        { name: "job", isMut: true, isSigner: false },
+        { name: "buyerAgent", isMut: false, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "jobId", type: "string" },
+        { name: "intent", type: "string" },
+        { name: "budget", type: "u64" },
+        { name: "requiredCapabilities", type: { vec: "string" } },
+        { name: "deadline", type: "i64" },
+      ],
+    },
+    {
+      name: "submitJobBid",
+      accounts: [
+        { name: "job", isMut: true, isSigner: false },
+        { name: "jobBid", isMut: true, isSigner: false },
+        { name: "providerAgent", isMut: false, isSigner: false },
+        { name: "service", isMut: false, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "price", type: "u64" },
+        { name: "deliverySeconds", type: "i64" },
+        { name: "message", type: "string" },
+      ],
+    },
+    {
+      name: "withdrawJobBid",
+      accounts: [
+        { name: "job", isMut: true, isSigner: false },
+        { name: "jobBid", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "closeJobBid",
+      accounts: [
+        { name: "job", isMut: true, isSigner: false },
+        { name: "jobBid", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "acceptJobBid",
+      accounts: [
+        { name: "job", isMut: true, isSigner: false },
+        { name: "jobBid", isMut: false, isSigner: false },
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "buyerAgent", isMut: true, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        {
+          name: "buyerAttestation",
+          isMut: false,
+          isSigner: false,
+          isOptional: true,
+        },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "cancelJob",
+      accounts: [
+        { name: "job", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "closeJob",
+      accounts: [
+        { name: "job", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "subscribe",
+      accounts: [
+        { name: "subscription", isMut: true, isSigner: false },
+        { name: "subscriptionVault", isMut: true, isSigner: false },
+        { name: "subscriberAgent", isMut: true, isSigner: false },
+        { name: "providerAgent", isMut: true, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        { name: "providerAuthority", isMut: true, isSigner: false },
+        {
+          name: "buyerAttestation",
+          isMut: false,
+          isSigner: false,
+          isOptional: true,
+        },
+        { name: "subscriberAuthority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "amountPerPeriod", type: "u64" },
+        { name: "periodSeconds", type: "i64" },
+        { name: "deposit", type: "u64" },
+      ],
+    },
+    {
+      name: "topUpSubscription",
+      accounts: [
+        { name: "subscription", isMut: true, isSigner: false },
+        { name: "subscriptionVault", isMut: true, isSigner: false },
+        { name: "subscriberAuthority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [{ name: "amount", type: "u64" }],
+    },
+    {
+      name: "chargeSubscription",
+      accounts: [
+        { name: "subscription", isMut: true, isSigner: false },
+        { name: "subscriptionVault", isMut: true, isSigner: false },
+        { name: "subscriberAgent", isMut: true, isSigner: false },
+        { name: "providerAgent", isMut: true, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        { name: "providerAuthority", isMut: true, isSigner: false },
+        { name: "cranker", isMut: false, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "cancelSubscription",
+      accounts: [
+        { name: "subscription", isMut: true, isSigner: false },
+        { name: "subscriptionVault", isMut: true, isSigner: false },
+        { name: "subscriberAuthority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "verifySubscription",
+      accounts: [
+        { name: "subscription", isMut: false, isSigner: false },
+        { name: "subscriberAgent", isMut: false, isSigner: false },
+        { name: "service", isMut: false, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "setPricingSchedule",
+      accounts: [
+        { name: "service", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [
+        {
+          name: "pricing",
+          type: { option: { defined: "PricingSchedule" } },
+        },
+      ],
+    },
+    {
+      name: "createCoupon",
+      accounts: [
+        { name: "coupon", isMut: true, isSigner: false },
+        { name: "service", isMut: false, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "code", type: "string" },
+        { name: "discount", type: { defined: "Discount" } },
+        { name: "maxRedemptions", type: "u64" },
+        { name: "expiresAt", type: "i64" },
+        { name: "allowedBuyers", type: { vec: "publicKey" } },
+      ],
+    },
+    {
+      name: "closeCoupon",
+      accounts: [
+        { name: "coupon", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "setReferralFee",
+      accounts: [
+        { name: "service", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [{ name: "referralBps", type: "u16" }],
+    },
+    {
+      name: "issueAttestation",
+      accounts: [
+        { name: "attestation", isMut: true, isSigner: false },
+        { name: "subjectAgent", isMut: false, isSigner: false },
+        { name: "issuer", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [{ name: "expiresAt", type: "i64" }],
+    },
+    {
+      name: "revokeAttestation",
+      accounts: [
+        { name: "attestation", isMut: true, isSigner: false },
+        { name: "issuer", isMut: true, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "setAccessRules",
+      accounts: [
+        { name: "service", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [
+        {
+          name: "access",
+          type: { option: { defined: "AccessRules" } },
+        },
+      ],
+    },
+    {
+      name: "setCapacity",
+      accounts: [
+        { name: "service", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [{ name: "maxConcurrentOrders", type: "u64" }],
+    },
+    {
+      name: "setSla",
+      accounts: [
+        { name: "service", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [
+        {
+          name: "sla",
+          type: { option: { defined: "SlaTerms" } },
+        },
+      ],
+    },
+    {
+      name: "setTimeoutPolicy",
+      accounts: [
+        { name: "service", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [
+        {
+          name: "policy",
+          type: { defined: "TimeoutPolicy" },
+        },
+      ],
+    },
+    {
+      name: "migrateService",
+      accounts: [
+        { name: "service", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "acknowledgePayment",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "receiverAgent", isMut: false, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "markDelivered",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "receiverAgent", isMut: false, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "updateCapabilities",
+      accounts: [
+        { name: "agent", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [{ name: "capabilities", type: { vec: "string" } }],
+    },
+    {
+      name: "migrateAgent",
+      accounts: [
+        { name: "agent", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "createCategory",
+      accounts: [
+        { name: "category", isMut: true, isSigner: false },
+        { name: "protocol", isMut: false, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "id", type: "u16" },
+        { name: "name", type: "string" },
+        { name: "description", type: "string" },
+      ],
+    },
+    {
+      name: "updateCategory",
+      accounts: [
+        { name: "category", isMut: true, isSigner: false },
+        { name: "protocol", isMut: false, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [
+        { name: "name", type: { option: "string" } },
+        { name: "description", type: { option: "string" } },
+        { name: "isActive", type: { option: "bool" } },
+      ],
+    },
+    {
+      name: "setServiceCategories",
+      accounts: [
+        { name: "service", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [{ name: "categoryIds", type: { vec: "u16" } }],
+    },
+    {
+      name: "createBundle",
+      accounts: [
+        { name: "bundle", isMut: true, isSigner: false },
+        { name: "agent", isMut: false, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "bundleId", type: "string" },
+        { name: "title", type: "string" },
+        { name: "description", type: "string" },
+        { name: "priceLamports", type: "u64" },
+        { name: "components", type: { vec: { defined: "BundleComponent" } } },
+      ],
+    },
+    {
+      name: "payForBundle",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "bundle", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "amount", type: "u64" },
+        { name: "intent", type: "string" },
+        { name: "timeoutSeconds", type: "i64" },
+      ],
+    },
+    {
+      name: "releaseBundlePayment",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: false, isSigner: false },
+        { name: "bundle", isMut: true, isSigner: false },
+        { name: "orchestratorAgent", isMut: true, isSigner: false },
+        { name: "orchestratorAuthority", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "refundBundlePayment",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: false, isSigner: false },
+        { name: "bundle", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "refreshBundleRating",
+      accounts: [{ name: "bundle", isMut: true, isSigner: false }],
+      args: [],
+    },
+    {
+      name: "pauseBundle",
+      accounts: [
+        { name: "bundle", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "resumeBundle",
+      accounts: [
+        { name: "bundle", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "closeBundle",
+      accounts: [
+        { name: "bundle", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "openDispute",
+      accounts: [
+        { name: "dispute", isMut: true, isSigner: false },
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "agent", isMut: false, isSigner: false },
+        { name: "listing", isMut: false, isSigner: false },
+        {
+          name: "arbiterPool",
+          isMut: false,
+          isSigner: false,
+          isOptional: true,
+        },
+        { name: "protocol", isMut: false, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [{ name: "reason", type: "string" }],
+    },
+    {
+      name: "drawDisputePanel",
+      accounts: [
+        { name: "dispute", isMut: true, isSigner: false },
+        { name: "arbiterPool", isMut: false, isSigner: false },
+        { name: "payerAgent", isMut: false, isSigner: false },
+        { name: "receiverAgent", isMut: false, isSigner: false },
+        { name: "slotHashes", isMut: false, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "submitEvidence",
+      accounts: [
+        { name: "dispute", isMut: true, isSigner: false },
+        { name: "agent", isMut: false, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [
+        { name: "hash", type: { array: ["u8", 32] } },
+        { name: "uri", type: "string" },
+      ],
+    },
+    {
+      name: "resolveDispute",
+      accounts: [
+        { name: "dispute", isMut: true, isSigner: false },
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "receiverAgent", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: false },
+        { name: "receiverAuthority", isMut: true, isSigner: false },
+        { name: "listing", isMut: true, isSigner: false },
+        {
+          name: "arbiterAccount",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        { name: "arbiter", isMut: true, isSigner: true },
+        {
+          name: "referrerAgent",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "referrerAuthority",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+      ],
+      args: [{ name: "payerAmount", type: "u64" }],
+    },
+    {
+      name: "setArbiterPool",
+      accounts: [
+        { name: "service", isMut: true, isSigner: false },
+        {
+          name: "arbiterPool",
+          isMut: false,
+          isSigner: false,
+          isOptional: true,
+        },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "appealDispute",
+      accounts: [
+        { name: "dispute", isMut: true, isSigner: false },
+        { name: "agent", isMut: false, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "resolveAppeal",
+      accounts: [
+        { name: "dispute", isMut: true, isSigner: false },
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "receiverAgent", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: false },
+        { name: "receiverAuthority", isMut: true, isSigner: false },
+        { name: "listing", isMut: true, isSigner: false },
+        { name: "protocol", isMut: false, isSigner: false },
+        { name: "treasury", isMut: true, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+        {
+          name: "referrerAgent",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "referrerAuthority",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+      ],
+      args: [{ name: "payerAmount", type: "u64" }],
+    },
+    {
+      name: "finalizeDispute",
+      accounts: [
+        { name: "dispute", isMut: true, isSigner: false },
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "receiverAgent", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: false },
+        { name: "receiverAuthority", isMut: true, isSigner: false },
+        { name: "listing", isMut: true, isSigner: false },
+        {
+          name: "referrerAgent",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "referrerAuthority",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+      ],
+      args: [],
+    },
+    {
+      name: "expireDispute",
+      accounts: [
+        { name: "dispute", isMut: true, isSigner: false },
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: false },
+        { name: "listing", isMut: true, isSigner: false },
+        { name: "protocol", isMut: false, isSigner: false },
+        { name: "treasury", isMut: true, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "releaseArbiterCase",
+      accounts: [
+        { name: "dispute", isMut: true, isSigner: false },
+        { name: "arbiter", isMut: true, isSigner: false },
+      ],
+      args: [],
+    },
+    {
+      name: "createArbiterPool",
+      accounts: [
+        { name: "arbiterPool", isMut: true, isSigner: false },
+        { name: "protocol", isMut: false, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "poolId", type: "u16" },
+        { name: "name", type: "string" },
+        { name: "minStake", type: "u64" },
+        { name: "feeBps", type: "u16" },
+        { name: "panelSize", type: "u8" },
+        { name: "appealWindowSeconds", type: "i64" },
+        { name: "slashBps", type: "u16" },
+        { name: "voteWindowSeconds", type: "i64" },
+      ],
+    },
+    {
+      name: "registerArbiter",
+      accounts: [
+        { name: "arbiter", isMut: true, isSigner: false },
+        { name: "arbiterPool", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [{ name: "stake", type: "u64" }],
+    },
+    {
+      name: "addArbiterStake",
+      accounts: [
+        { name: "arbiter", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [{ name: "amount", type: "u64" }],
+    },
+    {
+      name: "withdrawArbiter",
+      accounts: [
+        { name: "arbiter", isMut: true, isSigner: false },
+        { name: "arbiterPool", isMut: true, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "setMilestones",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: false, isSigner: false },
+        { name: "service", isMut: false, isSigner: false },
+        { name: "authority", isMut: false, isSigner: true },
+      ],
+      args: [
+        {
+          name: "milestones",
+          type: { vec: { defined: "MilestoneSpec" } },
+        },
+      ],
+    },
+    {
+      name: "releaseMilestone",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "receiverAgent", isMut: true, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        { name: "receiverAuthority", isMut: true, isSigner: false },
+        {
+          name: "referrerAgent",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "referrerAuthority",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        { name: "authority", isMut: true, isSigner: true },
+      ],
+      args: [{ name: "index", type: "u8" }],
+    },
+    {
+      name: "settlePayment",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "dispute", isMut: true, isSigner: false, isOptional: true },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "receiverAgent", isMut: true, isSigner: false },
+        { name: "listing", isMut: true, isSigner: false },
+        {
+          name: "referrerAgent",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "referrerAuthority",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        { name: "payerAuthority", isMut: true, isSigner: true },
+        { name: "receiverAuthority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [{ name: "payerAmount", type: "u64" }],
+    },
+    {
+      name: "executeConditionalRelease",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "receiverAgent", isMut: true, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: false },
+        { name: "receiverAuthority", isMut: true, isSigner: false },
+        {
+          name: "referrerAgent",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "referrerAuthority",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+      ],
+      args: [{ name: "preimages", type: { vec: "bytes" } }],
+    },
+    {
+      name: "claimWithPreimage",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "receiverAgent", isMut: true, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: false },
+        {
+          name: "referrerAgent",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "referrerAuthority",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        { name: "receiverAuthority", isMut: true, isSigner: true },
+      ],
+      args: [{ name: "preimages", type: { vec: "bytes" } }],
+    },
+    {
+      name: "createSessionKey",
+      accounts: [
+        { name: "sessionKey", isMut: true, isSigner: false },
+        { name: "agent", isMut: false, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [
+        { name: "key", type: "publicKey" },
+        { name: "expiresAt", type: "i64" },
+      ],
+    },
+    {
+      name: "revokeSessionKey",
+      accounts: [
+        { name: "sessionKey", isMut: true, isSigner: false },
+        { name: "agent", isMut: false, isSigner: false },
+        { name: "authority", isMut: true, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "releaseWithReceipt",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "receiverAgent", isMut: true, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: false },
+        {
+          name: "referrerAgent",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "referrerAuthority",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "sessionKey",
+          isMut: false,
+          isSigner: false,
+          isOptional: true,
+        },
+        { name: "instructions", isMut: false, isSigner: false },
+        { name: "receiverAuthority", isMut: true, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "releaseOnTimeout",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "escrow", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: true, isSigner: false },
+        { name: "receiverAgent", isMut: true, isSigner: false },
+        { name: "service", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: false },
+        {
+          name: "referrerAgent",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        {
+          name: "referrerAuthority",
+          isMut: true,
+          isSigner: false,
+          isOptional: true,
+        },
+        { name: "receiverAuthority", isMut: true, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "closePayment",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "payerAgent", isMut: false, isSigner: false },
+        { name: "listing", isMut: true, isSigner: false },
+        { name: "payerAuthority", isMut: true, isSigner: true },
+      ],
+      args: [],
+    },
+    {
+      name: "migratePayment",
+      accounts: [
+        { name: "payment", isMut: true, isSigner: false },
+        { name: "payer", isMut: true, isSigner: true },
+        { name: "systemProgram", isMut: false, isSigner: false },
+      ],
+      args: [],
+    },
+  ],
+  accounts: [
+    {
+      name: "Protocol",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "authority", type: "publicKey" },
+          { name: "totalAgents", type: "u64" },
+          { name: "totalServices", type: "u64" },
+          { name: "totalPayments", type: "u64" },
+          { name: "totalVolume", type: "u64" },
+          { name: "totalStaked", type: "u64" },
+          { name: "feeBps", type: "u16" },
+          { name: "treasury", type: "publicKey" },
+          { name: "bump", type: "u8" },
+        ],
+      },
+    },
+    {
+      name: "Agent",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "authority", type: "publicKey" },
+          { name: "name", type: "string" },
+          { name: "description", type: "string" },
+          { name: "capabilities", type: { vec: "string" } },
+          { name: "endpoint", type: "string" },
+          { name: "reputationScore", type: "u64" },
+          { name: "totalStaked", type: "u64" },
+          { name: "totalEarned", type: "u64" },
+          { name: "totalSpent", type: "u64" },
+          { name: "servicesCompleted", type: "u64" },
+          { name: "servicesRequested", type: "u64" },
+          { name: "feedbacksReceived", type: "u64" },
+          { name: "registeredAt", type: "i64" },
+          { name: "isActive", type: "bool" },
+          { name: "bump", type: "u8" },
+          { name: "referralEarned", type: "u64" },
+          { name: "referralFeesPaid", type: "u64" },
+          { name: "slaOrders", type: "u64" },
+          { name: "slaMet", type: "u64" },
+          { name: "disputes", type: "u64" },
+          { name: "disputesWon", type: "u64" },
+          { name: "disputesLost", type: "u64" },
+        ],
+      },
+    },
+    {
+      name: "Service",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "provider", type: "publicKey" },
+          { name: "authority", type: "publicKey" },
+          { name: "serviceId", type: "string" },
+          { name: "title", type: "string" },
+          { name: "description", type: "string" },
+          { name: "priceLamports", type: "u64" },
+          {
+            name: "priceModel",
+            type: { defined: "PriceModel" },
+          },
+          { name: "tags", type: { vec: "string" } },
+          { name: "totalOrders", type: "u64" },
+          { name: "totalRevenue", type: "u64" },
+          { name: "avgRating", type: "u8" },
+          { name: "isActive", type: "bool" },
+          { name: "createdAt", type: "i64" },
+          { name: "bump", type: "u8" },
+          {
+            name: "pricing",
+            type: { option: { defined: "PricingSchedule" } },
+          },
+          { name: "referralBps", type: "u16" },
+          {
+            name: "access",
+            type: { option: { defined: "AccessRules" } },
+          },
+          { name: "openOrders", type: "u64" },
+          { name: "maxConcurrentOrders", type: "u64" },
+          {
+            name: "sla",
+            type: { option: { defined: "SlaTerms" } },
+          },
+          { name: "slaOrders", type: "u64" },
+          { name: "slaMet", type: "u64" },
+          { name: "version", type: "u64" },
+          { name: "categories", type: { vec: "u16" } },
+          { name: "arbiterPool", type: { option: "publicKey" } },
+          { name: "unclosedPayments", type: "u64" },
+          {
+            name: "timeoutPolicy",
+            type: { defined: "TimeoutPolicy" },
+          },
+        ],
+      },
+    },
+    {
+      name: "Payment",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "payer", type: "publicKey" },
+          { name: "receiver", type: "publicKey" },
+          { name: "service", type: "publicKey" },
+          { name: "amount", type: "u64" },
+          { name: "intent", type: "string" },
+          { name: "legacyConditions", type: { vec: "string" } },
+          {
+            name: "status",
+            type: { defined: "PaymentStatus" },
+          },
+          { name: "createdAt", type: "i64" },
+          { name: "timeoutAt", type: "i64" },
+          { name: "completedAt", type: "i64" },
+          { name: "bump", type: "u8" },
+          { name: "escrowBump", type: "u8" },
+          { name: "quantity", type: "u64" },
+          {
+            name: "conditions",
+            type: { vec: { defined: "PaymentCondition" } },
+          },
+          { name: "referrer", type: { option: "publicKey" } },
+          { name: "referralBps", type: "u16" },
+          {
+            name: "sla",
+            type: { option: { defined: "SlaTerms" } },
+          },
+          { name: "serviceVersion", type: "u64" },
+          { name: "servicePrice", type: "u64" },
+          { name: "acknowledgedAt", type: "i64" },
+          { name: "deliveredAt", type: "i64" },
+          {
+            name: "milestones",
+            type: { vec: { defined: "Milestone" } },
+          },
+          { name: "releasedAmount", type: "u64" },
+          {
+            name: "timeoutPolicy",
+            type: { defined: "TimeoutPolicy" },
+          },
+        ],
+      },
+    },
+    {
+      name: "Feedback",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "fromAgent", type: "publicKey" },
+          { name: "toAgent", type: "publicKey" },
+          { name: "rating", type: "u8" },
+          { name: "comment", type: "string" },
+          { name: "timestamp", type: "i64" },
+          { name: "bump", type: "u8" },
+        ],
+      },
+    },
+    {
+      name: "Stream",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "payer", type: "publicKey" },
+          { name: "receiver", type: "publicKey" },
+          { name: "ratePerSecond", type: "u64" },
+          { name: "deposited", type: "u64" },
+          { name: "withdrawn", type: "u64" },
+          { name: "startedAt", type: "i64" },
+          { name: "maxEndAt", type: "i64" },
+          { name: "lastWithdrawnAt", type: "i64" },
+          { name: "isActive", type: "bool" },
+          { name: "bump", type: "u8" },
+          { name: "vaultBump", type: "u8" },
+        ],
+      },
+    },
+    {
+      name: "Auction",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "service", type: "publicKey" },
+          { name: "provider", type: "publicKey" },
+          { name: "authority", type: "publicKey" },
+          { name: "reservePrice", type: "u64" },
+          { name: "highestBid", type: "u64" },
+          { name: "highestBidder", type: "publicKey" },
+          { name: "highestBidderAuthority", type: "publicKey" },
+          { name: "intent", type: "string" },
+          { name: "bidCount", type: "u64" },
+          { name: "deliveryTimeoutSeconds", type: "i64" },
+          { name: "createdAt", type: "i64" },
+          { name: "endAt", type: "i64" },
+          {
+            name: "status",
+            type: { defined: "AuctionStatus" },
+          },
+          { name: "payment", type: "publicKey" },
+          { name: "bump", type: "u8" },
+          { name: "vaultBump", type: "u8" },
+        ],
+      },
+    },
+    {
+      name: "UsageEscrow",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "payer", type: "publicKey" },
+          { name: "payerAuthority", type: "publicKey" },
+          { name: "service", type: "publicKey" },
+          { name: "provider", type: "publicKey" },
+          { name: "pricePerUnit", type: "u64" },
+          {
+            name: "pricing",
+            type: { option: { defined: "PricingSchedule" } },
+          },
+          { name: "discountBps", type: "u16" },
+          { name: "deposited", type: "u64" },
+          { name: "drawn", type: "u64" },
+          { name: "pending", type: "u64" },
+          { name: "unitsBilled", type: "u64" },
+          { name: "unitsReported", type: "u64" },
+          { name: "maxUnitsPerReport", type: "u64" },
+          { name: "challengeWindowSeconds", type: "i64" },
+          { name: "reportCount", type: "u64" },
+          { name: "createdAt", type: "i64" },
+          { name: "bump", type: "u8" },
+          { name: "vaultBump", type: "u8" },
+          { name: "openReports", type: "u64" },
+        ],
+      },
+    },
+    {
+      name: "UsageReport",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "usageEscrow", type: "publicKey" },
+          { name: "index", type: "u64" },
+          { name: "units", type: "u64" },
+          { name: "amount", type: "u64" },
+          { name: "submittedAt", type: "i64" },
+          { name: "claimableAt", type: "i64" },
+          { name: "coSigned", type: "bool" },
+          {
+            name: "status",
+            type: { defined: "UsageReportStatus" },
+          },
+          { name: "bump", type: "u8" },
+        ],
+      },
+    },
+    {
+      name: "Job",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "buyer", type: "publicKey" },
+          { name: "authority", type: "publicKey" },
+          { name: "jobId", type: "string" },
+          { name: "intent", type: "string" },
+          { name: "budget", type: "u64" },
+          { name: "requiredCapabilities", type: { vec: "string" } },
+          { name: "deadline", type: "i64" },
+          {
+            name: "status",
+            type: { defined: "JobStatus" },
+          },
+          { name: "bidCount", type: "u64" },
+          { name: "acceptedBid", type: "publicKey" },
+          { name: "payment", type: "publicKey" },
+          { name: "createdAt", type: "i64" },
+          { name: "bump", type: "u8" },
+        ],
+      },
+    },
+    {
+      name: "JobBid",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "job", type: "publicKey" },
+          { name: "provider", type: "publicKey" },
+          { name: "authority", type: "publicKey" },
+          { name: "service", type: "publicKey" },
+          { name: "price", type: "u64" },
+          { name: "deliverySeconds", type: "i64" },
+          { name: "message", type: "string" },
+          { name: "submittedAt", type: "i64" },
+          { name: "bump", type: "u8" },
+        ],
+      },
+    },
+    {
+      name: "Subscription",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "subscriber", type: "publicKey" },
+          { name: "subscriberAuthority", type: "publicKey" },
+          { name: "service", type: "publicKey" },
+          { name: "provider", type: "publicKey" },
+          { name: "providerAuthority", type: "publicKey" },
+          { name: "amountPerPeriod", type: "u64" },
+          { name: "periodSeconds", type: "i64" },
+          { name: "deposited", type: "u64" },
+          { name: "charged", type: "u64" },
+          { name: "periodsCharged", type: "u64" },
+          { name: "paidUntil", type: "i64" },
+          {
+            name: "status",
+            type: { defined: "SubscriptionStatus" },
+          },
+          { name: "createdAt", type: "i64" },
+          { name: "bump", type: "u8" },
+          { name: "vaultBump", type: "u8" },
+        ],
+      },
+    },
+    {
+      name: "Coupon",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "service", type: "publicKey" },
+          { name: "authority", type: "publicKey" },
+          { name: "code", type: "string" },
+          { name: "discount", type: { defined: "Discount" } },
+          { name: "maxRedemptions", type: "u64" },
+          { name: "redemptions", type: "u64" },
+          { name: "expiresAt", type: "i64" },
+          { name: "allowedBuyers", type: { vec: "publicKey" } },
+          { name: "totalDiscounted", type: "u64" },
+          { name: "createdAt", type: "i64" },
+          { name: "bump", type: "u8" },
+        ],
+      },
+    },
+    {
+      name: "Attestation",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "issuer", type: "publicKey" },
+          { name: "subject", type: "publicKey" },
+          { name: "issuedAt", type: "i64" },
+          { name: "expiresAt", type: "i64" },
+          { name: "bump", type: "u8" },
+        ],
+      },
+    },
+    {
+      name: "IndexEntry",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "kind", type: { defined: "IndexKind" } },
+          { name: "key", type: "string" },
+          { name: "entry", type: "publicKey" },
+          { name: "bump", type: "u8" },
+        ],
+      },
+    },
+    {
+      name: "Category",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "id", type: "u16" },
+          { name: "name", type: "string" },
+          { name: "description", type: "string" },
+          { name: "isActive", type: "bool" },
+          { name: "createdAt", type: "i64" },
+          { name: "bump", type: "u8" },
+        ],
+      },
+    },
+    {
+      name: "Bundle",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "orchestrator", type: "publicKey" },
+          { name: "authority", type: "publicKey" },
+          { name: "bundleId", type: "string" },
+          { name: "title", type: "string" },
+          { name: "description", type: "string" },
+          { name: "priceLamports", type: "u64" },
+          {
+            name: "components",
+            type: { vec: { defined: "BundleComponent" } },
+          },
+          { name: "totalOrders", type: "u64" },
+          { name: "openOrders", type: "u64" },
+          { name: "totalRevenue", type: "u64" },
+          { name: "avgRating", type: "u8" },
+          { name: "componentReputation", type: "u64" },
+          { name: "isActive", type: "bool" },
+          { name: "createdAt", type: "i64" },
+          { name: "bump", type: "u8" },
+          { name: "unclosedPayments", type: "u64" },
+        ],
+      },
+    },
+    {
+      name: "Dispute",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "payment", type: "publicKey" },
+          { name: "payer", type: "publicKey" },
+          { name: "receiver", type: "publicKey" },
+          { name: "openedBy", type: "publicKey" },
+          { name: "pool", type: { option: "publicKey" } },
+          { name: "arbiters", type: { vec: "publicKey" } },
+          { name: "votes", type: { vec: { defined: "ArbiterVote" } } },
+          { name: "appealAuthority", type: "publicKey" },
+          { name: "amount", type: "u64" },
+          { name: "fee", type: "u64" },
+          { name: "feePaid", type: "u64" },
+          { name: "appealWindowSeconds", type: "i64" },
+          { name: "slashBps", type: "u16" },
+          { name: "reason", type: "string" },
+          { name: "evidence", type: { vec: { defined: "Evidence" } } },
+          { name: "status", type: { defined: "DisputeStatus" } },
+          { name: "payerAmount", type: "u64" },
+          { name: "receiverAmount", type: "u64" },
+          { name: "appealedBy", type: { option: "publicKey" } },
+          { name: "openedAt", type: "i64" },
+          { name: "ruledAt", type: "i64" },
+          { name: "resolvedAt", type: "i64" },
+          { name: "bump", type: "u8" },
+          { name: "drawSlot", type: "u64" },
+          { name: "voteDeadline", type: "i64" },
+          { name: "releasedArbiters", type: "u8" },
+        ],
+      },
+    },
+    {
+      name: "ArbiterPool",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "poolId", type: "u16" },
+          { name: "authority", type: "publicKey" },
+          { name: "name", type: "string" },
+          { name: "minStake", type: "u64" },
+          { name: "feeBps", type: "u16" },
+          { name: "panelSize", type: "u8" },
+          { name: "appealWindowSeconds", type: "i64" },
+          { name: "slashBps", type: "u16" },
+          { name: "arbiters", type: { vec: "publicKey" } },
+          { name: "createdAt", type: "i64" },
+          { name: "bump", type: "u8" },
+          { name: "voteWindowSeconds", type: "i64" },
+        ],
+      },
+    },
+    {
+      name: "Arbiter",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "authority", type: "publicKey" },
+          { name: "pool", type: "publicKey" },
+          { name: "stake", type: "u64" },
+          { name: "casesRuled", type: "u64" },
+          { name: "casesOverturned", type: "u64" },
+          { name: "feesEarned", type: "u64" },
+          { name: "totalSlashed", type: "u64" },
+          { name: "openAppeals", type: "u64" },
+          { name: "lockedUntil", type: "i64" },
+          { name: "registeredAt", type: "i64" },
+          { name: "bump", type: "u8" },
+          { name: "openCases", type: "u32" },
+        ],
+      },
+    },
+    {
+      name: "SessionKey",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "agent", type: "publicKey" },
+          { name: "key", type: "publicKey" },
+          { name: "createdAt", type: "i64" },
+          { name: "expiresAt", type: "i64" },
+          { name: "bump", type: "u8" },
+        ],
+      },
+    },
+  ],
+  types: [
+    {
+      name: "PriceModel",
+      type: {
+        kind: "enum",
+        variants: [
+          { name: "Fixed" },
+          { name: "PerRequest" },
+          { name: "PerSecond" },
+          { name: "PerToken" },
+          { name: "Auction" },
+          {
+            name: "Subscription",
+            fields: [{ name: "periodSeconds", type: "i64" }],
+          },
+        ],
+      },
+    },
+    {
+      name: "PaymentStatus",
+      type: {
+        kind: "enum",
+        variants: [
+          { name: "Escrowed" },
+          { name: "Released" },
+          { name: "Refunded" },
+          { name: "Disputed" },
+          { name: "Settled" },
+        ],
+      },
+    },
+    {
+      name: "AuctionStatus",
+      type: {
+        kind: "enum",
+        variants: [{ name: "Open" }, { name: "Settled" }],
+      },
+    },
+    {
+      name: "UsageReportStatus",
+      type: {
+        kind: "enum",
+        variants: [
+          { name: "Pending" },
+          { name: "Settled" },
+          { name: "Contested" },
+        ],
+      },
+    },
+    {
+      name: "JobStatus",
+      type: {
+        kind: "enum",
+        variants: [
+          { name: "Open" },
+          { name: "Awarded" },
+          { name: "Cancelled" },
+        ],
+      },
+    },
+    {
+      name: "SubscriptionStatus",
+      type: {
+        kind: "enum",
+        variants: [{ name: "Active" }, { name: "Lapsed" }],
+      },
+    },
+    {
+      name: "PricingSchedule",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "tiers", type: { vec: { defined: "PriceTier" } } },
+          {
+            name: "reputationDiscounts",
+            type: { vec: { defined: "ReputationDiscount" } },
+          },
+        ],
+      },
+    },
+    {
+      name: "PriceTier",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "fromUnit", type: "u64" },
+          { name: "priceLamports", type: "u64" },
+        ],
+      },
+    },
+    {
+      name: "ReputationDiscount",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "minReputation", type: "u64" },
+          { name: "discountBps", type: "u16" },
+        ],
+      },
+    },
+    {
+      name: "Discount",
+      type: {
+        kind: "enum",
+        variants: [
+          { name: "Percent", fields: [{ name: "bps", type: "u16" }] },
+          { name: "Fixed", fields: [{ name: "lamports", type: "u64" }] },
+        ],
+      },
+    },
+    {
+      name: "AccessRules",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "listMode", type: { defined: "AccessListMode" } },
+          { name: "list", type: { vec: "publicKey" } },
+          { name: "minReputation", type: "u64" },
+          { name: "requiredAttester", type: { option: "publicKey" } },
+        ],
+      },
+    },
+    {
+      name: "AccessListMode",
+      type: {
+        kind: "enum",
+        variants: [
+          { name: "Open" },
+          { name: "Allowlist" },
+          { name: "Denylist" },
+        ],
+      },
+    },
+    {
+      name: "TimeoutPolicy",
+      type: {
+        kind: "enum",
+        variants: [
+          { name: "RefundOnTimeout" },
+          {
+            name: "ReleaseAfterDelivery",
+            fields: [{ name: "challengeSeconds", type: "i64" }],
+          },
+        ],
+      },
+    },
+    {
+      name: "SlaTerms",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "maxAckSeconds", type: "i64" },
+          { name: "maxDeliverySeconds", type: "i64" },
+          { name: "penalties", type: { vec: { defined: "SlaPenalty" } } },
+        ],
+      },
+    },
+    {
+      name: "SlaPenalty",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "lateBySeconds", type: "i64" },
+          { name: "refundBps", type: "u16" },
+        ],
+      },
+    },
+    {
+      name: "ServiceTerms",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "title", type: "string" },
+          { name: "description", type: "string" },
+          { name: "priceLamports", type: "u64" },
+          {
+            name: "priceModel",
+            type: { defined: "PriceModel" },
+          },
+          {
+            name: "pricing",
+            type: { option: { defined: "PricingSchedule" } },
+          },
+          {
+            name: "sla",
+            type: { option: { defined: "SlaTerms" } },
+          },
+          {
+            name: "timeoutPolicy",
+            type: { defined: "TimeoutPolicy" },
+          },
+          { name: "tags", type: { vec: "string" } },
+        ],
+      },
+    },
+    {
+      name: "IndexKind",
+      type: {
+        kind: "enum",
+        variants: [{ name: "Tag" }, { name: "Capability" }],
+      },
+    },
+    {
+      name: "BundleComponent",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "service", type: "publicKey" },
+          { name: "provider", type: "publicKey" },
+          { name: "shareBps", type: "u16" },
+        ],
+      },
+    },
+    {
+      name: "Evidence",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "submitter", type: "publicKey" },
+          { name: "hash", type: { array: ["u8", 32] } },
+          { name: "uri", type: "string" },
+          { name: "submittedAt", type: "i64" },
+        ],
+      },
+    },
+    {
+      name: "DisputeStatus",
+      type: {
+        kind: "enum",
+        variants: [
+          { name: "Open" },
+          { name: "Ruled" },
+          { name: "Appealed" },
+          { name: "Resolved" },
+          { name: "Expired" },
+        ],
+      },
+    },
+    {
+      name: "ArbiterVote",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "arbiter", type: "publicKey" },
+          { name: "payerAmount", type: "u64" },
+        ],
+      },
+    },
+    {
+      name: "MilestoneSpec",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "amount", type: "u64" },
+          { name: "deadline", type: "i64" },
+        ],
+      },
+    },
+    {
+      name: "Milestone",
+      type: {
+        kind: "struct",
+        fields: [
+          { name: "amount", type: "u64" },
+          { name: "deadline", type: "i64" },
+          { name: "releasedAt", type: "i64" },
+        ],
+      },
+    },
+    {
+      name: "PaymentCondition",
+      type: {
+        kind: "enum",
+        variants: [
+          { name: "Deadline", fields: [{ name: "at", type: "i64" }] },
+          {
+            name: "OraclePrice",
+            fields: [
+              { name: "feedId", type: { array: ["u8", 32] } },
+              { name: "price", type: "i64" },
+              { name: "exponent", type: "i32" },
+              { name: "above", type: "bool" },
+              { name: "at", type: "i64" },
+              { name: "maxAgeSeconds", type: "u32" },
+              { name: "maxConfBps", type: "u16" },
+            ],
+          },
+          {
+            name: "HashPreimage",
+            fields: [{ name: "hash", type: { array: ["u8", 32] } }],
+          },
+          {
+            name: "ValidatorSignature",
+            fields: [{ name: "validator", type: "publicKey" }],
+          },
+          {
+            name: "AttestationPresent",
+            fields: [{ name: "issuer", type: "publicKey" }],
+          },
+          { name: "NotBefore", fields: [{ name: "at", type: "i64" }] },
+        ],
+      },
+    },
+  ],
+  events: [
+    {
+      name: "ProtocolInitialized",
+      fields: [
+        { name: "authority", type: "publicKey", index: false },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "AgentRegistered",
+      fields: [
+        { name: "agent", type: "publicKey", index: false },
+        { name: "authority", type: "publicKey", index: false },
+        { name: "name", type: "string", index: false },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "ReputationStaked",
+      fields: [
+        { name: "agent", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "newScore", type: "u64", index: false },
+        { name: "totalStaked", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "FeedbackSubmitted",
+      fields: [
+        { name: "from", type: "publicKey", index: false },
+        { name: "to", type: "publicKey", index: false },
+        { name: "rating", type: "u8", index: false },
+        { name: "newReputation", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "ServiceCreated",
+      fields: [
+        { name: "service", type: "publicKey", index: false },
+        { name: "provider", type: "publicKey", index: false },
+        { name: "title", type: "string", index: false },
+        { name: "price", type: "u64", index: false },
+        {
+          name: "priceModel",
+          type: { defined: "PriceModel" },
+          index: false,
+        },
+      ],
+    },
+    {
+      name: "ServiceUpdated",
+      fields: [
+        { name: "service", type: "publicKey", index: false },
+        { name: "version", type: "u64", index: false },
+        {
+          name: "oldTerms",
+          type: { defined: "ServiceTerms" },
+          index: false,
+        },
+        {
+          name: "newTerms",
+          type: { defined: "ServiceTerms" },
+          index: false,
+        },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "ServiceStatusChanged",
+      fields: [
+        { name: "service", type: "publicKey", index: false },
+        { name: "isActive", type: "bool", index: false },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "ServiceClosed",
+      fields: [
+        { name: "service", type: "publicKey", index: false },
+        { name: "provider", type: "publicKey", index: false },
+        { name: "totalOrders", type: "u64", index: false },
+        { name: "totalRevenue", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "PaymentCreated",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        { name: "payer", type: "publicKey", index: false },
+        { name: "receiver", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "intent", type: "string", index: false },
+      ],
+    },
+    {
+      name: "PaymentReleased",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        { name: "receiver", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "latencyMs", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "PaymentRefunded",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        { name: "payer", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "reason", type: "string", index: false },
+      ],
+    },
+    {
+      name: "StreamCreated",
+      fields: [
+        { name: "stream", type: "publicKey", index: false },
+        { name: "payer", type: "publicKey", index: false },
+        { name: "receiver", type: "publicKey", index: false },
+        { name: "ratePerSecond", type: "u64", index: false },
+        { name: "deposit", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "StreamWithdrawn",
+      fields: [
+        { name: "stream", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "totalWithdrawn", type: "u64", index: false },
+        { name: "isActive", type: "bool", index: false },
+      ],
+    },
+    {
+      name: "AuctionOpened",
+      fields: [
+        { name: "auction", type: "publicKey", index: false },
+        { name: "service", type: "publicKey", index: false },
+        { name: "reservePrice", type: "u64", index: false },
+        { name: "endAt", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "BidPlaced",
+      fields: [
+        { name: "auction", type: "publicKey", index: false },
+        { name: "bidder", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "previousBidder", type: "publicKey", index: false },
+        { name: "refunded", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "AuctionSettled",
+      fields: [
+        { name: "auction", type: "publicKey", index: false },
+        { name: "payment", type: "publicKey", index: false },
+        { name: "winner", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "AuctionCancelled",
+      fields: [
+        { name: "auction", type: "publicKey", index: false },
+        { name: "service", type: "publicKey", index: false },
+      ],
+    },
+    {
+      name: "UsageEscrowOpened",
+      fields: [
+        { name: "usageEscrow", type: "publicKey", index: false },
+        { name: "payer", type: "publicKey", index: false },
+        { name: "service", type: "publicKey", index: false },
+        { name: "pricePerUnit", type: "u64", index: false },
+        { name: "deposit", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "UsageEscrowFunded",
+      fields: [
+        { name: "usageEscrow", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "available", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "UsageReported",
+      fields: [
+        { name: "usageEscrow", type: "publicKey", index: false },
+        { name: "report", type: "publicKey", index: false },
+        { name: "units", type: "u64", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "coSigned", type: "bool", index: false },
+      ],
+    },
+    {
+      name: "UsageSettled",
+      fields: [
+        { name: "usageEscrow", type: "publicKey", index: false },
+        { name: "report", type: "publicKey", index: false },
+        { name: "units", type: "u64", index: false },
+        { name: "amount", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "UsageContested",
+      fields: [
+        { name: "usageEscrow", type: "publicKey", index: false },
+        { name: "report", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "UsageEscrowClosed",
+      fields: [
+        { name: "usageEscrow", type: "publicKey", index: false },
+        { name: "unitsBilled", type: "u64", index: false },
+        { name: "drawn", type: "u64", index: false },
+        { name: "refunded", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "JobPosted",
+      fields: [
+        { name: "job", type: "publicKey", index: false },
+        { name: "buyer", type: "publicKey", index: false },
+        { name: "budget", type: "u64", index: false },
+        { name: "deadline", type: "i64", index: false },
+        { name: "intent", type: "string", index: false },
+      ],
+    },
+    {
+      name: "JobBidSubmitted",
+      fields: [
+        { name: "job", type: "publicKey", index: false },
+        { name: "bid", type: "publicKey", index: false },
+        { name: "provider", type: "publicKey", index: false },
+        { name: "price", type: "u64", index: false },
+        { name: "deliverySeconds", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "JobBidWithdrawn",
+      fields: [
+        { name: "job", type: "publicKey", index: false },
+        { name: "bid", type: "publicKey", index: false },
+        { name: "provider", type: "publicKey", index: false },
+      ],
+    },
+    {
+      name: "JobAwarded",
+      fields: [
+        { name: "job", type: "publicKey", index: false },
+        { name: "bid", type: "publicKey", index: false },
+        { name: "payment", type: "publicKey", index: false },
+        { name: "provider", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "JobCancelled",
+      fields: [
+        { name: "job", type: "publicKey", index: false },
+        { name: "buyer", type: "publicKey", index: false },
+      ],
+    },
+    {
+      name: "JobClosed",
+      fields: [
+        { name: "job", type: "publicKey", index: false },
+        { name: "buyer", type: "publicKey", index: false },
+      ],
+    },
+    {
+      name: "Subscribed",
+      fields: [
+        { name: "subscription", type: "publicKey", index: false },
+        { name: "subscriber", type: "publicKey", index: false },
+        { name: "service", type: "publicKey", index: false },
+        { name: "amountPerPeriod", type: "u64", index: false },
+        { name: "periodSeconds", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "SubscriptionFunded",
+      fields: [
+        { name: "subscription", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "balance", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "SubscriptionCharged",
+      fields: [
+        { name: "subscription", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "period", type: "u64", index: false },
+        { name: "paidUntil", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "SubscriptionLapsed",
+      fields: [
+        { name: "subscription", type: "publicKey", index: false },
+        { name: "subscriber", type: "publicKey", index: false },
+        { name: "balance", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "SubscriptionCancelled",
+      fields: [
+        { name: "subscription", type: "publicKey", index: false },
+        { name: "subscriber", type: "publicKey", index: false },
+        { name: "periodsCharged", type: "u64", index: false },
+        { name: "refunded", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "PricingScheduleUpdated",
+      fields: [
+        { name: "service", type: "publicKey", index: false },
+        {
+          name: "pricing",
+          type: { option: { defined: "PricingSchedule" } },
+          index: false,
+        },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "CouponCreated",
+      fields: [
+        { name: "coupon", type: "publicKey", index: false },
+        { name: "service", type: "publicKey", index: false },
+        { name: "code", type: "string", index: false },
+        { name: "discount", type: { defined: "Discount" }, index: false },
+        { name: "maxRedemptions", type: "u64", index: false },
+        { name: "expiresAt", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "CouponRedeemed",
+      fields: [
+        { name: "coupon", type: "publicKey", index: false },
+        { name: "service", type: "publicKey", index: false },
+        { name: "payment", type: "publicKey", index: false },
+        { name: "buyer", type: "publicKey", index: false },
+        { name: "discount", type: "u64", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "redemptions", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "CouponClosed",
+      fields: [
+        { name: "coupon", type: "publicKey", index: false },
+        { name: "service", type: "publicKey", index: false },
+        { name: "redemptions", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "ReferralFeeUpdated",
+      fields: [
+        { name: "service", type: "publicKey", index: false },
+        { name: "referralBps", type: "u16", index: false },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "ReferralPaid",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        { name: "referrer", type: "publicKey", index: false },
+        { name: "receiver", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "AttestationIssued",
+      fields: [
+        { name: "attestation", type: "publicKey", index: false },
+        { name: "issuer", type: "publicKey", index: false },
+        { name: "subject", type: "publicKey", index: false },
+        { name: "expiresAt", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "AttestationRevoked",
+      fields: [
+        { name: "attestation", type: "publicKey", index: false },
+        { name: "issuer", type: "publicKey", index: false },
+        { name: "subject", type: "publicKey", index: false },
+      ],
+    },
+    {
+      name: "AccessRulesUpdated",
+      fields: [
+        { name: "service", type: "publicKey", index: false },
+        {
+          name: "access",
+          type: { option: { defined: "AccessRules" } },
+          index: false,
+        },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "ServiceCapacityUpdated",
+      fields: [
+        { name: "service", type: "publicKey", index: false },
+        { name: "maxConcurrentOrders", type: "u64", index: false },
+        { name: "openOrders", type: "u64", index: false },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "TimeoutPolicyUpdated",
+      fields: [
+        { name: "service", type: "publicKey", index: false },
+        {
+          name: "timeoutPolicy",
+          type: { defined: "TimeoutPolicy" },
+          index: false,
+        },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "ServiceMigrated",
+      fields: [
+        { name: "service", type: "publicKey", index: false },
+        { name: "space", type: "u64", index: false },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "SlaUpdated",
+      fields: [
+        { name: "service", type: "publicKey", index: false },
+        {
+          name: "sla",
+          type: { option: { defined: "SlaTerms" } },
+          index: false,
+        },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "PaymentAcknowledged",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        { name: "receiver", type: "publicKey", index: false },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "PaymentDelivered",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        { name: "receiver", type: "publicKey", index: false },
+        { name: "timestamp", type: "i64", index: false },
+        { name: "autoReleaseAt", type: { option: "i64" }, index: false },
+      ],
+    },
+    {
+      name: "SlaPenaltyApplied",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        { name: "lateSeconds", type: "i64", index: false },
+        { name: "refunded", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "AgentCapabilitiesUpdated",
+      fields: [
+        { name: "agent", type: "publicKey", index: false },
+        { name: "capabilities", type: { vec: "string" }, index: false },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "AgentMigrated",
+      fields: [
+        { name: "agent", type: "publicKey", index: false },
+        { name: "space", type: "u64", index: false },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "CategoryCreated",
+      fields: [
+        { name: "category", type: "publicKey", index: false },
+        { name: "id", type: "u16", index: false },
+        { name: "name", type: "string", index: false },
+      ],
+    },
+    {
+      name: "CategoryUpdated",
+      fields: [
+        { name: "category", type: "publicKey", index: false },
+        { name: "id", type: "u16", index: false },
+        { name: "name", type: "string", index: false },
+        { name: "isActive", type: "bool", index: false },
+      ],
+    },
+    {
+      name: "ServiceCategoriesUpdated",
+      fields: [
+        { name: "service", type: "publicKey", index: false },
+        { name: "categories", type: { vec: "u16" }, index: false },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "BundleCreated",
+      fields: [
+        { name: "bundle", type: "publicKey", index: false },
+        { name: "orchestrator", type: "publicKey", index: false },
+        { name: "price", type: "u64", index: false },
+        {
+          name: "components",
+          type: { vec: { defined: "BundleComponent" } },
+          index: false,
+        },
+      ],
+    },
+    {
+      name: "BundlePaymentReleased",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        { name: "bundle", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "componentsShare", type: "u64", index: false },
+        { name: "orchestratorShare", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "BundleRatingUpdated",
+      fields: [
+        { name: "bundle", type: "publicKey", index: false },
+        { name: "avgRating", type: "u8", index: false },
+        { name: "componentReputation", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "BundleStatusChanged",
+      fields: [
+        { name: "bundle", type: "publicKey", index: false },
+        { name: "isActive", type: "bool", index: false },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "BundleClosed",
+      fields: [
+        { name: "bundle", type: "publicKey", index: false },
+        { name: "totalOrders", type: "u64", index: false },
+        { name: "totalRevenue", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "DisputeOpened",
+      fields: [
+        { name: "dispute", type: "publicKey", index: false },
+        { name: "payment", type: "publicKey", index: false },
+        { name: "openedBy", type: "publicKey", index: false },
+        { name: "arbiters", type: { vec: "publicKey" }, index: false },
+        { name: "reason", type: "string", index: false },
+      ],
+    },
+    {
+      name: "EvidenceSubmitted",
+      fields: [
+        { name: "dispute", type: "publicKey", index: false },
+        { name: "submitter", type: "publicKey", index: false },
+        { name: "hash", type: { array: ["u8", 32] }, index: false },
+        { name: "uri", type: "string", index: false },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "DisputePanelDrawn",
+      fields: [
+        { name: "dispute", type: "publicKey", index: false },
+        { name: "arbiters", type: { vec: "publicKey" }, index: false },
+        { name: "voteDeadline", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "DisputePanelRecommitted",
+      fields: [
+        { name: "dispute", type: "publicKey", index: false },
+        { name: "drawSlot", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "DisputeExpired",
+      fields: [
+        { name: "dispute", type: "publicKey", index: false },
+        { name: "payment", type: "publicKey", index: false },
+        { name: "refunded", type: "u64", index: false },
+        { name: "slashedArbiters", type: "u8", index: false },
+      ],
+    },
+    {
+      name: "ArbiterReleased",
+      fields: [
+        { name: "arbiter", type: "publicKey", index: false },
+        { name: "dispute", type: "publicKey", index: false },
+        { name: "openCases", type: "u32", index: false },
+      ],
+    },
+    {
+      name: "DisputeResolved",
+      fields: [
+        { name: "dispute", type: "publicKey", index: false },
+        { name: "payment", type: "publicKey", index: false },
+        { name: "payerAmount", type: "u64", index: false },
+        { name: "receiverAmount", type: "u64", index: false },
+        { name: "appealed", type: "bool", index: false },
+      ],
+    },
+    {
+      name: "ArbiterVoted",
+      fields: [
+        { name: "dispute", type: "publicKey", index: false },
+        { name: "arbiter", type: "publicKey", index: false },
+        { name: "payerAmount", type: "u64", index: false },
+        { name: "fee", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "DisputeRuled",
+      fields: [
+        { name: "dispute", type: "publicKey", index: false },
+        { name: "payerAmount", type: "u64", index: false },
+        { name: "receiverAmount", type: "u64", index: false },
+        { name: "appealDeadline", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "DisputeAppealed",
+      fields: [
+        { name: "dispute", type: "publicKey", index: false },
+        { name: "appellant", type: "publicKey", index: false },
+      ],
+    },
+    {
+      name: "DisputeAppealResolved",
+      fields: [
+        { name: "dispute", type: "publicKey", index: false },
+        { name: "panelPayerAmount", type: "u64", index: false },
+        { name: "payerAmount", type: "u64", index: false },
+        { name: "overturned", type: "bool", index: false },
+      ],
+    },
+    {
+      name: "ArbiterPoolCreated",
+      fields: [
+        { name: "arbiterPool", type: "publicKey", index: false },
+        { name: "poolId", type: "u16", index: false },
+        { name: "minStake", type: "u64", index: false },
+        { name: "feeBps", type: "u16", index: false },
+        { name: "panelSize", type: "u8", index: false },
+        { name: "voteWindowSeconds", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "ArbiterRegistered",
+      fields: [
+        { name: "arbiter", type: "publicKey", index: false },
+        { name: "arbiterPool", type: "publicKey", index: false },
+        { name: "authority", type: "publicKey", index: false },
+        { name: "stake", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "ArbiterStakeAdded",
+      fields: [
+        { name: "arbiter", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "stake", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "ArbiterSlashed",
+      fields: [
+        { name: "arbiter", type: "publicKey", index: false },
+        { name: "dispute", type: "publicKey", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "remainingStake", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "ArbiterWithdrawn",
+      fields: [
+        { name: "arbiter", type: "publicKey", index: false },
+        { name: "arbiterPool", type: "publicKey", index: false },
+        { name: "stake", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "ServiceArbiterPoolSet",
+      fields: [
+        { name: "service", type: "publicKey", index: false },
+        { name: "arbiterPool", type: { option: "publicKey" }, index: false },
+      ],
+    },
+    {
+      name: "MilestonesSet",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        {
+          name: "milestones",
+          type: { vec: { defined: "MilestoneSpec" } },
+          index: false,
+        },
+      ],
+    },
+    {
+      name: "MilestoneReleased",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        { name: "index", type: "u8", index: false },
+        { name: "amount", type: "u64", index: false },
+        { name: "remaining", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "PaymentSettled",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        { name: "payerAmount", type: "u64", index: false },
+        { name: "receiverAmount", type: "u64", index: false },
+        { name: "referralShare", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "PreimageRevealed",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        { name: "hash", type: { array: ["u8", 32] }, index: false },
+        { name: "preimage", type: "bytes", index: false },
+      ],
+    },
+    {
+      name: "SessionKeyCreated",
+      fields: [
+        { name: "sessionKey", type: "publicKey", index: false },
+        { name: "agent", type: "publicKey", index: false },
+        { name: "key", type: "publicKey", index: false },
+        { name: "expiresAt", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "SessionKeyRevoked",
+      fields: [
+        { name: "sessionKey", type: "publicKey", index: false },
+        { name: "agent", type: "publicKey", index: false },
+        { name: "key", type: "publicKey", index: false },
+      ],
+    },
+    {
+      name: "ReceiptRedeemed",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        { name: "signer", type: "publicKey", index: false },
+      ],
+    },
+    {
+      name: "PaymentClosed",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        { name: "payer", type: "publicKey", index: false },
+        { name: "service", type: "publicKey", index: false },
+      ],
+    },
+    {
+      name: "PaymentMigrated",
+      fields: [
+        { name: "payment", type: "publicKey", index: false },
+        { name: "space", type: "u64", index: false },
+        { name: "timestamp", type: "i64", index: false },
+      ],
+    },
+    {
+      name: "AuctionClosed",
+      fields: [
+        { name: "auction", type: "publicKey", index: false },
+        { name: "service", type: "publicKey", index: false },
+        { name: "payment", type: "publicKey", index: false },
+      ],
+    },
+    {
+      name: "UsageReportResolved",
+      fields: [
+        { name: "usageEscrow", type: "publicKey", index: false },
+        { name: "report", type: "publicKey", index: false },
+        { name: "units", type: "u64", index: false },
+        { name: "amount", type: "u64", index: false },
+      ],
+    },
+    {
+      name: "UsageReportClosed",
+      fields: [
+        { name: "usageEscrow", type: "publicKey", index: false },
+        { name: "report", type: "publicKey", index: false },
+      ],
+    },
+  ],
+  errors: [
+    { code: 6000, name: "NameTooLong", msg: "Name exceeds 32 characters" },
+    {
+      code: 6001,
+      name: "DescriptionTooLong",
+      msg: "Description exceeds 256 characters",
+    },
+    {
+      code: 6002,
+      name: "TooManyCapabilities",
+      msg: "Too many capabilities (max 10)",
+    },
+    {
+      code: 6003,
+      name: "ZeroAmount",
+      msg: "Amount must be greater than zero",
+    },
+    {
+      code: 6004,
+      name: "InvalidRating",
+      msg: "Rating must be between 1 and 5",
+    },
+    {
+      code: 6005,
+      name: "CommentTooLong",
+      msg: "Comment exceeds 256 characters",
+    },
+    { code: 6006, name: "TitleTooLong", msg: "Title exceeds 64 characters" },
+    { code: 6007, name: "TooManyTags", msg: "Too many tags (max 5)" },
+    {
+      code: 6008,
+      name: "IntentTooLong",
+      msg: "Intent exceeds 256 characters",
+    },
+    {
+      code: 6009,
+      name: "PaymentNotEscrowed",
+      msg: "Payment is not in escrowed state",
+    },
+    { code: 6010, name: "RefundNotAllowed", msg: "Refund not allowed" },
+    {
+      code: 6011,
+      name: "InsufficientDeposit",
+      msg: "Insufficient deposit for stream",
+    },
+    { code: 6012, name: "StreamNotActive", msg: "Stream is not active" },
+    { code: 6013, name: "NothingToWithdraw", msg: "Nothing to withdraw" },
+    { code: 6014, name: "Unauthorized", msg: "Unauthorized" },
+    { code: 6015, name: "ServiceInactive", msg: "Service is not active" },
+    {
+      code: 6016,
+      name: "ServiceAlreadyActive",
+      msg: "Service is already active",
+    },
+    {
+      code: 6017,
+      name: "ServiceHasOpenOrders",
+      msg: "Service still has open orders",
+    },
+    {
+      code: 6018,
+      name: "ServiceMismatch",
+      msg: "Payment does not belong to this service",
+    },
+    {
+      code: 6019,
+      name: "ProviderMismatch",
+      msg: "Receiver agent is not the service provider",
+    },
+    {
+      code: 6020,
+      name: "AmountMismatch",
+      msg: "Payment amount does not match the service price",
+    },
+    {
+      code: 6021,
+      name: "InvalidQuantity",
+      msg: "Invalid quantity for this price model",
+    },
+    {
+      code: 6022,
+      name: "UnsupportedPriceModel",
+      msg: "Price model cannot be paid through pay_for_service",
+    },
+    { code: 6023, name: "MathOverflow", msg: "Arithmetic overflow" },
+    {
+      code: 6024,
+      name: "PriceModelMismatch",
+      msg: "Service price model does not support this operation",
+    },
+    {
+      code: 6025,
+      name: "InvalidDuration",
+      msg: "Duration must be greater than zero",
+    },
+    { code: 6026, name: "AuctionNotOpen", msg: "Auction is not open" },
+    { code: 6027, name: "AuctionEnded", msg: "Auction has ended" },
+    { code: 6028, name: "AuctionNotEnded", msg: "Auction has not ended yet" },
+    {
+      code: 6029,
+      name: "BidTooLow",
+      msg: "Bid must meet the reserve and exceed the highest bid",
+    },
+    {
+      code: 6030,
+      name: "PreviousBidderMissing",
+      msg: "Outbid bidder's wallet must be provided",
+    },
+    { code: 6031, name: "NoBids", msg: "Auction has no bids" },
+    { code: 6032, name: "AuctionHasBids", msg: "Auction already has bids" },
+    {
+      code: 6033,
+      name: "UsageCapExceeded",
+      msg: "Usage report exceeds the payer's per-report cap",
+    },
+    {
+      code: 6034,
+      name: "InsufficientEscrow",
+      msg: "Insufficient funds in usage escrow",
+    },
+    {
+      code: 6035,
+      name: "UsageReportNotPending",
+      msg: "Usage report is not pending",
+    },
+    {
+      code: 6036,
+      name: "ChallengeWindowClosed",
+      msg: "Challenge window has closed",
+    },
+    {
+      code: 6037,
+      name: "ChallengeWindowOpen",
+      msg: "Challenge window is still open",
+    },
+    {
+      code: 6038,
+      name: "UsageReportsPending",
+      msg: "Usage escrow has pending reports",
+    },
+    { code: 6039, name: "JobNotOpen", msg: "Job is not open" },
+    {
+      code: 6040,
+      name: "JobDeadlinePassed",
+      msg: "Job bidding deadline has passed",
+    },
+    {
+      code: 6041,
+      name: "BidExceedsBudget",
+      msg: "Bid price exceeds the job budget",
+    },
+    {
+      code: 6042,
+      name: "MissingCapability",
+      msg: "Provider lacks a capability required by the job",
+    },
+    {
+      code: 6043,
+      name: "SubscriptionInactive",
+      msg: "Subscription is not active",
+    },
+    {
+      code: 6044,
+      name: "SubscriptionNotDue",
+      msg: "Subscription period is not due yet",
+    },
+    {
+      code: 6045,
+      name: "InvalidPricingSchedule",
+      msg: "Pricing tiers must ascend and discounts cannot exceed 100%",
+    },
+    {
+      code: 6046,
+      name: "CouponCodeTooLong",
+      msg: "Coupon code exceeds 32 characters",
+    },
     {
-      name: "registerAgent",
-      accounts: [
-        { name: "agent", isMut: true, isSigner: false },
-        { name: "authority", isMut: true, isSigner: true },
-        { name: "systemProgram", isMut: false, isSigner: false },
-      ],
-      args: [
-        { name: "name", type: "string" },
-        { name: "description", type: "string" },
-        { name: "capabilities", type: { vec: "string" } },
-        { name: "endpoint", type: "string" },
-      ],
+      code: 6047,
+      name: "TooManyAllowedBuyers",
+      msg: "Too many allowed buyers (max 10)",
     },
     {
-      name: "stakeReputation",
-      accounts: [
-        { name: "agent", isMut: true, isSigner: false },
-        { name: "vault", isMut: true, isSigner: false },
-        { name: "authority", isMut: true, isSigner: true },
-        { name: "systemProgram", isMut: false, isSigner: false },
-      ],
-      args: [{ name: "amount", type: "u64" }],
+      code: 6048,
+      name: "InvalidDiscount",
+      msg: "Discount must be non-zero and at most 100%",
     },
+    { code: 6049, name: "CouponExpired", msg: "Coupon has expired" },
     {
-      name: "submitFeedback",
-      accounts: [
-        { name: "feedback", isMut: true, isSigner: false },
-        { name: "fromAgent", isMut: false, isSigner: false },
-        { name: "toAgent", isMut: true, isSigner: false },
-        { name: "authority", isMut: true, isSigner: true },
-        { name: "systemProgram", isMut: false, isSigner: false },
-      ],
-      args: [
-        { name: "rating", type: "u8" },
-        { name: "comment", type: "string" },
-      ],
+      code: 6050,
+      name: "CouponExhausted",
+      msg: "Coupon has no redemptions left",
     },
     {
-      name: "createService",
-      accounts: [
-        { name: "service", isMut: true, isSigner: false },
-        { name: "agent", isMut: false, isSigner: false },
-        { name: "authority", isMut: true, isSigner: true },
-        { name: "systemProgram", isMut: false, isSigner: false },
-      ],
-      args: [
-        { name: "serviceId", type: "string" },
-        { name: "title", type: "string" },
-        { name: "description", type: "string" },
-        { name: "priceLamports", type: "u64" },
-        {
-          name: "priceModel",
-          type: {
-            defined: "PriceModel",
-          },
-        },
-        { name: "tags", type: { vec: "string" } },
-      ],
+      code: 6051,
+      name: "CouponNotAllowed",
+      msg: "Buyer is not allowed to redeem this coupon",
     },
     {
-      name: "payForService",
-      accounts: [
-        { name: "payment", isMut: true, isSigner: false },
-        { name: "escrow", isMut: true, isSigner: false },
-        { name: "payerAgent", isMut: true, isSigner: false },
-        { name: "receiverAgent", isMut: false, isSigner: false },
-        { name: "service", isMut: true, isSigner: false },
-        { name: "payerAuthority", isMut: true, isSigner: true },
-        { name: "systemProgram", isMut: false, isSigner: false },
-      ],
-      args: [
-        { name: "amount", type: "u64" },
-        { name: "intent", type: "string" },
-        { name: "conditions", type: { vec: "string" } },
-        { name: "timeoutSeconds", type: "i64" },
-      ],
+      code: 6052,
+      name: "InvalidReferralFee",
+      msg: "Referral fee cannot exceed 100%",
     },
     {
-      name: "releasePayment",
-      accounts: [
-        { name: "payment", isMut: true, isSigner: false },
-        { name: "escrow", isMut: true, isSigner: false },
-        { name: "payerAgent", isMut: false, isSigner: false },
-        { name: "receiverAgent", isMut: true, isSigner: false },
-        { name: "service", isMut: true, isSigner: false },
-        { name: "receiverAuthority", isMut: true, isSigner: false },
-        { name: "authority", isMut: false, isSigner: true },
-      ],
-      args: [],
+      code: 6053,
+      name: "InvalidReferrer",
+      msg: "Referrer cannot be the payer or the provider",
     },
     {
-      name: "refundPayment",
-      accounts: [
-        { name: "payment", isMut: true, isSigner: false },
-        { name: "escrow", isMut: true, isSigner: false },
-        { name: "payerAgent", isMut: false, isSigner: false },
-        { name: "payerAuthority", isMut: true, isSigner: false },
-        { name: "authority", isMut: false, isSigner: true },
-      ],
-      args: [],
+      code: 6054,
+      name: "ReferrerMissing",
+      msg: "Referred payment requires the referrer agent and wallet",
     },
     {
-      name: "createStream",
-      accounts: [
-        { name: "stream", isMut: true, isSigner: false },
-        { name: "streamVault", isMut: true, isSigner: false },
-        { name: "payerAgent", isMut: false, isSigner: false },
-        { name: "receiverAgent", isMut: false, isSigner: false },
-        { name: "payerAuthority", isMut: true, isSigner: true },
-        { name: "systemProgram", isMut: false, isSigner: false },
-      ],
-      args: [
-        { name: "ratePerSecond", type: "u64" },
-        { name: "maxDurationSeconds", type: "u64" },
-        { name: "depositAmount", type: "u64" },
-      ],
+      code: 6055,
+      name: "TooManyAccessEntries",
+      msg: "Too many access list entries (max 10)",
     },
     {
-      name: "withdrawStream",
-      accounts: [
-        { name: "stream", isMut: true, isSigner: false },
-        { name: "streamVault", isMut: true, isSigner: false },
-        { name: "receiverAgent", isMut: true, isSigner: false },
-        { name: "receiverAuthority", isMut: true, isSigner: false },
-        { name: "payerAuthority", isMut: true, isSigner: false },
-        { name: "authority", isMut: false, isSigner: true },
-      ],
-      args: [],
+      code: 6056,
+      name: "BuyerNotAllowlisted",
+      msg: "Buyer is not on the service allowlist",
     },
-  ],
-  accounts: [
     {
-      name: "Protocol",
-      type: {
-        kind: "struct",
-        fields: [
-          { name: "authority", type: "publicKey" },
-          { name: "totalAgents", type: "u64" },
-          { name: "totalServices", type: "u64" },
-          { name: "totalPayments", type: "u64" },
-          { name: "totalVolume", type: "u64" },
-          { name: "totalStaked", type: "u64" },
-          { name: "feeBps", type: "u16" },
-          { name: "treasury", type: "publicKey" },
-          { name: "bump", type: "u8" },
-        ],
-      },
+      code: 6057,
+      name: "BuyerDenylisted",
+      msg: "Buyer is on the service denylist",
     },
     {
-      name: "Agent",
-      type: {
-        kind: "struct",
-        fields: [
-          { name: "authority", type: "publicKey" },
-          { name: "name", type: "string" },
-          { name: "description", type: "string" },
-          { name: "capabilities", type: { vec: "string" } },
-          { name: "endpoint", type: "string" },
-          { name: "reputationScore", type: "u64" },
-          { name: "totalStaked", type: "u64" },
-          { name: "totalEarned", type: "u64" },
-          { name: "totalSpent", type: "u64" },
-          { name: "servicesCompleted", type: "u64" },
-          { name: "servicesRequested", type: "u64" },
-          { name: "feedbacksReceived", type: "u64" },
-          { name: "registeredAt", type: "i64" },
-          { name: "isActive", type: "bool" },
-          { name: "bump", type: "u8" },
-        ],
-      },
+      code: 6058,
+      name: "ReputationTooLow",
+      msg: "Buyer reputation is below the service minimum",
+    },
+    {
+      code: 6059,
+      name: "AttestationRequired",
+      msg: "Service requires a valid attestation from its attester",
+    },
+    {
+      code: 6060,
+      name: "AttestationInvalid",
+      msg: "Attestation does not belong to the buyer",
+    },
+    {
+      code: 6061,
+      name: "ServiceAtCapacity",
+      msg: "Service has reached its maximum concurrent orders",
+    },
+    {
+      code: 6062,
+      name: "InvalidSla",
+      msg: "SLA needs a delivery deadline and ascending penalty steps of at most 100%",
+    },
+    {
+      code: 6063,
+      name: "AlreadyAcknowledged",
+      msg: "Payment has already been acknowledged",
+    },
+    {
+      code: 6064,
+      name: "AlreadyDelivered",
+      msg: "Payment has already been marked delivered",
+    },
+    {
+      code: 6065,
+      name: "InvalidIndexKey",
+      msg: "Tags and capabilities must be at most 32 characters",
+    },
+    {
+      code: 6066,
+      name: "IndexAccountMismatch",
+      msg: "Discovery index accounts missing or out of order",
+    },
+    {
+      code: 6067,
+      name: "TooManyCategories",
+      msg: "Too many categories (max 3)",
+    },
+    {
+      code: 6068,
+      name: "InvalidCategory",
+      msg: "Category accounts missing, duplicated or out of order",
+    },
+    { code: 6069, name: "CategoryInactive", msg: "Category has been retired" },
+    {
+      code: 6070,
+      name: "InvalidBundle",
+      msg: "Bundle needs 1-5 distinct third-party components with shares totalling at most 100%",
+    },
+    {
+      code: 6071,
+      name: "BundleComponentMismatch",
+      msg: "Bundle component accounts missing or out of order",
+    },
+    {
+      code: 6072,
+      name: "NotPaymentParty",
+      msg: "Only the payment's payer or receiver can do this",
+    },
+    { code: 6073, name: "DisputeNotOpen", msg: "Dispute is not open" },
+    {
+      code: 6074,
+      name: "EvidenceUriTooLong",
+      msg: "Evidence URI exceeds 128 characters",
+    },
+    {
+      code: 6075,
+      name: "EvidenceLimitReached",
+      msg: "This party already submitted the maximum evidence entries (8)",
+    },
+    {
+      code: 6076,
+      name: "InvalidDisputeSplit",
+      msg: "Dispute split exceeds the escrowed amount",
+    },
+    {
+      code: 6077,
+      name: "InvalidArbiterPool",
+      msg: "Arbiter panel must be odd and 1-5, fee below 100%, slash at most 100% and vote window positive",
+    },
+    {
+      code: 6078,
+      name: "ArbiterPoolFull",
+      msg: "Arbiter pool is full (max 16)",
+    },
+    {
+      code: 6079,
+      name: "NotEnoughArbiters",
+      msg: "Arbiter pool has fewer arbiters than its panel size",
+    },
+    {
+      code: 6080,
+      name: "InsufficientArbiterStake",
+      msg: "Stake is below the pool minimum",
+    },
+    {
+      code: 6081,
+      name: "ArbiterPoolMismatch",
+      msg: "Arbiter pool does not match the service",
+    },
+    {
+      code: 6082,
+      name: "NotAssignedArbiter",
+      msg: "Signer is not on this dispute's panel",
+    },
+    {
+      code: 6083,
+      name: "AlreadyVoted",
+      msg: "Arbiter has already voted on this dispute",
+    },
+    {
+      code: 6084,
+      name: "AppealWindowOpen",
+      msg: "Appeal window has not passed yet",
+    },
+    { code: 6085, name: "AppealWindowClosed", msg: "Appeal window has closed" },
+    {
+      code: 6086,
+      name: "ArbiterStakeLocked",
+      msg: "Arbiter stake is locked by an open case, recent ruling or pending appeal",
+    },
+    {
+      code: 6087,
+      name: "InvalidMilestones",
+      msg: "Milestones must be 1-5 non-zero amounts summing to the payment, with future non-decreasing deadlines by the timeout, set once before any release",
+    },
+    {
+      code: 6088,
+      name: "MilestoneReleased",
+      msg: "Milestone has already been released",
+    },
+    {
+      code: 6089,
+      name: "InvalidSettlement",
+      msg: "Settlement refund exceeds the unreleased escrow",
+    },
+    {
+      code: 6090,
+      name: "TooManyConditions",
+      msg: "At most 5 payment conditions",
+    },
+    { code: 6091, name: "NoConditions", msg: "Payment has no conditions" },
+    {
+      code: 6092,
+      name: "ConditionsPending",
+      msg: "Payment conditions are not decided yet",
+    },
+    {
+      code: 6093,
+      name: "ConditionWitnessMissing",
+      msg: "Missing witness account for a payment condition",
+    },
+    {
+      code: 6094,
+      name: "ConditionWitnessMismatch",
+      msg: "Witness account does not match the payment condition",
+    },
+    {
+      code: 6095,
+      name: "InvalidOracle",
+      msg: "Oracle account is not a matching, fully verified Pyth price update",
+    },
+    { code: 6096, name: "StaleOracle", msg: "Oracle price update is too old" },
+    {
+      code: 6097,
+      name: "OracleConfidenceTooWide",
+      msg: "Oracle price confidence interval is too wide",
+    },
+    {
+      code: 6098,
+      name: "PreimageMismatch",
+      msg: "Preimages must match every hashlock on the payment, in order",
+    },
+    {
+      code: 6099,
+      name: "InvalidSessionKey",
+      msg: "Session key is expired or belongs to another agent",
+    },
+    {
+      code: 6100,
+      name: "InvalidReceipt",
+      msg: "Missing or invalid Ed25519-signed delivery receipt",
+    },
+    {
+      code: 6101,
+      name: "InvalidTimeoutPolicy",
+      msg: "Release-after-delivery challenge window must be positive",
+    },
+    {
+      code: 6102,
+      name: "NoAutoRelease",
+      msg: "Payment does not release on timeout, or has not been delivered",
+    },
+    {
+      code: 6103,
+      name: "DeliveredReleasePending",
+      msg: "Delivered order releases to the provider unless disputed",
+    },
+    {
+      code: 6104,
+      name: "ServiceHasPayments",
+      msg: "Close the listing's payment accounts first",
+    },
+    {
+      code: 6105,
+      name: "PaymentNotFinished",
+      msg: "Payment has not been released, refunded or settled yet",
+    },
+    {
+      code: 6106,
+      name: "AuctionNotSettled",
+      msg: "Auction has not been settled",
+    },
+    {
+      code: 6107,
+      name: "UsageReportNotContested",
+      msg: "Usage report is not contested",
+    },
+    {
+      code: 6108,
+      name: "UsageReportsOpen",
+      msg: "Usage escrow still has unclosed reports",
+    },
+    {
+      code: 6109,
+      name: "UsageReportNotSettled",
+      msg: "Usage report has not been settled",
+    },
+    { code: 6110, name: "JobStillOpen", msg: "Job is still open" },
+    { code: 6111, name: "JobHasBids", msg: "Job still has bids" },
+    {
+      code: 6112,
+      name: "ComponentProviderNotSigner",
+      msg: "Every component provider must sign the bundle",
+    },
+    {
+      code: 6113,
+      name: "PanelAlreadyDrawn",
+      msg: "Dispute panel has already been drawn",
+    },
+    {
+      code: 6114,
+      name: "PanelDrawPending",
+      msg: "Panel draw slot has not passed yet",
+    },
+    {
+      code: 6115,
+      name: "VoteWindowClosed",
+      msg: "Panel vote deadline has passed",
+    },
+    {
+      code: 6116,
+      name: "VoteWindowOpen",
+      msg: "Panel vote deadline has not passed yet",
+    },
+    {
+      code: 6117,
+      name: "DisputeNotFinished",
+      msg: "Dispute has not been resolved yet",
+    },
+    {
+      code: 6118,
+      name: "ArbiterAlreadyReleased",
+      msg: "Arbiter has already been released from this dispute",
     },
     {
-      name: "Service",
-      type: {
-        kind: "struct",
-        fields: [
-          { name: "provider", type: "publicKey" },
-          { name: "authority", type: "publicKey" },
-          { name: "serviceId", type: "string" },
-          { name: "title", type: "string" },
-          { name: "description", type: "string" },
-          { name: "priceLamports", type: "u64" },
-          {
-            name: "priceModel",
-            type: { defined: "PriceModel" },
-          },
-          { name: "tags", type: { vec: "string" } },
-          { name: "totalOrders", type: "u64" },
-          { name: "totalRevenue", type: "u64" },
-          { name: "avgRating", type: "u8" },
-          { name: "isActive", type: "bool" },
-          { name: "createdAt", type: "i64" },
-          { name: "bump", type: "u8" },
-        ],
-      },
+      code: 6119,
+      name: "MilestonesWithSla",
+      msg: "Orders with an SLA are held to it and can't be split into milestones",
     },
     {
-      name: "Payment",
-      type: {
-        kind: "struct",
-        fields: [
-          { name: "payer", type: "publicKey" },
-          { name: "receiver", type: "publicKey" },
-          { name: "service", type: "publicKey" },
-          { name: "amount", type: "u64" },
-          { name: "intent", type: "string" },
-          { name: "conditions", type: { vec: "string" } },
-          {
-            name: "status",
-            type: { defined: "PaymentStatus" },
-          },
-          { name: "createdAt", type: "i64" },
-          { name: "timeoutAt", type: "i64" },
-          { name: "completedAt", type: "i64" },
-          { name: "bump", type: "u8" },
-          { name: "escrowBump", type: "u8" },
-        ],
-      },
+      code: 6120,
+      name: "DeadlineOnlyConditions",
+      msg: "Conditions need at least one condition besides a deadline",
     },
     {
-      name: "Feedback",
-      type: {
-        kind: "struct",
-        fields: [
-          { name: "fromAgent", type: "publicKey" },
-          { name: "toAgent", type: "publicKey" },
-          { name: "rating", type: "u8" },
-          { name: "comment", type: "string" },
-          { name: "timestamp", type: "i64" },
-          { name: "bump", type: "u8" },
-        ],
-      },
+      code: 6121,
+      name: "InvalidOracleCondition",
+      msg: "Oracle conditions need a max price age of 1-60 seconds",
     },
     {
-      name: "Stream",
-      type: {
-        kind: "struct",
-        fields: [
-          { name: "payer", type: "publicKey" },
-          { name: "receiver", type: "publicKey" },
-          { name: "ratePerSecond", type: "u64" },
-          { name: "deposited", type: "u64" },
-          { name: "withdrawn", type: "u64" },
-          { name: "startedAt", type: "i64" },
-          { name: "maxEndAt", type: "i64" },
-          { name: "lastWithdrawnAt", type: "i64" },
-          { name: "isActive", type: "bool" },
-          { name: "bump", type: "u8" },
-          { name: "vaultBump", type: "u8" },
-        ],
-      },
+      code: 6122,
+      name: "DeliveryAfterTimeout",
+      msg: "Payment timed out before it was marked delivered",
     },
-  ],
-  types: [
     {
-      name: "PriceModel",
-      type: {
-        kind: "enum",
-        variants: [
-          { name: "Fixed" },
-          { name: "PerRequest" },
-          { name: "PerSecond" },
-          { name: "PerToken" },
-          { name: "Auction" },
-        ],
-      },
+      code: 6123,
+      name: "MilestonesWithAutoRelease",
+      msg: "Orders that release to the provider after delivery can't be split into milestones",
     },
     {
-      name: "PaymentStatus",
-      type: {
-        kind: "enum",
-        variants: [
-          { name: "Escrowed" },
-          { name: "Released" },
-          { name: "Refunded" },
-          { name: "Disputed" },
-        ],
-      },
+      code: 6124,
+      name: "ServiceUpToDate",
+      msg: "Service already has the current layout",
     },
-  ],
-  events: [
     {
-      name: "ProtocolInitialized",
-      fields: [
-        { name: "authority", type: "publicKey", index: false },
-        { name: "timestamp", type: "i64", index: false },
+      code: 6125,
+      name: "PaymentUpToDate",
+      msg: "Payment already has the current layout",
+    },
+    {
+      code: 6126,
+      name: "AgentUpToDate",
+      msg: "Agent already has the current layout",
+    },
+    {
+      code: 6127,
+      name: "ConditionsWithAutoRelease",
+      msg: "Orders that release to the provider after delivery can't carry release conditions",
+    },
+  ],
+} as unknown as Idl;
+
+// ============================================================
+// HELPERS
+// ============================================================
+
+/**
+ * Convert a SOL amount (as a floating-point number) to lamports (as BN).
+ * @param sol - Amount in SOL (e.g. 1.5)
+ * @returns BN representing lamports
+ */
+export function solToLamports(sol: number): BN {
+  return new BN(Math.round(sol * LAMPORTS_PER_SOL));
+}
+
+/**
+ * Convert lamports (as BN) to a SOL amount (floating-point).
+ * @param lamports - BN representing lamports
+ * @returns number representing SOL
+ */
+export function lamportsToSol(lamports: BN): number {
+  return lamports.toNumber() / LAMPORTS_PER_SOL;
+}
+
+/**
+ * Convert a PriceModel enum string into the Anchor-compatible variant object.
+ * @param model - The PriceModel enum value
+ * @param periodSeconds - Billing period, required for PriceModel.Subscription
+ * @returns Anchor-serializable enum variant
+ */
+function priceModelToAnchor(
+  model: PriceModel,
+  periodSeconds?: number
+): Record<string, object> {
+  if (model === PriceModel.Subscription) {
+    if (!periodSeconds || periodSeconds <= 0) {
+      throw new Error("Subscription pricing requires a billing period");
+    }
+    return { subscription: { periodSeconds: new BN(periodSeconds) } };
+  }
+  const map: Record<PriceModel, string> = {
+    [PriceModel.Fixed]: "fixed",
+    [PriceModel.PerRequest]: "perRequest",
+    [PriceModel.PerSecond]: "perSecond",
+    [PriceModel.PerToken]: "perToken",
+    [PriceModel.Auction]: "auction",
+    [PriceModel.Subscription]: "subscription",
+  };
+  return { [map[model]]: {} };
+}
+
+/**
+ * Parse an Anchor enum variant object back to a PriceModel enum value.
+ * @param variant - The Anchor enum variant object
+ * @returns PriceModel value
+ */
+export function parsePriceModel(
+  variant: Record<string, unknown>
+): PriceModel {
+  const key = Object.keys(variant)[0];
+  const map: Record<string, PriceModel> = {
+    fixed: PriceModel.Fixed,
+    perRequest: PriceModel.PerRequest,
+    perSecond: PriceModel.PerSecond,
+    perToken: PriceModel.PerToken,
+    auction: PriceModel.Auction,
+    subscription: PriceModel.Subscription,
+  };
+  return map[key] ?? PriceModel.Fixed;
+}
+
+/**
+ * Normalise tags or capabilities the way the program keys its discovery
+ * indexes: trimmed, ASCII-lowercased and deduplicated. Blank values are
+ * not indexed.
+ * @param values - Tags or capabilities
+ * @returns Index keys in first-seen order
+ */
+export function indexKeys(values: string[]): string[] {
+  const keys: string[] = [];
+  for (const value of values) {
+    const key = value.trim().replace(/[A-Z]/g, (c) => c.toLowerCase());
+    if (key.length > 0 && !keys.includes(key)) keys.push(key);
+  }
+  return keys;
+}
+
+/**
+ * The message a payer (or its session key) signs off-chain to acknowledge
+ * delivery, redeemable by the provider with `releaseWithReceipt`. It names
+ * the program, so a receipt only redeems on that deployment.
+ * @param paymentPda - The delivered payment
+ * @param programId - The SolAgent program the payment lives on
+ * @returns Message bytes to sign with Ed25519
+ */
+export function deliveryReceiptMessage(
+  paymentPda: PublicKey,
+  programId: PublicKey = SOLAGENT_PROGRAM_ID
+): Buffer {
+  return Buffer.concat([
+    Buffer.from("solagent:delivery-receipt:"),
+    programId.toBuffer(),
+    paymentPda.toBuffer(),
+  ]);
+}
+
+/**
+ * Compute the lamports `payForService` expects for `quantity` units,
+ * mirroring the on-chain tier and reputation-discount rules.
+ * @param service - The service account
+ * @param quantity - Units being purchased
+ * @param buyerReputation - The paying agent's reputation score
+ * @returns Amount in lamports
+ */
+export function quoteServiceLamports(
+  service: ServiceAccount,
+  quantity: number,
+  buyerReputation: BN
+): BN {
+  const units = new BN(quantity);
+  const base = service.priceLamports;
+  const pricing = service.pricing;
+
+  let gross = base.mul(units);
+  if (pricing && pricing.tiers.length > 0) {
+    gross = new BN(0);
+    let bandStart = new BN(0);
+    let bandPrice = base;
+    for (const tier of [...pricing.tiers, null]) {
+      const bandEnd = tier ? tier.fromUnit : units;
+      const hi = BN.min(units, bandEnd);
+      if (hi.gt(bandStart)) {
+        gross = gross.add(bandPrice.mul(hi.sub(bandStart)));
+      }
+      if (!tier) break;
+      bandStart = BN.max(bandStart, tier.fromUnit);
+      bandPrice = tier.priceLamports;
+    }
+  }
+
+  let discountBps = 0;
+  for (const d of pricing?.reputationDiscounts ?? []) {
+    if (buyerReputation.gte(d.minReputation)) {
+      discountBps = Math.max(discountBps, d.discountBps);
+    }
+  }
+  return gross.sub(gross.muln(discountBps).divn(10_000));
+}
+
+/**
+ * Parse an Anchor enum variant object back to a PaymentStatus enum value.
+ * @param variant - The Anchor enum variant object
+ * @returns PaymentStatus value
+ */
+export function parsePaymentStatus(
+  variant: Record<string, unknown>
+): PaymentStatus {
+  const key = Object.keys(variant)[0];
+  const map: Record<string, PaymentStatus> = {
+    escrowed: PaymentStatus.Escrowed,
+    released: PaymentStatus.Released,
+    refunded: PaymentStatus.Refunded,
+    disputed: PaymentStatus.Disputed,
+    settled: PaymentStatus.Settled,
+  };
+  return map[key] ?? PaymentStatus.Escrowed;
+}
+
+// ============================================================
+// SDK CLASS
+// ============================================================
+
+/**
+ * SolAgentSDK provides a developer-friendly TypeScript interface for
+ * interacting with the SolAgent Economy Protocol on Solana.
+ *
+ * The protocol enables AI agent identity, reputation staking, on-chain
+ * marketplace, escrowed payments, and streaming payment channels.
+ *
+ * @example
+ * ```typescript
+ * import { SolAgentSDK } from '@solagent/sdk';
+ *
+ * const sdk = new SolAgentSDK(connection, wallet);
+ * await sdk.registerAgent("MyBot", "AI trading bot", ["trading"], "https://bot.ai");
+ * await sdk.stakeReputation(1.5); // 1.5 SOL
+ * ```
+ */
+export class SolAgentSDK {
+  /** The Anchor program instance. */
+  public readonly program: Program;
+
+  /** The Anchor provider instance. */
+  public readonly provider: AnchorProvider;
+
+  /** The underlying Solana connection. */
+  public readonly connection: Connection;
+
+  /** The program ID for the SolAgent protocol. */
+  public readonly programId: PublicKey;
+
+  constructor(
+    connection: Connection,
+    wallet: Wallet,
+    programId: PublicKey = SOLAGENT_PROGRAM_ID
+  ) {
+    this.connection = connection;
+    this.programId = programId;
+    this.provider = new AnchorProvider(connection, wallet, {
+      commitment: "confirmed",
+    });
+    this.program = new Program(SOLAGENT_IDL, programId, this.provider);
+  }
+
+  // ----------------------------------------------------------
+  // PDA DERIVATIONS
+  // ----------------------------------------------------------
+
+  /**
+   * Derive the Protocol PDA.
+   * Seeds: `["protocol"]`
+   * @returns [protocolPda, bump]
+   */
+  findProtocolPda(): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_PROTOCOL)],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive an Agent PDA for a given authority (wallet).
+   * Seeds: `["agent", authority]`
+   * @param authority - The wallet public key that owns this agent
+   * @returns [agentPda, bump]
+   */
+  findAgentPda(authority?: PublicKey): [PublicKey, number] {
+    const auth = authority ?? this.provider.wallet.publicKey;
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_AGENT), auth.toBuffer()],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a Vault PDA for a given agent.
+   * Seeds: `["vault", agentPda]`
+   * @param agentPda - The agent PDA
+   * @returns [vaultPda, bump]
+   */
+  findVaultPda(agentPda: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_VAULT), agentPda.toBuffer()],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a Feedback PDA for a specific from-agent / to-agent pair.
+   * Seeds: `["feedback", fromAgentPda, toAgentPda]`
+   * @param fromAgentPda - The reviewing agent's PDA
+   * @param toAgentPda - The reviewed agent's PDA
+   * @returns [feedbackPda, bump]
+   */
+  findFeedbackPda(
+    fromAgentPda: PublicKey,
+    toAgentPda: PublicKey
+  ): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(SEED_FEEDBACK),
+        fromAgentPda.toBuffer(),
+        toAgentPda.toBuffer(),
       ],
-    },
-    {
-      name: "AgentRegistered",
-      fields: [
-        { name: "agent", type: "publicKey", index: false },
-        { name: "authority", type: "publicKey", index: false },
-        { name: "name", type: "string", index: false },
-        { name: "timestamp", type: "i64", index: false },
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a Service PDA.
+   * Seeds: `["service", agentPda, serviceId]`
+   * @param agentPda - The provider agent PDA
+   * @param serviceId - The unique service identifier string
+   * @returns [servicePda, bump]
+   */
+  findServicePda(
+    agentPda: PublicKey,
+    serviceId: string
+  ): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(SEED_SERVICE),
+        agentPda.toBuffer(),
+        Buffer.from(serviceId),
       ],
-    },
-    {
-      name: "ReputationStaked",
-      fields: [
-        { name: "agent", type: "publicKey", index: false },
-        { name: "amount", type: "u64", index: false },
-        { name: "newScore", type: "u64", index: false },
-        { name: "totalStaked", type: "u64", index: false },
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a Payment PDA.
+   * Seeds: `["payment", payerAgentPda, servicePda, totalOrders (u64 LE bytes)]`
+   * @param payerAgentPda - The payer agent PDA
+   * @param servicePda - The service PDA being paid for
+   * @param totalOrders - The current total_orders count on the service (used as nonce)
+   * @returns [paymentPda, bump]
+   */
+  findPaymentPda(
+    payerAgentPda: PublicKey,
+    servicePda: PublicKey,
+    totalOrders: BN | number
+  ): [PublicKey, number] {
+    const ordersBn =
+      typeof totalOrders === "number" ? new BN(totalOrders) : totalOrders;
+    const orderBytes = ordersBn.toArrayLike(Buffer, "le", 8);
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(SEED_PAYMENT),
+        payerAgentPda.toBuffer(),
+        servicePda.toBuffer(),
+        orderBytes,
       ],
-    },
-    {
-      name: "FeedbackSubmitted",
-      fields: [
-        { name: "from", type: "publicKey", index: false },
-        { name: "to", type: "publicKey", index: false },
-        { name: "rating", type: "u8", index: false },
-        { name: "newReputation", type: "u64", index: false },
+      this.programId
+    );
+  }
+
+  /**
+   * Derive an Escrow PDA for a given payment.
+   * Seeds: `["escrow", paymentPda]`
+   * @param paymentPda - The payment PDA
+   * @returns [escrowPda, bump]
+   */
+  findEscrowPda(paymentPda: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_ESCROW), paymentPda.toBuffer()],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a Stream PDA.
+   * Seeds: `["stream", payerAgentPda, receiverAgentPda]`
+   * @param payerAgentPda - The payer agent PDA
+   * @param receiverAgentPda - The receiver agent PDA
+   * @returns [streamPda, bump]
+   */
+  findStreamPda(
+    payerAgentPda: PublicKey,
+    receiverAgentPda: PublicKey
+  ): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(SEED_STREAM),
+        payerAgentPda.toBuffer(),
+        receiverAgentPda.toBuffer(),
       ],
-    },
-    {
-      name: "ServiceCreated",
-      fields: [
-        { name: "service", type: "publicKey", index: false },
-        { name: "provider", type: "publicKey", index: false },
-        { name: "title", type: "string", index: false },
-        { name: "price", type: "u64", index: false },
-        {
-          name: "priceModel",
-          type: { defined: "PriceModel" },
-          index: false,
-        },
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a Stream Vault PDA.
+   * Seeds: `["stream_vault", streamPda]`
+   * @param streamPda - The stream PDA
+   * @returns [streamVaultPda, bump]
+   */
+  findStreamVaultPda(streamPda: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_STREAM_VAULT), streamPda.toBuffer()],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive an Auction PDA.
+   * Seeds: `["auction", servicePda, totalOrders (u64 LE bytes)]`
+   * @param servicePda - The Auction-priced service PDA
+   * @param totalOrders - The service's total_orders when the auction was opened
+   * @returns [auctionPda, bump]
+   */
+  findAuctionPda(
+    servicePda: PublicKey,
+    totalOrders: BN | number
+  ): [PublicKey, number] {
+    const ordersBn =
+      typeof totalOrders === "number" ? new BN(totalOrders) : totalOrders;
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(SEED_AUCTION),
+        servicePda.toBuffer(),
+        ordersBn.toArrayLike(Buffer, "le", 8),
       ],
-    },
-    {
-      name: "PaymentCreated",
-      fields: [
-        { name: "payment", type: "publicKey", index: false },
-        { name: "payer", type: "publicKey", index: false },
-        { name: "receiver", type: "publicKey", index: false },
-        { name: "amount", type: "u64", index: false },
-        { name: "intent", type: "string", index: false },
+      this.programId
+    );
+  }
+
+  /**
+   * Derive the vault PDA holding an auction's highest bid.
+   * Seeds: `["auction_vault", auctionPda]`
+   * @param auctionPda - The auction PDA
+   * @returns [auctionVaultPda, bump]
+   */
+  findAuctionVaultPda(auctionPda: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_AUCTION_VAULT), auctionPda.toBuffer()],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a UsageEscrow PDA for a payer agent and metered service.
+   * Seeds: `["usage", payerAgentPda, servicePda]`
+   * @param payerAgentPda - The payer agent PDA
+   * @param servicePda - The metered service PDA
+   * @returns [usageEscrowPda, bump]
+   */
+  findUsageEscrowPda(
+    payerAgentPda: PublicKey,
+    servicePda: PublicKey
+  ): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(SEED_USAGE),
+        payerAgentPda.toBuffer(),
+        servicePda.toBuffer(),
       ],
-    },
-    {
-      name: "PaymentReleased",
-      fields: [
-        { name: "payment", type: "publicKey", index: false },
-        { name: "receiver", type: "publicKey", index: false },
-        { name: "amount", type: "u64", index: false },
-        { name: "latencyMs", type: "u64", index: false },
+      this.programId
+    );
+  }
+
+  /**
+   * Derive the vault PDA holding a usage escrow's balance.
+   * Seeds: `["usage_vault", usageEscrowPda]`
+   * @param usageEscrowPda - The usage escrow PDA
+   * @returns [usageVaultPda, bump]
+   */
+  findUsageVaultPda(usageEscrowPda: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_USAGE_VAULT), usageEscrowPda.toBuffer()],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a UsageReport PDA.
+   * Seeds: `["usage_report", usageEscrowPda, reportIndex (u64 LE bytes)]`
+   * @param usageEscrowPda - The usage escrow PDA
+   * @param reportIndex - The escrow's report_count at submission time
+   * @returns [usageReportPda, bump]
+   */
+  findUsageReportPda(
+    usageEscrowPda: PublicKey,
+    reportIndex: BN | number
+  ): [PublicKey, number] {
+    const indexBn =
+      typeof reportIndex === "number" ? new BN(reportIndex) : reportIndex;
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(SEED_USAGE_REPORT),
+        usageEscrowPda.toBuffer(),
+        indexBn.toArrayLike(Buffer, "le", 8),
+      ],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a Job PDA.
+   * Seeds: `["job", buyerAgentPda, jobId]`
+   * @param buyerAgentPda - The buyer agent PDA
+   * @param jobId - The buyer-chosen job identifier
+   * @returns [jobPda, bump]
+   */
+  findJobPda(buyerAgentPda: PublicKey, jobId: string): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_JOB), buyerAgentPda.toBuffer(), Buffer.from(jobId)],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a JobBid PDA.
+   * Seeds: `["job_bid", jobPda, providerAgentPda]`
+   * @param jobPda - The job PDA
+   * @param providerAgentPda - The bidding provider's agent PDA
+   * @returns [jobBidPda, bump]
+   */
+  findJobBidPda(
+    jobPda: PublicKey,
+    providerAgentPda: PublicKey
+  ): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(SEED_JOB_BID),
+        jobPda.toBuffer(),
+        providerAgentPda.toBuffer(),
       ],
-    },
-    {
-      name: "PaymentRefunded",
-      fields: [
-        { name: "payment", type: "publicKey", index: false },
-        { name: "payer", type: "publicKey", index: false },
-        { name: "amount", type: "u64", index: false },
-        { name: "reason", type: "string", index: false },
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a Subscription PDA.
+   * Seeds: `["subscription", subscriberAgentPda, servicePda]`
+   * @param subscriberAgentPda - The subscribing agent PDA
+   * @param servicePda - The subscription service PDA
+   * @returns [subscriptionPda, bump]
+   */
+  findSubscriptionPda(
+    subscriberAgentPda: PublicKey,
+    servicePda: PublicKey
+  ): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(SEED_SUBSCRIPTION),
+        subscriberAgentPda.toBuffer(),
+        servicePda.toBuffer(),
       ],
-    },
-    {
-      name: "StreamCreated",
-      fields: [
-        { name: "stream", type: "publicKey", index: false },
-        { name: "payer", type: "publicKey", index: false },
-        { name: "receiver", type: "publicKey", index: false },
-        { name: "ratePerSecond", type: "u64", index: false },
-        { name: "deposit", type: "u64", index: false },
+      this.programId
+    );
+  }
+
+  /**
+   * Derive the vault PDA holding a subscription's pre-funded balance.
+   * Seeds: `["subscription_vault", subscriptionPda]`
+   * @param subscriptionPda - The subscription PDA
+   * @returns [subscriptionVaultPda, bump]
+   */
+  findSubscriptionVaultPda(subscriptionPda: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_SUBSCRIPTION_VAULT), subscriptionPda.toBuffer()],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a Coupon PDA.
+   * Seeds: `["coupon", servicePda, code]`
+   * @param servicePda - The service the coupon applies to
+   * @param code - The coupon code (max 32 chars)
+   * @returns [couponPda, bump]
+   */
+  findCouponPda(servicePda: PublicKey, code: string): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_COUPON), servicePda.toBuffer(), Buffer.from(code)],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive an Attestation PDA.
+   * Seeds: `["attestation", issuer, subjectAgentPda]`
+   * @param issuer - The attesting wallet (e.g. an organisation key)
+   * @param subjectAgentPda - The attested agent PDA
+   * @returns [attestationPda, bump]
+   */
+  findAttestationPda(
+    issuer: PublicKey,
+    subjectAgentPda: PublicKey
+  ): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(SEED_ATTESTATION),
+        issuer.toBuffer(),
+        subjectAgentPda.toBuffer(),
       ],
-    },
-    {
-      name: "StreamWithdrawn",
-      fields: [
-        { name: "stream", type: "publicKey", index: false },
-        { name: "amount", type: "u64", index: false },
-        { name: "totalWithdrawn", type: "u64", index: false },
-        { name: "isActive", type: "bool", index: false },
+      this.programId
+    );
+  }
+
+  /**
+   * Derive the index entry PDA listing a service under a tag, or an agent
+   * under a capability.
+   * Seeds: `["tag_index", key, service]` or `["capability_index", key, agent]`
+   * @param kind - Tag or Capability
+   * @param value - The tag or capability; normalised before deriving
+   * @param entry - The service or agent PDA
+   * @returns [indexEntryPda, bump]
+   */
+  findIndexPda(
+    kind: IndexKind,
+    value: string,
+    entry: PublicKey
+  ): [PublicKey, number] {
+    const [key] = indexKeys([value]);
+    const seed =
+      kind === IndexKind.Tag ? SEED_TAG_INDEX : SEED_CAPABILITY_INDEX;
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(seed), Buffer.from(key), entry.toBuffer()],
+      this.programId
+    );
+  }
+
+  /**
+   * Index entry accounts an instruction needs when `entry`'s keys change
+   * from `oldValues` to `newValues`: dropped keys first, then added keys.
+   */
+  private reindexAccounts(
+    kind: IndexKind,
+    entry: PublicKey,
+    oldValues: string[],
+    newValues: string[]
+  ): AccountMeta[] {
+    const oldKeys = indexKeys(oldValues);
+    const newKeys = indexKeys(newValues);
+    return [
+      ...oldKeys.filter((k) => !newKeys.includes(k)),
+      ...newKeys.filter((k) => !oldKeys.includes(k)),
+    ].map((key) => ({
+      pubkey: this.findIndexPda(kind, key, entry)[0],
+      isWritable: true,
+      isSigner: false,
+    }));
+  }
+
+  /**
+   * Derive a Category PDA.
+   * Seeds: `["category", id (u16 LE)]`
+   * @param id - The category id
+   * @returns [categoryPda, bump]
+   */
+  findCategoryPda(id: number): [PublicKey, number] {
+    const idBuf = Buffer.alloc(2);
+    idBuf.writeUInt16LE(id);
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_CATEGORY), idBuf],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a Bundle PDA.
+   * Seeds: `["bundle", orchestratorAgentPda, bundleId]`
+   * @param orchestratorAgentPda - The orchestrator agent PDA
+   * @param bundleId - The orchestrator-chosen bundle identifier
+   * @returns [bundlePda, bump]
+   */
+  findBundlePda(
+    orchestratorAgentPda: PublicKey,
+    bundleId: string
+  ): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(SEED_BUNDLE),
+        orchestratorAgentPda.toBuffer(),
+        Buffer.from(bundleId),
       ],
-    },
-  ],
-  errors: [
-    { code: 6000, name: "NameTooLong", msg: "Name exceeds 32 characters" },
-    {
-      code: 6001,
-      name: "DescriptionTooLong",
-      msg: "Description exceeds 256 characters",
-    },
-    {
-      code: 6002,
-      name: "TooManyCapabilities",
-      msg: "Too many capabilities (max 10)",
-    },
-    {
-      code: 6003,
-      name: "ZeroAmount",
-      msg: "Amount must be greater than zero",
-    },
-    {
-      code: 6004,
-      name: "InvalidRating",
-      msg: "Rating must be between 1 and 5",
-    },
-    {
-      code: 6005,
-      name: "CommentTooLong",
-      msg: "Comment exceeds 256 characters",
-    },
-    { code: 6006, name: "TitleTooLong", msg: "Title exceeds 64 characters" },
-    { code: 6007, name: "TooManyTags", msg: "Too many tags (max 5)" },
-    {
-      code: 6008,
-      name: "IntentTooLong",
-      msg: "Intent exceeds 256 characters",
-    },
-    {
-      code: 6009,
-      name: "PaymentNotEscrowed",
-      msg: "Payment is not in escrowed state",
-    },
-    { code: 6010, name: "RefundNotAllowed", msg: "Refund not allowed" },
-    {
-      code: 6011,
-      name: "InsufficientDeposit",
-      msg: "Insufficient deposit for stream",
-    },
-    { code: 6012, name: "StreamNotActive", msg: "Stream is not active" },
-    { code: 6013, name: "NothingToWithdraw", msg: "Nothing to withdraw" },
-    { code: 6014, name: "Unauthorized", msg: "Unauthorized" },
-  ],
-} as unknown as Idl;
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a Dispute PDA.
+   * Seeds: `["dispute", paymentPda]`
+   * @param paymentPda - The disputed payment PDA
+   * @returns [disputePda, bump]
+   */
+  findDisputePda(paymentPda: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_DISPUTE), paymentPda.toBuffer()],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive an ArbiterPool PDA.
+   * Seeds: `["arbiter_pool", poolId (u16 LE)]`
+   * @param poolId - The pool id
+   * @returns [arbiterPoolPda, bump]
+   */
+  findArbiterPoolPda(poolId: number): [PublicKey, number] {
+    const idBuf = Buffer.alloc(2);
+    idBuf.writeUInt16LE(poolId);
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_ARBITER_POOL), idBuf],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive an Arbiter PDA.
+   * Seeds: `["arbiter", arbiterPoolPda, authority]`
+   * @param arbiterPoolPda - The pool PDA
+   * @param authority - The arbiter's wallet (defaults to the connected wallet)
+   * @returns [arbiterPda, bump]
+   */
+  findArbiterPda(
+    arbiterPoolPda: PublicKey,
+    authority?: PublicKey
+  ): [PublicKey, number] {
+    const auth = authority ?? this.provider.wallet.publicKey;
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_ARBITER), arbiterPoolPda.toBuffer(), auth.toBuffer()],
+      this.programId
+    );
+  }
+
+  /**
+   * Derive a SessionKey PDA.
+   * Seeds: `["session_key", agentPda, key]`
+   * @param agentPda - The agent the key signs for
+   * @param key - The session public key
+   * @returns [sessionKeyPda, bump]
+   */
+  findSessionKeyPda(agentPda: PublicKey, key: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(SEED_SESSION_KEY), agentPda.toBuffer(), key.toBuffer()],
+      this.programId
+    );
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: initializeProtocol
+  // ----------------------------------------------------------
+
+  /**
+   * Initialize the global protocol state. This should only be called once
+   * by the protocol deployer / authority.
+   *
+   * @returns Transaction signature
+   */
+  async initializeProtocol(): Promise<TransactionSignature> {
+    const [protocolPda] = this.findProtocolPda();
+
+    return this.program.methods
+      .initializeProtocol()
+      .accounts({
+        protocol: protocolPda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: registerAgent
+  // ----------------------------------------------------------
+
+  /**
+   * Register a new AI agent on-chain with identity metadata.
+   *
+   * Creates a PDA account derived from the wallet's public key that stores
+   * the agent's name, description, capabilities, and endpoint.
+   *
+   * @param name - Agent display name (max 32 chars)
+   * @param description - Agent description (max 256 chars)
+   * @param capabilities - List of capability strings (max 10 items, each max 32 chars)
+   * @param endpoint - The agent's API endpoint URL (max 128 chars)
+   * @returns Transaction signature
+   *
+   * @example
+   * ```typescript
+   * await sdk.registerAgent(
+   *   "TranslatorBot",
+   *   "AI-powered translation service",
+   *   ["translation", "nlp", "multilingual"],
+   *   "https://translator.example.com/api"
+   * );
+   * ```
+   */
+  async registerAgent(
+    name: string,
+    description: string,
+    capabilities: string[],
+    endpoint: string
+  ): Promise<TransactionSignature> {
+    const [agentPda] = this.findAgentPda();
+
+    return this.program.methods
+      .registerAgent(name, description, capabilities, endpoint)
+      .accounts({
+        agent: agentPda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(
+        this.reindexAccounts(
+          IndexKind.Capability,
+          agentPda,
+          [],
+          capabilities
+        )
+      )
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: stakeReputation
+  // ----------------------------------------------------------
+
+  /**
+   * Stake SOL to boost your agent's on-chain reputation score.
+   *
+   * Reputation is calculated with diminishing returns:
+   * `score = log2(staked_sol) * 10 + 50 + feedback_bonus + completion_bonus`
+   *
+   * @param amountSol - Amount of SOL to stake (e.g. 1.5 for 1.5 SOL)
+   * @returns Transaction signature
+   *
+   * @example
+   * ```typescript
+   * await sdk.stakeReputation(1.5); // Stake 1.5 SOL
+   * ```
+   */
+  async stakeReputation(amountSol: number): Promise<TransactionSignature> {
+    const [agentPda] = this.findAgentPda();
+    const [vaultPda] = this.findVaultPda(agentPda);
+    const lamports = solToLamports(amountSol);
+
+    return this.program.methods
+      .stakeReputation(lamports)
+      .accounts({
+        agent: agentPda,
+        vault: vaultPda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: submitFeedback
+  // ----------------------------------------------------------
+
+  /**
+   * Submit feedback for another agent after a service interaction.
+   *
+   * A feedback PDA is created for the (fromAgent, toAgent) pair, so each
+   * agent pair can only have one feedback record. The target agent's
+   * reputation score is recalculated to include the new feedback bonus.
+   *
+   * @param toAgentPda - The PDA of the agent being reviewed
+   * @param rating - Rating from 1 (worst) to 5 (best)
+   * @param comment - Feedback comment (max 256 chars)
+   * @returns Transaction signature
+   *
+   * @example
+   * ```typescript
+   * await sdk.submitFeedback(targetAgentPda, 5, "Excellent translation quality!");
+   * ```
+   */
+  async submitFeedback(
+    toAgentPda: PublicKey,
+    rating: number,
+    comment: string
+  ): Promise<TransactionSignature> {
+    const [fromAgentPda] = this.findAgentPda();
+    const [feedbackPda] = this.findFeedbackPda(fromAgentPda, toAgentPda);
+
+    return this.program.methods
+      .submitFeedback(rating, comment)
+      .accounts({
+        feedback: feedbackPda,
+        fromAgent: fromAgentPda,
+        toAgent: toAgentPda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: createService
+  // ----------------------------------------------------------
+
+  /**
+   * Register a new service on the agent marketplace.
+   *
+   * Services are discoverable on-chain and can be paid for via the
+   * escrowed payment flow.
+   *
+   * @param serviceId - Unique identifier for this service (used in PDA derivation)
+   * @param title - Service display title (max 64 chars)
+   * @param description - Service description (max 256 chars)
+   * @param priceSol - Price in SOL (e.g. 0.001)
+   * @param priceModel - Pricing model (Fixed, PerRequest, PerSecond, PerToken, Auction, Subscription)
+   * @param tags - Discoverable tags for marketplace search (max 5 items, each max 32 chars)
+   * @param subscriptionPeriodSeconds - Billing period, required for Subscription pricing
+   * @returns Transaction signature
+   *
+   * @example
+   * ```typescript
+   * await sdk.createService(
+   *   "translate-en-es",
+   *   "English to Spanish Translation",
+   *   "AI-powered EN->ES translation with 99% accuracy",
+   *   0.001,
+   *   PriceModel.PerRequest,
+   *   ["ai", "translation", "nlp"]
+   * );
+   * ```
+   */
+  async createService(
+    serviceId: string,
+    title: string,
+    description: string,
+    priceSol: number,
+    priceModel: PriceModel,
+    tags: string[],
+    subscriptionPeriodSeconds?: number
+  ): Promise<TransactionSignature> {
+    const [agentPda] = this.findAgentPda();
+    const [servicePda] = this.findServicePda(agentPda, serviceId);
+    const priceLamports = solToLamports(priceSol);
+
+    return this.program.methods
+      .createService(
+        serviceId,
+        title,
+        description,
+        priceLamports,
+        priceModelToAnchor(priceModel, subscriptionPeriodSeconds),
+        tags
+      )
+      .accounts({
+        service: servicePda,
+        agent: agentPda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(
+        this.reindexAccounts(IndexKind.Tag, servicePda, [], tags)
+      )
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: updateService
+  // ----------------------------------------------------------
+
+  /**
+   * Update the terms of a service owned by the connected wallet.
+   *
+   * Price changes only affect new orders; payments already in escrow keep
+   * the amount they were created with. Any effective change bumps the
+   * service `version`, which new payments record in `serviceVersion`.
+   *
+   * @param servicePda - The PDA of the service to update
+   * @param options - Fields to change (omitted fields are unchanged)
+   * @returns Transaction signature
+   *
+   * @example
+   * ```typescript
+   * await sdk.updateService(servicePda, { priceSol: 0.002, tags: ["ai"] });
+   * ```
+   */
+  async updateService(
+    servicePda: PublicKey,
+    options: UpdateServiceOptions
+  ): Promise<TransactionSignature> {
+    let indexAccounts: AccountMeta[] = [];
+    if (options.tags) {
+      const service = (await this.program.account.service.fetch(
+        servicePda
+      )) as unknown as ServiceAccount;
+      indexAccounts = this.reindexAccounts(
+        IndexKind.Tag,
+        servicePda,
+        service.tags,
+        options.tags
+      );
+    }
+
+    return this.program.methods
+      .updateService(
+        options.title ?? null,
+        options.description ?? null,
+        options.priceSol !== undefined ? solToLamports(options.priceSol) : null,
+        options.priceModel !== undefined
+          ? priceModelToAnchor(
+              options.priceModel,
+              options.subscriptionPeriodSeconds
+            )
+          : null,
+        options.tags ?? null
+      )
+      .accounts({
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(indexAccounts)
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: setPricingSchedule
+  // ----------------------------------------------------------
+
+  /**
+   * Set or clear the volume pricing schedule of a service.
+   *
+   * Units before the first tier's `fromUnit` are billed at the base price.
+   * Tiers apply to the quantity of a single order, and to the cumulative
+   * units reported against a usage escrow. The best reputation discount the
+   * buyer qualifies for is taken off the total.
+   *
+   * @param servicePda - The PDA of the service
+   * @param pricing - The new schedule, or null to go back to flat pricing
+   * @returns Transaction signature
+   *
+   * @example
+   * ```typescript
+   * // First 1,000 requests at the base price, then 0.0005 SOL each;
+   * // 10% off for buyers with reputation >= 100
+   * await sdk.setPricingSchedule(servicePda, {
+   *   tiers: [{ fromUnit: new BN(1000), priceLamports: new BN(500_000) }],
+   *   reputationDiscounts: [{ minReputation: new BN(100), discountBps: 1000 }],
+   * });
+   * ```
+   */
+  async setPricingSchedule(
+    servicePda: PublicKey,
+    pricing: PricingSchedule | null
+  ): Promise<TransactionSignature> {
+    return this.program.methods
+      .setPricingSchedule(pricing)
+      .accounts({
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: setReferralFee
+  // ----------------------------------------------------------
+
+  /**
+   * Set the share of each referred order paid to the referring agent.
+   *
+   * The fee is locked into each payment when it is created and paid out of
+   * the escrow on release.
+   *
+   * @param servicePda - The PDA of the service
+   * @param referralBps - Referral share in basis points (e.g. 500 = 5%)
+   * @returns Transaction signature
+   */
+  async setReferralFee(
+    servicePda: PublicKey,
+    referralBps: number
+  ): Promise<TransactionSignature> {
+    return this.program.methods
+      .setReferralFee(referralBps)
+      .accounts({
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: setAccessRules
+  // ----------------------------------------------------------
+
+  /**
+   * Set or clear who may buy from a service.
+   *
+   * List entries match either the buyer agent PDA itself or the issuer of
+   * the attestation the buyer presents, so an organisation key can stand in
+   * for all agents it has attested. Buyers choose which attestation to
+   * present, so a denylisted organisation only stops buyers who present its
+   * attestation; keep an organisation out with an allowlist instead.
+   *
+   * @param servicePda - The PDA of the service
+   * @param options - Access rules, or null to open the service to everyone
+   * @returns Transaction signature
+   *
+   * @example
+   * ```typescript
+   * await sdk.setAccessRules(servicePda, {
+   *   listMode: AccessListMode.Allowlist,
+   *   list: [partnerOrgKey],
+   *   minReputation: 50,
+   * });
+   * ```
+   */
+  async setAccessRules(
+    servicePda: PublicKey,
+    options: AccessRulesOptions | null
+  ): Promise<TransactionSignature> {
+    const access = options
+      ? {
+          listMode: { [options.listMode ?? AccessListMode.Open]: {} },
+          list: options.list ?? [],
+          minReputation: new BN(options.minReputation ?? 0),
+          requiredAttester: options.requiredAttester ?? null,
+        }
+      : null;
+
+    return this.program.methods
+      .setAccessRules(access)
+      .accounts({
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: setCapacity
+  // ----------------------------------------------------------
+
+  /**
+   * Limit how many escrowed orders a service can have open at once.
+   *
+   * When the limit is reached, `payForService`, `acceptJobBid` and
+   * `openAuction` fail with `ServiceAtCapacity` until an order is released
+   * or refunded.
+   *
+   * @param servicePda - The PDA of the service
+   * @param maxConcurrentOrders - Maximum open orders (0 = unlimited)
+   * @returns Transaction signature
+   */
+  async setCapacity(
+    servicePda: PublicKey,
+    maxConcurrentOrders: number
+  ): Promise<TransactionSignature> {
+    return this.program.methods
+      .setCapacity(new BN(maxConcurrentOrders))
+      .accounts({
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: setServiceCategories
+  // ----------------------------------------------------------
+
+  /**
+   * Place a service in up to three curated categories, replacing its
+   * current ones. Every category must exist and be active.
+   * @param servicePda - The PDA of the service
+   * @param categoryIds - Category ids (see `getAllCategories`)
+   * @returns Transaction signature
+   */
+  async setServiceCategories(
+    servicePda: PublicKey,
+    categoryIds: number[]
+  ): Promise<TransactionSignature> {
+    return this.program.methods
+      .setServiceCategories(categoryIds)
+      .accounts({
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .remainingAccounts(
+        categoryIds.map((id) => ({
+          pubkey: this.findCategoryPda(id)[0],
+          isWritable: false,
+          isSigner: false,
+        }))
+      )
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: setArbiterPool
+  // ----------------------------------------------------------
+
+  /**
+   * Route disputes on a service's orders to an arbiter pool, or back to
+   * the protocol authority. Applies to disputes opened afterwards.
+   * @param servicePda - The PDA of the service
+   * @param poolId - Arbiter pool id, or null for the protocol authority
+   * @returns Transaction signature
+   */
+  async setArbiterPool(
+    servicePda: PublicKey,
+    poolId: number | null
+  ): Promise<TransactionSignature> {
+    return this.program.methods
+      .setArbiterPool()
+      .accounts({
+        service: servicePda,
+        arbiterPool:
+          poolId === null ? null : this.findArbiterPoolPda(poolId)[0],
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: setSla
+  // ----------------------------------------------------------
+
+  /**
+   * Set or clear the delivery SLA a service commits to.
+   *
+   * Terms are copied into each new payment. On release, an order delivered
+   * later than the deadlines refunds the highest penalty step it reached to
+   * the payer. Orders never marked delivered are timed at release.
+   *
+   * @param servicePda - The PDA of the service
+   * @param sla - The new terms, or null to drop the SLA
+   * @returns Transaction signature
+   *
+   * @example
+   * ```typescript
+   * // Deliver within an hour; 10% back if late, 50% back if over a day late
+   * await sdk.setSla(servicePda, {
+   *   maxAckSeconds: new BN(0),
+   *   maxDeliverySeconds: new BN(3600),
+   *   penalties: [
+   *     { lateBySeconds: new BN(0), refundBps: 1000 },
+   *     { lateBySeconds: new BN(86400), refundBps: 5000 },
+   *   ],
+   * });
+   * ```
+   */
+  async setSla(
+    servicePda: PublicKey,
+    sla: SlaTerms | null
+  ): Promise<TransactionSignature> {
+    return this.program.methods
+      .setSla(sla)
+      .accounts({
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: setTimeoutPolicy
+  // ----------------------------------------------------------
+
+  /**
+   * Choose what happens to orders the payer never releases.
+   *
+   * The policy is copied into each new payment. Under `refundOnTimeout` the
+   * payer can reclaim the escrow once it times out. Under
+   * `releaseAfterDelivery`, once the provider calls `markDelivered` the payer
+   * has `challengeSeconds` to dispute; after that the provider can claim the
+   * escrow with `releaseOnTimeout`, and the payer can no longer refund.
+   * Orders under `releaseAfterDelivery` can't carry release conditions.
+   *
+   * @param servicePda - The PDA of the service
+   * @param policy - The new timeout policy
+   * @returns Transaction signature
+   *
+   * @example
+   * ```typescript
+   * // Release to the provider unless the payer disputes within a day
+   * await sdk.setTimeoutPolicy(servicePda, {
+   *   releaseAfterDelivery: { challengeSeconds: new BN(86400) },
+   * });
+   * ```
+   */
+  async setTimeoutPolicy(
+    servicePda: PublicKey,
+    policy: TimeoutPolicy
+  ): Promise<TransactionSignature> {
+    return this.program.methods
+      .setTimeoutPolicy(policy)
+      .accounts({
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: migrateService
+  // ----------------------------------------------------------
+
+  /**
+   * Grow a service registered before the current account layout so it can
+   * be used with the newer instructions. The authority pays the extra rent.
+   *
+   * @param servicePda - The PDA of the service
+   * @returns Transaction signature
+   */
+  async migrateService(servicePda: PublicKey): Promise<TransactionSignature> {
+    return this.program.methods
+      .migrateService()
+      .accounts({
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: pauseService / resumeService
+  // ----------------------------------------------------------
+
+  /**
+   * Pause a service so it stops accepting new payments.
+   * @param servicePda - The PDA of the service to pause
+   * @returns Transaction signature
+   */
+  async pauseService(servicePda: PublicKey): Promise<TransactionSignature> {
+    return this.program.methods
+      .pauseService()
+      .accounts({
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  /**
+   * Resume a previously paused service.
+   * @param servicePda - The PDA of the service to resume
+   * @returns Transaction signature
+   */
+  async resumeService(servicePda: PublicKey): Promise<TransactionSignature> {
+    return this.program.methods
+      .resumeService()
+      .accounts({
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: closeService
+  // ----------------------------------------------------------
+
+  /**
+   * Delist a service and reclaim its rent.
+   *
+   * Fails with `ServiceHasOpenOrders` while any payment for the service is
+   * still escrowed, and with `ServiceHasPayments` until every finished
+   * payment has been closed with `closePayment`.
+   *
+   * @param servicePda - The PDA of the service to close
+   * @returns Transaction signature
+   */
+  async closeService(servicePda: PublicKey): Promise<TransactionSignature> {
+    const service = (await this.program.account.service.fetch(
+      servicePda
+    )) as unknown as ServiceAccount;
+
+    return this.program.methods
+      .closeService()
+      .accounts({
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .remainingAccounts(
+        this.reindexAccounts(IndexKind.Tag, servicePda, service.tags, [])
+      )
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: payForService
+  // ----------------------------------------------------------
+
+  /**
+   * Pay for a service with automatic escrow.
+   *
+   * Funds are locked in an escrow PDA until the payer confirms delivery
+   * (releasePayment) or the payment times out / is refunded.
+   *
+   * @param servicePda - The PDA of the service being paid for
+   * @param amountSol - Amount of SOL to escrow (must equal the service quote, see quoteServiceLamports)
+   * @param intent - Freeform intent string describing what is being requested (max 256 chars)
+   * @param conditions - Optional typed release conditions (max 5, not only a deadline), see executeConditionalRelease
+   * @param timeoutSeconds - Seconds before the escrowed payment can be refunded (default: 3600)
+   * @param quantity - Units purchased for PerRequest / PerToken services (default: 1)
+   * @param couponPda - Optional coupon to redeem; `amountSol` must already include its discount
+   * @param referrerAgentPda - Optional agent that routed this order; earns the service's referral fee on release
+   * @param attestationPda - Attestation of the payer agent, for services with access rules that need one
+   * @returns The payment PDA public key
+   *
+   * @example
+   * ```typescript
+   * const paymentPda = await sdk.payForService(
+   *   servicePda,
+   *   0.1,
+   *   "Translate 'hello world' to Spanish"
+   * );
+   * ```
+   */
+  async payForService(
+    servicePda: PublicKey,
+    amountSol: number,
+    intent: string,
+    conditions: PaymentCondition[] = [],
+    timeoutSeconds: number = 3600,
+    quantity: number = 1,
+    couponPda: PublicKey | null = null,
+    referrerAgentPda: PublicKey | null = null,
+    attestationPda: PublicKey | null = null
+  ): Promise<PublicKey> {
+    // Fetch the service to get the provider (receiver agent) and current total_orders
+    const serviceAccount =
+      (await this.program.account.service.fetch(servicePda)) as unknown as ServiceAccount;
+    const receiverAgentPda = serviceAccount.provider;
+    const totalOrders = serviceAccount.totalOrders;
+
+    const [payerAgentPda] = this.findAgentPda();
+    const [paymentPda] = this.findPaymentPda(
+      payerAgentPda,
+      servicePda,
+      totalOrders
+    );
+    const [escrowPda] = this.findEscrowPda(paymentPda);
+    const lamports = solToLamports(amountSol);
+
+    await this.program.methods
+      .payForService(
+        lamports,
+        new BN(quantity),
+        intent,
+        conditions,
+        new BN(timeoutSeconds)
+      )
+      .accounts({
+        payment: paymentPda,
+        escrow: escrowPda,
+        payerAgent: payerAgentPda,
+        receiverAgent: receiverAgentPda,
+        service: servicePda,
+        coupon: couponPda,
+        referrerAgent: referrerAgentPda,
+        buyerAttestation: attestationPda,
+        payerAuthority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    return paymentPda;
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: acknowledgePayment / markDelivered
+  // ----------------------------------------------------------
+
+  /**
+   * Acknowledge an escrowed order as its provider, stopping the SLA
+   * acknowledgement clock.
+   * @param paymentPda - The PDA of the payment
+   * @returns Transaction signature
+   */
+  async acknowledgePayment(
+    paymentPda: PublicKey
+  ): Promise<TransactionSignature> {
+    const paymentAccount =
+      (await this.program.account.payment.fetch(paymentPda)) as unknown as PaymentAccount;
+
+    return this.program.methods
+      .acknowledgePayment()
+      .accounts({
+        payment: paymentPda,
+        receiverAgent: paymentAccount.receiver,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  /**
+   * Mark an escrowed order delivered as its provider, stopping the SLA
+   * delivery clock. Also acknowledges the order if that hasn't happened yet.
+   * @param paymentPda - The PDA of the payment
+   * @returns Transaction signature
+   */
+  async markDelivered(paymentPda: PublicKey): Promise<TransactionSignature> {
+    const paymentAccount =
+      (await this.program.account.payment.fetch(paymentPda)) as unknown as PaymentAccount;
+
+    return this.program.methods
+      .markDelivered()
+      .accounts({
+        payment: paymentPda,
+        receiverAgent: paymentAccount.receiver,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: releasePayment
+  // ----------------------------------------------------------
+
+  /**
+   * Release an escrowed payment to the service provider after delivery.
+   *
+   * Only the payer agent's authority can call this instruction. The escrowed
+   * SOL is transferred to the receiver agent's authority wallet, and both
+   * agents' stats are updated. If the order carries an SLA and the provider
+   * was late, the penalty share is refunded to the payer's wallet first. If
+   * the order was referred, the referrer's share of the rest is paid to the
+   * referrer agent's wallet. For milestone payments, only the milestones not
+   * yet released are paid out.
+   *
+   * @param paymentPda - The PDA of the payment to release
+   * @returns Transaction signature
+   *
+   * @example
+   * ```typescript
+   * await sdk.releasePayment(paymentPda);
+   * ```
+   */
+  async releasePayment(
+    paymentPda: PublicKey
+  ): Promise<TransactionSignature> {
+    return this.program.methods
+      .releasePayment()
+      .accounts(await this.releaseAccounts(paymentPda))
+      .rpc();
+  }
+
+  /** Resolve the accounts shared by `releasePayment` and `releaseMilestone`. */
+  private async releaseAccounts(paymentPda: PublicKey) {
+    // Fetch payment to get related accounts
+    const paymentAccount =
+      (await this.program.account.payment.fetch(paymentPda)) as unknown as PaymentAccount;
+    const [escrowPda] = this.findEscrowPda(paymentPda);
+
+    // Fetch receiver agent to get its authority
+    const receiverAgentAccount =
+      (await this.program.account.agent.fetch(
+        paymentAccount.receiver
+      )) as unknown as AgentAccount;
+
+    let referrerAuthority: PublicKey | null = null;
+    if (paymentAccount.referrer) {
+      const referrerAgentAccount =
+        (await this.program.account.agent.fetch(
+          paymentAccount.referrer
+        )) as unknown as AgentAccount;
+      referrerAuthority = referrerAgentAccount.authority;
+    }
+
+    return {
+      payment: paymentPda,
+      escrow: escrowPda,
+      payerAgent: paymentAccount.payer,
+      receiverAgent: paymentAccount.receiver,
+      service: paymentAccount.service,
+      receiverAuthority: receiverAgentAccount.authority,
+      referrerAgent: paymentAccount.referrer,
+      referrerAuthority,
+      authority: this.provider.wallet.publicKey,
+    };
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: refundPayment
+  // ----------------------------------------------------------
+
+  /**
+   * Refund an escrowed payment back to the payer.
+   *
+   * Can be called by the payer at any time, or by anyone after the
+   * payment's timeout or an unreleased milestone's deadline has elapsed.
+   * Only the part not yet released through milestones is returned.
+   *
+   * @param paymentPda - The PDA of the payment to refund
+   * @returns Transaction signature
+   *
+   * @example
+   * ```typescript
+   * await sdk.refundPayment(paymentPda);
+   * ```
+   */
+  async refundPayment(
+    paymentPda: PublicKey
+  ): Promise<TransactionSignature> {
+    const paymentAccount =
+      (await this.program.account.payment.fetch(paymentPda)) as unknown as PaymentAccount;
+    const [escrowPda] = this.findEscrowPda(paymentPda);
+
+    // Fetch payer agent to get authority for refund destination
+    const payerAgentAccount =
+      (await this.program.account.agent.fetch(
+        paymentAccount.payer
+      )) as unknown as AgentAccount;
+
+    return this.program.methods
+      .refundPayment()
+      .accounts({
+        payment: paymentPda,
+        escrow: escrowPda,
+        payerAgent: paymentAccount.payer,
+        service: paymentAccount.service,
+        payerAuthority: payerAgentAccount.authority,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: closePayment
+  // ----------------------------------------------------------
+
+  /**
+   * Close a released, refunded or settled payment as its payer, reclaiming
+   * the account's rent. A service or bundle can only be closed once all of
+   * its payments have been.
+   *
+   * @param paymentPda - The PDA of the finished payment
+   * @returns Transaction signature
+   */
+  async closePayment(paymentPda: PublicKey): Promise<TransactionSignature> {
+    const paymentAccount =
+      (await this.program.account.payment.fetch(paymentPda)) as unknown as PaymentAccount;
+
+    return this.program.methods
+      .closePayment()
+      .accounts({
+        payment: paymentPda,
+        payerAgent: paymentAccount.payer,
+        listing: paymentAccount.service,
+        payerAuthority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: migratePayment
+  // ----------------------------------------------------------
+
+  /**
+   * Grow a payment placed before the current account layout so it can be
+   * released, refunded or disputed again. Anyone can call it; the wallet
+   * pays the extra rent, which goes to the payer when the payment closes.
+   *
+   * @param paymentPda - The PDA of the payment
+   * @returns Transaction signature
+   */
+  async migratePayment(paymentPda: PublicKey): Promise<TransactionSignature> {
+    return this.program.methods
+      .migratePayment()
+      .accounts({
+        payment: paymentPda,
+        payer: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: releaseWithReceipt
+  // ----------------------------------------------------------
+
+  /**
+   * Release an escrowed payment as its provider, using a delivery receipt
+   * the payer signed off-chain.
+   *
+   * The receipt is an Ed25519 signature over
+   * `deliveryReceiptMessage(paymentPda)` by the payer's authority or one of
+   * its session keys; it is checked on-chain through an Ed25519 sig-verify
+   * instruction placed right before the release. SLA penalties and referral
+   * shares apply as in `releasePayment`.
+   *
+   * @param paymentPda - The PDA of the payment
+   * @param receipt - The payer's signed receipt
+   * @returns Transaction signature
+   */
+  async releaseWithReceipt(
+    paymentPda: PublicKey,
+    receipt: DeliveryReceipt
+  ): Promise<TransactionSignature> {
+    return this.program.methods
+      .releaseWithReceipt()
+      .accounts(await this.providerReleaseAccounts(paymentPda, receipt.signer))
+      .preInstructions([
+        Ed25519Program.createInstructionWithPublicKey({
+          publicKey: receipt.signer.toBytes(),
+          message: deliveryReceiptMessage(paymentPda, this.program.programId),
+          signature: receipt.signature,
+        }),
+      ])
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: setMilestones
+  // ----------------------------------------------------------
+
+  /**
+   * Split an escrowed service payment into milestones (payer only).
+   *
+   * Amounts must be non-zero and add up to the escrowed amount, and
+   * milestones can only be set once, before the provider acknowledges the
+   * order and before anything is released. Deadlines must be in the
+   * future, no later than the payment's timeout and non-decreasing; a
+   * milestone without a deadline can only be followed by others without
+   * one. Orders with an SLA can't use milestones. Once an unreleased
+   * milestone's deadline passes, anyone can refund the remainder.
+   *
+   * @param paymentPda - The PDA of the payment
+   * @param milestones - Up to 5 milestones (deadline 0 = none)
+   * @returns Transaction signature
+   *
+   * @example
+   * ```typescript
+   * await sdk.setMilestones(paymentPda, [
+   *   { amount: new BN(LAMPORTS_PER_SOL / 2), deadline: new BN(deadline) },
+   *   { amount: new BN(LAMPORTS_PER_SOL / 2), deadline: new BN(0) },
+   * ]);
+   * ```
+   */
+  async setMilestones(
+    paymentPda: PublicKey,
+    milestones: MilestoneSpec[]
+  ): Promise<TransactionSignature> {
+    const paymentAccount =
+      (await this.program.account.payment.fetch(paymentPda)) as unknown as PaymentAccount;
+
+    return this.program.methods
+      .setMilestones(milestones)
+      .accounts({
+        payment: paymentPda,
+        payerAgent: paymentAccount.payer,
+        service: paymentAccount.service,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: releaseMilestone
+  // ----------------------------------------------------------
+
+  /**
+   * Release a single milestone of an escrowed payment to the receiver.
+   *
+   * Only the payer agent's authority can call this instruction. Referral
+   * shares are split off each milestone; releasing the last one completes
+   * the payment.
+   *
+   * @param paymentPda - The PDA of the payment
+   * @param index - Index of the milestone to release
+   * @returns Transaction signature
+   */
+  async releaseMilestone(
+    paymentPda: PublicKey,
+    index: number
+  ): Promise<TransactionSignature> {
+    return this.program.methods
+      .releaseMilestone(index)
+      .accounts(await this.releaseAccounts(paymentPda))
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: settlePayment
+  // ----------------------------------------------------------
+
+  /**
+   * Settle an escrowed or disputed payment at a split agreed by both parties.
+   *
+   * The payer and receiver authorities must both sign: the SDK wallet signs
+   * as one party and `counterparty` as the other. `payerAmount` of the
+   * unreleased escrow goes back to the payer and the rest to the receiver,
+   * minus any referral share and bundle components' shares. Settling a
+   * disputed payment resolves its dispute. The payment and its escrow are
+   * closed, with their rent going to the payer.
+   *
+   * @param paymentPda - The PDA of the payment
+   * @param payerAmount - Lamports refunded to the payer
+   * @param counterparty - Authority keypair of the other party
+   * @returns Transaction signature
+   */
+  async settlePayment(
+    paymentPda: PublicKey,
+    payerAmount: number,
+    counterparty: Keypair
+  ): Promise<TransactionSignature> {
+    const paymentAccount =
+      (await this.program.account.payment.fetch(paymentPda)) as unknown as PaymentAccount;
+    const [escrowPda] = this.findEscrowPda(paymentPda);
+    const payerAgentAccount =
+      (await this.program.account.agent.fetch(
+        paymentAccount.payer
+      )) as unknown as AgentAccount;
+    const receiverAgentAccount =
+      (await this.program.account.agent.fetch(
+        paymentAccount.receiver
+      )) as unknown as AgentAccount;
+
+    let referrerAuthority: PublicKey | null = null;
+    if (paymentAccount.referrer) {
+      const referrerAgentAccount =
+        (await this.program.account.agent.fetch(
+          paymentAccount.referrer
+        )) as unknown as AgentAccount;
+      referrerAuthority = referrerAgentAccount.authority;
+    }
+
+    return this.program.methods
+      .settlePayment(new BN(payerAmount))
+      .accounts({
+        payment: paymentPda,
+        dispute:
+          "disputed" in paymentAccount.status
+            ? this.findDisputePda(paymentPda)[0]
+            : null,
+        escrow: escrowPda,
+        payerAgent: paymentAccount.payer,
+        receiverAgent: paymentAccount.receiver,
+        listing: paymentAccount.service,
+        referrerAgent: paymentAccount.referrer,
+        referrerAuthority,
+        payerAuthority: payerAgentAccount.authority,
+        receiverAuthority: receiverAgentAccount.authority,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(await this.bundleComponentAccounts(paymentAccount.service))
+      .signers([counterparty])
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: executeConditionalRelease
+  // ----------------------------------------------------------
+
+  /**
+   * Release or refund a conditional payment once its conditions decide it.
+   *
+   * Anyone can call this. The escrow goes to the receiver once every
+   * condition holds, or back to the payer once a `deadline` condition has
+   * passed with others still unmet. Oracle, validator and attestation
+   * conditions each take a witness account, passed in condition order;
+   * validators must also sign.
+   *
+   * @param paymentPda - The PDA of the payment
+   * @param witnesses - Price update, validator or attestation accounts, in condition order
+   * @param preimages - Preimages for `hashPreimage` conditions, in condition order
+   * @param validators - Validator keypairs co-signing `validatorSignature` conditions
+   * @returns Transaction signature
+   */
+  async executeConditionalRelease(
+    paymentPda: PublicKey,
+    witnesses: PublicKey[] = [],
+    preimages: Buffer[] = [],
+    validators: Keypair[] = []
+  ): Promise<TransactionSignature> {
+    const signers = new Set(validators.map((kp) => kp.publicKey.toBase58()));
+    return this.program.methods
+      .executeConditionalRelease(preimages)
+      .accounts(await this.conditionalReleaseAccounts(paymentPda))
+      .remainingAccounts(
+        witnesses.map(
+          (pubkey): AccountMeta => ({
+            pubkey,
+            isWritable: false,
+            isSigner: signers.has(pubkey.toBase58()),
+          })
+        )
+      )
+      .signers(validators)
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: claimWithPreimage
+  // ----------------------------------------------------------
+
+  /**
+   * Claim a hashlocked payment as its receiver by revealing the preimages.
+   *
+   * The payer commits to `sha256(preimage)` with a `hashPreimage` condition
+   * when paying (e.g. the hash of a result's decryption key). Revealing
+   * every committed preimage, in condition order, releases the escrow
+   * without the payer coming back online; each is emitted in a
+   * `PreimageRevealed` event. Any other conditions on the payment must hold
+   * too. The SDK wallet must be the receiver's authority.
+   *
+   * @param paymentPda - The PDA of the payment
+   * @param preimages - The committed preimages, in condition order
+   * @param witnesses - Witness accounts for the payment's other conditions, in condition order
+   * @returns Transaction signature
+   */
+  async claimWithPreimage(
+    paymentPda: PublicKey,
+    preimages: Buffer[],
+    witnesses: PublicKey[] = []
+  ): Promise<TransactionSignature> {
+    return this.program.methods
+      .claimWithPreimage(preimages)
+      .accounts({
+        ...(await this.conditionalReleaseAccounts(paymentPda)),
+        receiverAuthority: this.provider.wallet.publicKey,
+      })
+      .remainingAccounts(
+        witnesses.map(
+          (pubkey): AccountMeta => ({ pubkey, isWritable: false, isSigner: false })
+        )
+      )
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: releaseOnTimeout
+  // ----------------------------------------------------------
+
+  /**
+   * Release a delivered payment as its provider once the payer's challenge
+   * window has passed without a dispute.
+   *
+   * Only for payments placed under a `releaseAfterDelivery` timeout policy,
+   * which never carry release conditions; the window starts when the provider calls `markDelivered`. SLA penalties
+   * and referral shares apply as in `releasePayment`.
+   *
+   * @param paymentPda - The PDA of the payment
+   * @returns Transaction signature
+   */
+  async releaseOnTimeout(paymentPda: PublicKey): Promise<TransactionSignature> {
+    // No receipt to verify, so no session key or instructions sysvar
+    const { sessionKey: _sessionKey, instructions: _instructions, ...accounts } =
+      await this.providerReleaseAccounts(paymentPda);
+    return this.program.methods.releaseOnTimeout().accounts(accounts).rpc();
+  }
+
+  /**
+   * Resolve the accounts for a provider-side release (`releaseWithReceipt`,
+   * and `releaseOnTimeout` minus the receipt accounts). A receipt signer other
+   * than the payer authority is looked up as one of the payer's session keys.
+   */
+  private async providerReleaseAccounts(
+    paymentPda: PublicKey,
+    receiptSigner?: PublicKey
+  ) {
+    const paymentAccount =
+      (await this.program.account.payment.fetch(paymentPda)) as unknown as PaymentAccount;
+    const [escrowPda] = this.findEscrowPda(paymentPda);
+    const payerAgentAccount =
+      (await this.program.account.agent.fetch(
+        paymentAccount.payer
+      )) as unknown as AgentAccount;
+
+    let referrerAuthority: PublicKey | null = null;
+    if (paymentAccount.referrer) {
+      const referrerAgentAccount =
+        (await this.program.account.agent.fetch(
+          paymentAccount.referrer
+        )) as unknown as AgentAccount;
+      referrerAuthority = referrerAgentAccount.authority;
+    }
+    const sessionKey =
+      !receiptSigner || receiptSigner.equals(payerAgentAccount.authority)
+        ? null
+        : this.findSessionKeyPda(paymentAccount.payer, receiptSigner)[0];
+
+    return {
+      payment: paymentPda,
+      escrow: escrowPda,
+      payerAgent: paymentAccount.payer,
+      receiverAgent: paymentAccount.receiver,
+      service: paymentAccount.service,
+      payerAuthority: payerAgentAccount.authority,
+      referrerAgent: paymentAccount.referrer,
+      referrerAuthority,
+      sessionKey,
+      instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      receiverAuthority: this.provider.wallet.publicKey,
+    };
+  }
+
+  /** Resolve the accounts shared by `executeConditionalRelease` and `claimWithPreimage`. */
+  private async conditionalReleaseAccounts(paymentPda: PublicKey) {
+    const paymentAccount =
+      (await this.program.account.payment.fetch(paymentPda)) as unknown as PaymentAccount;
+    const [escrowPda] = this.findEscrowPda(paymentPda);
+    const payerAgentAccount =
+      (await this.program.account.agent.fetch(
+        paymentAccount.payer
+      )) as unknown as AgentAccount;
+    const receiverAgentAccount =
+      (await this.program.account.agent.fetch(
+        paymentAccount.receiver
+      )) as unknown as AgentAccount;
+
+    let referrerAuthority: PublicKey | null = null;
+    if (paymentAccount.referrer) {
+      const referrerAgentAccount =
+        (await this.program.account.agent.fetch(
+          paymentAccount.referrer
+        )) as unknown as AgentAccount;
+      referrerAuthority = referrerAgentAccount.authority;
+    }
+
+    return {
+      payment: paymentPda,
+      escrow: escrowPda,
+      payerAgent: paymentAccount.payer,
+      receiverAgent: paymentAccount.receiver,
+      service: paymentAccount.service,
+      payerAuthority: payerAgentAccount.authority,
+      receiverAuthority: receiverAgentAccount.authority,
+      referrerAgent: paymentAccount.referrer,
+      referrerAuthority,
+    };
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: createStream
+  // ----------------------------------------------------------
+
+  /**
+   * Create a streaming payment channel between two agents.
+   *
+   * The payer deposits SOL into a stream vault, and the receiver can
+   * withdraw accumulated funds over time based on the rate_per_second.
+   *
+   * @param receiverAgentPda - The PDA of the receiving agent
+   * @param ratePerSecondSol - Rate in SOL per second (e.g. 0.001 = 0.001 SOL/sec)
+   * @param maxDurationSeconds - Maximum stream duration in seconds
+   * @param depositSol - Optional deposit amount in SOL. If omitted, defaults to rate * duration.
+   * @returns The stream PDA public key
+   *
+   * @example
+   * ```typescript
+   * const streamPda = await sdk.createStream(
+   *   receiverAgentPda,
+   *   0.001,  // 0.001 SOL per second
+   *   3600    // 1 hour max
+   * );
+   * ```
+   */
+  async createStream(
+    receiverAgentPda: PublicKey,
+    ratePerSecondSol: number,
+    maxDurationSeconds: number,
+    depositSol?: number
+  ): Promise<PublicKey> {
+    const [payerAgentPda] = this.findAgentPda();
+    const [streamPda] = this.findStreamPda(payerAgentPda, receiverAgentPda);
+    const [streamVaultPda] = this.findStreamVaultPda(streamPda);
+
+    const rateLamports = solToLamports(ratePerSecondSol);
+    const depositLamports = depositSol
+      ? solToLamports(depositSol)
+      : rateLamports.mul(new BN(maxDurationSeconds));
+
+    await this.program.methods
+      .createStream(
+        rateLamports,
+        new BN(maxDurationSeconds),
+        depositLamports
+      )
+      .accounts({
+        stream: streamPda,
+        streamVault: streamVaultPda,
+        payerAgent: payerAgentPda,
+        receiverAgent: receiverAgentPda,
+        payerAuthority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    return streamPda;
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: withdrawStream
+  // ----------------------------------------------------------
+
+  /**
+   * Withdraw accumulated funds from a streaming payment channel.
+   *
+   * The receiver calls this to claim SOL that has accrued since the last
+   * withdrawal. If the stream is fully consumed or past max duration, the
+   * stream is automatically closed and remaining funds are refunded to the payer.
+   *
+   * @param streamPda - The PDA of the stream to withdraw from
+   * @returns Transaction signature
+   *
+   * @example
+   * ```typescript
+   * await sdk.withdrawStream(streamPda);
+   * ```
+   */
+  async withdrawStream(
+    streamPda: PublicKey
+  ): Promise<TransactionSignature> {
+    const streamAccount =
+      (await this.program.account.stream.fetch(streamPda)) as unknown as StreamAccount;
+    const [streamVaultPda] = this.findStreamVaultPda(streamPda);
+
+    // Fetch the receiver agent to get authority
+    const receiverAgentAccount =
+      (await this.program.account.agent.fetch(
+        streamAccount.receiver
+      )) as unknown as AgentAccount;
+
+    // Fetch the payer agent to get authority for potential refund
+    const payerAgentAccount =
+      (await this.program.account.agent.fetch(
+        streamAccount.payer
+      )) as unknown as AgentAccount;
+
+    return this.program.methods
+      .withdrawStream()
+      .accounts({
+        stream: streamPda,
+        streamVault: streamVaultPda,
+        receiverAgent: streamAccount.receiver,
+        receiverAuthority: receiverAgentAccount.authority,
+        payerAgent: streamAccount.payer,
+        payerAuthority: payerAgentAccount.authority,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: openAuction
+  // ----------------------------------------------------------
+
+  /**
+   * Open an auction on an Auction-priced service owned by the connected wallet.
+   *
+   * @param servicePda - The service PDA (price model must be Auction)
+   * @param reservePriceSol - Minimum acceptable bid in SOL
+   * @param durationSeconds - How long bidding stays open
+   * @param deliveryTimeoutSeconds - Timeout applied to the winner's payment escrow, > 0 (default: 3600)
+   * @returns The auction PDA public key
+   */
+  async openAuction(
+    servicePda: PublicKey,
+    reservePriceSol: number,
+    durationSeconds: number,
+    deliveryTimeoutSeconds: number = 3600
+  ): Promise<PublicKey> {
+    const serviceAccount =
+      (await this.program.account.service.fetch(servicePda)) as unknown as ServiceAccount;
+    const [auctionPda] = this.findAuctionPda(
+      servicePda,
+      serviceAccount.totalOrders
+    );
+    const [auctionVaultPda] = this.findAuctionVaultPda(auctionPda);
+
+    await this.program.methods
+      .openAuction(
+        solToLamports(reservePriceSol),
+        new BN(durationSeconds),
+        new BN(deliveryTimeoutSeconds)
+      )
+      .accounts({
+        auction: auctionPda,
+        auctionVault: auctionVaultPda,
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    return auctionPda;
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: placeBid
+  // ----------------------------------------------------------
+
+  /**
+   * Place an escrowed bid on an open auction.
+   *
+   * The bid is held in the auction vault; if it is later outbid, it is
+   * refunded automatically to the connected wallet.
+   *
+   * @param auctionPda - The auction PDA
+   * @param amountSol - Bid amount in SOL
+   * @param intent - What the bidder wants delivered if they win (max 256 chars)
+   * @param attestationPda - Attestation of the buyer agent, for services with access rules that need one
+   * @returns Transaction signature
+   */
+  async placeBid(
+    auctionPda: PublicKey,
+    amountSol: number,
+    intent: string,
+    attestationPda: PublicKey | null = null
+  ): Promise<TransactionSignature> {
+    const auctionAccount =
+      (await this.program.account.auction.fetch(auctionPda)) as unknown as AuctionAccount;
+    const [auctionVaultPda] = this.findAuctionVaultPda(auctionPda);
+    const [bidderAgentPda] = this.findAgentPda();
+    const hasBid = auctionAccount.bidCount.gtn(0);
+
+    return this.program.methods
+      .placeBid(solToLamports(amountSol), intent)
+      .accounts({
+        auction: auctionPda,
+        auctionVault: auctionVaultPda,
+        bidderAgent: bidderAgentPda,
+        service: auctionAccount.service,
+        buyerAttestation: attestationPda,
+        previousBidderAuthority: hasBid
+          ? auctionAccount.highestBidderAuthority
+          : null,
+        bidderAuthority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: settleAuction
+  // ----------------------------------------------------------
+
+  /**
+   * Settle an ended auction, converting the winning bid into an escrowed payment.
+   *
+   * Anyone may call this once the auction has ended; the caller pays rent for
+   * the new payment account.
+   *
+   * @param auctionPda - The auction PDA
+   * @returns The payment PDA created for the winning bid
+   */
+  async settleAuction(auctionPda: PublicKey): Promise<PublicKey> {
+    const auctionAccount =
+      (await this.program.account.auction.fetch(auctionPda)) as unknown as AuctionAccount;
+    const serviceAccount =
+      (await this.program.account.service.fetch(
+        auctionAccount.service
+      )) as unknown as ServiceAccount;
+    const [auctionVaultPda] = this.findAuctionVaultPda(auctionPda);
+    const [paymentPda] = this.findPaymentPda(
+      auctionAccount.highestBidder,
+      auctionAccount.service,
+      serviceAccount.totalOrders
+    );
+    const [escrowPda] = this.findEscrowPda(paymentPda);
+
+    await this.program.methods
+      .settleAuction()
+      .accounts({
+        auction: auctionPda,
+        auctionVault: auctionVaultPda,
+        payment: paymentPda,
+        escrow: escrowPda,
+        winnerAgent: auctionAccount.highestBidder,
+        service: auctionAccount.service,
+        settler: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    return paymentPda;
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: cancelAuction
+  // ----------------------------------------------------------
+
+  /**
+   * Cancel an auction that has received no bids and reclaim its rent.
+   * @param auctionPda - The auction PDA
+   * @returns Transaction signature
+   */
+  async cancelAuction(auctionPda: PublicKey): Promise<TransactionSignature> {
+    return this.program.methods
+      .cancelAuction()
+      .accounts({
+        auction: auctionPda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: closeAuction
+  // ----------------------------------------------------------
+
+  /**
+   * Close a settled auction and reclaim its rent. The winning bid stays
+   * escrowed in the payment created by `settleAuction`.
+   * @param auctionPda - The auction PDA
+   * @returns Transaction signature
+   */
+  async closeAuction(auctionPda: PublicKey): Promise<TransactionSignature> {
+    return this.program.methods
+      .closeAuction()
+      .accounts({
+        auction: auctionPda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: openUsageEscrow / topUpUsageEscrow / setUsageCap
+  // ----------------------------------------------------------
+
+  /**
+   * Pre-fund a usage escrow for a PerRequest / PerToken service.
+   *
+   * The service's current price per unit is locked in. The provider draws
+   * `units * price` from the escrow by submitting usage reports.
+   *
+   * @param servicePda - The metered service PDA
+   * @param depositSol - Initial deposit in SOL
+   * @param maxUnitsPerReport - Cap on units billable per report (0 = no cap)
+   * @param challengeWindowSeconds - How long the payer can contest an un-co-signed report (default: 3600)
+   * @param attestationPda - Attestation of the buyer agent, for services with access rules that need one
+   * @returns The usage escrow PDA
+   */
+  async openUsageEscrow(
+    servicePda: PublicKey,
+    depositSol: number,
+    maxUnitsPerReport: number = 0,
+    challengeWindowSeconds: number = 3600,
+    attestationPda: PublicKey | null = null
+  ): Promise<PublicKey> {
+    const [payerAgentPda] = this.findAgentPda();
+    const [usageEscrowPda] = this.findUsageEscrowPda(payerAgentPda, servicePda);
+    const [usageVaultPda] = this.findUsageVaultPda(usageEscrowPda);
+
+    await this.program.methods
+      .openUsageEscrow(
+        solToLamports(depositSol),
+        new BN(maxUnitsPerReport),
+        new BN(challengeWindowSeconds)
+      )
+      .accounts({
+        usageEscrow: usageEscrowPda,
+        usageVault: usageVaultPda,
+        payerAgent: payerAgentPda,
+        service: servicePda,
+        buyerAttestation: attestationPda,
+        payerAuthority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    return usageEscrowPda;
+  }
+
+  /**
+   * Add funds to a usage escrow owned by the connected wallet.
+   * @param usageEscrowPda - The usage escrow PDA
+   * @param amountSol - Amount to add in SOL
+   * @returns Transaction signature
+   */
+  async topUpUsageEscrow(
+    usageEscrowPda: PublicKey,
+    amountSol: number
+  ): Promise<TransactionSignature> {
+    const [usageVaultPda] = this.findUsageVaultPda(usageEscrowPda);
+    return this.program.methods
+      .topUpUsageEscrow(solToLamports(amountSol))
+      .accounts({
+        usageEscrow: usageEscrowPda,
+        usageVault: usageVaultPda,
+        payerAuthority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  /**
+   * Change the per-report unit cap on a usage escrow (0 = no cap).
+   * @param usageEscrowPda - The usage escrow PDA
+   * @param maxUnitsPerReport - New cap
+   * @returns Transaction signature
+   */
+  async setUsageCap(
+    usageEscrowPda: PublicKey,
+    maxUnitsPerReport: number
+  ): Promise<TransactionSignature> {
+    return this.program.methods
+      .setUsageCap(new BN(maxUnitsPerReport))
+      .accounts({
+        usageEscrow: usageEscrowPda,
+        payerAuthority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: submitUsageReport
+  // ----------------------------------------------------------
+
+  /**
+   * Report metered usage as the service provider.
+   *
+   * If `payerCoSigner` is given, the payer's authority co-signs and the
+   * report is paid out immediately; otherwise it becomes claimable after the
+   * escrow's challenge window.
+   *
+   * @param usageEscrowPda - The usage escrow PDA
+   * @param units - Tokens or requests consumed
+   * @param payerCoSigner - Optional payer authority keypair co-signing the report
+   * @returns The usage report PDA
+   */
+  async submitUsageReport(
+    usageEscrowPda: PublicKey,
+    units: number,
+    payerCoSigner?: Keypair
+  ): Promise<PublicKey> {
+    const usage =
+      (await this.program.account.usageEscrow.fetch(
+        usageEscrowPda
+      )) as unknown as UsageEscrowAccount;
+    const [usageVaultPda] = this.findUsageVaultPda(usageEscrowPda);
+    const [usageReportPda] = this.findUsageReportPda(
+      usageEscrowPda,
+      usage.reportCount
+    );
+
+    await this.program.methods
+      .submitUsageReport(new BN(units))
+      .accounts({
+        usageEscrow: usageEscrowPda,
+        usageVault: usageVaultPda,
+        usageReport: usageReportPda,
+        service: usage.service,
+        payerAgent: usage.payer,
+        providerAgent: usage.provider,
+        payerAuthority: payerCoSigner ? payerCoSigner.publicKey : null,
+        providerAuthority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers(payerCoSigner ? [payerCoSigner] : [])
+      .rpc();
+
+    return usageReportPda;
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: contestUsageReport / claimUsageReport
+  // ----------------------------------------------------------
+
+  /**
+   * Contest a pending usage report as the payer, before its window closes.
+   * @param usageReportPda - The usage report PDA
+   * @returns Transaction signature
+   */
+  async contestUsageReport(
+    usageReportPda: PublicKey
+  ): Promise<TransactionSignature> {
+    const report =
+      (await this.program.account.usageReport.fetch(
+        usageReportPda
+      )) as unknown as UsageReportAccount;
+    return this.program.methods
+      .contestUsageReport()
+      .accounts({
+        usageEscrow: report.usageEscrow,
+        usageReport: usageReportPda,
+        payerAuthority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  /**
+   * Claim an uncontested usage report as the provider once its window has passed.
+   * @param usageReportPda - The usage report PDA
+   * @returns Transaction signature
+   */
+  async claimUsageReport(
+    usageReportPda: PublicKey
+  ): Promise<TransactionSignature> {
+    const report =
+      (await this.program.account.usageReport.fetch(
+        usageReportPda
+      )) as unknown as UsageReportAccount;
+    const usage =
+      (await this.program.account.usageEscrow.fetch(
+        report.usageEscrow
+      )) as unknown as UsageEscrowAccount;
+    const [usageVaultPda] = this.findUsageVaultPda(report.usageEscrow);
+
+    return this.program.methods
+      .claimUsageReport()
+      .accounts({
+        usageEscrow: report.usageEscrow,
+        usageVault: usageVaultPda,
+        usageReport: usageReportPda,
+        service: usage.service,
+        payerAgent: usage.payer,
+        providerAgent: usage.provider,
+        providerAuthority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: resolveUsageReport / closeUsageReport
+  // ----------------------------------------------------------
+
+  /**
+   * Resolve a contested usage report as the payer by accepting `units` of it
+   * (0 drops it), typically after agreeing with the provider off-chain. The
+   * accepted units are paid out and the report account is closed.
+   * @param usageReportPda - The contested usage report PDA
+   * @param units - Units the payer accepts (at most the reported units)
+   * @returns Transaction signature
+   */
+  async resolveUsageReport(
+    usageReportPda: PublicKey,
+    units: number | BN
+  ): Promise<TransactionSignature> {
+    const report =
+      (await this.program.account.usageReport.fetch(
+        usageReportPda
+      )) as unknown as UsageReportAccount;
+    const usage =
+      (await this.program.account.usageEscrow.fetch(
+        report.usageEscrow
+      )) as unknown as UsageEscrowAccount;
+    const service = (await this.program.account.service.fetch(
+      usage.service
+    )) as unknown as ServiceAccount;
+    const [usageVaultPda] = this.findUsageVaultPda(report.usageEscrow);
+
+    return this.program.methods
+      .resolveUsageReport(new BN(units))
+      .accounts({
+        usageEscrow: report.usageEscrow,
+        usageVault: usageVaultPda,
+        usageReport: usageReportPda,
+        service: usage.service,
+        payerAgent: usage.payer,
+        providerAgent: usage.provider,
+        providerAuthority: service.authority,
+        payerAuthority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  /**
+   * Close a settled usage report, returning its rent to the provider. Anyone
+   * can call this; a usage escrow can only be closed once all of its reports
+   * are.
+   * @param usageReportPda - The settled usage report PDA
+   * @returns Transaction signature
+   */
+  async closeUsageReport(
+    usageReportPda: PublicKey
+  ): Promise<TransactionSignature> {
+    const report =
+      (await this.program.account.usageReport.fetch(
+        usageReportPda
+      )) as unknown as UsageReportAccount;
+    const usage =
+      (await this.program.account.usageEscrow.fetch(
+        report.usageEscrow
+      )) as unknown as UsageEscrowAccount;
+    const service = (await this.program.account.service.fetch(
+      usage.service
+    )) as unknown as ServiceAccount;
+
+    return this.program.methods
+      .closeUsageReport()
+      .accounts({
+        usageEscrow: report.usageEscrow,
+        usageReport: usageReportPda,
+        service: usage.service,
+        providerAuthority: service.authority,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: closeUsageEscrow
+  // ----------------------------------------------------------
+
+  /**
+   * Close a usage escrow and refund its unused balance to the connected wallet.
+   * Every report must have been settled or resolved, and closed, first.
+   * @param usageEscrowPda - The usage escrow PDA
+   * @returns Transaction signature
+   */
+  async closeUsageEscrow(
+    usageEscrowPda: PublicKey
+  ): Promise<TransactionSignature> {
+    const [usageVaultPda] = this.findUsageVaultPda(usageEscrowPda);
+    return this.program.methods
+      .closeUsageEscrow()
+      .accounts({
+        usageEscrow: usageEscrowPda,
+        usageVault: usageVaultPda,
+        payerAuthority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: postJob / cancelJob / closeJob
+  // ----------------------------------------------------------
+
+  /**
+   * Post a job to the job board as the connected wallet's agent.
+   *
+   * @param jobId - Unique identifier for this job (used in PDA derivation)
+   * @param intent - What needs doing (max 256 chars)
+   * @param budgetSol - Maximum price the buyer will accept, in SOL
+   * @param requiredCapabilities - Capabilities a bidder's agent must list
+   * @param deadline - Unix timestamp after which bids are no longer accepted
+   * @returns The job PDA
+   */
+  async postJob(
+    jobId: string,
+    intent: string,
+    budgetSol: number,
+    requiredCapabilities: string[],
+    deadline: number
+  ): Promise<PublicKey> {
+    const [buyerAgentPda] = this.findAgentPda();
+    const [jobPda] = this.findJobPda(buyerAgentPda, jobId);
+
+    await this.program.methods
+      .postJob(
+        jobId,
+        intent,
+        solToLamports(budgetSol),
+        requiredCapabilities,
+        new BN(deadline)
+      )
+      .accounts({
+        job: jobPda,
+        buyerAgent: buyerAgentPda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    return jobPda;
+  }
+
+  /**
+   * Cancel an open job. Outstanding bids can then be closed with
+   * `closeJobBid`, after which `closeJob` reclaims the job's rent.
+   * @param jobPda - The job PDA
+   * @returns Transaction signature
+   */
+  async cancelJob(jobPda: PublicKey): Promise<TransactionSignature> {
+    return this.program.methods
+      .cancelJob()
+      .accounts({
+        job: jobPda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  /**
+   * Close an awarded or cancelled job that has no bids left and reclaim its rent.
+   * @param jobPda - The job PDA
+   * @returns Transaction signature
+   */
+  async closeJob(jobPda: PublicKey): Promise<TransactionSignature> {
+    return this.program.methods
+      .closeJob()
+      .accounts({
+        job: jobPda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: submitJobBid / withdrawJobBid / closeJobBid
+  // ----------------------------------------------------------
+
+  /**
+   * Bid on an open job through one of the connected wallet's services.
+   *
+   * @param jobPda - The job PDA
+   * @param servicePda - The provider's service the work will be billed under
+   * @param priceSol - Quoted price in SOL (must not exceed the job budget)
+   * @param deliverySeconds - Promised delivery time; becomes the payment timeout
+   * @param message - Free-form quote details (max 256 chars)
+   * @returns The job bid PDA
+   */
+  async submitJobBid(
+    jobPda: PublicKey,
+    servicePda: PublicKey,
+    priceSol: number,
+    deliverySeconds: number,
+    message: string = ""
+  ): Promise<PublicKey> {
+    const [providerAgentPda] = this.findAgentPda();
+    const [jobBidPda] = this.findJobBidPda(jobPda, providerAgentPda);
+
+    await this.program.methods
+      .submitJobBid(solToLamports(priceSol), new BN(deliverySeconds), message)
+      .accounts({
+        job: jobPda,
+        jobBid: jobBidPda,
+        providerAgent: providerAgentPda,
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    return jobBidPda;
+  }
+
+  /**
+   * Withdraw a job bid owned by the connected wallet.
+   * @param jobBidPda - The job bid PDA
+   * @returns Transaction signature
+   */
+  async withdrawJobBid(jobBidPda: PublicKey): Promise<TransactionSignature> {
+    const bid =
+      (await this.program.account.jobBid.fetch(
+        jobBidPda
+      )) as unknown as JobBidAccount;
+
+    return this.program.methods
+      .withdrawJobBid()
+      .accounts({
+        job: bid.job,
+        jobBid: jobBidPda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  /**
+   * Close a bid on an awarded or cancelled job. Anyone may call this;
+   * the rent goes back to the bidder.
+   * @param jobBidPda - The job bid PDA
+   * @returns Transaction signature
+   */
+  async closeJobBid(jobBidPda: PublicKey): Promise<TransactionSignature> {
+    const bid =
+      (await this.program.account.jobBid.fetch(
+        jobBidPda
+      )) as unknown as JobBidAccount;
+
+    return this.program.methods
+      .closeJobBid()
+      .accounts({
+        job: bid.job,
+        jobBid: jobBidPda,
+        authority: bid.authority,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: acceptJobBid
+  // ----------------------------------------------------------
+
+  /**
+   * Accept a bid on one of the connected wallet's jobs.
+   *
+   * The bid price is escrowed as a normal payment against the bidder's
+   * service, released or refunded through the usual payment instructions.
+   *
+   * @param jobBidPda - The job bid PDA to accept
+   * @param attestationPda - Attestation of the buyer agent, for services with access rules that need one
+   * @returns The payment PDA
+   */
+  async acceptJobBid(
+    jobBidPda: PublicKey,
+    attestationPda: PublicKey | null = null
+  ): Promise<PublicKey> {
+    const bid =
+      (await this.program.account.jobBid.fetch(
+        jobBidPda
+      )) as unknown as JobBidAccount;
+    const job =
+      (await this.program.account.job.fetch(bid.job)) as unknown as JobAccount;
+    const serviceAccount =
+      (await this.program.account.service.fetch(
+        bid.service
+      )) as unknown as ServiceAccount;
+    const [paymentPda] = this.findPaymentPda(
+      job.buyer,
+      bid.service,
+      serviceAccount.totalOrders
+    );
+    const [escrowPda] = this.findEscrowPda(paymentPda);
+
+    await this.program.methods
+      .acceptJobBid()
+      .accounts({
+        job: bid.job,
+        jobBid: jobBidPda,
+        payment: paymentPda,
+        escrow: escrowPda,
+        buyerAgent: job.buyer,
+        service: bid.service,
+        buyerAttestation: attestationPda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    return paymentPda;
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: subscribe / topUpSubscription
+  // ----------------------------------------------------------
+
+  /**
+   * Subscribe to a Subscription-priced service.
+   *
+   * The deposit is held in a vault and the first period is charged
+   * immediately. Later periods are charged by `chargeSubscription`.
+   *
+   * @param servicePda - The subscription service PDA
+   * @param depositSol - Pre-funded balance in SOL (at least one period)
+   * @param attestationPda - Attestation of the buyer agent, for services with access rules that need one
+   * @returns The subscription PDA
+   */
+  async subscribe(
+    servicePda: PublicKey,
+    depositSol: number,
+    attestationPda: PublicKey | null = null
+  ): Promise<PublicKey> {
+    const service = await this.getService(servicePda);
+    if (!service) {
+      throw new Error(`Service not found: ${servicePda.toBase58()}`);
+    }
+    const terms = service.priceModel.subscription as
+      | { periodSeconds: BN }
+      | undefined;
+    if (!terms) {
+      throw new Error("Service is not subscription-priced");
+    }
+
+    const [subscriberAgentPda] = this.findAgentPda();
+    const [subscriptionPda] = this.findSubscriptionPda(
+      subscriberAgentPda,
+      servicePda
+    );
+    const [subscriptionVaultPda] = this.findSubscriptionVaultPda(
+      subscriptionPda
+    );
+
+    await this.program.methods
+      .subscribe(
+        service.priceLamports,
+        terms.periodSeconds,
+        solToLamports(depositSol)
+      )
+      .accounts({
+        subscription: subscriptionPda,
+        subscriptionVault: subscriptionVaultPda,
+        subscriberAgent: subscriberAgentPda,
+        providerAgent: service.provider,
+        service: servicePda,
+        providerAuthority: service.authority,
+        buyerAttestation: attestationPda,
+        subscriberAuthority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    return subscriptionPda;
+  }
+
+  /**
+   * Add funds to a subscription owned by the connected wallet.
+   * A lapsed subscription becomes active again and can be charged once
+   * its balance covers at least one period.
+   * @param subscriptionPda - The subscription PDA
+   * @param amountSol - Amount to add in SOL
+   * @returns Transaction signature
+   */
+  async topUpSubscription(
+    subscriptionPda: PublicKey,
+    amountSol: number
+  ): Promise<TransactionSignature> {
+    const [subscriptionVaultPda] = this.findSubscriptionVaultPda(
+      subscriptionPda
+    );
+    return this.program.methods
+      .topUpSubscription(solToLamports(amountSol))
+      .accounts({
+        subscription: subscriptionPda,
+        subscriptionVault: subscriptionVaultPda,
+        subscriberAuthority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: chargeSubscription
+  // ----------------------------------------------------------
+
+  /**
+   * Charge the next due period of a subscription. Anyone can call this.
+   *
+   * If the balance cannot cover the period the subscription lapses
+   * instead of failing.
+   *
+   * @param subscriptionPda - The subscription PDA
+   * @returns Transaction signature
+   */
+  async chargeSubscription(
+    subscriptionPda: PublicKey
+  ): Promise<TransactionSignature> {
+    const subscription = await this.getSubscription(subscriptionPda);
+    if (!subscription) {
+      throw new Error(
+        `Subscription not found: ${subscriptionPda.toBase58()}`
+      );
+    }
+    const [subscriptionVaultPda] = this.findSubscriptionVaultPda(
+      subscriptionPda
+    );
+
+    return this.program.methods
+      .chargeSubscription()
+      .accounts({
+        subscription: subscriptionPda,
+        subscriptionVault: subscriptionVaultPda,
+        subscriberAgent: subscription.subscriber,
+        providerAgent: subscription.provider,
+        service: subscription.service,
+        providerAuthority: subscription.providerAuthority,
+        cranker: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: cancelSubscription
+  // ----------------------------------------------------------
+
+  /**
+   * Cancel a subscription, refunding the unused balance.
+   * The current paid period is not refunded.
+   * @param subscriptionPda - The subscription PDA
+   * @returns Transaction signature
+   */
+  async cancelSubscription(
+    subscriptionPda: PublicKey
+  ): Promise<TransactionSignature> {
+    const [subscriptionVaultPda] = this.findSubscriptionVaultPda(
+      subscriptionPda
+    );
+    return this.program.methods
+      .cancelSubscription()
+      .accounts({
+        subscription: subscriptionPda,
+        subscriptionVault: subscriptionVaultPda,
+        subscriberAuthority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: createCoupon / closeCoupon
+  // ----------------------------------------------------------
+
+  /**
+   * Create a discount code for a service owned by the connected wallet.
+   *
+   * Buyers redeem it by passing the coupon PDA to `payForService`; the
+   * escrowed amount must then equal the quote minus the discount. The
+   * discount must leave something to pay: under 100%, or a fixed amount
+   * below the service's unit price.
+   *
+   * @param servicePda - The service the coupon applies to
+   * @param code - Coupon code (max 32 chars)
+   * @param options - Discount, redemption cap, expiry and allowlist
+   * @returns The coupon PDA
+   *
+   * @example
+   * ```typescript
+   * await sdk.createCoupon(servicePda, "LAUNCH20", {
+   *   percent: 20,
+   *   maxRedemptions: 100,
+   * });
+   * ```
+   */
+  async createCoupon(
+    servicePda: PublicKey,
+    code: string,
+    options: CreateCouponOptions
+  ): Promise<PublicKey> {
+    if ((options.percent === undefined) === (options.fixedSol === undefined)) {
+      throw new Error("Specify exactly one of percent or fixedSol");
+    }
+    const discount =
+      options.percent !== undefined
+        ? { percent: { bps: Math.round(options.percent * 100) } }
+        : { fixed: { lamports: solToLamports(options.fixedSol!) } };
+    const [couponPda] = this.findCouponPda(servicePda, code);
 
-// ============================================================
-// HELPERS
-// ============================================================
+    await this.program.methods
+      .createCoupon(
+        code,
+        discount,
+        new BN(options.maxRedemptions ?? 0),
+        new BN(options.expiresAt ?? 0),
+        options.allowedBuyers ?? []
+      )
+      .accounts({
+        coupon: couponPda,
+        service: servicePda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
 
-/**
- * Convert a SOL amount (as a floating-point number) to lamports (as BN).
- * @param sol - Amount in SOL (e.g. 1.5)
- * @returns BN representing lamports
- */
-export function solToLamports(sol: number): BN {
-  return new BN(Math.round(sol * LAMPORTS_PER_SOL));
-}
+    return couponPda;
+  }
 
-/**
- * Convert lamports (as BN) to a SOL amount (floating-point).
- * @param lamports - BN representing lamports
- * @returns number representing SOL
- */
-export function lamportsToSol(lamports: BN): number {
-  return lamports.toNumber() / LAMPORTS_PER_SOL;
-}
+  /**
+   * Withdraw a coupon and reclaim its rent.
+   * @param couponPda - The coupon PDA
+   * @returns Transaction signature
+   */
+  async closeCoupon(couponPda: PublicKey): Promise<TransactionSignature> {
+    return this.program.methods
+      .closeCoupon()
+      .accounts({
+        coupon: couponPda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
 
-/**
- * Convert a PriceModel enum string into the Anchor-compatible variant object.
- * @param model - The PriceModel enum value
- * @returns Anchor-serializable enum variant
- */
-function priceModelToAnchor(
-  model: PriceModel
-): Record<string, Record<string, never>> {
-  const map: Record<PriceModel, string> = {
-    [PriceModel.Fixed]: "fixed",
-    [PriceModel.PerRequest]: "perRequest",
-    [PriceModel.PerSecond]: "perSecond",
-    [PriceModel.PerToken]: "perToken",
-    [PriceModel.Auction]: "auction",
-  };
-  return { [map[model]]: {} };
-}
+  // ----------------------------------------------------------
+  // INSTRUCTION: issueAttestation / revokeAttestation
+  // ----------------------------------------------------------
 
-/**
- * Parse an Anchor enum variant object back to a PriceModel enum value.
- * @param variant - The Anchor enum variant object
- * @returns PriceModel value
- */
-export function parsePriceModel(
-  variant: Record<string, unknown>
-): PriceModel {
-  const key = Object.keys(variant)[0];
-  const map: Record<string, PriceModel> = {
-    fixed: PriceModel.Fixed,
-    perRequest: PriceModel.PerRequest,
-    perSecond: PriceModel.PerSecond,
-    perToken: PriceModel.PerToken,
-    auction: PriceModel.Auction,
-  };
-  return map[key] ?? PriceModel.Fixed;
-}
+  /**
+   * Attest an agent with the connected wallet as issuer.
+   *
+   * Services can require an attestation from a given issuer, or list the
+   * issuer as an organisation in their allow/deny list.
+   *
+   * @param subjectAgentPda - The agent being attested
+   * @param expiresAt - Unix timestamp the attestation expires (default: 0 = never)
+   * @returns The attestation PDA
+   */
+  async issueAttestation(
+    subjectAgentPda: PublicKey,
+    expiresAt: number = 0
+  ): Promise<PublicKey> {
+    const issuer = this.provider.wallet.publicKey;
+    const [attestationPda] = this.findAttestationPda(issuer, subjectAgentPda);
 
-/**
- * Parse an Anchor enum variant object back to a PaymentStatus enum value.
- * @param variant - The Anchor enum variant object
- * @returns PaymentStatus value
- */
-export function parsePaymentStatus(
-  variant: Record<string, unknown>
-): PaymentStatus {
-  const key = Object.keys(variant)[0];
-  const map: Record<string, PaymentStatus> = {
-    escrowed: PaymentStatus.Escrowed,
-    released: PaymentStatus.Released,
-    refunded: PaymentStatus.Refunded,
-    disputed: PaymentStatus.Disputed,
-  };
-  return map[key] ?? PaymentStatus.Escrowed;
-}
+    await this.program.methods
+      .issueAttestation(new BN(expiresAt))
+      .accounts({
+        attestation: attestationPda,
+        subjectAgent: subjectAgentPda,
+        issuer,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
 
-// ============================================================
-// SDK CLASS
-// ============================================================
+    return attestationPda;
+  }
 
-/**
- * SolAgentSDK provides a developer-friendly TypeScript interface for
- * interacting with the SolAgent Economy Protocol on Solana.
- *
- * The protocol enables AI agent identity, reputation staking, on-chain
- * marketplace, escrowed payments, and streaming payment channels.
- *
- * @example
- * ```typescript
- * import { SolAgentSDK } from '@solagent/sdk';
- *
- * const sdk = new SolAgentSDK(connection, wallet);
- * await sdk.registerAgent("MyBot", "AI trading bot", ["trading"], "https://bot.ai");
- * await sdk.stakeReputation(1.5); // 1.5 SOL
- * ```
- */
-export class SolAgentSDK {
-  /** The Anchor program instance. */
-  public readonly program: Program;
+  /**
+   * Revoke an attestation issued by the connected wallet.
+   * @param subjectAgentPda - The attested agent
+   * @returns Transaction signature
+   */
+  async revokeAttestation(
+    subjectAgentPda: PublicKey
+  ): Promise<TransactionSignature> {
+    const issuer = this.provider.wallet.publicKey;
+    const [attestationPda] = this.findAttestationPda(issuer, subjectAgentPda);
+    return this.program.methods
+      .revokeAttestation()
+      .accounts({ attestation: attestationPda, issuer })
+      .rpc();
+  }
 
-  /** The Anchor provider instance. */
-  public readonly provider: AnchorProvider;
+  // ----------------------------------------------------------
+  // INSTRUCTION: createSessionKey / revokeSessionKey
+  // ----------------------------------------------------------
 
-  /** The underlying Solana connection. */
-  public readonly connection: Connection;
+  /**
+   * Authorise a session key to sign delivery receipts for the connected agent.
+   * @param key - The session public key (e.g. a hot key held by the agent runtime)
+   * @param expiresAt - Unix timestamp the key expires (default: 0 = never)
+   * @returns The session key PDA
+   */
+  async createSessionKey(
+    key: PublicKey,
+    expiresAt: number = 0
+  ): Promise<PublicKey> {
+    const [agentPda] = this.findAgentPda();
+    const [sessionKeyPda] = this.findSessionKeyPda(agentPda, key);
 
-  /** The program ID for the SolAgent protocol. */
-  public readonly programId: PublicKey;
+    await this.program.methods
+      .createSessionKey(key, new BN(expiresAt))
+      .accounts({
+        sessionKey: sessionKeyPda,
+        agent: agentPda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
 
-  constructor(
-    connection: Connection,
-    wallet: Wallet,
-    programId: PublicKey = SOLAGENT_PROGRAM_ID
-  ) {
-    this.connection = connection;
-    this.programId = programId;
-    this.provider = new AnchorProvider(connection, wallet, {
-      commitment: "confirmed",
+    return sessionKeyPda;
+  }
+
+  /**
+   * Revoke one of the connected agent's session keys.
+   * @param key - The session public key
+   * @returns Transaction signature
+   */
+  async revokeSessionKey(key: PublicKey): Promise<TransactionSignature> {
+    const [agentPda] = this.findAgentPda();
+    const [sessionKeyPda] = this.findSessionKeyPda(agentPda, key);
+    return this.program.methods
+      .revokeSessionKey()
+      .accounts({
+        sessionKey: sessionKeyPda,
+        agent: agentPda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: updateCapabilities
+  // ----------------------------------------------------------
+
+  /**
+   * Replace the connected agent's capabilities, moving it between
+   * capability indexes.
+   * @param capabilities - The new capability list (max 10)
+   * @returns Transaction signature
+   */
+  async updateCapabilities(
+    capabilities: string[]
+  ): Promise<TransactionSignature> {
+    const [agentPda] = this.findAgentPda();
+    const agent = (await this.program.account.agent.fetch(
+      agentPda
+    )) as unknown as AgentAccount;
+
+    return this.program.methods
+      .updateCapabilities(capabilities)
+      .accounts({
+        agent: agentPda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(
+        this.reindexAccounts(
+          IndexKind.Capability,
+          agentPda,
+          agent.capabilities,
+          capabilities
+        )
+      )
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: migrateAgent
+  // ----------------------------------------------------------
+
+  /**
+   * Grow the connected wallet's agent if it was registered before the
+   * current account layout. The wallet pays the extra rent.
+   * @returns Transaction signature
+   */
+  async migrateAgent(): Promise<TransactionSignature> {
+    const [agentPda] = this.findAgentPda();
+    return this.program.methods
+      .migrateAgent()
+      .accounts({
+        agent: agentPda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: createCategory / updateCategory
+  // ----------------------------------------------------------
+
+  /**
+   * Add a category to the curated taxonomy. Only the protocol authority
+   * can call this.
+   * @param id - Stable numeric id services will reference
+   * @param name - Display name (max 32 chars)
+   * @param description - Description (max 128 chars)
+   * @returns Transaction signature
+   */
+  async createCategory(
+    id: number,
+    name: string,
+    description: string
+  ): Promise<TransactionSignature> {
+    const [categoryPda] = this.findCategoryPda(id);
+    const [protocolPda] = this.findProtocolPda();
+
+    return this.program.methods
+      .createCategory(id, name, description)
+      .accounts({
+        category: categoryPda,
+        protocol: protocolPda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  /**
+   * Rename, redescribe or retire a category. Only the protocol authority
+   * can call this. Retired categories stay on services already using them.
+   * @param id - The category id
+   * @param options - Fields to change (omitted fields are unchanged)
+   * @returns Transaction signature
+   */
+  async updateCategory(
+    id: number,
+    options: { name?: string; description?: string; isActive?: boolean }
+  ): Promise<TransactionSignature> {
+    const [categoryPda] = this.findCategoryPda(id);
+    const [protocolPda] = this.findProtocolPda();
+
+    return this.program.methods
+      .updateCategory(
+        options.name ?? null,
+        options.description ?? null,
+        options.isActive ?? null
+      )
+      .accounts({
+        category: categoryPda,
+        protocol: protocolPda,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
+
+  // ----------------------------------------------------------
+  // INSTRUCTION: createBundle
+  // ----------------------------------------------------------
+
+  /**
+   * List a bundle of other agents' services under the caller's agent.
+   *
+   * Each component's provider earns `shareBps` of every bundle order; the
+   * orchestrator keeps whatever the shares leave. Every component provider
+   * must co-sign, so pass their authority keypairs as `providerSigners`.
+   *
+   * @param bundleId - Unique bundle identifier for this orchestrator (max 32 chars)
+   * @param title - Bundle title (max 64 chars)
+   * @param description - Bundle description (max 256 chars)
+   * @param priceSol - Bundle price in SOL
+   * @param components - Component services and their shares (1-5, distinct services and providers, not the caller's own agent)
+   * @param providerSigners - Authority keypairs of the component providers
+   * @returns The bundle PDA public key
+   */
+  async createBundle(
+    bundleId: string,
+    title: string,
+    description: string,
+    priceSol: number,
+    components: { service: PublicKey; shareBps: number }[],
+    providerSigners: Keypair[] = []
+  ): Promise<PublicKey> {
+    const [agentPda] = this.findAgentPda();
+    const [bundlePda] = this.findBundlePda(agentPda, bundleId);
+    const resolved = await this.resolveBundleComponents(components);
+    const providers = (await this.program.account.agent.fetchMultiple(
+      resolved.map((c) => c.provider)
+    )) as unknown as (AgentAccount | null)[];
+    const providerAuthorities: AccountMeta[] = providers.map((agent, i) => {
+      if (!agent) {
+        throw new Error(
+          `Provider agent not found: ${resolved[i].provider.toBase58()}`
+        );
+      }
+      return { pubkey: agent.authority, isWritable: false, isSigner: true };
     });
-    this.program = new Program(SOLAGENT_IDL, programId, this.provider);
+
+    await this.program.methods
+      .createBundle(
+        bundleId,
+        title,
+        description,
+        solToLamports(priceSol),
+        resolved
+      )
+      .accounts({
+        bundle: bundlePda,
+        agent: agentPda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts([
+        ...this.bundleRatingAccounts(resolved),
+        ...providerAuthorities,
+      ])
+      .signers(providerSigners)
+      .rpc();
+
+    return bundlePda;
   }
 
   // ----------------------------------------------------------
-  // PDA DERIVATIONS
+  // INSTRUCTION: payForBundle / releaseBundlePayment / refundBundlePayment
   // ----------------------------------------------------------
 
   /**
-   * Derive the Protocol PDA.
-   * Seeds: `["protocol"]`
-   * @returns [protocolPda, bump]
+   * Pay for a bundle. The full price is escrowed once and recorded as a
+   * payment whose `service` is the bundle and `receiver` the orchestrator.
+   * @param bundlePda - The PDA of the bundle being paid for
+   * @param intent - What is being requested (max 256 chars)
+   * @param timeoutSeconds - Seconds before the payment can be refunded (default: 3600)
+   * @returns The payment PDA public key
    */
-  findProtocolPda(): [PublicKey, number] {
-    return PublicKey.findProgramAddressSync(
-      [Buffer.from(SEED_PROTOCOL)],
-      this.programId
+  async payForBundle(
+    bundlePda: PublicKey,
+    intent: string,
+    timeoutSeconds: number = 3600
+  ): Promise<PublicKey> {
+    const bundle = (await this.program.account.bundle.fetch(
+      bundlePda
+    )) as unknown as BundleAccount;
+    const [payerAgentPda] = this.findAgentPda();
+    const [paymentPda] = this.findPaymentPda(
+      payerAgentPda,
+      bundlePda,
+      bundle.totalOrders
     );
-  }
+    const [escrowPda] = this.findEscrowPda(paymentPda);
 
-  /**
-   * Derive an Agent PDA for a given authority (wallet).
-   * Seeds: `["agent", authority]`
-   * @param authority - The wallet public key that owns this agent
-   * @returns [agentPda, bump]
-   */
-  findAgentPda(authority?: PublicKey): [PublicKey, number] {
-    const auth = authority ?? this.provider.wallet.publicKey;
-    return PublicKey.findProgramAddressSync(
-      [Buffer.from(SEED_AGENT), auth.toBuffer()],
-      this.programId
-    );
-  }
+    await this.program.methods
+      .payForBundle(bundle.priceLamports, intent, new BN(timeoutSeconds))
+      .accounts({
+        payment: paymentPda,
+        escrow: escrowPda,
+        payerAgent: payerAgentPda,
+        bundle: bundlePda,
+        payerAuthority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
 
-  /**
-   * Derive a Vault PDA for a given agent.
-   * Seeds: `["vault", agentPda]`
-   * @param agentPda - The agent PDA
-   * @returns [vaultPda, bump]
-   */
-  findVaultPda(agentPda: PublicKey): [PublicKey, number] {
-    return PublicKey.findProgramAddressSync(
-      [Buffer.from(SEED_VAULT), agentPda.toBuffer()],
-      this.programId
-    );
+    return paymentPda;
   }
 
   /**
-   * Derive a Feedback PDA for a specific from-agent / to-agent pair.
-   * Seeds: `["feedback", fromAgentPda, toAgentPda]`
-   * @param fromAgentPda - The reviewing agent's PDA
-   * @param toAgentPda - The reviewed agent's PDA
-   * @returns [feedbackPda, bump]
+   * Release a bundle payment as its payer. Each component provider's wallet
+   * receives its share of the escrow and the orchestrator's wallet the rest.
+   * @param paymentPda - The PDA of the bundle payment
+   * @returns Transaction signature
    */
-  findFeedbackPda(
-    fromAgentPda: PublicKey,
-    toAgentPda: PublicKey
-  ): [PublicKey, number] {
-    return PublicKey.findProgramAddressSync(
-      [
-        Buffer.from(SEED_FEEDBACK),
-        fromAgentPda.toBuffer(),
-        toAgentPda.toBuffer(),
-      ],
-      this.programId
-    );
-  }
+  async releaseBundlePayment(
+    paymentPda: PublicKey
+  ): Promise<TransactionSignature> {
+    const payment = (await this.program.account.payment.fetch(
+      paymentPda
+    )) as unknown as PaymentAccount;
+    const bundle = (await this.program.account.bundle.fetch(
+      payment.service
+    )) as unknown as BundleAccount;
+    const orchestrator = (await this.program.account.agent.fetch(
+      bundle.orchestrator
+    )) as unknown as AgentAccount;
 
-  /**
-   * Derive a Service PDA.
-   * Seeds: `["service", agentPda, serviceId]`
-   * @param agentPda - The provider agent PDA
-   * @param serviceId - The unique service identifier string
-   * @returns [servicePda, bump]
-   */
-  findServicePda(
-    agentPda: PublicKey,
-    serviceId: string
-  ): [PublicKey, number] {
-    return PublicKey.findProgramAddressSync(
-      [
-        Buffer.from(SEED_SERVICE),
-        agentPda.toBuffer(),
-        Buffer.from(serviceId),
-      ],
-      this.programId
-    );
+    return this.program.methods
+      .releaseBundlePayment()
+      .accounts({
+        payment: paymentPda,
+        escrow: this.findEscrowPda(paymentPda)[0],
+        payerAgent: payment.payer,
+        bundle: payment.service,
+        orchestratorAgent: bundle.orchestrator,
+        orchestratorAuthority: orchestrator.authority,
+        authority: this.provider.wallet.publicKey,
+      })
+      .remainingAccounts(await this.bundleComponentAccounts(payment.service))
+      .rpc();
   }
 
   /**
-   * Derive a Payment PDA.
-   * Seeds: `["payment", payerAgentPda, servicePda, totalOrders (u64 LE bytes)]`
-   * @param payerAgentPda - The payer agent PDA
-   * @param servicePda - The service PDA being paid for
-   * @param totalOrders - The current total_orders count on the service (used as nonce)
-   * @returns [paymentPda, bump]
+   * Refund a bundle payment to the payer. The payer can call this at any
+   * time; anyone can once the payment has timed out.
+   * @param paymentPda - The PDA of the bundle payment
+   * @returns Transaction signature
    */
-  findPaymentPda(
-    payerAgentPda: PublicKey,
-    servicePda: PublicKey,
-    totalOrders: BN | number
-  ): [PublicKey, number] {
-    const ordersBn =
-      typeof totalOrders === "number" ? new BN(totalOrders) : totalOrders;
-    const orderBytes = ordersBn.toArrayLike(Buffer, "le", 8);
-    return PublicKey.findProgramAddressSync(
-      [
-        Buffer.from(SEED_PAYMENT),
-        payerAgentPda.toBuffer(),
-        servicePda.toBuffer(),
-        orderBytes,
-      ],
-      this.programId
-    );
+  async refundBundlePayment(
+    paymentPda: PublicKey
+  ): Promise<TransactionSignature> {
+    const payment = (await this.program.account.payment.fetch(
+      paymentPda
+    )) as unknown as PaymentAccount;
+    const payer = (await this.program.account.agent.fetch(
+      payment.payer
+    )) as unknown as AgentAccount;
+
+    return this.program.methods
+      .refundBundlePayment()
+      .accounts({
+        payment: paymentPda,
+        escrow: this.findEscrowPda(paymentPda)[0],
+        payerAgent: payment.payer,
+        bundle: payment.service,
+        payerAuthority: payer.authority,
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
   }
 
+  // ----------------------------------------------------------
+  // INSTRUCTION: refreshBundleRating / closeBundle
+  // ----------------------------------------------------------
+
   /**
-   * Derive an Escrow PDA for a given payment.
-   * Seeds: `["escrow", paymentPda]`
-   * @param paymentPda - The payment PDA
-   * @returns [escrowPda, bump]
+   * Recompute a bundle's rating from its components' current ratings and
+   * reputations. Anyone can call this.
+   * @param bundlePda - The PDA of the bundle
+   * @returns Transaction signature
    */
-  findEscrowPda(paymentPda: PublicKey): [PublicKey, number] {
-    return PublicKey.findProgramAddressSync(
-      [Buffer.from(SEED_ESCROW), paymentPda.toBuffer()],
-      this.programId
-    );
+  async refreshBundleRating(
+    bundlePda: PublicKey
+  ): Promise<TransactionSignature> {
+    const bundle = (await this.program.account.bundle.fetch(
+      bundlePda
+    )) as unknown as BundleAccount;
+
+    return this.program.methods
+      .refreshBundleRating()
+      .accounts({ bundle: bundlePda })
+      .remainingAccounts(this.bundleRatingAccounts(bundle.components))
+      .rpc();
   }
 
   /**
-   * Derive a Stream PDA.
-   * Seeds: `["stream", payerAgentPda, receiverAgentPda]`
-   * @param payerAgentPda - The payer agent PDA
-   * @param receiverAgentPda - The receiver agent PDA
-   * @returns [streamPda, bump]
+   * Pause a bundle owned by the connected wallet so it stops accepting orders.
+   * @param bundleId - The bundle identifier
+   * @returns Transaction signature
    */
-  findStreamPda(
-    payerAgentPda: PublicKey,
-    receiverAgentPda: PublicKey
-  ): [PublicKey, number] {
-    return PublicKey.findProgramAddressSync(
-      [
-        Buffer.from(SEED_STREAM),
-        payerAgentPda.toBuffer(),
-        receiverAgentPda.toBuffer(),
-      ],
-      this.programId
-    );
+  async pauseBundle(bundleId: string): Promise<TransactionSignature> {
+    const [agentPda] = this.findAgentPda();
+
+    return this.program.methods
+      .pauseBundle()
+      .accounts({
+        bundle: this.findBundlePda(agentPda, bundleId)[0],
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
   }
 
   /**
-   * Derive a Stream Vault PDA.
-   * Seeds: `["stream_vault", streamPda]`
-   * @param streamPda - The stream PDA
-   * @returns [streamVaultPda, bump]
+   * Resume a paused bundle owned by the connected wallet.
+   * @param bundleId - The bundle identifier
+   * @returns Transaction signature
    */
-  findStreamVaultPda(streamPda: PublicKey): [PublicKey, number] {
-    return PublicKey.findProgramAddressSync(
-      [Buffer.from(SEED_STREAM_VAULT), streamPda.toBuffer()],
-      this.programId
-    );
-  }
+  async resumeBundle(bundleId: string): Promise<TransactionSignature> {
+    const [agentPda] = this.findAgentPda();
 
-  // ----------------------------------------------------------
-  // INSTRUCTION: initializeProtocol
-  // ----------------------------------------------------------
+    return this.program.methods
+      .resumeBundle()
+      .accounts({
+        bundle: this.findBundlePda(agentPda, bundleId)[0],
+        authority: this.provider.wallet.publicKey,
+      })
+      .rpc();
+  }
 
   /**
-   * Initialize the global protocol state. This should only be called once
-   * by the protocol deployer / authority.
-   *
+   * Delist a bundle with no open orders or unclosed payments, refunding its
+   * rent.
+   * @param bundleId - The bundle identifier
    * @returns Transaction signature
    */
-  async initializeProtocol(): Promise<TransactionSignature> {
-    const [protocolPda] = this.findProtocolPda();
+  async closeBundle(bundleId: string): Promise<TransactionSignature> {
+    const [agentPda] = this.findAgentPda();
 
     return this.program.methods
-      .initializeProtocol()
+      .closeBundle()
       .accounts({
-        protocol: protocolPda,
+        bundle: this.findBundlePda(agentPda, bundleId)[0],
         authority: this.provider.wallet.publicKey,
-        systemProgram: SystemProgram.programId,
       })
       .rpc();
   }
 
+  /** Fill in each component's provider from its service. */
+  private async resolveBundleComponents(
+    components: { service: PublicKey; shareBps: number }[]
+  ): Promise<BundleComponent[]> {
+    const resolved: BundleComponent[] = [];
+    for (const { service, shareBps } of components) {
+      const account = (await this.program.account.service.fetch(
+        service
+      )) as unknown as ServiceAccount;
+      resolved.push({ service, provider: account.provider, shareBps });
+    }
+    return resolved;
+  }
+
+  /** Service/provider account pairs read by createBundle and refreshBundleRating. */
+  private bundleRatingAccounts(components: BundleComponent[]): AccountMeta[] {
+    return components.flatMap((c) => [
+      { pubkey: c.service, isWritable: false, isSigner: false },
+      { pubkey: c.provider, isWritable: false, isSigner: false },
+    ]);
+  }
+
   // ----------------------------------------------------------
-  // INSTRUCTION: registerAgent
+  // INSTRUCTION: openDispute / submitEvidence / resolveDispute
   // ----------------------------------------------------------
 
   /**
-   * Register a new AI agent on-chain with identity metadata.
-   *
-   * Creates a PDA account derived from the wallet's public key that stores
-   * the agent's name, description, capabilities, and endpoint.
-   *
-   * @param name - Agent display name (max 32 chars)
-   * @param description - Agent description (max 256 chars)
-   * @param capabilities - List of capability strings (max 10 items, each max 32 chars)
-   * @param endpoint - The agent's API endpoint URL (max 128 chars)
-   * @returns Transaction signature
-   *
-   * @example
-   * ```typescript
-   * await sdk.registerAgent(
-   *   "TranslatorBot",
-   *   "AI-powered translation service",
-   *   ["translation", "nlp", "multilingual"],
-   *   "https://translator.example.com/api"
-   * );
-   * ```
+   * Dispute an escrowed payment as its payer or receiver. The payment can
+   * no longer be released or refunded on timeout until the arbiter rules.
+   * If the service has an arbiter pool, the dispute commits to a future
+   * slot and `drawDisputePanel` draws the panel once it has passed. Under a
+   * `releaseAfterDelivery` policy the payer must dispute before the challenge
+   * window closes.
+   * @param paymentPda - The PDA of the escrowed payment
+   * @param reason - Why the payment is disputed (max 256 chars)
+   * @returns The dispute PDA public key
    */
-  async registerAgent(
-    name: string,
-    description: string,
-    capabilities: string[],
-    endpoint: string
-  ): Promise<TransactionSignature> {
+  async openDispute(paymentPda: PublicKey, reason: string): Promise<PublicKey> {
+    const [disputePda] = this.findDisputePda(paymentPda);
     const [agentPda] = this.findAgentPda();
-
-    return this.program.methods
-      .registerAgent(name, description, capabilities, endpoint)
+    const [protocolPda] = this.findProtocolPda();
+    const payment = (await this.program.account.payment.fetch(
+      paymentPda
+    )) as unknown as PaymentAccount;
+    const service = (await this.program.account.service.fetchNullable(
+      payment.service
+    )) as unknown as ServiceAccount | null;
+
+    await this.program.methods
+      .openDispute(reason)
       .accounts({
+        dispute: disputePda,
+        payment: paymentPda,
         agent: agentPda,
+        listing: payment.service,
+        arbiterPool: service?.arbiterPool ?? null,
+        protocol: protocolPda,
         authority: this.provider.wallet.publicKey,
         systemProgram: SystemProgram.programId,
       })
       .rpc();
+
+    return disputePda;
   }
 
-  // ----------------------------------------------------------
-  // INSTRUCTION: stakeReputation
-  // ----------------------------------------------------------
+  /**
+   * Draw a pooled dispute's panel from the hash of the slot it committed to.
+   * Anyone can call this once that slot has passed. Arbiters whose wallet
+   * is the payer's or receiver's authority are skipped, and if too few
+   * remain the pool authority arbitrates alone. If the slot has aged out of
+   * the SlotHashes sysvar a new one is committed and this must be called
+   * again.
+   * @param disputePda - The PDA of the dispute
+   * @returns Transaction signature
+   */
+  async drawDisputePanel(
+    disputePda: PublicKey
+  ): Promise<TransactionSignature> {
+    const dispute = (await this.program.account.dispute.fetch(
+      disputePda
+    )) as unknown as DisputeAccount;
+    if (!dispute.pool) {
+      throw new Error("Dispute is not routed to an arbiter pool");
+    }
+    const pool = (await this.program.account.arbiterPool.fetch(
+      dispute.pool
+    )) as unknown as ArbiterPoolAccount;
+
+    return this.program.methods
+      .drawDisputePanel()
+      .accounts({
+        dispute: disputePda,
+        arbiterPool: dispute.pool,
+        payerAgent: dispute.payer,
+        receiverAgent: dispute.receiver,
+        slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+      })
+      .remainingAccounts(
+        pool.arbiters.map((pubkey) => ({
+          pubkey,
+          isWritable: true,
+          isSigner: false,
+        }))
+      )
+      .rpc();
+  }
 
   /**
-   * Stake SOL to boost your agent's on-chain reputation score.
-   *
-   * Reputation is calculated with diminishing returns:
-   * `score = log2(staked_sol) * 10 + 50 + feedback_bonus + completion_bonus`
-   *
-   * @param amountSol - Amount of SOL to stake (e.g. 1.5 for 1.5 SOL)
+   * Attach evidence to an open dispute as the payer or receiver. Each
+   * party can submit up to 8 entries.
+   * @param disputePda - The PDA of the dispute
+   * @param hash - 32-byte hash of the evidence content
+   * @param uri - Where the evidence can be fetched (max 128 chars)
    * @returns Transaction signature
-   *
-   * @example
-   * ```typescript
-   * await sdk.stakeReputation(1.5); // Stake 1.5 SOL
-   * ```
    */
-  async stakeReputation(amountSol: number): Promise<TransactionSignature> {
+  async submitEvidence(
+    disputePda: PublicKey,
+    hash: Buffer | number[],
+    uri: string
+  ): Promise<TransactionSignature> {
     const [agentPda] = this.findAgentPda();
-    const [vaultPda] = this.findVaultPda(agentPda);
-    const lamports = solToLamports(amountSol);
 
     return this.program.methods
-      .stakeReputation(lamports)
+      .submitEvidence(Array.from(hash), uri)
       .accounts({
+        dispute: disputePda,
         agent: agentPda,
-        vault: vaultPda,
         authority: this.provider.wallet.publicKey,
-        systemProgram: SystemProgram.programId,
       })
       .rpc();
   }
 
-  // ----------------------------------------------------------
-  // INSTRUCTION: submitFeedback
-  // ----------------------------------------------------------
-
   /**
-   * Submit feedback for another agent after a service interaction.
-   *
-   * A feedback PDA is created for the (fromAgent, toAgent) pair, so each
-   * agent pair can only have one feedback record. The target agent's
-   * reputation score is recalculated to include the new feedback bonus.
-   *
-   * @param toAgentPda - The PDA of the agent being reviewed
-   * @param rating - Rating from 1 (worst) to 5 (best)
-   * @param comment - Feedback comment (max 256 chars)
+   * Rule on a dispute as a panel member (or the protocol authority for
+   * unpooled disputes), collecting this arbiter's fee share. Once a
+   * majority has ruled, the median split is paid out, immediately or after
+   * the pool's appeal window. Votes must land before the dispute's
+   * `voteDeadline`. The receiver's award is paid like a release:
+   * SLA penalty, referral share and bundle component shares come out of it.
+   * @param disputePda - The PDA of the dispute
+   * @param payerAmountSol - SOL awarded to the payer out of the escrow after fees
    * @returns Transaction signature
-   *
-   * @example
-   * ```typescript
-   * await sdk.submitFeedback(targetAgentPda, 5, "Excellent translation quality!");
-   * ```
    */
-  async submitFeedback(
-    toAgentPda: PublicKey,
-    rating: number,
-    comment: string
+  async resolveDispute(
+    disputePda: PublicKey,
+    payerAmountSol: number
   ): Promise<TransactionSignature> {
-    const [fromAgentPda] = this.findAgentPda();
-    const [feedbackPda] = this.findFeedbackPda(fromAgentPda, toAgentPda);
+    const dispute = (await this.program.account.dispute.fetch(
+      disputePda
+    )) as unknown as DisputeAccount;
+    const payment = (await this.program.account.payment.fetch(
+      dispute.payment
+    )) as unknown as PaymentAccount;
 
     return this.program.methods
-      .submitFeedback(rating, comment)
+      .resolveDispute(solToLamports(payerAmountSol))
       .accounts({
-        feedback: feedbackPda,
-        fromAgent: fromAgentPda,
-        toAgent: toAgentPda,
-        authority: this.provider.wallet.publicKey,
-        systemProgram: SystemProgram.programId,
+        ...(await this.disputePayoutAccounts(dispute)),
+        dispute: disputePda,
+        arbiterAccount: dispute.pool
+          ? this.findArbiterPda(dispute.pool)[0]
+          : null,
+        arbiter: this.provider.wallet.publicKey,
       })
+      .remainingAccounts(await this.bundleComponentAccounts(payment.service))
       .rpc();
   }
 
-  // ----------------------------------------------------------
-  // INSTRUCTION: createService
-  // ----------------------------------------------------------
+  /**
+   * Appeal a panel ruling inside the appeal window as the payer or
+   * receiver. The voting arbiters' stake stays locked until it is decided.
+   * @param disputePda - The PDA of the dispute
+   * @returns Transaction signature
+   */
+  async appealDispute(disputePda: PublicKey): Promise<TransactionSignature> {
+    const dispute = (await this.program.account.dispute.fetch(
+      disputePda
+    )) as unknown as DisputeAccount;
+
+    return this.program.methods
+      .appealDispute()
+      .accounts({
+        dispute: disputePda,
+        agent: this.findAgentPda()[0],
+        authority: this.provider.wallet.publicKey,
+      })
+      .remainingAccounts(this.voterAccounts(dispute))
+      .rpc();
+  }
 
   /**
-   * Register a new service on the agent marketplace.
-   *
-   * Services are discoverable on-chain and can be paid for via the
-   * escrowed payment flow.
-   *
-   * @param serviceId - Unique identifier for this service (used in PDA derivation)
-   * @param title - Service display title (max 64 chars)
-   * @param description - Service description (max 256 chars)
-   * @param priceSol - Price in SOL (e.g. 0.001)
-   * @param priceModel - Pricing model (Fixed, PerRequest, PerSecond, PerToken, Auction)
-   * @param tags - Discoverable tags for marketplace search (max 5 items, each max 32 chars)
+   * Decide an appeal as the pool authority. Panel members whose vote
+   * differs from an overturning decision are slashed to the treasury.
+   * @param disputePda - The PDA of the dispute
+   * @param payerAmountSol - SOL awarded to the payer out of the escrow after fees
    * @returns Transaction signature
-   *
-   * @example
-   * ```typescript
-   * await sdk.createService(
-   *   "translate-en-es",
-   *   "English to Spanish Translation",
-   *   "AI-powered EN->ES translation with 99% accuracy",
-   *   0.001,
-   *   PriceModel.PerRequest,
-   *   ["ai", "translation", "nlp"]
-   * );
-   * ```
    */
-  async createService(
-    serviceId: string,
-    title: string,
-    description: string,
-    priceSol: number,
-    priceModel: PriceModel,
-    tags: string[]
+  async resolveAppeal(
+    disputePda: PublicKey,
+    payerAmountSol: number
   ): Promise<TransactionSignature> {
-    const [agentPda] = this.findAgentPda();
-    const [servicePda] = this.findServicePda(agentPda, serviceId);
-    const priceLamports = solToLamports(priceSol);
+    const dispute = (await this.program.account.dispute.fetch(
+      disputePda
+    )) as unknown as DisputeAccount;
+    const [protocolPda] = this.findProtocolPda();
+    const protocol = (await this.program.account.protocol.fetch(
+      protocolPda
+    )) as unknown as ProtocolAccount;
 
     return this.program.methods
-      .createService(
-        serviceId,
-        title,
-        description,
-        priceLamports,
-        priceModelToAnchor(priceModel),
-        tags
-      )
+      .resolveAppeal(solToLamports(payerAmountSol))
       .accounts({
-        service: servicePda,
-        agent: agentPda,
+        ...(await this.disputePayoutAccounts(dispute)),
+        dispute: disputePda,
+        protocol: protocolPda,
+        treasury: protocol.treasury,
         authority: this.provider.wallet.publicKey,
-        systemProgram: SystemProgram.programId,
       })
+      .remainingAccounts([
+        ...this.voterAccounts(dispute),
+        ...(await this.disputeComponentAccounts(dispute)),
+      ])
       .rpc();
   }
 
-  // ----------------------------------------------------------
-  // INSTRUCTION: payForService
-  // ----------------------------------------------------------
-
   /**
-   * Pay for a service with automatic escrow.
-   *
-   * Funds are locked in an escrow PDA until the payer confirms delivery
-   * (releasePayment) or the payment times out / is refunded.
-   *
-   * @param servicePda - The PDA of the service being paid for
-   * @param amountSol - Amount of SOL to escrow
-   * @param intent - Freeform intent string describing what is being requested (max 256 chars)
-   * @param conditions - Optional list of conditions for payment release (max 5 items, each max 64 chars)
-   * @param timeoutSeconds - Seconds before the escrowed payment can be refunded (default: 3600)
-   * @returns The payment PDA public key
-   *
-   * @example
-   * ```typescript
-   * const paymentPda = await sdk.payForService(
-   *   servicePda,
-   *   0.1,
-   *   "Translate 'hello world' to Spanish"
-   * );
-   * ```
+   * Pay out a panel ruling after its appeal window passed unappealed.
+   * Anyone can call this.
+   * @param disputePda - The PDA of the dispute
+   * @returns Transaction signature
    */
-  async payForService(
-    servicePda: PublicKey,
-    amountSol: number,
-    intent: string,
-    conditions: string[] = [],
-    timeoutSeconds: number = 3600
-  ): Promise<PublicKey> {
-    // Fetch the service to get the provider (receiver agent) and current total_orders
-    const serviceAccount =
-      (await this.program.account.service.fetch(servicePda)) as unknown as ServiceAccount;
-    const receiverAgentPda = serviceAccount.provider;
-    const totalOrders = serviceAccount.totalOrders;
-
-    const [payerAgentPda] = this.findAgentPda();
-    const [paymentPda] = this.findPaymentPda(
-      payerAgentPda,
-      servicePda,
-      totalOrders
-    );
-    const [escrowPda] = this.findEscrowPda(paymentPda);
-    const lamports = solToLamports(amountSol);
+  async finalizeDispute(disputePda: PublicKey): Promise<TransactionSignature> {
+    const dispute = (await this.program.account.dispute.fetch(
+      disputePda
+    )) as unknown as DisputeAccount;
 
-    await this.program.methods
-      .payForService(
-        lamports,
-        intent,
-        conditions,
-        new BN(timeoutSeconds)
-      )
+    return this.program.methods
+      .finalizeDispute()
       .accounts({
-        payment: paymentPda,
-        escrow: escrowPda,
-        payerAgent: payerAgentPda,
-        receiverAgent: receiverAgentPda,
-        service: servicePda,
-        payerAuthority: this.provider.wallet.publicKey,
-        systemProgram: SystemProgram.programId,
+        ...(await this.disputePayoutAccounts(dispute)),
+        dispute: disputePda,
       })
+      .remainingAccounts(await this.disputeComponentAccounts(dispute))
       .rpc();
-
-    return paymentPda;
   }
 
-  // ----------------------------------------------------------
-  // INSTRUCTION: releasePayment
-  // ----------------------------------------------------------
-
   /**
-   * Release an escrowed payment to the service provider after delivery.
-   *
-   * Only the payer agent's authority can call this instruction. The escrowed
-   * SOL is transferred to the receiver agent's authority wallet, and both
-   * agents' stats are updated.
-   *
-   * @param paymentPda - The PDA of the payment to release
+   * Refund a dispute whose panel (or the protocol authority, for unpooled
+   * disputes) missed its vote deadline without a majority. Anyone can call
+   * this. Panel members who never voted are slashed to the treasury.
+   * @param disputePda - The PDA of the dispute
    * @returns Transaction signature
-   *
-   * @example
-   * ```typescript
-   * await sdk.releasePayment(paymentPda);
-   * ```
    */
-  async releasePayment(
-    paymentPda: PublicKey
-  ): Promise<TransactionSignature> {
-    // Fetch payment to get related accounts
-    const paymentAccount =
-      (await this.program.account.payment.fetch(paymentPda)) as unknown as PaymentAccount;
-    const [escrowPda] = this.findEscrowPda(paymentPda);
-
-    // Fetch receiver agent to get its authority
-    const receiverAgentAccount =
-      (await this.program.account.agent.fetch(
-        paymentAccount.receiver
-      )) as unknown as AgentAccount;
+  async expireDispute(disputePda: PublicKey): Promise<TransactionSignature> {
+    const dispute = (await this.program.account.dispute.fetch(
+      disputePda
+    )) as unknown as DisputeAccount;
+    const payout = await this.disputePayoutAccounts(dispute);
+    const [protocolPda] = this.findProtocolPda();
+    const protocol = (await this.program.account.protocol.fetch(
+      protocolPda
+    )) as unknown as ProtocolAccount;
 
     return this.program.methods
-      .releasePayment()
+      .expireDispute()
       .accounts({
-        payment: paymentPda,
-        escrow: escrowPda,
-        payerAgent: paymentAccount.payer,
-        receiverAgent: paymentAccount.receiver,
-        service: paymentAccount.service,
-        receiverAuthority: receiverAgentAccount.authority,
-        authority: this.provider.wallet.publicKey,
+        dispute: disputePda,
+        payment: payout.payment,
+        escrow: payout.escrow,
+        payerAgent: payout.payerAgent,
+        payerAuthority: payout.payerAuthority,
+        listing: payout.listing,
+        protocol: protocolPda,
+        treasury: protocol.treasury,
       })
+      .remainingAccounts(
+        dispute.pool
+          ? dispute.arbiters.map((pubkey) => ({
+              pubkey,
+              isWritable: true,
+              isSigner: false,
+            }))
+          : []
+      )
       .rpc();
   }
 
-  // ----------------------------------------------------------
-  // INSTRUCTION: refundPayment
-  // ----------------------------------------------------------
-
   /**
-   * Refund an escrowed payment back to the payer.
-   *
-   * Can be called by the payer at any time, or by anyone after the
-   * payment's timeout has elapsed.
-   *
-   * @param paymentPda - The PDA of the payment to refund
+   * Release a panel member's stake lock once the dispute is resolved or
+   * expired. Anyone can call this; an arbiter can only withdraw once every
+   * dispute it was drawn for has been released.
+   * @param disputePda - The PDA of the dispute
+   * @param arbiterPda - The panel member's `Arbiter` account
    * @returns Transaction signature
-   *
-   * @example
-   * ```typescript
-   * await sdk.refundPayment(paymentPda);
-   * ```
    */
-  async refundPayment(
-    paymentPda: PublicKey
+  async releaseArbiterCase(
+    disputePda: PublicKey,
+    arbiterPda: PublicKey
   ): Promise<TransactionSignature> {
-    const paymentAccount =
-      (await this.program.account.payment.fetch(paymentPda)) as unknown as PaymentAccount;
-    const [escrowPda] = this.findEscrowPda(paymentPda);
+    return this.program.methods
+      .releaseArbiterCase()
+      .accounts({ dispute: disputePda, arbiter: arbiterPda })
+      .rpc();
+  }
 
-    // Fetch payer agent to get authority for refund destination
-    const payerAgentAccount =
-      (await this.program.account.agent.fetch(
-        paymentAccount.payer
+  /** Accounts every dispute payout instruction needs. */
+  private async disputePayoutAccounts(dispute: DisputeAccount) {
+    const payment = (await this.program.account.payment.fetch(
+      dispute.payment
+    )) as unknown as PaymentAccount;
+    const payer = (await this.program.account.agent.fetch(
+      dispute.payer
+    )) as unknown as AgentAccount;
+    const receiver = (await this.program.account.agent.fetch(
+      dispute.receiver
+    )) as unknown as AgentAccount;
+    const referrer = payment.referrer
+      ? ((await this.program.account.agent.fetch(
+          payment.referrer
+        )) as unknown as AgentAccount)
+      : null;
+
+    return {
+      payment: dispute.payment,
+      escrow: this.findEscrowPda(dispute.payment)[0],
+      payerAgent: dispute.payer,
+      receiverAgent: dispute.receiver,
+      payerAuthority: payer.authority,
+      receiverAuthority: receiver.authority,
+      listing: payment.service,
+      referrerAgent: payment.referrer,
+      referrerAuthority: referrer?.authority ?? null,
+    };
+  }
+
+  /** Component accounts for paying out a dispute over a bundle payment. */
+  private async disputeComponentAccounts(
+    dispute: DisputeAccount
+  ): Promise<AccountMeta[]> {
+    const payment = (await this.program.account.payment.fetch(
+      dispute.payment
+    )) as unknown as PaymentAccount;
+    return this.bundleComponentAccounts(payment.service);
+  }
+
+  /**
+   * Each bundle component's provider agent and wallet, in bundle order;
+   * empty when `listing` is a service.
+   */
+  private async bundleComponentAccounts(
+    listing: PublicKey
+  ): Promise<AccountMeta[]> {
+    const bundle = (await this.program.account.bundle.fetchNullable(
+      listing
+    )) as unknown as BundleAccount | null;
+    const remaining: AccountMeta[] = [];
+    for (const component of bundle?.components ?? []) {
+      const provider = (await this.program.account.agent.fetch(
+        component.provider
       )) as unknown as AgentAccount;
+      remaining.push(
+        { pubkey: component.provider, isWritable: true, isSigner: false },
+        { pubkey: provider.authority, isWritable: true, isSigner: false }
+      );
+    }
+    return remaining;
+  }
 
-    return this.program.methods
-      .refundPayment()
-      .accounts({
-        payment: paymentPda,
-        escrow: escrowPda,
-        payerAgent: paymentAccount.payer,
-        payerAuthority: payerAgentAccount.authority,
-        authority: this.provider.wallet.publicKey,
-      })
-      .rpc();
+  /** The voting arbiters' accounts, in vote order. */
+  private voterAccounts(dispute: DisputeAccount): AccountMeta[] {
+    return dispute.votes.map((vote) => ({
+      pubkey: vote.arbiter,
+      isWritable: true,
+      isSigner: false,
+    }));
   }
 
+
   // ----------------------------------------------------------
-  // INSTRUCTION: createStream
+  // INSTRUCTION: createArbiterPool
   // ----------------------------------------------------------
 
   /**
-   * Create a streaming payment channel between two agents.
-   *
-   * The payer deposits SOL into a stream vault, and the receiver can
-   * withdraw accumulated funds over time based on the rate_per_second.
-   *
-   * @param receiverAgentPda - The PDA of the receiving agent
-   * @param ratePerSecondSol - Rate in SOL per second (e.g. 0.001 = 0.001 SOL/sec)
-   * @param maxDurationSeconds - Maximum stream duration in seconds
-   * @param depositSol - Optional deposit amount in SOL. If omitted, defaults to rate * duration.
-   * @returns The stream PDA public key
-   *
-   * @example
-   * ```typescript
-   * const streamPda = await sdk.createStream(
-   *   receiverAgentPda,
-   *   0.001,  // 0.001 SOL per second
-   *   3600    // 1 hour max
-   * );
-   * ```
+   * Create an arbiter pool services can route disputes to. Only the
+   * protocol authority can call this; it also decides appeals.
+   * @param poolId - Stable numeric pool id
+   * @param name - Display name (max 32 chars)
+   * @param options - Pool parameters
+   * @returns The arbiter pool PDA public key
    */
-  async createStream(
-    receiverAgentPda: PublicKey,
-    ratePerSecondSol: number,
-    maxDurationSeconds: number,
-    depositSol?: number
+  async createArbiterPool(
+    poolId: number,
+    name: string,
+    options: {
+      minStakeSol: number;
+      feeBps: number;
+      /** Arbiters per dispute: 1, 3 or 5. */
+      panelSize: number;
+      appealWindowSeconds: number;
+      slashBps: number;
+      /** How long a drawn panel has to rule before the dispute can expire. */
+      voteWindowSeconds: number;
+    }
   ): Promise<PublicKey> {
-    const [payerAgentPda] = this.findAgentPda();
-    const [streamPda] = this.findStreamPda(payerAgentPda, receiverAgentPda);
-    const [streamVaultPda] = this.findStreamVaultPda(streamPda);
-
-    const rateLamports = solToLamports(ratePerSecondSol);
-    const depositLamports = depositSol
-      ? solToLamports(depositSol)
-      : rateLamports.mul(new BN(maxDurationSeconds));
+    const [arbiterPoolPda] = this.findArbiterPoolPda(poolId);
 
     await this.program.methods
-      .createStream(
-        rateLamports,
-        new BN(maxDurationSeconds),
-        depositLamports
+      .createArbiterPool(
+        poolId,
+        name,
+        solToLamports(options.minStakeSol),
+        options.feeBps,
+        options.panelSize,
+        new BN(options.appealWindowSeconds),
+        options.slashBps,
+        new BN(options.voteWindowSeconds)
       )
       .accounts({
-        stream: streamPda,
-        streamVault: streamVaultPda,
-        payerAgent: payerAgentPda,
-        receiverAgent: receiverAgentPda,
-        payerAuthority: this.provider.wallet.publicKey,
+        arbiterPool: arbiterPoolPda,
+        protocol: this.findProtocolPda()[0],
+        authority: this.provider.wallet.publicKey,
         systemProgram: SystemProgram.programId,
       })
       .rpc();
 
-    return streamPda;
+    return arbiterPoolPda;
   }
 
   // ----------------------------------------------------------
-  // INSTRUCTION: withdrawStream
+  // INSTRUCTION: registerArbiter / addArbiterStake / withdrawArbiter
   // ----------------------------------------------------------
 
   /**
-   * Withdraw accumulated funds from a streaming payment channel.
-   *
-   * The receiver calls this to claim SOL that has accrued since the last
-   * withdrawal. If the stream is fully consumed or past max duration, the
-   * stream is automatically closed and remaining funds are refunded to the payer.
-   *
-   * @param streamPda - The PDA of the stream to withdraw from
+   * Join an arbiter pool by staking at least its minimum. Any wallet can
+   * join, whether or not it runs an agent.
+   * @param poolId - The pool id
+   * @param stakeSol - SOL to stake
+   * @returns The arbiter PDA public key
+   */
+  async registerArbiter(poolId: number, stakeSol: number): Promise<PublicKey> {
+    const [arbiterPoolPda] = this.findArbiterPoolPda(poolId);
+    const [arbiterPda] = this.findArbiterPda(arbiterPoolPda);
+
+    await this.program.methods
+      .registerArbiter(solToLamports(stakeSol))
+      .accounts({
+        arbiter: arbiterPda,
+        arbiterPool: arbiterPoolPda,
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    return arbiterPda;
+  }
+
+  /**
+   * Add stake to the connected wallet's arbiter account in a pool.
+   * @param poolId - The pool id
+   * @param amountSol - SOL to add
    * @returns Transaction signature
-   *
-   * @example
-   * ```typescript
-   * await sdk.withdrawStream(streamPda);
-   * ```
    */
-  async withdrawStream(
-    streamPda: PublicKey
+  async addArbiterStake(
+    poolId: number,
+    amountSol: number
   ): Promise<TransactionSignature> {
-    const streamAccount =
-      (await this.program.account.stream.fetch(streamPda)) as unknown as StreamAccount;
-    const [streamVaultPda] = this.findStreamVaultPda(streamPda);
+    const [arbiterPoolPda] = this.findArbiterPoolPda(poolId);
 
-    // Fetch the receiver agent to get authority
-    const receiverAgentAccount =
-      (await this.program.account.agent.fetch(
-        streamAccount.receiver
-      )) as unknown as AgentAccount;
+    return this.program.methods
+      .addArbiterStake(solToLamports(amountSol))
+      .accounts({
+        arbiter: this.findArbiterPda(arbiterPoolPda)[0],
+        authority: this.provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  }
 
-    // Fetch the payer agent to get authority for potential refund
-    const payerAgentAccount =
-      (await this.program.account.agent.fetch(
-        streamAccount.payer
-      )) as unknown as AgentAccount;
+  /**
+   * Leave an arbiter pool and reclaim stake plus rent. Fails while a recent
+   * ruling is inside its appeal window or under appeal, or while any
+   * dispute this arbiter was drawn for has not been released.
+   * @param poolId - The pool id
+   * @returns Transaction signature
+   */
+  async withdrawArbiter(poolId: number): Promise<TransactionSignature> {
+    const [arbiterPoolPda] = this.findArbiterPoolPda(poolId);
 
     return this.program.methods
-      .withdrawStream()
+      .withdrawArbiter()
       .accounts({
-        stream: streamPda,
-        streamVault: streamVaultPda,
-        receiverAgent: streamAccount.receiver,
-        receiverAuthority: receiverAgentAccount.authority,
-        payerAuthority: payerAgentAccount.authority,
+        arbiter: this.findArbiterPda(arbiterPoolPda)[0],
+        arbiterPool: arbiterPoolPda,
         authority: this.provider.wallet.publicKey,
       })
       .rpc();
@@ -1612,6 +9021,312 @@ export class SolAgentSDK {
     }
   }
 
+  /**
+   * Fetch an auction account by its PDA.
+   * @param auctionPda - The auction PDA
+   * @returns The Auction account data, or null if not found
+   */
+  async getAuction(auctionPda: PublicKey): Promise<AuctionAccount | null> {
+    try {
+      return (await this.program.account.auction.fetch(
+        auctionPda
+      )) as unknown as AuctionAccount;
+    } catch {
+      return null;
+    }
+  }
+
+  /**
+   * Fetch a usage escrow account by its PDA.
+   * @param usageEscrowPda - The usage escrow PDA
+   * @returns The UsageEscrow account data, or null if not found
+   */
+  async getUsageEscrow(
+    usageEscrowPda: PublicKey
+  ): Promise<UsageEscrowAccount | null> {
+    try {
+      return (await this.program.account.usageEscrow.fetch(
+        usageEscrowPda
+      )) as unknown as UsageEscrowAccount;
+    } catch {
+      return null;
+    }
+  }
+
+  /**
+   * Fetch a job account by its PDA.
+   * @param jobPda - The job PDA
+   * @returns The Job account data, or null if not found
+   */
+  async getJob(jobPda: PublicKey): Promise<JobAccount | null> {
+    try {
+      return (await this.program.account.job.fetch(
+        jobPda
+      )) as unknown as JobAccount;
+    } catch {
+      return null;
+    }
+  }
+
+  /**
+   * Fetch all bids submitted for a job.
+   * @param jobPda - The job PDA
+   * @returns Array of job bids with their public keys
+   */
+  async getJobBids(
+    jobPda: PublicKey
+  ): Promise<{ publicKey: PublicKey; account: JobBidAccount }[]> {
+    const bids = await this.program.account.jobBid.all([
+      { memcmp: { offset: DISCRIMINATOR_SIZE, bytes: jobPda.toBase58() } },
+    ]);
+    return bids.map((b) => ({
+      publicKey: b.publicKey,
+      account: b.account as unknown as JobBidAccount,
+    }));
+  }
+
+  /**
+   * Fetch a subscription account by its PDA.
+   * @param subscriptionPda - The subscription PDA
+   * @returns The Subscription account data, or null if not found
+   */
+  async getSubscription(
+    subscriptionPda: PublicKey
+  ): Promise<SubscriptionAccount | null> {
+    try {
+      return (await this.program.account.subscription.fetch(
+        subscriptionPda
+      )) as unknown as SubscriptionAccount;
+    } catch {
+      return null;
+    }
+  }
+
+  /**
+   * Check whether an agent currently holds a paid-up subscription to a service.
+   * @param subscriberAgentPda - The subscriber agent PDA
+   * @param servicePda - The service PDA
+   * @returns true if the subscription is active and the current period is paid
+   */
+  async hasActiveSubscription(
+    subscriberAgentPda: PublicKey,
+    servicePda: PublicKey
+  ): Promise<boolean> {
+    const [subscriptionPda] = this.findSubscriptionPda(
+      subscriberAgentPda,
+      servicePda
+    );
+    const subscription = await this.getSubscription(subscriptionPda);
+    if (!subscription || !("active" in subscription.status)) {
+      return false;
+    }
+    const now = Math.floor(Date.now() / 1000);
+    return subscription.paidUntil.toNumber() > now;
+  }
+
+  /**
+   * Fetch a coupon account by its PDA.
+   * @param couponPda - The coupon PDA
+   * @returns The Coupon account data, or null if not found
+   */
+  async getCoupon(couponPda: PublicKey): Promise<CouponAccount | null> {
+    try {
+      return (await this.program.account.coupon.fetch(
+        couponPda
+      )) as unknown as CouponAccount;
+    } catch {
+      return null;
+    }
+  }
+
+  /**
+   * Fetch an attestation account by its PDA.
+   * @param attestationPda - The attestation PDA
+   * @returns The Attestation account data, or null if not found
+   */
+  async getAttestation(
+    attestationPda: PublicKey
+  ): Promise<AttestationAccount | null> {
+    try {
+      return (await this.program.account.attestation.fetch(
+        attestationPda
+      )) as unknown as AttestationAccount;
+    } catch {
+      return null;
+    }
+  }
+
+  /**
+   * List the entries indexed under a tag or capability by filtering index
+   * entry accounts on their kind and key.
+   * @param kind - Tag or Capability
+   * @param value - The tag or capability (normalised before lookup)
+   * @returns Service PDAs for a tag, agent PDAs for a capability
+   */
+  async getIndexEntries(kind: IndexKind, value: string): Promise<PublicKey[]> {
+    const [key] = indexKeys([value]);
+    if (key === undefined) return [];
+    const keyLen = Buffer.alloc(4);
+    keyLen.writeUInt32LE(Buffer.byteLength(key));
+    const prefix = Buffer.concat([
+      Buffer.from([kind === IndexKind.Tag ? 0 : 1]),
+      keyLen,
+      Buffer.from(key),
+    ]);
+    const entries = await this.program.account.indexEntry.all([
+      {
+        memcmp: {
+          offset: DISCRIMINATOR_SIZE,
+          bytes: utils.bytes.bs58.encode(prefix),
+        },
+      },
+    ]);
+    return entries.map(
+      (e) => (e.account as unknown as IndexEntryAccount).entry
+    );
+  }
+
+  /**
+   * List every service carrying a tag, using the on-chain tag index.
+   * @param tag - The tag to look up
+   * @returns Services with their public keys
+   */
+  async findServicesByTag(
+    tag: string
+  ): Promise<{ publicKey: PublicKey; account: ServiceAccount }[]> {
+    const entries = await this.getIndexEntries(IndexKind.Tag, tag);
+    if (entries.length === 0) return [];
+    const accounts = await this.program.account.service.fetchMultiple(entries);
+    return entries.flatMap((publicKey, i) =>
+      accounts[i]
+        ? [{ publicKey, account: accounts[i] as unknown as ServiceAccount }]
+        : []
+    );
+  }
+
+  /**
+   * List every agent advertising a capability, using the on-chain
+   * capability index.
+   * @param capability - The capability to look up
+   * @returns Agents with their public keys
+   */
+  async findAgentsByCapability(
+    capability: string
+  ): Promise<{ publicKey: PublicKey; account: AgentAccount }[]> {
+    const entries = await this.getIndexEntries(
+      IndexKind.Capability,
+      capability
+    );
+    if (entries.length === 0) return [];
+    const accounts = await this.program.account.agent.fetchMultiple(entries);
+    return entries.flatMap((publicKey, i) =>
+      accounts[i]
+        ? [{ publicKey, account: accounts[i] as unknown as AgentAccount }]
+        : []
+    );
+  }
+
+  /**
+   * Fetch a category by id.
+   * @param id - The category id
+   * @returns The category, or null if it doesn't exist
+   */
+  async getCategory(id: number): Promise<CategoryAccount | null> {
+    try {
+      return (await this.program.account.category.fetch(
+        this.findCategoryPda(id)[0]
+      )) as unknown as CategoryAccount;
+    } catch {
+      return null;
+    }
+  }
+
+  /**
+   * Fetch the whole category registry.
+   * @returns Every category, active or retired
+   */
+  async getAllCategories(): Promise<CategoryAccount[]> {
+    const accounts = await this.program.account.category.all();
+    return accounts.map((a) => a.account as unknown as CategoryAccount);
+  }
+
+  /**
+   * Fetch a bundle account.
+   * @param bundlePda - The PDA of the bundle
+   * @returns The bundle, or null if it doesn't exist
+   */
+  async getBundle(bundlePda: PublicKey): Promise<BundleAccount | null> {
+    try {
+      return (await this.program.account.bundle.fetch(
+        bundlePda
+      )) as unknown as BundleAccount;
+    } catch {
+      return null;
+    }
+  }
+
+  /**
+   * Fetch the dispute on a payment.
+   * @param paymentPda - The PDA of the disputed payment
+   * @returns The dispute, or null if the payment was never disputed
+   */
+  async getDispute(paymentPda: PublicKey): Promise<DisputeAccount | null> {
+    try {
+      return (await this.program.account.dispute.fetch(
+        this.findDisputePda(paymentPda)[0]
+      )) as unknown as DisputeAccount;
+    } catch {
+      return null;
+    }
+  }
+
+  /**
+   * Fetch an arbiter pool by id.
+   * @param poolId - The pool id
+   * @returns The pool, or null if it doesn't exist
+   */
+  async getArbiterPool(poolId: number): Promise<ArbiterPoolAccount | null> {
+    try {
+      return (await this.program.account.arbiterPool.fetch(
+        this.findArbiterPoolPda(poolId)[0]
+      )) as unknown as ArbiterPoolAccount;
+    } catch {
+      return null;
+    }
+  }
+
+  /**
+   * Fetch an arbiter account.
+   * @param arbiterPda - The PDA of the arbiter
+   * @returns The arbiter, or null if it doesn't exist
+   */
+  async getArbiter(arbiterPda: PublicKey): Promise<ArbiterAccount | null> {
+    try {
+      return (await this.program.account.arbiter.fetch(
+        arbiterPda
+      )) as unknown as ArbiterAccount;
+    } catch {
+      return null;
+    }
+  }
+
+  /**
+   * Fetch a session key account by its PDA.
+   * @param sessionKeyPda - The session key PDA
+   * @returns The SessionKey account data, or null if not found
+   */
+  async getSessionKey(
+    sessionKeyPda: PublicKey
+  ): Promise<SessionKeyAccount | null> {
+    try {
+      return (await this.program.account.sessionKey.fetch(
+        sessionKeyPda
+      )) as unknown as SessionKeyAccount;
+    } catch {
+      return null;
+    }
+  }
+
   // ----------------------------------------------------------
   // BATCH / QUERY METHODS
   // ----------------------------------------------------------
@@ -1683,6 +9398,11 @@ export class SolAgentSDK {
       );
     }
 
+    if (filter?.category !== undefined) {
+      const category = filter.category;
+      results = results.filter((s) => s.account.categories.includes(category));
+    }
+
     // Apply minimum reputation filter (requires fetching agent accounts)
     if (filter?.minReputation !== undefined) {
       const minRep = filter.minReputation;
@@ -1830,6 +9550,7 @@ export class SolAgentSDK {
     const {
       servicePda,
       amountSol,
+      quantity = 1,
       intent,
       conditions = [],
       timeoutSeconds = 3600,
@@ -1855,6 +9576,7 @@ export class SolAgentSDK {
     const payTxSig = await this.program.methods
       .payForService(
         lamports,
+        new BN(quantity),
         intent,
         conditions,
         new BN(timeoutSeconds)
@@ -1865,6 +9587,9 @@ export class SolAgentSDK {
         payerAgent: payerAgentPda,
         receiverAgent: receiverAgentPda,
         service: servicePda,
+        coupon: null,
+        referrerAgent: null,
+        buyerAttestation: null,
         payerAuthority: this.provider.wallet.publicKey,
         systemProgram: SystemProgram.programId,
       })
@@ -1880,6 +9605,8 @@ export class SolAgentSDK {
         receiverAgent: receiverAgentPda,
         service: servicePda,
         receiverAuthority,
+        referrerAgent: null,
+        referrerAuthority: null,
         authority: this.provider.wallet.publicKey,
       })
       .rpc();
@@ -5,8 +5,12 @@ import {
   PublicKey,
   SystemProgram,
   LAMPORTS_PER_SOL,
+  Ed25519Program,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  SYSVAR_SLOT_HASHES_PUBKEY,
 } from "@solana/web3.js";
 import { expect } from "chai";
+import { createHash } from "crypto";
 
 // The IDL type will be generated by Anchor build. We reference it generically here.
 // When `anchor build` runs, it produces target/types/solagent.ts with the `Solagent` type.
@@ -112,6 +116,45 @@ describe("solagent", () => {
     );
   }
 
+  function findIndexPDA(
+    kind: "tag" | "capability",
+    key: string,
+    entry: PublicKey
+  ): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(`${kind}_index`), Buffer.from(key), entry.toBuffer()],
+      program.programId
+    );
+  }
+
+  // ----------------------------------------------------------------
+  // Helper: discovery index accounts for a tag/capability change of
+  // `entry`, dropped keys first, then added keys (normalised like the
+  // program; blank values are not indexed)
+  // ----------------------------------------------------------------
+  function indexAccounts(
+    kind: "tag" | "capability",
+    entry: PublicKey,
+    oldValues: string[],
+    newValues: string[]
+  ) {
+    const keys = (values: string[]) => [
+      ...new Set(
+        values.map((v) => v.trim().toLowerCase()).filter((k) => k.length > 0)
+      ),
+    ];
+    const oldKeys = keys(oldValues);
+    const newKeys = keys(newValues);
+    return [
+      ...oldKeys.filter((k) => !newKeys.includes(k)),
+      ...newKeys.filter((k) => !oldKeys.includes(k)),
+    ].map((key) => ({
+      pubkey: findIndexPDA(kind, key, entry)[0],
+      isWritable: true,
+      isSigner: false,
+    }));
+  }
+
   // ----------------------------------------------------------------
   // Helper: airdrop SOL to a keypair
   // ----------------------------------------------------------------
@@ -154,6 +197,9 @@ describe("solagent", () => {
         authority: kp.publicKey,
         systemProgram: SystemProgram.programId,
       })
+      .remainingAccounts(
+        indexAccounts("capability", agentPDA, [], capabilities)
+      )
       .signers([kp])
       .rpc();
     return agentPDA;
@@ -245,6 +291,14 @@ describe("solagent", () => {
           authority: agentAKeypair.publicKey,
           systemProgram: SystemProgram.programId,
         })
+        .remainingAccounts(
+          indexAccounts(
+            "capability",
+            agentAPDA,
+            [],
+            ["inference", "summarization", "translation"]
+          )
+        )
         .signers([agentAKeypair])
         .rpc();
 
@@ -286,6 +340,12 @@ describe("solagent", () => {
           authority: agentBKeypair.publicKey,
           systemProgram: SystemProgram.programId,
         })
+        .remainingAccounts(
+          indexAccounts("capability", agentBPDA, [], [
+            "image-gen",
+            "upscaling",
+          ])
+        )
         .signers([agentBKeypair])
         .rpc();
 
@@ -620,6 +680,13 @@ describe("solagent", () => {
           authority: agentAKeypair.publicKey,
           systemProgram: SystemProgram.programId,
         })
+        .remainingAccounts(
+          indexAccounts("tag", servicePDA, [], [
+            "nlp",
+            "summarization",
+            "gpt4",
+          ])
+        )
         .signers([agentAKeypair])
         .rpc();
 
@@ -734,6 +801,7 @@ describe("solagent", () => {
           authority: agentBKeypair.publicKey,
           systemProgram: SystemProgram.programId,
         })
+        .remainingAccounts(indexAccounts("tag", svcPDA, [], ["nlp"]))
         .signers([agentBKeypair])
         .rpc();
 
@@ -768,6 +836,7 @@ describe("solagent", () => {
             authority: agentAKeypair.publicKey,
             systemProgram: SystemProgram.programId,
           })
+          .remainingAccounts(indexAccounts("tag", svcPDA, [], ["test"]))
           .signers([agentAKeypair])
           .rpc();
 
@@ -798,12 +867,17 @@ describe("solagent", () => {
       const payerBalBefore = await provider.connection.getBalance(
         agentBKeypair.publicKey
       );
+      const deliverBy = Math.floor(Date.now() / 1000) + 60;
 
       await program.methods
         .payForService(
           paymentAmount,
+          new anchor.BN(5), // 5 requests at 0.01 SOL each
           "Summarize my 10-page document",
-          ["deliver within 60s", "min 500 words"],
+          [
+            { notBefore: { at: new anchor.BN(deliverBy - 60) } },
+            { deadline: { at: new anchor.BN(deliverBy) } },
+          ],
           new anchor.BN(3600) // 1 hour timeout
         )
         .accounts({
@@ -812,6 +886,9 @@ describe("solagent", () => {
           payerAgent: agentBPDA,
           receiverAgent: agentAPDA,
           service: servicePDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
           payerAuthority: agentBKeypair.publicKey,
           systemProgram: SystemProgram.programId,
         })
@@ -824,11 +901,10 @@ describe("solagent", () => {
       expect(payment.receiver.toBase58()).to.equal(agentAPDA.toBase58());
       expect(payment.service.toBase58()).to.equal(servicePDA.toBase58());
       expect(payment.amount.toNumber()).to.equal(paymentAmount.toNumber());
+      expect(payment.quantity.toNumber()).to.equal(5);
       expect(payment.intent).to.equal("Summarize my 10-page document");
-      expect(payment.conditions).to.deep.equal([
-        "deliver within 60s",
-        "min 500 words",
-      ]);
+      expect(payment.conditions).to.have.length(2);
+      expect(payment.conditions[1].deadline.at.toNumber()).to.equal(deliverBy);
       expect(payment.status).to.deep.equal({ escrowed: {} });
       expect(payment.completedAt.toNumber()).to.equal(0);
 
@@ -861,6 +937,7 @@ describe("solagent", () => {
         await program.methods
           .payForService(
             new anchor.BN(0),
+            new anchor.BN(1),
             "Zero pay",
             [],
             new anchor.BN(3600)
@@ -871,6 +948,9 @@ describe("solagent", () => {
             payerAgent: agentBPDA,
             receiverAgent: agentAPDA,
             service: servicePDA,
+            coupon: null,
+            referrerAgent: null,
+            buyerAttestation: null,
             payerAuthority: agentBKeypair.publicKey,
             systemProgram: SystemProgram.programId,
           })
@@ -895,6 +975,7 @@ describe("solagent", () => {
         await program.methods
           .payForService(
             new anchor.BN(1000),
+            new anchor.BN(1),
             "I".repeat(257),
             [],
             new anchor.BN(3600)
@@ -905,6 +986,9 @@ describe("solagent", () => {
             payerAgent: agentBPDA,
             receiverAgent: agentAPDA,
             service: servicePDA,
+            coupon: null,
+            referrerAgent: null,
+            buyerAttestation: null,
             payerAuthority: agentBKeypair.publicKey,
             systemProgram: SystemProgram.programId,
           })
@@ -916,6 +1000,82 @@ describe("solagent", () => {
       }
     });
 
+    it("fails when amount does not match price * quantity", async () => {
+      const svc = await program.account.service.fetch(servicePDA);
+      const [pmtPDA] = findPaymentPDA(
+        agentBPDA,
+        servicePDA,
+        svc.totalOrders.toNumber()
+      );
+      const [escPDA] = findEscrowPDA(pmtPDA);
+
+      try {
+        await program.methods
+          .payForService(
+            new anchor.BN(0.01 * LAMPORTS_PER_SOL),
+            new anchor.BN(3), // 3 requests cost 0.03 SOL
+            "Underpaying",
+            [],
+            new anchor.BN(3600)
+          )
+          .accounts({
+            payment: pmtPDA,
+            escrow: escPDA,
+            payerAgent: agentBPDA,
+            receiverAgent: agentAPDA,
+            service: servicePDA,
+            coupon: null,
+            referrerAgent: null,
+            buyerAttestation: null,
+            payerAuthority: agentBKeypair.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([agentBKeypair])
+          .rpc();
+        expect.fail("Should have thrown AmountMismatch");
+      } catch (err: any) {
+        expect(err.toString()).to.include("AmountMismatch");
+      }
+    });
+
+    it("fails when receiver agent is not the service provider", async () => {
+      const svc = await program.account.service.fetch(servicePDA);
+      const [pmtPDA] = findPaymentPDA(
+        agentBPDA,
+        servicePDA,
+        svc.totalOrders.toNumber()
+      );
+      const [escPDA] = findEscrowPDA(pmtPDA);
+
+      try {
+        await program.methods
+          .payForService(
+            new anchor.BN(0.01 * LAMPORTS_PER_SOL),
+            new anchor.BN(1),
+            "Redirected payment",
+            [],
+            new anchor.BN(3600)
+          )
+          .accounts({
+            payment: pmtPDA,
+            escrow: escPDA,
+            payerAgent: agentBPDA,
+            receiverAgent: agentBPDA, // not agent A's service
+            service: servicePDA,
+            coupon: null,
+            referrerAgent: null,
+            buyerAttestation: null,
+            payerAuthority: agentBKeypair.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([agentBKeypair])
+          .rpc();
+        expect.fail("Should have thrown ProviderMismatch");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ProviderMismatch");
+      }
+    });
+
     // Store paymentPDA/escrowPDA for release_payment tests
     after(() => {
       // Make these available to subsequent describe blocks via closure
@@ -952,6 +1112,8 @@ describe("solagent", () => {
           receiverAgent: agentAPDA,
           service: servicePDA,
           receiverAuthority: agentAKeypair.publicKey,
+          referrerAgent: null,
+          referrerAuthority: null,
           authority: agentBKeypair.publicKey,
         })
         .signers([agentBKeypair])
@@ -989,6 +1151,8 @@ describe("solagent", () => {
             receiverAgent: agentAPDA,
             service: servicePDA,
             receiverAuthority: agentAKeypair.publicKey,
+            referrerAgent: null,
+            referrerAuthority: null,
             authority: agentBKeypair.publicKey,
           })
           .signers([agentBKeypair])
@@ -1013,6 +1177,7 @@ describe("solagent", () => {
       await program.methods
         .payForService(
           new anchor.BN(0.01 * LAMPORTS_PER_SOL),
+          new anchor.BN(1),
           "Test unauthorized release",
           [],
           new anchor.BN(3600)
@@ -1023,6 +1188,9 @@ describe("solagent", () => {
           payerAgent: agentBPDA,
           receiverAgent: agentAPDA,
           service: servicePDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
           payerAuthority: agentBKeypair.publicKey,
           systemProgram: SystemProgram.programId,
         })
@@ -1040,6 +1208,8 @@ describe("solagent", () => {
             receiverAgent: agentBPDA,
             service: servicePDA,
             receiverAuthority: agentBKeypair.publicKey,
+            referrerAgent: null,
+            referrerAuthority: null,
             authority: agentAKeypair.publicKey,
           })
           .signers([agentAKeypair])
@@ -1049,6 +1219,28 @@ describe("solagent", () => {
         expect(err).to.exist;
       }
 
+      // The payer cannot route the release to a wallet other than the receiver's
+      try {
+        await program.methods
+          .releasePayment()
+          .accounts({
+            payment: newPmtPDA,
+            escrow: newEscPDA,
+            payerAgent: agentBPDA,
+            receiverAgent: agentAPDA,
+            service: servicePDA,
+            receiverAuthority: agentBKeypair.publicKey, // payer's own wallet
+            referrerAgent: null,
+            referrerAuthority: null,
+            authority: agentBKeypair.publicKey,
+          })
+          .signers([agentBKeypair])
+          .rpc();
+        expect.fail("Should have thrown ProviderMismatch");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ProviderMismatch");
+      }
+
       // Store for refund test
       (describe as any)._newPmtPDA = newPmtPDA;
       (describe as any)._newEscPDA = newEscPDA;
@@ -1080,6 +1272,7 @@ describe("solagent", () => {
           payment: refundPaymentPDA,
           escrow: refundEscrowPDA,
           payerAgent: agentBPDA,
+          service: servicePDA,
           payerAuthority: agentBKeypair.publicKey,
           authority: agentBKeypair.publicKey,
         })
@@ -1108,6 +1301,7 @@ describe("solagent", () => {
             payment: refundPaymentPDA,
             escrow: refundEscrowPDA,
             payerAgent: agentBPDA,
+            service: servicePDA,
             payerAuthority: agentBKeypair.publicKey,
             authority: agentBKeypair.publicKey,
           })
@@ -1132,6 +1326,7 @@ describe("solagent", () => {
       await program.methods
         .payForService(
           new anchor.BN(0.01 * LAMPORTS_PER_SOL),
+          new anchor.BN(1),
           "Test non-payer refund",
           [],
           new anchor.BN(99999) // long timeout
@@ -1142,6 +1337,9 @@ describe("solagent", () => {
           payerAgent: agentBPDA,
           receiverAgent: agentAPDA,
           service: servicePDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
           payerAuthority: agentBKeypair.publicKey,
           systemProgram: SystemProgram.programId,
         })
@@ -1156,6 +1354,7 @@ describe("solagent", () => {
             payment: freshPmtPDA,
             escrow: freshEscPDA,
             payerAgent: agentBPDA,
+            service: servicePDA,
             payerAuthority: agentBKeypair.publicKey,
             authority: agentAKeypair.publicKey, // not the payer
           })
@@ -1165,6 +1364,25 @@ describe("solagent", () => {
       } catch (err: any) {
         expect(err.toString()).to.include("RefundNotAllowed");
       }
+
+      // Passing their own agent as the payer doesn't make them the payer
+      try {
+        await program.methods
+          .refundPayment()
+          .accounts({
+            payment: freshPmtPDA,
+            escrow: freshEscPDA,
+            payerAgent: agentAPDA,
+            service: servicePDA,
+            payerAuthority: agentAKeypair.publicKey,
+            authority: agentAKeypair.publicKey,
+          })
+          .signers([agentAKeypair])
+          .rpc();
+        expect.fail("Should have thrown Unauthorized");
+      } catch (err: any) {
+        expect(err.toString()).to.include("Unauthorized");
+      }
     });
   });
 
@@ -1310,6 +1528,7 @@ describe("solagent", () => {
           streamVault: streamVaultPDA,
           receiverAgent: agentAPDA,
           receiverAuthority: agentAKeypair.publicKey,
+          payerAgent: agentBPDA,
           payerAuthority: agentBKeypair.publicKey,
           authority: agentAKeypair.publicKey,
         })
@@ -1343,6 +1562,7 @@ describe("solagent", () => {
           streamVault: streamVaultPDA,
           receiverAgent: agentAPDA,
           receiverAuthority: agentAKeypair.publicKey,
+          payerAgent: agentBPDA,
           payerAuthority: agentBKeypair.publicKey,
           authority: agentAKeypair.publicKey,
         })
@@ -1394,6 +1614,7 @@ describe("solagent", () => {
           authority: receiverKp.publicKey,
           systemProgram: SystemProgram.programId,
         })
+        .remainingAccounts(indexAccounts("tag", svcPDA, [], ["e2e"]))
         .signers([receiverKp])
         .rpc();
     });
@@ -1420,6 +1641,7 @@ describe("solagent", () => {
       await program.methods
         .payForService(
           payAmount,
+          new anchor.BN(1),
           "Full flow test payment",
           ["condition-a"],
           new anchor.BN(3600)
@@ -1430,6 +1652,9 @@ describe("solagent", () => {
           payerAgent: payerAgentPDA,
           receiverAgent: receiverAgentPDA,
           service: svcPDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
           payerAuthority: payerKp.publicKey,
           systemProgram: SystemProgram.programId,
         })
@@ -1449,6 +1674,8 @@ describe("solagent", () => {
           receiverAgent: receiverAgentPDA,
           service: svcPDA,
           receiverAuthority: receiverKp.publicKey,
+          referrerAgent: null,
+          referrerAuthority: null,
           authority: payerKp.publicKey,
         })
         .signers([payerKp])
@@ -1523,6 +1750,7 @@ describe("solagent", () => {
           authority: receiverKp.publicKey,
           systemProgram: SystemProgram.programId,
         })
+        .remainingAccounts(indexAccounts("tag", svcPDA, [], ["refund"]))
         .signers([receiverKp])
         .rpc();
     });
@@ -1544,6 +1772,7 @@ describe("solagent", () => {
       await program.methods
         .payForService(
           payAmount,
+          new anchor.BN(1),
           "Payment that will be refunded",
           [],
           new anchor.BN(3600)
@@ -1554,6 +1783,9 @@ describe("solagent", () => {
           payerAgent: payerAgentPDA,
           receiverAgent: receiverAgentPDA,
           service: svcPDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
           payerAuthority: payerKp.publicKey,
           systemProgram: SystemProgram.programId,
         })
@@ -1576,6 +1808,7 @@ describe("solagent", () => {
           payment: pmtPDA,
           escrow: escPDA,
           payerAgent: payerAgentPDA,
+          service: svcPDA,
           payerAuthority: payerKp.publicKey,
           authority: payerKp.publicKey,
         })
@@ -1660,6 +1893,7 @@ describe("solagent", () => {
           streamVault: streamVaultPDA,
           receiverAgent: receiverAgentPDA,
           receiverAuthority: receiverKp.publicKey,
+          payerAgent: payerAgentPDA,
           payerAuthority: payerKp.publicKey,
           authority: receiverKp.publicKey,
         })
@@ -1674,6 +1908,28 @@ describe("solagent", () => {
       );
       expect(receiverBalAfter).to.be.greaterThan(receiverBalBefore);
     });
+
+    it("rejects a withdrawal that routes the payer refund elsewhere", async () => {
+      const stranger = await createFundedKeypair();
+      try {
+        await program.methods
+          .withdrawStream()
+          .accounts({
+            stream: streamPDA,
+            streamVault: streamVaultPDA,
+            receiverAgent: receiverAgentPDA,
+            receiverAuthority: receiverKp.publicKey,
+            payerAgent: payerAgentPDA,
+            payerAuthority: stranger.publicKey,
+            authority: receiverKp.publicKey,
+          })
+          .signers([receiverKp])
+          .rpc();
+        expect.fail("Should have thrown Unauthorized");
+      } catch (err: any) {
+        expect(err.toString()).to.include("Unauthorized");
+      }
+    });
   });
 
   // ================================================================
@@ -1837,6 +2093,7 @@ describe("solagent", () => {
           authority: kp.publicKey,
           systemProgram: SystemProgram.programId,
         })
+        .remainingAccounts(indexAccounts("capability", pda, [], ["test"]))
         .signers([kp])
         .rpc();
 
@@ -1865,6 +2122,14 @@ describe("solagent", () => {
           authority: kp.publicKey,
           systemProgram: SystemProgram.programId,
         })
+        .remainingAccounts(
+          indexAccounts(
+            "tag",
+            svcPDA,
+            [],
+            ["tag1", "tag2", "tag3", "tag4", "tag5"]
+          )
+        )
         .signers([kp])
         .rpc();
 
@@ -1981,4 +2246,5570 @@ describe("solagent", () => {
       expect(stream.isActive).to.be.true;
     });
   });
+
+  // ================================================================
+  // SERVICE LIFECYCLE: UPDATE -> PAUSE -> RESUME -> CLOSE
+  // ================================================================
+  describe("service lifecycle", () => {
+    let providerKp: Keypair;
+    let buyerKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let buyerAgentPDA: PublicKey;
+    let svcPDA: PublicKey;
+    let pmtPDA: PublicKey;
+    let escPDA: PublicKey;
+    const lifecycleSvcId = "lifecycle-svc";
+    const initialPrice = new anchor.BN(0.01 * LAMPORTS_PER_SOL);
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      buyerKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(
+        providerKp,
+        "LifecycleProv"
+      );
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "LifecycleBuyer");
+
+      const [sPDA] = findServicePDA(providerAgentPDA, lifecycleSvcId);
+      svcPDA = sPDA;
+      await program.methods
+        .createService(
+          lifecycleSvcId,
+          "Lifecycle Service",
+          "Gets edited, paused and closed",
+          initialPrice,
+          { fixed: {} },
+          ["lifecycle"]
+        )
+        .accounts({
+          service: svcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(indexAccounts("tag", svcPDA, [], ["lifecycle"]))
+        .signers([providerKp])
+        .rpc();
+    });
+
+    it("escrows an order at the original price", async () => {
+      const [pPDA] = findPaymentPDA(buyerAgentPDA, svcPDA, 0);
+      pmtPDA = pPDA;
+      [escPDA] = findEscrowPDA(pmtPDA);
+
+      await program.methods
+        .payForService(
+          initialPrice,
+          new anchor.BN(1),
+          "Order before update",
+          [],
+          new anchor.BN(3600)
+        )
+        .accounts({
+          payment: pmtPDA,
+          escrow: escPDA,
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: svcPDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+
+      const svc = await program.account.service.fetch(svcPDA);
+      expect(svc.openOrders.toNumber()).to.equal(1);
+      expect(svc.version.toNumber()).to.equal(1);
+
+      const payment = await program.account.payment.fetch(pmtPDA);
+      expect(payment.serviceVersion.toNumber()).to.equal(1);
+      expect(payment.servicePrice.toNumber()).to.equal(initialPrice.toNumber());
+    });
+
+    it("provider updates title, price and tags", async () => {
+      await program.methods
+        .updateService(
+          "Lifecycle Service v2",
+          null,
+          new anchor.BN(0.02 * LAMPORTS_PER_SOL),
+          null,
+          ["lifecycle", "v2"]
+        )
+        .accounts({
+          service: svcPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          indexAccounts("tag", svcPDA, ["lifecycle"], ["lifecycle", "v2"])
+        )
+        .signers([providerKp])
+        .rpc();
+
+      const svc = await program.account.service.fetch(svcPDA);
+      expect(svc.title).to.equal("Lifecycle Service v2");
+      expect(svc.description).to.equal("Gets edited, paused and closed");
+      expect(svc.priceLamports.toNumber()).to.equal(0.02 * LAMPORTS_PER_SOL);
+      expect(svc.tags).to.deep.equal(["lifecycle", "v2"]);
+      expect(svc.version.toNumber()).to.equal(2);
+
+      // Already-escrowed payment keeps its original amount and terms version
+      const payment = await program.account.payment.fetch(pmtPDA);
+      expect(payment.amount.toNumber()).to.equal(initialPrice.toNumber());
+      expect(payment.serviceVersion.toNumber()).to.equal(1);
+    });
+
+    it("does not bump the version when nothing changes", async () => {
+      await program.methods
+        .updateService("Lifecycle Service v2", null, null, null, null)
+        .accounts({
+          service: svcPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+
+      const svc = await program.account.service.fetch(svcPDA);
+      expect(svc.version.toNumber()).to.equal(2);
+    });
+
+    it("fails when a non-authority updates the service", async () => {
+      try {
+        await program.methods
+          .updateService("Hijacked", null, null, null, null)
+          .accounts({
+            service: svcPDA,
+            authority: buyerKp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([buyerKp])
+          .rpc();
+        expect.fail("Should have thrown Unauthorized");
+      } catch (err: any) {
+        expect(err.toString()).to.include("Unauthorized");
+      }
+    });
+
+    it("paused service rejects new payments", async () => {
+      await program.methods
+        .pauseService()
+        .accounts({ service: svcPDA, authority: providerKp.publicKey })
+        .signers([providerKp])
+        .rpc();
+
+      let svc = await program.account.service.fetch(svcPDA);
+      expect(svc.isActive).to.be.false;
+
+      const [pPDA] = findPaymentPDA(
+        buyerAgentPDA,
+        svcPDA,
+        svc.totalOrders.toNumber()
+      );
+      const [ePDA] = findEscrowPDA(pPDA);
+      try {
+        await program.methods
+          .payForService(
+            svc.priceLamports,
+            new anchor.BN(1),
+            "Order while paused",
+            [],
+            new anchor.BN(3600)
+          )
+          .accounts({
+            payment: pPDA,
+            escrow: ePDA,
+            payerAgent: buyerAgentPDA,
+            receiverAgent: providerAgentPDA,
+            service: svcPDA,
+            coupon: null,
+            referrerAgent: null,
+            buyerAttestation: null,
+            payerAuthority: buyerKp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([buyerKp])
+          .rpc();
+        expect.fail("Should have thrown ServiceInactive");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ServiceInactive");
+      }
+
+      await program.methods
+        .resumeService()
+        .accounts({ service: svcPDA, authority: providerKp.publicKey })
+        .signers([providerKp])
+        .rpc();
+
+      svc = await program.account.service.fetch(svcPDA);
+      expect(svc.isActive).to.be.true;
+    });
+
+    it("cannot close a service with open orders", async () => {
+      try {
+        await program.methods
+          .closeService()
+          .accounts({ service: svcPDA, authority: providerKp.publicKey })
+          .signers([providerKp])
+          .rpc();
+        expect.fail("Should have thrown ServiceHasOpenOrders");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ServiceHasOpenOrders");
+      }
+    });
+
+    it("closes the service once all orders are settled", async () => {
+      await program.methods
+        .releasePayment()
+        .accounts({
+          payment: pmtPDA,
+          escrow: escPDA,
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: svcPDA,
+          receiverAuthority: providerKp.publicKey,
+          referrerAgent: null,
+          referrerAuthority: null,
+          authority: buyerKp.publicKey,
+        })
+        .signers([buyerKp])
+        .rpc();
+
+      // The finished payment still holds the order number the service would reuse
+      try {
+        await program.methods
+          .closeService()
+          .accounts({ service: svcPDA, authority: providerKp.publicKey })
+          .remainingAccounts(
+            indexAccounts("tag", svcPDA, ["lifecycle", "v2"], [])
+          )
+          .signers([providerKp])
+          .rpc();
+        expect.fail("Should have thrown ServiceHasPayments");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ServiceHasPayments");
+      }
+      await program.methods
+        .closePayment()
+        .accounts({
+          payment: pmtPDA,
+          payerAgent: buyerAgentPDA,
+          listing: svcPDA,
+          payerAuthority: buyerKp.publicKey,
+        })
+        .signers([buyerKp])
+        .rpc();
+      expect(await provider.connection.getAccountInfo(pmtPDA)).to.be.null;
+
+      const balBefore = await provider.connection.getBalance(
+        providerKp.publicKey
+      );
+      await program.methods
+        .closeService()
+        .accounts({ service: svcPDA, authority: providerKp.publicKey })
+        .remainingAccounts(
+          indexAccounts("tag", svcPDA, ["lifecycle", "v2"], [])
+        )
+        .signers([providerKp])
+        .rpc();
+
+      const info = await provider.connection.getAccountInfo(svcPDA);
+      expect(info).to.be.null;
+      const balAfter = await provider.connection.getBalance(
+        providerKp.publicKey
+      );
+      expect(balAfter).to.be.greaterThan(balBefore);
+    });
+  });
+
+  // ================================================================
+  // AUCTIONS: OPEN -> BID -> OUTBID REFUND -> SETTLE
+  // ================================================================
+  describe("auctions", () => {
+    let providerKp: Keypair;
+    let bidder1Kp: Keypair;
+    let bidder2Kp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let bidder1AgentPDA: PublicKey;
+    let bidder2AgentPDA: PublicKey;
+    let svcPDA: PublicKey;
+    let auctionPDA: PublicKey;
+    let auctionVaultPDA: PublicKey;
+    const auctionSvcId = "auction-svc";
+    const reserve = new anchor.BN(0.1 * LAMPORTS_PER_SOL);
+
+    function findAuctionPDA(
+      serviceKey: PublicKey,
+      orderIndex: number
+    ): [PublicKey, number] {
+      const buf = Buffer.alloc(8);
+      buf.writeBigUInt64LE(BigInt(orderIndex));
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("auction"), serviceKey.toBuffer(), buf],
+        program.programId
+      );
+    }
+
+    function findAuctionVaultPDA(auctionKey: PublicKey): [PublicKey, number] {
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("auction_vault"), auctionKey.toBuffer()],
+        program.programId
+      );
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      bidder1Kp = await createFundedKeypair();
+      bidder2Kp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(providerKp, "Auctioneer");
+      bidder1AgentPDA = await registerAgentHelper(bidder1Kp, "Bidder1");
+      bidder2AgentPDA = await registerAgentHelper(bidder2Kp, "Bidder2");
+
+      [svcPDA] = findServicePDA(providerAgentPDA, auctionSvcId);
+      await program.methods
+        .createService(
+          auctionSvcId,
+          "Priority GPU Slot",
+          "Highest bidder gets the next inference slot",
+          reserve,
+          { auction: {} },
+          ["gpu"]
+        )
+        .accounts({
+          service: svcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(indexAccounts("tag", svcPDA, [], ["gpu"]))
+        .signers([providerKp])
+        .rpc();
+
+      [auctionPDA] = findAuctionPDA(svcPDA, 0);
+      [auctionVaultPDA] = findAuctionVaultPDA(auctionPDA);
+    });
+
+    it("rejects direct pay_for_service on an auction service", async () => {
+      const [pmtPDA] = findPaymentPDA(bidder1AgentPDA, svcPDA, 0);
+      const [escPDA] = findEscrowPDA(pmtPDA);
+      try {
+        await program.methods
+          .payForService(
+            reserve,
+            new anchor.BN(1),
+            "Skip the auction",
+            [],
+            new anchor.BN(3600)
+          )
+          .accounts({
+            payment: pmtPDA,
+            escrow: escPDA,
+            payerAgent: bidder1AgentPDA,
+            receiverAgent: providerAgentPDA,
+            service: svcPDA,
+            coupon: null,
+            referrerAgent: null,
+            buyerAttestation: null,
+            payerAuthority: bidder1Kp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([bidder1Kp])
+          .rpc();
+        expect.fail("Should have thrown UnsupportedPriceModel");
+      } catch (err: any) {
+        expect(err.toString()).to.include("UnsupportedPriceModel");
+      }
+    });
+
+    it("rejects a non-positive delivery timeout", async () => {
+      try {
+        await program.methods
+          .openAuction(reserve, new anchor.BN(5), new anchor.BN(0))
+          .accounts({
+            auction: auctionPDA,
+            auctionVault: auctionVaultPDA,
+            service: svcPDA,
+            authority: providerKp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([providerKp])
+          .rpc();
+        expect.fail("Should have thrown InvalidDuration");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidDuration");
+      }
+    });
+
+    it("provider opens an auction", async () => {
+      await program.methods
+        .openAuction(reserve, new anchor.BN(5), new anchor.BN(3600))
+        .accounts({
+          auction: auctionPDA,
+          auctionVault: auctionVaultPDA,
+          service: svcPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+
+      const auction = await program.account.auction.fetch(auctionPDA);
+      expect(auction.service.toBase58()).to.equal(svcPDA.toBase58());
+      expect(auction.reservePrice.toNumber()).to.equal(reserve.toNumber());
+      expect(auction.status).to.deep.equal({ open: {} });
+      expect(auction.bidCount.toNumber()).to.equal(0);
+    });
+
+    it("fails when bid is below the reserve", async () => {
+      try {
+        await program.methods
+          .placeBid(new anchor.BN(1000), "Lowball")
+          .accounts({
+            auction: auctionPDA,
+            auctionVault: auctionVaultPDA,
+            bidderAgent: bidder1AgentPDA,
+            service: svcPDA,
+            buyerAttestation: null,
+            previousBidderAuthority: null,
+            bidderAuthority: bidder1Kp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([bidder1Kp])
+          .rpc();
+        expect.fail("Should have thrown BidTooLow");
+      } catch (err: any) {
+        expect(err.toString()).to.include("BidTooLow");
+      }
+    });
+
+    it("outbid bidder is refunded automatically", async () => {
+      const firstBid = reserve;
+      const secondBid = new anchor.BN(0.15 * LAMPORTS_PER_SOL);
+
+      await program.methods
+        .placeBid(firstBid, "Run my batch job")
+        .accounts({
+          auction: auctionPDA,
+          auctionVault: auctionVaultPDA,
+          bidderAgent: bidder1AgentPDA,
+          service: svcPDA,
+          buyerAttestation: null,
+          previousBidderAuthority: null,
+          bidderAuthority: bidder1Kp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([bidder1Kp])
+        .rpc();
+
+      const bidder1BalBefore = await provider.connection.getBalance(
+        bidder1Kp.publicKey
+      );
+
+      await program.methods
+        .placeBid(secondBid, "Fine-tune my model")
+        .accounts({
+          auction: auctionPDA,
+          auctionVault: auctionVaultPDA,
+          bidderAgent: bidder2AgentPDA,
+          service: svcPDA,
+          buyerAttestation: null,
+          previousBidderAuthority: bidder1Kp.publicKey,
+          bidderAuthority: bidder2Kp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([bidder2Kp])
+        .rpc();
+
+      const bidder1BalAfter = await provider.connection.getBalance(
+        bidder1Kp.publicKey
+      );
+      expect(bidder1BalAfter - bidder1BalBefore).to.equal(
+        firstBid.toNumber()
+      );
+
+      const auction = await program.account.auction.fetch(auctionPDA);
+      expect(auction.highestBid.toNumber()).to.equal(secondBid.toNumber());
+      expect(auction.highestBidder.toBase58()).to.equal(
+        bidder2AgentPDA.toBase58()
+      );
+      expect(auction.intent).to.equal("Fine-tune my model");
+      expect(auction.bidCount.toNumber()).to.equal(2);
+    });
+
+    it("cannot settle before the auction ends", async () => {
+      const [pmtPDA] = findPaymentPDA(bidder2AgentPDA, svcPDA, 0);
+      const [escPDA] = findEscrowPDA(pmtPDA);
+      try {
+        await program.methods
+          .settleAuction()
+          .accounts({
+            auction: auctionPDA,
+            auctionVault: auctionVaultPDA,
+            payment: pmtPDA,
+            escrow: escPDA,
+            winnerAgent: bidder2AgentPDA,
+            service: svcPDA,
+            settler: providerKp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([providerKp])
+          .rpc();
+        expect.fail("Should have thrown AuctionNotEnded");
+      } catch (err: any) {
+        expect(err.toString()).to.include("AuctionNotEnded");
+      }
+    });
+
+    it("settles the winning bid into an escrowed payment", async () => {
+      await new Promise((resolve) => setTimeout(resolve, 6000));
+
+      const [pmtPDA] = findPaymentPDA(bidder2AgentPDA, svcPDA, 0);
+      const [escPDA] = findEscrowPDA(pmtPDA);
+      await program.methods
+        .settleAuction()
+        .accounts({
+          auction: auctionPDA,
+          auctionVault: auctionVaultPDA,
+          payment: pmtPDA,
+          escrow: escPDA,
+          winnerAgent: bidder2AgentPDA,
+          service: svcPDA,
+          settler: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+
+      const payment = await program.account.payment.fetch(pmtPDA);
+      expect(payment.payer.toBase58()).to.equal(bidder2AgentPDA.toBase58());
+      expect(payment.receiver.toBase58()).to.equal(
+        providerAgentPDA.toBase58()
+      );
+      expect(payment.amount.toNumber()).to.equal(0.15 * LAMPORTS_PER_SOL);
+      expect(payment.intent).to.equal("Fine-tune my model");
+      expect(payment.status).to.deep.equal({ escrowed: {} });
+
+      const auction = await program.account.auction.fetch(auctionPDA);
+      expect(auction.status).to.deep.equal({ settled: {} });
+      expect(auction.payment.toBase58()).to.equal(pmtPDA.toBase58());
+
+      const svc = await program.account.service.fetch(svcPDA);
+      expect(svc.totalOrders.toNumber()).to.equal(1);
+      expect(svc.openOrders.toNumber()).to.equal(1);
+    });
+
+    it("closes the settled auction", async () => {
+      await program.methods
+        .closeAuction()
+        .accounts({ auction: auctionPDA, authority: providerKp.publicKey })
+        .signers([providerKp])
+        .rpc();
+      expect(await provider.connection.getAccountInfo(auctionPDA)).to.be.null;
+    });
+  });
+
+  // ================================================================
+  // METERED BILLING: FUND -> REPORT -> CONTEST / CLAIM -> CLOSE
+  // ================================================================
+  describe("metered billing", () => {
+    let providerKp: Keypair;
+    let buyerKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let buyerAgentPDA: PublicKey;
+    let svcPDA: PublicKey;
+    let usagePDA: PublicKey;
+    let usageVaultPDA: PublicKey;
+    const meteredSvcId = "llm-tokens";
+    const pricePerToken = new anchor.BN(100); // 100 lamports per token
+    const deposit = new anchor.BN(0.01 * LAMPORTS_PER_SOL);
+
+    function findUsageReportPDA(
+      usageKey: PublicKey,
+      index: number
+    ): [PublicKey, number] {
+      const buf = Buffer.alloc(8);
+      buf.writeBigUInt64LE(BigInt(index));
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("usage_report"), usageKey.toBuffer(), buf],
+        program.programId
+      );
+    }
+
+    function reportAccounts(reportPDA: PublicKey) {
+      return {
+        usageEscrow: usagePDA,
+        usageVault: usageVaultPDA,
+        usageReport: reportPDA,
+        service: svcPDA,
+        payerAgent: buyerAgentPDA,
+        providerAgent: providerAgentPDA,
+        providerAuthority: providerKp.publicKey,
+      };
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      buyerKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(
+        providerKp,
+        "MeterProvider"
+      );
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "MeterBuyer");
+
+      [svcPDA] = findServicePDA(providerAgentPDA, meteredSvcId);
+      await program.methods
+        .createService(
+          meteredSvcId,
+          "LLM Completions",
+          "Billed per output token",
+          pricePerToken,
+          { perToken: {} },
+          ["llm"]
+        )
+        .accounts({
+          service: svcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(indexAccounts("tag", svcPDA, [], ["llm"]))
+        .signers([providerKp])
+        .rpc();
+
+      [usagePDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("usage"), buyerAgentPDA.toBuffer(), svcPDA.toBuffer()],
+        program.programId
+      );
+      [usageVaultPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("usage_vault"), usagePDA.toBuffer()],
+        program.programId
+      );
+    });
+
+    it("buyer pre-funds a usage escrow", async () => {
+      await program.methods
+        .openUsageEscrow(deposit, new anchor.BN(50_000), new anchor.BN(2))
+        .accounts({
+          usageEscrow: usagePDA,
+          usageVault: usageVaultPDA,
+          payerAgent: buyerAgentPDA,
+          service: svcPDA,
+          buyerAttestation: null,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+
+      const usage = await program.account.usageEscrow.fetch(usagePDA);
+      expect(usage.pricePerUnit.toNumber()).to.equal(pricePerToken.toNumber());
+      expect(usage.deposited.toNumber()).to.equal(deposit.toNumber());
+      expect(usage.maxUnitsPerReport.toNumber()).to.equal(50_000);
+    });
+
+    it("co-signed report is paid out immediately", async () => {
+      const [reportPDA] = findUsageReportPDA(usagePDA, 0);
+
+      await program.methods
+        .submitUsageReport(new anchor.BN(1_000))
+        .accounts({
+          ...reportAccounts(reportPDA),
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp, buyerKp])
+        .rpc();
+
+      const report = await program.account.usageReport.fetch(reportPDA);
+      expect(report.status).to.deep.equal({ settled: {} });
+      expect(report.amount.toNumber()).to.equal(100_000);
+
+      const usage = await program.account.usageEscrow.fetch(usagePDA);
+      expect(usage.drawn.toNumber()).to.equal(100_000);
+      expect(usage.unitsBilled.toNumber()).to.equal(1_000);
+
+      const providerAgent = await program.account.agent.fetch(
+        providerAgentPDA
+      );
+      expect(providerAgent.totalEarned.toNumber()).to.equal(100_000);
+    });
+
+    it("rejects a report above the payer's per-report cap", async () => {
+      const [reportPDA] = findUsageReportPDA(usagePDA, 1);
+      try {
+        await program.methods
+          .submitUsageReport(new anchor.BN(50_001))
+          .accounts({
+            ...reportAccounts(reportPDA),
+            payerAuthority: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([providerKp])
+          .rpc();
+        expect.fail("Should have thrown UsageCapExceeded");
+      } catch (err: any) {
+        expect(err.toString()).to.include("UsageCapExceeded");
+      }
+    });
+
+    it("payer contests an unsigned report within the window", async () => {
+      const [reportPDA] = findUsageReportPDA(usagePDA, 1);
+      await program.methods
+        .submitUsageReport(new anchor.BN(5_000))
+        .accounts({
+          ...reportAccounts(reportPDA),
+          payerAuthority: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+
+      let usage = await program.account.usageEscrow.fetch(usagePDA);
+      expect(usage.pending.toNumber()).to.equal(500_000);
+
+      await program.methods
+        .contestUsageReport()
+        .accounts({
+          usageEscrow: usagePDA,
+          usageReport: reportPDA,
+          payerAuthority: buyerKp.publicKey,
+        })
+        .signers([buyerKp])
+        .rpc();
+
+      const report = await program.account.usageReport.fetch(reportPDA);
+      expect(report.status).to.deep.equal({ contested: {} });
+      usage = await program.account.usageEscrow.fetch(usagePDA);
+      expect(usage.pending.toNumber()).to.equal(0);
+      expect(usage.drawn.toNumber()).to.equal(100_000);
+    });
+
+    it("provider claims an uncontested report after the window", async () => {
+      const [reportPDA] = findUsageReportPDA(usagePDA, 2);
+      await program.methods
+        .submitUsageReport(new anchor.BN(2_000))
+        .accounts({
+          ...reportAccounts(reportPDA),
+          payerAuthority: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+
+      try {
+        await program.methods
+          .claimUsageReport()
+          .accounts({
+            ...reportAccounts(reportPDA),
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([providerKp])
+          .rpc();
+        expect.fail("Should have thrown ChallengeWindowOpen");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ChallengeWindowOpen");
+      }
+
+      await new Promise((resolve) => setTimeout(resolve, 3000));
+
+      await program.methods
+        .claimUsageReport()
+        .accounts({
+          ...reportAccounts(reportPDA),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+
+      const usage = await program.account.usageEscrow.fetch(usagePDA);
+      expect(usage.drawn.toNumber()).to.equal(300_000);
+      expect(usage.unitsBilled.toNumber()).to.equal(3_000);
+
+      const buyer = await program.account.agent.fetch(buyerAgentPDA);
+      expect(buyer.totalSpent.toNumber()).to.equal(300_000);
+      const svc = await program.account.service.fetch(svcPDA);
+      expect(svc.totalRevenue.toNumber()).to.equal(300_000);
+    });
+
+    it("payer resolves the contested report and reports are closed", async () => {
+      const closeEscrow = () =>
+        program.methods
+          .closeUsageEscrow()
+          .accounts({
+            usageEscrow: usagePDA,
+            usageVault: usageVaultPDA,
+            payerAuthority: buyerKp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([buyerKp])
+          .rpc();
+      try {
+        await closeEscrow();
+        expect.fail("Should have thrown UsageReportsOpen");
+      } catch (err: any) {
+        expect(err.toString()).to.include("UsageReportsOpen");
+      }
+
+      // The payer accepts 1,000 of the 5,000 contested tokens
+      const [contestedPDA] = findUsageReportPDA(usagePDA, 1);
+      await program.methods
+        .resolveUsageReport(new anchor.BN(1_000))
+        .accounts({
+          usageEscrow: usagePDA,
+          usageVault: usageVaultPDA,
+          usageReport: contestedPDA,
+          service: svcPDA,
+          payerAgent: buyerAgentPDA,
+          providerAgent: providerAgentPDA,
+          providerAuthority: providerKp.publicKey,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+      expect(await provider.connection.getAccountInfo(contestedPDA)).to.be.null;
+
+      // Settled reports can be closed by anyone; rent goes back to the provider
+      for (const index of [0, 2]) {
+        await program.methods
+          .closeUsageReport()
+          .accounts({
+            usageEscrow: usagePDA,
+            usageReport: findUsageReportPDA(usagePDA, index)[0],
+            service: svcPDA,
+            providerAuthority: providerKp.publicKey,
+          })
+          .rpc();
+      }
+
+      const usage = await program.account.usageEscrow.fetch(usagePDA);
+      expect(usage.drawn.toNumber()).to.equal(400_000);
+      expect(usage.unitsBilled.toNumber()).to.equal(4_000);
+      expect(usage.openReports.toNumber()).to.equal(0);
+    });
+
+    it("closing the escrow refunds the unused balance", async () => {
+      const buyerBalBefore = await provider.connection.getBalance(
+        buyerKp.publicKey
+      );
+      await program.methods
+        .closeUsageEscrow()
+        .accounts({
+          usageEscrow: usagePDA,
+          usageVault: usageVaultPDA,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+
+      const buyerBalAfter = await provider.connection.getBalance(
+        buyerKp.publicKey
+      );
+      expect(buyerBalAfter - buyerBalBefore).to.be.greaterThanOrEqual(
+        deposit.toNumber() - 400_000
+      );
+      const info = await provider.connection.getAccountInfo(usagePDA);
+      expect(info).to.be.null;
+    });
+  });
+
+  // ================================================================
+  // JOB BOARD: POST -> BID -> ACCEPT (escrowed payment)
+  // ================================================================
+  describe("job board", () => {
+    let buyerKp: Keypair;
+    let providerKp: Keypair;
+    let outsiderKp: Keypair;
+    let buyerAgentPDA: PublicKey;
+    let providerAgentPDA: PublicKey;
+    let outsiderAgentPDA: PublicKey;
+    let providerSvcPDA: PublicKey;
+    let outsiderSvcPDA: PublicKey;
+    let jobPDA: PublicKey;
+    const jobId = "translate-docs";
+    const budget = new anchor.BN(0.2 * LAMPORTS_PER_SOL);
+
+    function findJobBidPDA(
+      jobKey: PublicKey,
+      providerAgentKey: PublicKey
+    ): [PublicKey, number] {
+      return PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("job_bid"),
+          jobKey.toBuffer(),
+          providerAgentKey.toBuffer(),
+        ],
+        program.programId
+      );
+    }
+
+    async function createServiceFor(
+      kp: Keypair,
+      agentPDA: PublicKey,
+      svcId: string
+    ): Promise<PublicKey> {
+      const [svcPDA] = findServicePDA(agentPDA, svcId);
+      await program.methods
+        .createService(
+          svcId,
+          "Quoted Work",
+          "Priced per job",
+          new anchor.BN(1),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: svcPDA,
+          agent: agentPDA,
+          authority: kp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([kp])
+        .rpc();
+      return svcPDA;
+    }
+
+    before(async () => {
+      buyerKp = await createFundedKeypair();
+      providerKp = await createFundedKeypair();
+      outsiderKp = await createFundedKeypair();
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "JobBuyer");
+      providerAgentPDA = await registerAgentHelper(
+        providerKp,
+        "Translator",
+        "Translates documents",
+        ["translation", "nlp"]
+      );
+      outsiderAgentPDA = await registerAgentHelper(
+        outsiderKp,
+        "ImageBot",
+        "Generates images",
+        ["image-gen"]
+      );
+      providerSvcPDA = await createServiceFor(
+        providerKp,
+        providerAgentPDA,
+        "quoted-translation"
+      );
+      outsiderSvcPDA = await createServiceFor(
+        outsiderKp,
+        outsiderAgentPDA,
+        "quoted-images"
+      );
+      [jobPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("job"), buyerAgentPDA.toBuffer(), Buffer.from(jobId)],
+        program.programId
+      );
+    });
+
+    it("buyer posts a job with budget and required capabilities", async () => {
+      const deadline = Math.floor(Date.now() / 1000) + 3600;
+      await program.methods
+        .postJob(
+          jobId,
+          "Translate 20 pages EN->ES",
+          budget,
+          ["translation"],
+          new anchor.BN(deadline)
+        )
+        .accounts({
+          job: jobPDA,
+          buyerAgent: buyerAgentPDA,
+          authority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+
+      const job = await program.account.job.fetch(jobPDA);
+      expect(job.buyer.toBase58()).to.equal(buyerAgentPDA.toBase58());
+      expect(job.budget.toNumber()).to.equal(budget.toNumber());
+      expect(job.requiredCapabilities).to.deep.equal(["translation"]);
+      expect(job.status).to.deep.equal({ open: {} });
+    });
+
+    it("rejects a bid from a provider missing a required capability", async () => {
+      const [bidPDA] = findJobBidPDA(jobPDA, outsiderAgentPDA);
+      try {
+        await program.methods
+          .submitJobBid(new anchor.BN(1000), new anchor.BN(600), "I can try")
+          .accounts({
+            job: jobPDA,
+            jobBid: bidPDA,
+            providerAgent: outsiderAgentPDA,
+            service: outsiderSvcPDA,
+            authority: outsiderKp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([outsiderKp])
+          .rpc();
+        expect.fail("Should have thrown MissingCapability");
+      } catch (err: any) {
+        expect(err.toString()).to.include("MissingCapability");
+      }
+    });
+
+    it("rejects a bid above the budget", async () => {
+      const [bidPDA] = findJobBidPDA(jobPDA, providerAgentPDA);
+      try {
+        await program.methods
+          .submitJobBid(budget.addn(1), new anchor.BN(600), "Premium")
+          .accounts({
+            job: jobPDA,
+            jobBid: bidPDA,
+            providerAgent: providerAgentPDA,
+            service: providerSvcPDA,
+            authority: providerKp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([providerKp])
+          .rpc();
+        expect.fail("Should have thrown BidExceedsBudget");
+      } catch (err: any) {
+        expect(err.toString()).to.include("BidExceedsBudget");
+      }
+    });
+
+    it("buyer accepts a bid and the price is escrowed", async () => {
+      const price = new anchor.BN(0.15 * LAMPORTS_PER_SOL);
+      const [bidPDA] = findJobBidPDA(jobPDA, providerAgentPDA);
+      await program.methods
+        .submitJobBid(price, new anchor.BN(7200), "Done in 2 hours")
+        .accounts({
+          job: jobPDA,
+          jobBid: bidPDA,
+          providerAgent: providerAgentPDA,
+          service: providerSvcPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+
+      const [pmtPDA] = findPaymentPDA(buyerAgentPDA, providerSvcPDA, 0);
+      const [escPDA] = findEscrowPDA(pmtPDA);
+      await program.methods
+        .acceptJobBid()
+        .accounts({
+          job: jobPDA,
+          jobBid: bidPDA,
+          payment: pmtPDA,
+          escrow: escPDA,
+          buyerAgent: buyerAgentPDA,
+          service: providerSvcPDA,
+          buyerAttestation: null,
+          authority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+
+      const payment = await program.account.payment.fetch(pmtPDA);
+      expect(payment.payer.toBase58()).to.equal(buyerAgentPDA.toBase58());
+      expect(payment.receiver.toBase58()).to.equal(
+        providerAgentPDA.toBase58()
+      );
+      expect(payment.amount.toNumber()).to.equal(price.toNumber());
+      expect(payment.intent).to.equal("Translate 20 pages EN->ES");
+      expect(
+        payment.timeoutAt.toNumber() - payment.createdAt.toNumber()
+      ).to.equal(7200);
+
+      const escrowBal = await provider.connection.getBalance(escPDA);
+      expect(escrowBal).to.be.gte(price.toNumber());
+
+      const job = await program.account.job.fetch(jobPDA);
+      expect(job.status).to.deep.equal({ awarded: {} });
+      expect(job.payment.toBase58()).to.equal(pmtPDA.toBase58());
+    });
+
+    it("cannot bid on an awarded job", async () => {
+      const [bidPDA] = findJobBidPDA(jobPDA, outsiderAgentPDA);
+      try {
+        await program.methods
+          .submitJobBid(new anchor.BN(1000), new anchor.BN(600), "Late")
+          .accounts({
+            job: jobPDA,
+            jobBid: bidPDA,
+            providerAgent: outsiderAgentPDA,
+            service: outsiderSvcPDA,
+            authority: outsiderKp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([outsiderKp])
+          .rpc();
+        expect.fail("Should have thrown JobNotOpen");
+      } catch (err: any) {
+        expect(err.toString()).to.include("JobNotOpen");
+      }
+    });
+
+    it("closes the awarded job once its bids are closed", async () => {
+      const closeJob = () =>
+        program.methods
+          .closeJob()
+          .accounts({ job: jobPDA, authority: buyerKp.publicKey })
+          .signers([buyerKp])
+          .rpc();
+      try {
+        await closeJob();
+        expect.fail("Should have thrown JobHasBids");
+      } catch (err: any) {
+        expect(err.toString()).to.include("JobHasBids");
+      }
+
+      // Anyone can close a bid on an awarded job; rent goes to the bidder
+      const [bidPDA] = findJobBidPDA(jobPDA, providerAgentPDA);
+      await program.methods
+        .closeJobBid()
+        .accounts({
+          job: jobPDA,
+          jobBid: bidPDA,
+          authority: providerKp.publicKey,
+        })
+        .rpc();
+      expect(await provider.connection.getAccountInfo(bidPDA)).to.be.null;
+
+      await closeJob();
+      expect(await provider.connection.getAccountInfo(jobPDA)).to.be.null;
+    });
+
+    it("withdrawn bids leave the count and cancelled jobs can be closed", async () => {
+      const cancelledId = "cancelled-job";
+      const [cancelledPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("job"),
+          buyerAgentPDA.toBuffer(),
+          Buffer.from(cancelledId),
+        ],
+        program.programId
+      );
+      await program.methods
+        .postJob(
+          cancelledId,
+          "Anything at all",
+          budget,
+          [],
+          new anchor.BN(Math.floor(Date.now() / 1000) + 3600)
+        )
+        .accounts({
+          job: cancelledPDA,
+          buyerAgent: buyerAgentPDA,
+          authority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+
+      const bidders: [Keypair, PublicKey, PublicKey][] = [
+        [providerKp, providerAgentPDA, providerSvcPDA],
+        [outsiderKp, outsiderAgentPDA, outsiderSvcPDA],
+      ];
+      for (const [kp, agentPDA, svcPDA] of bidders) {
+        await program.methods
+          .submitJobBid(new anchor.BN(1000), new anchor.BN(600), "Quote")
+          .accounts({
+            job: cancelledPDA,
+            jobBid: findJobBidPDA(cancelledPDA, agentPDA)[0],
+            providerAgent: agentPDA,
+            service: svcPDA,
+            authority: kp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([kp])
+          .rpc();
+      }
+
+      const [outsiderBidPDA] = findJobBidPDA(cancelledPDA, outsiderAgentPDA);
+      try {
+        await program.methods
+          .closeJobBid()
+          .accounts({
+            job: cancelledPDA,
+            jobBid: outsiderBidPDA,
+            authority: outsiderKp.publicKey,
+          })
+          .rpc();
+        expect.fail("Should have thrown JobStillOpen");
+      } catch (err: any) {
+        expect(err.toString()).to.include("JobStillOpen");
+      }
+
+      await program.methods
+        .withdrawJobBid()
+        .accounts({
+          job: cancelledPDA,
+          jobBid: outsiderBidPDA,
+          authority: outsiderKp.publicKey,
+        })
+        .signers([outsiderKp])
+        .rpc();
+      let job = await program.account.job.fetch(cancelledPDA);
+      expect(job.bidCount.toNumber()).to.equal(1);
+
+      await program.methods
+        .cancelJob()
+        .accounts({ job: cancelledPDA, authority: buyerKp.publicKey })
+        .signers([buyerKp])
+        .rpc();
+      job = await program.account.job.fetch(cancelledPDA);
+      expect(job.status).to.deep.equal({ cancelled: {} });
+
+      await program.methods
+        .closeJobBid()
+        .accounts({
+          job: cancelledPDA,
+          jobBid: findJobBidPDA(cancelledPDA, providerAgentPDA)[0],
+          authority: providerKp.publicKey,
+        })
+        .rpc();
+      await program.methods
+        .closeJob()
+        .accounts({ job: cancelledPDA, authority: buyerKp.publicKey })
+        .signers([buyerKp])
+        .rpc();
+      expect(await provider.connection.getAccountInfo(cancelledPDA)).to.be.null;
+    });
+  });
+
+  describe("subscriptions", () => {
+    let subscriberKp: Keypair;
+    let providerKp: Keypair;
+    let crankerKp: Keypair;
+    let subscriberAgentPDA: PublicKey;
+    let providerAgentPDA: PublicKey;
+    let subSvcPDA: PublicKey;
+    let subscriptionPDA: PublicKey;
+    let subscriptionVaultPDA: PublicKey;
+    const periodSeconds = 2;
+    const pricePerPeriod = new anchor.BN(0.05 * LAMPORTS_PER_SOL);
+
+    function chargeAccounts() {
+      return {
+        subscription: subscriptionPDA,
+        subscriptionVault: subscriptionVaultPDA,
+        subscriberAgent: subscriberAgentPDA,
+        providerAgent: providerAgentPDA,
+        service: subSvcPDA,
+        providerAuthority: providerKp.publicKey,
+        cranker: crankerKp.publicKey,
+        systemProgram: SystemProgram.programId,
+      };
+    }
+
+    async function verify(): Promise<void> {
+      await program.methods
+        .verifySubscription()
+        .accounts({
+          subscription: subscriptionPDA,
+          subscriberAgent: subscriberAgentPDA,
+          service: subSvcPDA,
+        })
+        .rpc();
+    }
+
+    before(async () => {
+      subscriberKp = await createFundedKeypair();
+      providerKp = await createFundedKeypair();
+      crankerKp = await createFundedKeypair();
+      subscriberAgentPDA = await registerAgentHelper(subscriberKp, "Subscriber");
+      providerAgentPDA = await registerAgentHelper(providerKp, "FeedProvider");
+
+      [subSvcPDA] = findServicePDA(providerAgentPDA, "market-feed");
+      await program.methods
+        .createService(
+          "market-feed",
+          "Market Data Feed",
+          "Realtime prices, billed every period",
+          pricePerPeriod,
+          { subscription: { periodSeconds: new anchor.BN(periodSeconds) } },
+          ["data"]
+        )
+        .accounts({
+          service: subSvcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(indexAccounts("tag", subSvcPDA, [], ["data"]))
+        .signers([providerKp])
+        .rpc();
+
+      [subscriptionPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("subscription"),
+          subscriberAgentPDA.toBuffer(),
+          subSvcPDA.toBuffer(),
+        ],
+        program.programId
+      );
+      [subscriptionVaultPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("subscription_vault"), subscriptionPDA.toBuffer()],
+        program.programId
+      );
+    });
+
+    function subscribeWith(period: number) {
+      return program.methods
+        .subscribe(
+          pricePerPeriod,
+          new anchor.BN(period),
+          new anchor.BN(0.1 * LAMPORTS_PER_SOL)
+        )
+        .accounts({
+          subscription: subscriptionPDA,
+          subscriptionVault: subscriptionVaultPDA,
+          subscriberAgent: subscriberAgentPDA,
+          providerAgent: providerAgentPDA,
+          service: subSvcPDA,
+          providerAuthority: providerKp.publicKey,
+          buyerAttestation: null,
+          subscriberAuthority: subscriberKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([subscriberKp]);
+    }
+
+    it("rejects a subscription that does not match the listed period", async () => {
+      try {
+        await subscribeWith(periodSeconds * 10).rpc();
+        expect.fail("Should have thrown InvalidDuration");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidDuration");
+      }
+    });
+
+    it("subscribes and charges the first period immediately", async () => {
+      const providerBefore = await provider.connection.getBalance(
+        providerKp.publicKey
+      );
+      await subscribeWith(periodSeconds).rpc();
+
+      const sub = await program.account.subscription.fetch(subscriptionPDA);
+      expect(sub.status).to.deep.equal({ active: {} });
+      expect(sub.periodsCharged.toNumber()).to.equal(1);
+      expect(sub.charged.toNumber()).to.equal(pricePerPeriod.toNumber());
+
+      const providerAfter = await provider.connection.getBalance(
+        providerKp.publicKey
+      );
+      expect(providerAfter - providerBefore).to.equal(
+        pricePerPeriod.toNumber()
+      );
+
+      await verify();
+    });
+
+    it("refuses to charge before the period is due", async () => {
+      try {
+        await program.methods
+          .chargeSubscription()
+          .accounts(chargeAccounts())
+          .signers([crankerKp])
+          .rpc();
+        expect.fail("Should have thrown SubscriptionNotDue");
+      } catch (err: any) {
+        expect(err.toString()).to.include("SubscriptionNotDue");
+      }
+    });
+
+    it("any cranker can charge the next period once due", async () => {
+      await new Promise((resolve) =>
+        setTimeout(resolve, (periodSeconds + 1) * 1000)
+      );
+      await program.methods
+        .chargeSubscription()
+        .accounts(chargeAccounts())
+        .signers([crankerKp])
+        .rpc();
+
+      const sub = await program.account.subscription.fetch(subscriptionPDA);
+      expect(sub.periodsCharged.toNumber()).to.equal(2);
+      expect(sub.deposited.sub(sub.charged).toNumber()).to.equal(0);
+
+      const providerAgent = await program.account.agent.fetch(
+        providerAgentPDA
+      );
+      expect(providerAgent.totalEarned.toNumber()).to.equal(
+        2 * pricePerPeriod.toNumber()
+      );
+    });
+
+    it("lapses when the balance cannot cover a period", async () => {
+      await new Promise((resolve) =>
+        setTimeout(resolve, (periodSeconds + 1) * 1000)
+      );
+      try {
+        await verify();
+        expect.fail("Should have thrown SubscriptionInactive");
+      } catch (err: any) {
+        expect(err.toString()).to.include("SubscriptionInactive");
+      }
+
+      await program.methods
+        .chargeSubscription()
+        .accounts(chargeAccounts())
+        .signers([crankerKp])
+        .rpc();
+
+      const sub = await program.account.subscription.fetch(subscriptionPDA);
+      expect(sub.status).to.deep.equal({ lapsed: {} });
+      expect(sub.periodsCharged.toNumber()).to.equal(2);
+    });
+
+    it("top-up reactivates a lapsed subscription", async () => {
+      const topUp = (amount: number) =>
+        program.methods
+          .topUpSubscription(new anchor.BN(amount))
+          .accounts({
+            subscription: subscriptionPDA,
+            subscriptionVault: subscriptionVaultPDA,
+            subscriberAuthority: subscriberKp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([subscriberKp])
+          .rpc();
+
+      // Less than one period keeps it lapsed
+      await topUp(0.02 * LAMPORTS_PER_SOL);
+      let sub = await program.account.subscription.fetch(subscriptionPDA);
+      expect(sub.status).to.deep.equal({ lapsed: {} });
+
+      await topUp(0.08 * LAMPORTS_PER_SOL);
+
+      await program.methods
+        .chargeSubscription()
+        .accounts(chargeAccounts())
+        .signers([crankerKp])
+        .rpc();
+
+      sub = await program.account.subscription.fetch(subscriptionPDA);
+      expect(sub.status).to.deep.equal({ active: {} });
+      expect(sub.periodsCharged.toNumber()).to.equal(3);
+      await verify();
+    });
+
+    it("cancel refunds the unused balance and closes the subscription", async () => {
+      const before = await provider.connection.getBalance(
+        subscriberKp.publicKey
+      );
+      await program.methods
+        .cancelSubscription()
+        .accounts({
+          subscription: subscriptionPDA,
+          subscriptionVault: subscriptionVaultPDA,
+          subscriberAuthority: subscriberKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([subscriberKp])
+        .rpc();
+
+      const after = await provider.connection.getBalance(
+        subscriberKp.publicKey
+      );
+      // Unused period plus account rent, minus the transaction fee
+      expect(after - before).to.be.greaterThan(pricePerPeriod.toNumber());
+
+      const closed = await provider.connection.getAccountInfo(subscriptionPDA);
+      expect(closed).to.be.null;
+    });
+  });
+
+  describe("tiered pricing", () => {
+    let buyerKp: Keypair;
+    let providerKp: Keypair;
+    let buyerAgentPDA: PublicKey;
+    let providerAgentPDA: PublicKey;
+    let tieredSvcPDA: PublicKey;
+    const basePrice = 10_000;
+
+    function setSchedule(pricing: any) {
+      return program.methods
+        .setPricingSchedule(pricing)
+        .accounts({ service: tieredSvcPDA, authority: providerKp.publicKey })
+        .signers([providerKp])
+        .rpc();
+    }
+
+    async function payFor(amount: number, quantity: number): Promise<void> {
+      const svc = await program.account.service.fetch(tieredSvcPDA);
+      const [paymentPDA] = findPaymentPDA(
+        buyerAgentPDA,
+        tieredSvcPDA,
+        svc.totalOrders.toNumber()
+      );
+      const [escrowPDA] = findEscrowPDA(paymentPDA);
+      await program.methods
+        .payForService(
+          new anchor.BN(amount),
+          new anchor.BN(quantity),
+          "Bulk requests",
+          [],
+          new anchor.BN(3600)
+        )
+        .accounts({
+          payment: paymentPDA,
+          escrow: escrowPDA,
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: tieredSvcPDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+    }
+
+    before(async () => {
+      buyerKp = await createFundedKeypair();
+      providerKp = await createFundedKeypair();
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "BulkBuyer");
+      providerAgentPDA = await registerAgentHelper(providerKp, "BulkProvider");
+
+      [tieredSvcPDA] = findServicePDA(providerAgentPDA, "bulk-ocr");
+      await program.methods
+        .createService(
+          "bulk-ocr",
+          "Bulk OCR",
+          "Cheaper at volume",
+          new anchor.BN(basePrice),
+          { perRequest: {} },
+          []
+        )
+        .accounts({
+          service: tieredSvcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+    });
+
+    it("rejects tiers that do not ascend", async () => {
+      try {
+        await setSchedule({
+          tiers: [
+            { fromUnit: new anchor.BN(10), priceLamports: new anchor.BN(6000) },
+            { fromUnit: new anchor.BN(5), priceLamports: new anchor.BN(4000) },
+          ],
+          reputationDiscounts: [],
+        });
+        expect.fail("Should have thrown InvalidPricingSchedule");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidPricingSchedule");
+      }
+    });
+
+    it("charges tiered prices with the buyer's reputation discount", async () => {
+      // First 10 requests at 10_000, then 6_000; 10% off for everyone
+      await setSchedule({
+        tiers: [
+          { fromUnit: new anchor.BN(10), priceLamports: new anchor.BN(6000) },
+        ],
+        reputationDiscounts: [
+          { minReputation: new anchor.BN(0), discountBps: 1000 },
+        ],
+      });
+
+      const svc = await program.account.service.fetch(tieredSvcPDA);
+      expect(svc.pricing.tiers.length).to.equal(1);
+
+      // Flat price is no longer accepted
+      try {
+        await payFor(15 * basePrice, 15);
+        expect.fail("Should have thrown AmountMismatch");
+      } catch (err: any) {
+        expect(err.toString()).to.include("AmountMismatch");
+      }
+
+      // (10 * 10_000 + 5 * 6_000) * 0.9
+      await payFor(117_000, 15);
+      // Exactly at the boundary: 10 * 10_000 * 0.9
+      await payFor(90_000, 10);
+
+      const after = await program.account.service.fetch(tieredSvcPDA);
+      expect(after.totalOrders.toNumber()).to.equal(2);
+    });
+
+    it("clearing the schedule restores flat pricing", async () => {
+      await setSchedule(null);
+      const svc = await program.account.service.fetch(tieredSvcPDA);
+      expect(svc.pricing).to.be.null;
+      await payFor(3 * basePrice, 3);
+    });
+  });
+
+  describe("coupons", () => {
+    let providerKp: Keypair;
+    let buyerKp: Keypair;
+    let strangerKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let buyerAgentPDA: PublicKey;
+    let strangerAgentPDA: PublicKey;
+    let promoSvcPDA: PublicKey;
+    let launchCouponPDA: PublicKey;
+    const price = 100_000;
+
+    function findCouponPDA(code: string): PublicKey {
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("coupon"), promoSvcPDA.toBuffer(), Buffer.from(code)],
+        program.programId
+      )[0];
+    }
+
+    async function payWithCoupon(
+      kp: Keypair,
+      agentPDA: PublicKey,
+      coupon: PublicKey,
+      amount: number
+    ): Promise<void> {
+      const svc = await program.account.service.fetch(promoSvcPDA);
+      const [paymentPDA] = findPaymentPDA(
+        agentPDA,
+        promoSvcPDA,
+        svc.totalOrders.toNumber()
+      );
+      const [escrowPDA] = findEscrowPDA(paymentPDA);
+      await program.methods
+        .payForService(
+          new anchor.BN(amount),
+          new anchor.BN(1),
+          "Promo order",
+          [],
+          new anchor.BN(3600)
+        )
+        .accounts({
+          payment: paymentPDA,
+          escrow: escrowPDA,
+          payerAgent: agentPDA,
+          receiverAgent: providerAgentPDA,
+          service: promoSvcPDA,
+          coupon,
+          referrerAgent: null,
+          buyerAttestation: null,
+          payerAuthority: kp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([kp])
+        .rpc();
+    }
+
+    async function createCoupon(
+      code: string,
+      discount: any,
+      maxRedemptions: number,
+      expiresAt: number,
+      allowedBuyers: PublicKey[]
+    ): Promise<PublicKey> {
+      const couponPDA = findCouponPDA(code);
+      await program.methods
+        .createCoupon(
+          code,
+          discount,
+          new anchor.BN(maxRedemptions),
+          new anchor.BN(expiresAt),
+          allowedBuyers
+        )
+        .accounts({
+          coupon: couponPDA,
+          service: promoSvcPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+      return couponPDA;
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      buyerKp = await createFundedKeypair();
+      strangerKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(providerKp, "PromoProvider");
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "PromoBuyer");
+      strangerAgentPDA = await registerAgentHelper(strangerKp, "Stranger");
+
+      [promoSvcPDA] = findServicePDA(providerAgentPDA, "promo-svc");
+      await program.methods
+        .createService(
+          "promo-svc",
+          "Logo Design",
+          "One logo",
+          new anchor.BN(price),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: promoSvcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+    });
+
+    it("rejects discounts that leave nothing to pay", async () => {
+      const free: [string, any][] = [
+        ["TOOMUCH", { percent: { bps: 10_001 } }],
+        ["FREE", { percent: { bps: 10_000 } }],
+        ["FREEFIXED", { fixed: { lamports: new anchor.BN(price) } }],
+      ];
+      for (const [code, discount] of free) {
+        try {
+          await createCoupon(code, discount, 0, 0, []);
+          expect.fail("Should have thrown InvalidDiscount");
+        } catch (err: any) {
+          expect(err.toString()).to.include("InvalidDiscount");
+        }
+      }
+    });
+
+    it("service authority creates a percent coupon with an allowlist", async () => {
+      launchCouponPDA = await createCoupon(
+        "LAUNCH20",
+        { percent: { bps: 2000 } },
+        2,
+        0,
+        [buyerAgentPDA]
+      );
+      const coupon = await program.account.coupon.fetch(launchCouponPDA);
+      expect(coupon.code).to.equal("LAUNCH20");
+      expect(coupon.redemptions.toNumber()).to.equal(0);
+    });
+
+    it("requires the discounted amount when a coupon is used", async () => {
+      try {
+        await payWithCoupon(buyerKp, buyerAgentPDA, launchCouponPDA, price);
+        expect.fail("Should have thrown AmountMismatch");
+      } catch (err: any) {
+        expect(err.toString()).to.include("AmountMismatch");
+      }
+    });
+
+    it("redeems the coupon and counts redemptions", async () => {
+      await payWithCoupon(buyerKp, buyerAgentPDA, launchCouponPDA, 80_000);
+      const coupon = await program.account.coupon.fetch(launchCouponPDA);
+      expect(coupon.redemptions.toNumber()).to.equal(1);
+      expect(coupon.totalDiscounted.toNumber()).to.equal(20_000);
+    });
+
+    it("rejects buyers outside the allowlist", async () => {
+      try {
+        await payWithCoupon(
+          strangerKp,
+          strangerAgentPDA,
+          launchCouponPDA,
+          80_000
+        );
+        expect.fail("Should have thrown CouponNotAllowed");
+      } catch (err: any) {
+        expect(err.toString()).to.include("CouponNotAllowed");
+      }
+    });
+
+    it("stops redemptions at the cap", async () => {
+      await payWithCoupon(buyerKp, buyerAgentPDA, launchCouponPDA, 80_000);
+      try {
+        await payWithCoupon(buyerKp, buyerAgentPDA, launchCouponPDA, 80_000);
+        expect.fail("Should have thrown CouponExhausted");
+      } catch (err: any) {
+        expect(err.toString()).to.include("CouponExhausted");
+      }
+    });
+
+    it("rejects expired coupons", async () => {
+      const expired = await createCoupon(
+        "OLDPROMO",
+        { fixed: { lamports: new anchor.BN(10_000) } },
+        0,
+        Math.floor(Date.now() / 1000) - 60,
+        []
+      );
+      try {
+        await payWithCoupon(strangerKp, strangerAgentPDA, expired, 90_000);
+        expect.fail("Should have thrown CouponExpired");
+      } catch (err: any) {
+        expect(err.toString()).to.include("CouponExpired");
+      }
+    });
+
+    it("service authority closes a coupon", async () => {
+      await program.methods
+        .closeCoupon()
+        .accounts({ coupon: launchCouponPDA, authority: providerKp.publicKey })
+        .signers([providerKp])
+        .rpc();
+      const info = await provider.connection.getAccountInfo(launchCouponPDA);
+      expect(info).to.be.null;
+    });
+  });
+
+  describe("referral fees", () => {
+    let providerKp: Keypair;
+    let buyerKp: Keypair;
+    let routerKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let buyerAgentPDA: PublicKey;
+    let routerAgentPDA: PublicKey;
+    let refSvcPDA: PublicKey;
+    let paymentPDA: PublicKey;
+    let escrowPDA: PublicKey;
+    const price = 1_000_000;
+
+    async function pay(referrerAgent: PublicKey | null): Promise<void> {
+      const svc = await program.account.service.fetch(refSvcPDA);
+      [paymentPDA] = findPaymentPDA(
+        buyerAgentPDA,
+        refSvcPDA,
+        svc.totalOrders.toNumber()
+      );
+      [escrowPDA] = findEscrowPDA(paymentPDA);
+      await program.methods
+        .payForService(
+          new anchor.BN(price),
+          new anchor.BN(1),
+          "Routed order",
+          [],
+          new anchor.BN(3600)
+        )
+        .accounts({
+          payment: paymentPDA,
+          escrow: escrowPDA,
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: refSvcPDA,
+          coupon: null,
+          referrerAgent,
+          buyerAttestation: null,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+    }
+
+    function release(withReferrer: boolean) {
+      return program.methods
+        .releasePayment()
+        .accounts({
+          payment: paymentPDA,
+          escrow: escrowPDA,
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: refSvcPDA,
+          receiverAuthority: providerKp.publicKey,
+          referrerAgent: withReferrer ? routerAgentPDA : null,
+          referrerAuthority: withReferrer ? routerKp.publicKey : null,
+          authority: buyerKp.publicKey,
+        })
+        .signers([buyerKp])
+        .rpc();
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      buyerKp = await createFundedKeypair();
+      routerKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(providerKp, "RefProvider");
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "RefBuyer");
+      routerAgentPDA = await registerAgentHelper(routerKp, "Router");
+
+      [refSvcPDA] = findServicePDA(providerAgentPDA, "ref-svc");
+      await program.methods
+        .createService(
+          "ref-svc",
+          "Code Review",
+          "One review",
+          new anchor.BN(price),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: refSvcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+
+      // 5% to whoever routed the order
+      await program.methods
+        .setReferralFee(500)
+        .accounts({ service: refSvcPDA, authority: providerKp.publicKey })
+        .signers([providerKp])
+        .rpc();
+    });
+
+    it("rejects the payer as its own referrer", async () => {
+      try {
+        await pay(buyerAgentPDA);
+        expect.fail("Should have thrown InvalidReferrer");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidReferrer");
+      }
+    });
+
+    it("locks the referrer and fee into the payment", async () => {
+      await pay(routerAgentPDA);
+      const payment = await program.account.payment.fetch(paymentPDA);
+      expect(payment.referrer.toBase58()).to.equal(routerAgentPDA.toBase58());
+      expect(payment.referralBps).to.equal(500);
+    });
+
+    it("requires the referrer accounts to release a referred payment", async () => {
+      try {
+        await release(false);
+        expect.fail("Should have thrown ReferrerMissing");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ReferrerMissing");
+      }
+    });
+
+    it("splits the escrow between provider and referrer on release", async () => {
+      const routerBefore = await provider.connection.getBalance(
+        routerKp.publicKey
+      );
+      const providerBefore = await provider.connection.getBalance(
+        providerKp.publicKey
+      );
+
+      await release(true);
+
+      const routerAfter = await provider.connection.getBalance(
+        routerKp.publicKey
+      );
+      const providerAfter = await provider.connection.getBalance(
+        providerKp.publicKey
+      );
+      expect(routerAfter - routerBefore).to.equal(50_000);
+      expect(providerAfter - providerBefore).to.equal(950_000);
+
+      const router = await program.account.agent.fetch(routerAgentPDA);
+      expect(router.referralEarned.toNumber()).to.equal(50_000);
+      const providerAgent = await program.account.agent.fetch(
+        providerAgentPDA
+      );
+      expect(providerAgent.referralFeesPaid.toNumber()).to.equal(50_000);
+      expect(providerAgent.totalEarned.toNumber()).to.equal(950_000);
+    });
+
+    it("pays the provider in full when there is no referrer", async () => {
+      await pay(null);
+      const payment = await program.account.payment.fetch(paymentPDA);
+      expect(payment.referrer).to.be.null;
+      await release(false);
+
+      const providerAgent = await program.account.agent.fetch(
+        providerAgentPDA
+      );
+      expect(providerAgent.totalEarned.toNumber()).to.equal(1_950_000);
+    });
+  });
+
+  describe("buyer access rules", () => {
+    let providerKp: Keypair;
+    let partnerKp: Keypair;
+    let memberKp: Keypair;
+    let orgKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let partnerAgentPDA: PublicKey;
+    let memberAgentPDA: PublicKey;
+    let gatedSvcPDA: PublicKey;
+    let memberAttestationPDA: PublicKey;
+    const price = 10_000;
+
+    function setAccess(access: any) {
+      return program.methods
+        .setAccessRules(access)
+        .accounts({ service: gatedSvcPDA, authority: providerKp.publicKey })
+        .signers([providerKp])
+        .rpc();
+    }
+
+    function rules(
+      listMode: any,
+      list: PublicKey[],
+      minReputation = 0,
+      requiredAttester: PublicKey | null = null
+    ) {
+      return {
+        listMode,
+        list,
+        minReputation: new anchor.BN(minReputation),
+        requiredAttester,
+      };
+    }
+
+    async function pay(
+      kp: Keypair,
+      agentPDA: PublicKey,
+      buyerAttestation: PublicKey | null = null
+    ): Promise<void> {
+      const svc = await program.account.service.fetch(gatedSvcPDA);
+      const [paymentPDA] = findPaymentPDA(
+        agentPDA,
+        gatedSvcPDA,
+        svc.totalOrders.toNumber()
+      );
+      const [escrowPDA] = findEscrowPDA(paymentPDA);
+      await program.methods
+        .payForService(
+          new anchor.BN(price),
+          new anchor.BN(1),
+          "Partner-only order",
+          [],
+          new anchor.BN(3600)
+        )
+        .accounts({
+          payment: paymentPDA,
+          escrow: escrowPDA,
+          payerAgent: agentPDA,
+          receiverAgent: providerAgentPDA,
+          service: gatedSvcPDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation,
+          payerAuthority: kp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([kp])
+        .rpc();
+    }
+
+    async function expectPayError(
+      promise: Promise<void>,
+      error: string
+    ): Promise<void> {
+      try {
+        await promise;
+        expect.fail(`Should have thrown ${error}`);
+      } catch (err: any) {
+        expect(err.toString()).to.include(error);
+      }
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      partnerKp = await createFundedKeypair();
+      memberKp = await createFundedKeypair();
+      orgKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(providerKp, "GatedProvider");
+      partnerAgentPDA = await registerAgentHelper(partnerKp, "Partner");
+      memberAgentPDA = await registerAgentHelper(memberKp, "OrgMember");
+
+      [gatedSvcPDA] = findServicePDA(providerAgentPDA, "gated-svc");
+      await program.methods
+        .createService(
+          "gated-svc",
+          "Private Model",
+          "Trusted partners only",
+          new anchor.BN(price),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: gatedSvcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+
+      [memberAttestationPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("attestation"),
+          orgKp.publicKey.toBuffer(),
+          memberAgentPDA.toBuffer(),
+        ],
+        program.programId
+      );
+      await program.methods
+        .issueAttestation(new anchor.BN(0))
+        .accounts({
+          attestation: memberAttestationPDA,
+          subjectAgent: memberAgentPDA,
+          issuer: orgKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([orgKp])
+        .rpc();
+    });
+
+    it("enforces a minimum buyer reputation", async () => {
+      await setAccess(rules({ open: {} }, [], 1));
+      await expectPayError(
+        pay(partnerKp, partnerAgentPDA),
+        "ReputationTooLow"
+      );
+    });
+
+    it("allowlists individual buyer agents", async () => {
+      await setAccess(rules({ allowlist: {} }, [partnerAgentPDA]));
+      await pay(partnerKp, partnerAgentPDA);
+      await expectPayError(
+        pay(memberKp, memberAgentPDA),
+        "BuyerNotAllowlisted"
+      );
+    });
+
+    it("allowlists an organisation through its attestations", async () => {
+      await setAccess(rules({ allowlist: {} }, [orgKp.publicKey]));
+      await pay(memberKp, memberAgentPDA, memberAttestationPDA);
+      await expectPayError(
+        pay(partnerKp, partnerAgentPDA),
+        "BuyerNotAllowlisted"
+      );
+    });
+
+    it("denylists buyers", async () => {
+      await setAccess(rules({ denylist: {} }, [orgKp.publicKey]));
+      await expectPayError(
+        pay(memberKp, memberAgentPDA, memberAttestationPDA),
+        "BuyerDenylisted"
+      );
+      await pay(partnerKp, partnerAgentPDA);
+
+      await setAccess(rules({ denylist: {} }, [partnerAgentPDA]));
+      await expectPayError(
+        pay(partnerKp, partnerAgentPDA),
+        "BuyerDenylisted"
+      );
+      await pay(memberKp, memberAgentPDA);
+    });
+
+    it("applies the rules when a job bid is accepted", async () => {
+      await setAccess(rules({ allowlist: {} }, [partnerAgentPDA]));
+      const [jobPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("job"), memberAgentPDA.toBuffer(), Buffer.from("gated")],
+        program.programId
+      );
+      await program.methods
+        .postJob(
+          "gated",
+          "Run the private model",
+          new anchor.BN(price),
+          [],
+          new anchor.BN(Math.floor(Date.now() / 1000) + 3600)
+        )
+        .accounts({
+          job: jobPDA,
+          buyerAgent: memberAgentPDA,
+          authority: memberKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([memberKp])
+        .rpc();
+
+      const [bidPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("job_bid"), jobPDA.toBuffer(), providerAgentPDA.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .submitJobBid(new anchor.BN(price), new anchor.BN(600), "Quote")
+        .accounts({
+          job: jobPDA,
+          jobBid: bidPDA,
+          providerAgent: providerAgentPDA,
+          service: gatedSvcPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+
+      const svc = await program.account.service.fetch(gatedSvcPDA);
+      const [paymentPDA] = findPaymentPDA(
+        memberAgentPDA,
+        gatedSvcPDA,
+        svc.totalOrders.toNumber()
+      );
+      await expectPayError(
+        program.methods
+          .acceptJobBid()
+          .accounts({
+            job: jobPDA,
+            jobBid: bidPDA,
+            payment: paymentPDA,
+            escrow: findEscrowPDA(paymentPDA)[0],
+            buyerAgent: memberAgentPDA,
+            service: gatedSvcPDA,
+            buyerAttestation: null,
+            authority: memberKp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([memberKp])
+          .rpc()
+          .then(() => undefined),
+        "BuyerNotAllowlisted"
+      );
+    });
+
+    it("requires an attestation from the service's attester", async () => {
+      await setAccess(rules({ open: {} }, [], 0, orgKp.publicKey));
+      await expectPayError(
+        pay(partnerKp, partnerAgentPDA),
+        "AttestationRequired"
+      );
+      // Someone else's attestation does not count
+      await expectPayError(
+        pay(partnerKp, partnerAgentPDA, memberAttestationPDA),
+        "AttestationInvalid"
+      );
+      await pay(memberKp, memberAgentPDA, memberAttestationPDA);
+    });
+
+    it("revoked attestations no longer grant access", async () => {
+      await program.methods
+        .revokeAttestation()
+        .accounts({ attestation: memberAttestationPDA, issuer: orgKp.publicKey })
+        .signers([orgKp])
+        .rpc();
+      await expectPayError(
+        pay(memberKp, memberAgentPDA),
+        "AttestationRequired"
+      );
+
+      await setAccess(null);
+      await pay(memberKp, memberAgentPDA);
+    });
+  });
+
+  describe("service capacity", () => {
+    let providerKp: Keypair;
+    let buyerKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let buyerAgentPDA: PublicKey;
+    let busySvcPDA: PublicKey;
+    let firstPaymentPDA: PublicKey;
+    const price = 10_000;
+
+    async function pay(): Promise<PublicKey> {
+      const svc = await program.account.service.fetch(busySvcPDA);
+      const [paymentPDA] = findPaymentPDA(
+        buyerAgentPDA,
+        busySvcPDA,
+        svc.totalOrders.toNumber()
+      );
+      const [escrowPDA] = findEscrowPDA(paymentPDA);
+      await program.methods
+        .payForService(
+          new anchor.BN(price),
+          new anchor.BN(1),
+          "Render a video",
+          [],
+          new anchor.BN(3600)
+        )
+        .accounts({
+          payment: paymentPDA,
+          escrow: escrowPDA,
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: busySvcPDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+      return paymentPDA;
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      buyerKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(providerKp, "BusyProvider");
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "BusyBuyer");
+
+      [busySvcPDA] = findServicePDA(providerAgentPDA, "busy-svc");
+      await program.methods
+        .createService(
+          "busy-svc",
+          "Video Rendering",
+          "One render at a time",
+          new anchor.BN(price),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: busySvcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+
+      await program.methods
+        .setCapacity(new anchor.BN(1))
+        .accounts({ service: busySvcPDA, authority: providerKp.publicKey })
+        .signers([providerKp])
+        .rpc();
+    });
+
+    it("accepts orders up to the limit", async () => {
+      firstPaymentPDA = await pay();
+      const svc = await program.account.service.fetch(busySvcPDA);
+      expect(svc.openOrders.toNumber()).to.equal(1);
+      expect(svc.maxConcurrentOrders.toNumber()).to.equal(1);
+    });
+
+    it("rejects new orders while the service is full", async () => {
+      try {
+        await pay();
+        expect.fail("Should have thrown ServiceAtCapacity");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ServiceAtCapacity");
+      }
+    });
+
+    it("frees a slot when an order is refunded", async () => {
+      await program.methods
+        .refundPayment()
+        .accounts({
+          payment: firstPaymentPDA,
+          escrow: findEscrowPDA(firstPaymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          service: busySvcPDA,
+          payerAuthority: buyerKp.publicKey,
+          authority: buyerKp.publicKey,
+        })
+        .signers([buyerKp])
+        .rpc();
+
+      await pay();
+      const svc = await program.account.service.fetch(busySvcPDA);
+      expect(svc.openOrders.toNumber()).to.equal(1);
+    });
+  });
+
+  describe("delivery SLA", () => {
+    let providerKp: Keypair;
+    let buyerKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let buyerAgentPDA: PublicKey;
+    let slaSvcPDA: PublicKey;
+    let paymentPDA: PublicKey;
+    let escrowPDA: PublicKey;
+    const price = 1_000_000;
+    const maxDeliverySeconds = 2;
+
+    async function pay(conditions: any[] = []): Promise<void> {
+      const svc = await program.account.service.fetch(slaSvcPDA);
+      [paymentPDA] = findPaymentPDA(
+        buyerAgentPDA,
+        slaSvcPDA,
+        svc.totalOrders.toNumber()
+      );
+      [escrowPDA] = findEscrowPDA(paymentPDA);
+      await program.methods
+        .payForService(
+          new anchor.BN(price),
+          new anchor.BN(1),
+          "Translate a document",
+          conditions,
+          new anchor.BN(3600)
+        )
+        .accounts({
+          payment: paymentPDA,
+          escrow: escrowPDA,
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: slaSvcPDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+    }
+
+    function release() {
+      return program.methods
+        .releasePayment()
+        .accounts({
+          payment: paymentPDA,
+          escrow: escrowPDA,
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: slaSvcPDA,
+          receiverAuthority: providerKp.publicKey,
+          referrerAgent: null,
+          referrerAuthority: null,
+          authority: buyerKp.publicKey,
+        })
+        .signers([buyerKp])
+        .rpc();
+    }
+
+    function markDelivered(signer: Keypair) {
+      return program.methods
+        .markDelivered()
+        .accounts({
+          payment: paymentPDA,
+          receiverAgent: providerAgentPDA,
+          authority: signer.publicKey,
+        })
+        .signers([signer])
+        .rpc();
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      buyerKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(providerKp, "SlaProvider");
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "SlaBuyer");
+
+      [slaSvcPDA] = findServicePDA(providerAgentPDA, "sla-svc");
+      await program.methods
+        .createService(
+          "sla-svc",
+          "Translation",
+          "Delivered within the SLA or partly refunded",
+          new anchor.BN(price),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: slaSvcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+    });
+
+    it("rejects penalty steps that are not ascending", async () => {
+      try {
+        await program.methods
+          .setSla({
+            maxAckSeconds: new anchor.BN(0),
+            maxDeliverySeconds: new anchor.BN(maxDeliverySeconds),
+            penalties: [
+              { lateBySeconds: new anchor.BN(60), refundBps: 5000 },
+              { lateBySeconds: new anchor.BN(0), refundBps: 1000 },
+            ],
+          })
+          .accounts({ service: slaSvcPDA, authority: providerKp.publicKey })
+          .signers([providerKp])
+          .rpc();
+        expect.fail("Should have thrown InvalidSla");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidSla");
+      }
+    });
+
+    it("copies the service SLA into new payments", async () => {
+      // 20% back on any late delivery
+      await program.methods
+        .setSla({
+          maxAckSeconds: new anchor.BN(0),
+          maxDeliverySeconds: new anchor.BN(maxDeliverySeconds),
+          penalties: [{ lateBySeconds: new anchor.BN(0), refundBps: 2000 }],
+        })
+        .accounts({ service: slaSvcPDA, authority: providerKp.publicKey })
+        .signers([providerKp])
+        .rpc();
+
+      await pay();
+      const payment = await program.account.payment.fetch(paymentPDA);
+      expect(payment.sla.maxDeliverySeconds.toNumber()).to.equal(
+        maxDeliverySeconds
+      );
+      expect(payment.deliveredAt.toNumber()).to.equal(0);
+    });
+
+    it("only lets the provider mark delivery", async () => {
+      try {
+        await markDelivered(buyerKp);
+        expect.fail("Should have thrown Unauthorized");
+      } catch (err: any) {
+        expect(err.toString()).to.include("Unauthorized");
+      }
+    });
+
+    it("pays in full when delivered on time", async () => {
+      await markDelivered(providerKp);
+      const payment = await program.account.payment.fetch(paymentPDA);
+      expect(payment.deliveredAt.toNumber()).to.be.greaterThan(0);
+      expect(payment.acknowledgedAt.toNumber()).to.equal(
+        payment.deliveredAt.toNumber()
+      );
+
+      try {
+        await markDelivered(providerKp);
+        expect.fail("Should have thrown AlreadyDelivered");
+      } catch (err: any) {
+        expect(err.toString()).to.include("AlreadyDelivered");
+      }
+
+      // Releasing late doesn't matter once delivery was recorded in time
+      await new Promise((resolve) =>
+        setTimeout(resolve, (maxDeliverySeconds + 2) * 1000)
+      );
+      const providerBefore = await provider.connection.getBalance(
+        providerKp.publicKey
+      );
+      await release();
+      const providerAfter = await provider.connection.getBalance(
+        providerKp.publicKey
+      );
+      expect(providerAfter - providerBefore).to.equal(price);
+
+      const svc = await program.account.service.fetch(slaSvcPDA);
+      expect(svc.slaOrders.toNumber()).to.equal(1);
+      expect(svc.slaMet.toNumber()).to.equal(1);
+    });
+
+    it("refunds the penalty to the payer when delivery is late", async () => {
+      await pay();
+      await new Promise((resolve) =>
+        setTimeout(resolve, (maxDeliverySeconds + 2) * 1000)
+      );
+
+      const providerBefore = await provider.connection.getBalance(
+        providerKp.publicKey
+      );
+      await release();
+      const providerAfter = await provider.connection.getBalance(
+        providerKp.publicKey
+      );
+      expect(providerAfter - providerBefore).to.equal(800_000);
+
+      const svc = await program.account.service.fetch(slaSvcPDA);
+      expect(svc.slaOrders.toNumber()).to.equal(2);
+      expect(svc.slaMet.toNumber()).to.equal(1);
+      expect(svc.totalRevenue.toNumber()).to.equal(1_800_000);
+
+      const providerAgent = await program.account.agent.fetch(
+        providerAgentPDA
+      );
+      expect(providerAgent.slaOrders.toNumber()).to.equal(2);
+      expect(providerAgent.slaMet.toNumber()).to.equal(1);
+
+      const buyer = await program.account.agent.fetch(buyerAgentPDA);
+      expect(buyer.totalSpent.toNumber()).to.equal(1_800_000);
+    });
+
+    it("applies the penalty to conditional releases too", async () => {
+      await pay([
+        { notBefore: { at: new anchor.BN(Math.floor(Date.now() / 1000)) } },
+      ]);
+      await new Promise((resolve) =>
+        setTimeout(resolve, (maxDeliverySeconds + 2) * 1000)
+      );
+
+      const [buyerBefore, providerBefore] = await Promise.all(
+        [buyerKp, providerKp].map((kp) =>
+          provider.connection.getBalance(kp.publicKey)
+        )
+      );
+      await program.methods
+        .executeConditionalRelease([])
+        .accounts({
+          payment: paymentPDA,
+          escrow: escrowPDA,
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: slaSvcPDA,
+          payerAuthority: buyerKp.publicKey,
+          receiverAuthority: providerKp.publicKey,
+          referrerAgent: null,
+          referrerAuthority: null,
+        })
+        .rpc();
+
+      expect(
+        (await provider.connection.getBalance(buyerKp.publicKey)) - buyerBefore
+      ).to.equal(200_000);
+      expect(
+        (await provider.connection.getBalance(providerKp.publicKey)) -
+          providerBefore
+      ).to.equal(800_000);
+      const svc = await program.account.service.fetch(slaSvcPDA);
+      expect(svc.slaOrders.toNumber()).to.equal(3);
+      expect(svc.slaMet.toNumber()).to.equal(1);
+    });
+  });
+
+  describe("discovery indexes", () => {
+    let providerKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let svcAPDA: PublicKey;
+    let svcBPDA: PublicKey;
+
+    function createService(serviceId: string, tags: string[]) {
+      const [servicePDA] = findServicePDA(providerAgentPDA, serviceId);
+      return program.methods
+        .createService(
+          serviceId,
+          "Document OCR",
+          "Scanned documents to text",
+          new anchor.BN(10_000),
+          { fixed: {} },
+          tags
+        )
+        .accounts({
+          service: servicePDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(indexAccounts("tag", servicePDA, [], tags))
+        .signers([providerKp])
+        .rpc();
+    }
+
+    // Every entry listed under a key, found by filtering on kind and key
+    async function listIndex(
+      kind: "tag" | "capability",
+      key: string
+    ): Promise<string[]> {
+      const len = Buffer.alloc(4);
+      len.writeUInt32LE(key.length);
+      const prefix = Buffer.concat([
+        Buffer.from([kind === "tag" ? 0 : 1]),
+        len,
+        Buffer.from(key),
+      ]);
+      const entries = await program.account.indexEntry.all([
+        {
+          memcmp: {
+            offset: 8,
+            bytes: anchor.utils.bytes.bs58.encode(prefix),
+          },
+        },
+      ]);
+      return entries.map((e) => e.account.entry.toBase58()).sort();
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(
+        providerKp,
+        "IndexedProvider",
+        "Reads documents",
+        ["IX-Vision", "ix-ocr"]
+      );
+      [svcAPDA] = findServicePDA(providerAgentPDA, "ix-svc-a");
+      [svcBPDA] = findServicePDA(providerAgentPDA, "ix-svc-b");
+    });
+
+    it("lists a new agent under its normalised capabilities", async () => {
+      const entry = await program.account.indexEntry.fetch(
+        findIndexPDA("capability", "ix-vision", providerAgentPDA)[0]
+      );
+      expect(entry.key).to.equal("ix-vision");
+      expect(entry.entry.toBase58()).to.equal(providerAgentPDA.toBase58());
+    });
+
+    it("skips blank capabilities instead of rejecting the agent", async () => {
+      const kp = await createFundedKeypair();
+      const agentPDA = await registerAgentHelper(kp, "Sloppy", "Blank cap", [
+        "  ",
+        "ix-blank",
+      ]);
+      const agent = await program.account.agent.fetch(agentPDA);
+      expect(agent.capabilities).to.deep.equal(["  ", "ix-blank"]);
+      expect(await listIndex("capability", "ix-blank")).to.deep.equal([
+        agentPDA.toBase58(),
+      ]);
+    });
+
+    it("rejects index accounts that don't match the tags", async () => {
+      try {
+        const [servicePDA] = findServicePDA(providerAgentPDA, "ix-svc-bad");
+        await program.methods
+          .createService(
+            "ix-svc-bad",
+            "Document OCR",
+            "Wrong index",
+            new anchor.BN(10_000),
+            { fixed: {} },
+            ["ix-ocr"]
+          )
+          .accounts({
+            service: servicePDA,
+            agent: providerAgentPDA,
+            authority: providerKp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .remainingAccounts(indexAccounts("tag", servicePDA, [], ["ix-pdf"]))
+          .signers([providerKp])
+          .rpc();
+        expect.fail("Should have thrown IndexAccountMismatch");
+      } catch (err: any) {
+        expect(err.toString()).to.include("IndexAccountMismatch");
+      }
+    });
+
+    it("gives every service under a tag its own entry account", async () => {
+      await createService("ix-svc-a", ["IX-OCR"]);
+      await createService("ix-svc-b", ["ix-ocr"]);
+
+      const sizes = await Promise.all(
+        [svcAPDA, svcBPDA].map(
+          async (svc) =>
+            (
+              await provider.connection.getAccountInfo(
+                findIndexPDA("tag", "ix-ocr", svc)[0]
+              )
+            ).data.length
+        )
+      );
+      expect(sizes[0]).to.equal(sizes[1]);
+      expect(await listIndex("tag", "ix-ocr")).to.deep.equal(
+        [svcAPDA.toBase58(), svcBPDA.toBase58()].sort()
+      );
+    });
+
+    it("indexes a service whose entry account was pre-funded", async () => {
+      // Anyone can send lamports to the entry PDA before the service exists
+      const [svcPDA] = findServicePDA(providerAgentPDA, "ix-svc-funded");
+      const [indexPDA] = findIndexPDA("tag", "ix-funded", svcPDA);
+      await airdrop(indexPDA, 0.001);
+
+      await createService("ix-svc-funded", ["ix-funded"]);
+      const entry = await program.account.indexEntry.fetch(indexPDA);
+      expect(entry.entry.toBase58()).to.equal(svcPDA.toBase58());
+    });
+
+    it("moves a service between indexes when its tags change", async () => {
+      await program.methods
+        .updateService(null, null, null, null, ["ix-pdf"])
+        .accounts({
+          service: svcAPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          indexAccounts("tag", svcAPDA, ["IX-OCR"], ["ix-pdf"])
+        )
+        .signers([providerKp])
+        .rpc();
+
+      expect(await listIndex("tag", "ix-ocr")).to.deep.equal([
+        svcBPDA.toBase58(),
+      ]);
+      expect(await listIndex("tag", "ix-pdf")).to.deep.equal([
+        svcAPDA.toBase58(),
+      ]);
+    });
+
+    it("closes a service's entries when it is closed", async () => {
+      await program.methods
+        .closeService()
+        .accounts({ service: svcAPDA, authority: providerKp.publicKey })
+        .remainingAccounts(indexAccounts("tag", svcAPDA, ["ix-pdf"], []))
+        .signers([providerKp])
+        .rpc();
+
+      const info = await provider.connection.getAccountInfo(
+        findIndexPDA("tag", "ix-pdf", svcAPDA)[0]
+      );
+      expect(info).to.be.null;
+      expect(await listIndex("tag", "ix-pdf")).to.deep.equal([]);
+    });
+
+    it("updates capability indexes when an agent's capabilities change", async () => {
+      await program.methods
+        .updateCapabilities(["ix-vision", "ix-layout"])
+        .accounts({
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          indexAccounts(
+            "capability",
+            providerAgentPDA,
+            ["IX-Vision", "ix-ocr"],
+            ["ix-vision", "ix-layout"]
+          )
+        )
+        .signers([providerKp])
+        .rpc();
+
+      const agent = await program.account.agent.fetch(providerAgentPDA);
+      expect(agent.capabilities).to.deep.equal(["ix-vision", "ix-layout"]);
+      expect(
+        await provider.connection.getAccountInfo(
+          findIndexPDA("capability", "ix-ocr", providerAgentPDA)[0]
+        )
+      ).to.be.null;
+      expect(await listIndex("capability", "ix-layout")).to.deep.equal([
+        providerAgentPDA.toBase58(),
+      ]);
+    });
+  });
+
+  describe("service categories", () => {
+    let providerKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let catSvcPDA: PublicKey;
+    const TRANSLATION = 9001;
+    const VISION = 9002;
+
+    function findCategoryPDA(id: number): PublicKey {
+      const idBuf = Buffer.alloc(2);
+      idBuf.writeUInt16LE(id);
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("category"), idBuf],
+        program.programId
+      )[0];
+    }
+
+    function createCategory(id: number, name: string, signer?: Keypair) {
+      const builder = program.methods
+        .createCategory(id, name, `${name} services`)
+        .accounts({
+          category: findCategoryPDA(id),
+          protocol: findProtocolPDA()[0],
+          authority: signer ? signer.publicKey : authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        });
+      return signer ? builder.signers([signer]).rpc() : builder.rpc();
+    }
+
+    function setCategories(ids: number[], accounts: number[] = ids) {
+      return program.methods
+        .setServiceCategories(ids)
+        .accounts({ service: catSvcPDA, authority: providerKp.publicKey })
+        .remainingAccounts(
+          accounts.map((id) => ({
+            pubkey: findCategoryPDA(id),
+            isWritable: false,
+            isSigner: false,
+          }))
+        )
+        .signers([providerKp])
+        .rpc();
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(providerKp, "CatProvider");
+
+      [catSvcPDA] = findServicePDA(providerAgentPDA, "cat-svc");
+      await program.methods
+        .createService(
+          "cat-svc",
+          "Translation",
+          "Categorised listing",
+          new anchor.BN(10_000),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: catSvcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+    });
+
+    it("only lets the protocol authority create categories", async () => {
+      try {
+        await createCategory(TRANSLATION, "Translation", providerKp);
+        expect.fail("Should have thrown Unauthorized");
+      } catch (err: any) {
+        expect(err.toString()).to.include("Unauthorized");
+      }
+
+      await createCategory(TRANSLATION, "Translation");
+      await createCategory(VISION, "Vision");
+      const category = await program.account.category.fetch(
+        findCategoryPDA(TRANSLATION)
+      );
+      expect(category.id).to.equal(TRANSLATION);
+      expect(category.isActive).to.be.true;
+    });
+
+    it("assigns categories to a service", async () => {
+      await setCategories([TRANSLATION, VISION]);
+      const svc = await program.account.service.fetch(catSvcPDA);
+      expect(svc.categories).to.deep.equal([TRANSLATION, VISION]);
+    });
+
+    it("rejects category accounts that don't match the ids", async () => {
+      try {
+        await setCategories([TRANSLATION], [VISION]);
+        expect.fail("Should have thrown InvalidCategory");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidCategory");
+      }
+      try {
+        await setCategories([TRANSLATION, TRANSLATION]);
+        expect.fail("Should have thrown InvalidCategory");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidCategory");
+      }
+    });
+
+    it("rejects retired categories for new assignments", async () => {
+      await program.methods
+        .updateCategory(null, null, false)
+        .accounts({
+          category: findCategoryPDA(VISION),
+          protocol: findProtocolPDA()[0],
+          authority: authority.publicKey,
+        })
+        .rpc();
+
+      try {
+        await setCategories([VISION]);
+        expect.fail("Should have thrown CategoryInactive");
+      } catch (err: any) {
+        expect(err.toString()).to.include("CategoryInactive");
+      }
+
+      // The service keeps the retired category until it changes its list
+      const svc = await program.account.service.fetch(catSvcPDA);
+      expect(svc.categories).to.include(VISION);
+      await setCategories([TRANSLATION]);
+      expect(
+        (await program.account.service.fetch(catSvcPDA)).categories
+      ).to.deep.equal([TRANSLATION]);
+    });
+  });
+
+  describe("service bundles", () => {
+    let orchestratorKp: Keypair;
+    let orchestratorAgentPDA: PublicKey;
+    let providerAKp: Keypair;
+    let providerAAgentPDA: PublicKey;
+    let providerBKp: Keypair;
+    let providerBAgentPDA: PublicKey;
+    let buyerKp: Keypair;
+    let buyerAgentPDA: PublicKey;
+    let svcAPDA: PublicKey;
+    let svcBPDA: PublicKey;
+    let bundlePDA: PublicKey;
+    const PRICE = 1_000_000;
+    const payments: PublicKey[] = [];
+
+    function findBundlePDA(agent: PublicKey, bundleId: string): PublicKey {
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("bundle"), agent.toBuffer(), Buffer.from(bundleId)],
+        program.programId
+      )[0];
+    }
+
+    async function createServiceFor(
+      kp: Keypair,
+      agentPDA: PublicKey,
+      serviceId: string
+    ): Promise<PublicKey> {
+      const [servicePDA] = findServicePDA(agentPDA, serviceId);
+      await program.methods
+        .createService(
+          serviceId,
+          "Component",
+          "Bundle component",
+          new anchor.BN(500_000),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: servicePDA,
+          agent: agentPDA,
+          authority: kp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([kp])
+        .rpc();
+      return servicePDA;
+    }
+
+    function components(shareA: number, shareB: number) {
+      return [
+        { service: svcAPDA, provider: providerAAgentPDA, shareBps: shareA },
+        { service: svcBPDA, provider: providerBAgentPDA, shareBps: shareB },
+      ];
+    }
+
+    function ratingAccounts(comps = components(0, 0)) {
+      return comps
+        .flatMap((c) => [c.service, c.provider])
+        .map((pubkey) => ({ pubkey, isWritable: false, isSigner: false }));
+    }
+
+    // Every component provider co-signs; `unsigned` passes their keys
+    // without signatures
+    function createBundle(
+      bundleId: string,
+      comps: ReturnType<typeof components>,
+      providerKps: Keypair[] = [providerAKp, providerBKp],
+      unsigned: Keypair[] = []
+    ) {
+      return program.methods
+        .createBundle(
+          bundleId,
+          "Translate + summarise",
+          "Two-step pipeline",
+          new anchor.BN(PRICE),
+          comps
+        )
+        .accounts({
+          bundle: findBundlePDA(orchestratorAgentPDA, bundleId),
+          agent: orchestratorAgentPDA,
+          authority: orchestratorKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts([
+          ...ratingAccounts(comps),
+          ...providerKps.map((kp) => ({
+            pubkey: kp.publicKey,
+            isWritable: false,
+            isSigner: !unsigned.includes(kp),
+          })),
+        ])
+        .signers([
+          orchestratorKp,
+          ...providerKps.filter((kp) => !unsigned.includes(kp)),
+        ])
+        .rpc();
+    }
+
+    async function pay(): Promise<PublicKey> {
+      const bundle = await program.account.bundle.fetch(bundlePDA);
+      const [paymentPDA] = findPaymentPDA(
+        buyerAgentPDA,
+        bundlePDA,
+        bundle.totalOrders.toNumber()
+      );
+      await program.methods
+        .payForBundle(
+          new anchor.BN(PRICE),
+          "Run the pipeline",
+          new anchor.BN(3600)
+        )
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          bundle: bundlePDA,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+      payments.push(paymentPDA);
+      return paymentPDA;
+    }
+
+    function release(paymentPDA: PublicKey, providerAccounts: PublicKey[]) {
+      return program.methods
+        .releaseBundlePayment()
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          bundle: bundlePDA,
+          orchestratorAgent: orchestratorAgentPDA,
+          orchestratorAuthority: orchestratorKp.publicKey,
+          authority: buyerKp.publicKey,
+        })
+        .remainingAccounts(
+          providerAccounts.map((pubkey) => ({
+            pubkey,
+            isWritable: true,
+            isSigner: false,
+          }))
+        )
+        .signers([buyerKp])
+        .rpc();
+    }
+
+    before(async () => {
+      orchestratorKp = await createFundedKeypair();
+      orchestratorAgentPDA = await registerAgentHelper(orchestratorKp, "Orchestrator");
+      providerAKp = await createFundedKeypair();
+      providerAAgentPDA = await registerAgentHelper(providerAKp, "BundleProvA");
+      providerBKp = await createFundedKeypair();
+      providerBAgentPDA = await registerAgentHelper(providerBKp, "BundleProvB");
+      buyerKp = await createFundedKeypair();
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "BundleBuyer");
+
+      svcAPDA = await createServiceFor(providerAKp, providerAAgentPDA, "bundle-a");
+      svcBPDA = await createServiceFor(providerBKp, providerBAgentPDA, "bundle-b");
+      bundlePDA = findBundlePDA(orchestratorAgentPDA, "pipeline");
+    });
+
+    it("rejects shares over 100% and mismatched components", async () => {
+      try {
+        await createBundle("too-much", components(6_000, 5_000));
+        expect.fail("Should have thrown InvalidBundle");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidBundle");
+      }
+
+      try {
+        await createBundle("wrong-provider", [
+          { service: svcAPDA, provider: providerBAgentPDA, shareBps: 1_000 },
+          { service: svcBPDA, provider: providerAAgentPDA, shareBps: 1_000 },
+        ]);
+        expect.fail("Should have thrown BundleComponentMismatch");
+      } catch (err: any) {
+        expect(err.toString()).to.include("BundleComponentMismatch");
+      }
+    });
+
+    it("rejects bundles listing one provider twice", async () => {
+      const svcA2PDA = await createServiceFor(
+        providerAKp,
+        providerAAgentPDA,
+        "bundle-a2"
+      );
+      try {
+        await createBundle(
+          "same-provider",
+          [
+            { service: svcAPDA, provider: providerAAgentPDA, shareBps: 3_000 },
+            { service: svcA2PDA, provider: providerAAgentPDA, shareBps: 3_000 },
+          ],
+          [providerAKp, providerAKp]
+        );
+        expect.fail("Should have thrown InvalidBundle");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidBundle");
+      }
+    });
+
+    it("requires every component provider to sign", async () => {
+      try {
+        await createBundle(
+          "unsigned",
+          components(4_000, 3_000),
+          [providerAKp, providerBKp],
+          [providerBKp]
+        );
+        expect.fail("Should have thrown ComponentProviderNotSigner");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ComponentProviderNotSigner");
+      }
+    });
+
+    it("creates a bundle over two providers' services", async () => {
+      await createBundle("pipeline", components(4_000, 3_000));
+
+      const bundle = await program.account.bundle.fetch(bundlePDA);
+      expect(bundle.orchestrator.toBase58()).to.equal(
+        orchestratorAgentPDA.toBase58()
+      );
+      expect(bundle.components.length).to.equal(2);
+      expect(bundle.components[0].shareBps).to.equal(4_000);
+      expect(bundle.isActive).to.be.true;
+    });
+
+    it("splits one bundle payment between every provider on release", async () => {
+      const paymentPDA = await pay();
+      const payment = await program.account.payment.fetch(paymentPDA);
+      expect(payment.service.toBase58()).to.equal(bundlePDA.toBase58());
+      expect(payment.receiver.toBase58()).to.equal(
+        orchestratorAgentPDA.toBase58()
+      );
+
+      const before = await Promise.all(
+        [providerAKp, providerBKp, orchestratorKp].map((kp) =>
+          provider.connection.getBalance(kp.publicKey)
+        )
+      );
+
+      // Component accounts are checked in bundle order
+      try {
+        await release(paymentPDA, [
+          providerBAgentPDA,
+          providerBKp.publicKey,
+          providerAAgentPDA,
+          providerAKp.publicKey,
+        ]);
+        expect.fail("Should have thrown BundleComponentMismatch");
+      } catch (err: any) {
+        expect(err.toString()).to.include("BundleComponentMismatch");
+      }
+
+      await release(paymentPDA, [
+        providerAAgentPDA,
+        providerAKp.publicKey,
+        providerBAgentPDA,
+        providerBKp.publicKey,
+      ]);
+
+      const after = await Promise.all(
+        [providerAKp, providerBKp, orchestratorKp].map((kp) =>
+          provider.connection.getBalance(kp.publicKey)
+        )
+      );
+      expect(after[0] - before[0]).to.equal(400_000);
+      expect(after[1] - before[1]).to.equal(300_000);
+      expect(after[2] - before[2]).to.equal(300_000);
+
+      const provA = await program.account.agent.fetch(providerAAgentPDA);
+      expect(provA.totalEarned.toNumber()).to.equal(400_000);
+      const orchestrator = await program.account.agent.fetch(orchestratorAgentPDA);
+      expect(orchestrator.totalEarned.toNumber()).to.equal(300_000);
+      const bundle = await program.account.bundle.fetch(bundlePDA);
+      expect(bundle.totalRevenue.toNumber()).to.equal(PRICE);
+      expect(bundle.openOrders.toNumber()).to.equal(0);
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ released: {} });
+    });
+
+    it("pauses and resumes new bundle orders", async () => {
+      await program.methods
+        .pauseBundle()
+        .accounts({ bundle: bundlePDA, authority: orchestratorKp.publicKey })
+        .signers([orchestratorKp])
+        .rpc();
+      expect((await program.account.bundle.fetch(bundlePDA)).isActive).to.be
+        .false;
+
+      try {
+        await pay();
+        expect.fail("Should have thrown ServiceInactive");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ServiceInactive");
+      }
+
+      await program.methods
+        .resumeBundle()
+        .accounts({ bundle: bundlePDA, authority: orchestratorKp.publicKey })
+        .signers([orchestratorKp])
+        .rpc();
+      expect((await program.account.bundle.fetch(bundlePDA)).isActive).to.be
+        .true;
+    });
+
+    it("pays bundle components their shares of a dispute award", async () => {
+      const paymentPDA = await pay();
+      const disputePDA = PublicKey.findProgramAddressSync(
+        [Buffer.from("dispute"), paymentPDA.toBuffer()],
+        program.programId
+      )[0];
+      await program.methods
+        .openDispute("Second step never ran")
+        .accounts({
+          dispute: disputePDA,
+          payment: paymentPDA,
+          agent: buyerAgentPDA,
+          listing: bundlePDA,
+          arbiterPool: null,
+          protocol: findProtocolPDA()[0],
+          authority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+
+      const before = await Promise.all(
+        [buyerKp, providerAKp, providerBKp, orchestratorKp].map((kp) =>
+          provider.connection.getBalance(kp.publicKey)
+        )
+      );
+      await program.methods
+        .resolveDispute(new anchor.BN(250_000))
+        .accounts({
+          dispute: disputePDA,
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: orchestratorAgentPDA,
+          payerAuthority: buyerKp.publicKey,
+          receiverAuthority: orchestratorKp.publicKey,
+          listing: bundlePDA,
+          arbiterAccount: null,
+          arbiter: authority.publicKey,
+          referrerAgent: null,
+          referrerAuthority: null,
+        })
+        .remainingAccounts(
+          [
+            providerAAgentPDA,
+            providerAKp.publicKey,
+            providerBAgentPDA,
+            providerBKp.publicKey,
+          ].map((pubkey) => ({ pubkey, isWritable: true, isSigner: false }))
+        )
+        .rpc();
+
+      const after = await Promise.all(
+        [buyerKp, providerAKp, providerBKp, orchestratorKp].map((kp) =>
+          provider.connection.getBalance(kp.publicKey)
+        )
+      );
+      expect(after[0] - before[0]).to.equal(250_000);
+      expect(after[1] - before[1]).to.equal(300_000);
+      expect(after[2] - before[2]).to.equal(225_000);
+      expect(after[3] - before[3]).to.equal(225_000);
+      const bundle = await program.account.bundle.fetch(bundlePDA);
+      expect(bundle.openOrders.toNumber()).to.equal(0);
+      expect(bundle.totalRevenue.toNumber()).to.equal(PRICE + 750_000);
+    });
+
+    it("settles a disputed bundle payment through the component split", async () => {
+      const paymentPDA = await pay();
+      const disputePDA = PublicKey.findProgramAddressSync(
+        [Buffer.from("dispute"), paymentPDA.toBuffer()],
+        program.programId
+      )[0];
+      await program.methods
+        .openDispute("Agreed to a partial refund")
+        .accounts({
+          dispute: disputePDA,
+          payment: paymentPDA,
+          agent: buyerAgentPDA,
+          listing: bundlePDA,
+          arbiterPool: null,
+          protocol: findProtocolPDA()[0],
+          authority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+
+      const rent = await provider.connection.getBalance(paymentPDA);
+      const before = await Promise.all(
+        [buyerKp, providerAKp, providerBKp, orchestratorKp].map((kp) =>
+          provider.connection.getBalance(kp.publicKey)
+        )
+      );
+      await program.methods
+        .settlePayment(new anchor.BN(200_000))
+        .accounts({
+          payment: paymentPDA,
+          dispute: disputePDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: orchestratorAgentPDA,
+          listing: bundlePDA,
+          referrerAgent: null,
+          referrerAuthority: null,
+          payerAuthority: buyerKp.publicKey,
+          receiverAuthority: orchestratorKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(
+          [
+            providerAAgentPDA,
+            providerAKp.publicKey,
+            providerBAgentPDA,
+            providerBKp.publicKey,
+          ].map((pubkey) => ({ pubkey, isWritable: true, isSigner: false }))
+        )
+        .signers([buyerKp, orchestratorKp])
+        .rpc();
+
+      const after = await Promise.all(
+        [buyerKp, providerAKp, providerBKp, orchestratorKp].map((kp) =>
+          provider.connection.getBalance(kp.publicKey)
+        )
+      );
+      expect(after[0] - before[0]).to.equal(200_000 + rent);
+      expect(after[1] - before[1]).to.equal(320_000);
+      expect(after[2] - before[2]).to.equal(240_000);
+      expect(after[3] - before[3]).to.equal(240_000);
+      expect(await program.account.payment.fetchNullable(paymentPDA)).to.be
+        .null;
+      const dispute = await program.account.dispute.fetch(disputePDA);
+      expect(dispute.status).to.deep.equal({ resolved: {} });
+      expect(dispute.receiverAmount.toNumber()).to.equal(800_000);
+      const bundle = await program.account.bundle.fetch(bundlePDA);
+      expect(bundle.openOrders.toNumber()).to.equal(0);
+      expect(bundle.totalRevenue.toNumber()).to.equal(PRICE + 1_550_000);
+    });
+
+    it("refunds a bundle payment and blocks closing while orders are open", async () => {
+      const paymentPDA = await pay();
+
+      try {
+        await program.methods
+          .closeBundle()
+          .accounts({ bundle: bundlePDA, authority: orchestratorKp.publicKey })
+          .signers([orchestratorKp])
+          .rpc();
+        expect.fail("Should have thrown ServiceHasOpenOrders");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ServiceHasOpenOrders");
+      }
+
+      await program.methods
+        .refundBundlePayment()
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          bundle: bundlePDA,
+          payerAuthority: buyerKp.publicKey,
+          authority: buyerKp.publicKey,
+        })
+        .signers([buyerKp])
+        .rpc();
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ refunded: {} });
+
+      await program.methods
+        .refreshBundleRating()
+        .accounts({ bundle: bundlePDA })
+        .remainingAccounts(ratingAccounts())
+        .rpc();
+
+      for (const paymentPDA of payments) {
+        await program.methods
+          .closePayment()
+          .accounts({
+            payment: paymentPDA,
+            payerAgent: buyerAgentPDA,
+            listing: bundlePDA,
+            payerAuthority: buyerKp.publicKey,
+          })
+          .signers([buyerKp])
+          .rpc();
+      }
+      await program.methods
+        .closeBundle()
+        .accounts({ bundle: bundlePDA, authority: orchestratorKp.publicKey })
+        .signers([orchestratorKp])
+        .rpc();
+      expect(await provider.connection.getAccountInfo(bundlePDA)).to.be.null;
+    });
+  });
+
+  describe("disputes", () => {
+    let providerKp: Keypair;
+    let buyerKp: Keypair;
+    let outsiderKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let buyerAgentPDA: PublicKey;
+    let outsiderAgentPDA: PublicKey;
+    let svcPDA: PublicKey;
+    const price = 1_000_000;
+
+    function findDisputePDA(paymentPDA: PublicKey): PublicKey {
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("dispute"), paymentPDA.toBuffer()],
+        program.programId
+      )[0];
+    }
+
+    async function pay(timeoutSeconds = 3600): Promise<PublicKey> {
+      const svc = await program.account.service.fetch(svcPDA);
+      const [paymentPDA] = findPaymentPDA(
+        buyerAgentPDA,
+        svcPDA,
+        svc.totalOrders.toNumber()
+      );
+      await program.methods
+        .payForService(
+          new anchor.BN(price),
+          new anchor.BN(1),
+          "Label 10k images",
+          [],
+          new anchor.BN(timeoutSeconds)
+        )
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: svcPDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+      return paymentPDA;
+    }
+
+    function openDispute(
+      paymentPDA: PublicKey,
+      kp: Keypair,
+      agentPDA: PublicKey
+    ) {
+      return program.methods
+        .openDispute("Labels are mostly wrong")
+        .accounts({
+          dispute: findDisputePDA(paymentPDA),
+          payment: paymentPDA,
+          agent: agentPDA,
+          listing: svcPDA,
+          arbiterPool: null,
+          protocol: findProtocolPDA()[0],
+          authority: kp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([kp])
+        .rpc();
+    }
+
+    function resolve(paymentPDA: PublicKey, payerAmount: number) {
+      return program.methods
+        .resolveDispute(new anchor.BN(payerAmount))
+        .accounts({
+          dispute: findDisputePDA(paymentPDA),
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          payerAuthority: buyerKp.publicKey,
+          receiverAuthority: providerKp.publicKey,
+          listing: svcPDA,
+          arbiterAccount: null,
+          arbiter: authority.publicKey,
+          referrerAgent: null,
+          referrerAuthority: null,
+        })
+        .rpc();
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      buyerKp = await createFundedKeypair();
+      outsiderKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(providerKp, "DisputeProvider");
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "DisputeBuyer");
+      outsiderAgentPDA = await registerAgentHelper(outsiderKp, "DisputeOutsider");
+
+      [svcPDA] = findServicePDA(providerAgentPDA, "dispute-svc");
+      await program.methods
+        .createService(
+          "dispute-svc",
+          "Image labelling",
+          "Bulk labelling",
+          new anchor.BN(price),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: svcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+    });
+
+    it("only lets the payer or receiver open a dispute", async () => {
+      const paymentPDA = await pay();
+      try {
+        await openDispute(paymentPDA, outsiderKp, outsiderAgentPDA);
+        expect.fail("Should have thrown NotPaymentParty");
+      } catch (err: any) {
+        expect(err.toString()).to.include("NotPaymentParty");
+      }
+
+      await openDispute(paymentPDA, buyerKp, buyerAgentPDA);
+      const dispute = await program.account.dispute.fetch(
+        findDisputePDA(paymentPDA)
+      );
+      expect(dispute.openedBy.toBase58()).to.equal(buyerAgentPDA.toBase58());
+      expect(dispute.arbiters.map((a) => a.toBase58())).to.deep.equal([
+        authority.publicKey.toBase58(),
+      ]);
+      // The protocol authority has a deadline too, so the escrow can't stay frozen
+      expect(dispute.voteDeadline.sub(dispute.openedAt).toNumber()).to.equal(
+        14 * 24 * 60 * 60
+      );
+      expect(dispute.status).to.deep.equal({ open: {} });
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ disputed: {} });
+    });
+
+    it("freezes timeout refunds while disputed", async () => {
+      const paymentPDA = await pay(1);
+      await openDispute(paymentPDA, providerKp, providerAgentPDA);
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+
+      try {
+        await program.methods
+          .refundPayment()
+          .accounts({
+            payment: paymentPDA,
+            escrow: findEscrowPDA(paymentPDA)[0],
+            payerAgent: buyerAgentPDA,
+            service: svcPDA,
+            payerAuthority: buyerKp.publicKey,
+            authority: outsiderKp.publicKey,
+          })
+          .signers([outsiderKp])
+          .rpc();
+        expect.fail("Should have thrown PaymentNotEscrowed");
+      } catch (err: any) {
+        expect(err.toString()).to.include("PaymentNotEscrowed");
+      }
+
+      // Settle it in the provider's favour so later stats are predictable
+      await resolve(paymentPDA, 0);
+      const providerAgent = await program.account.agent.fetch(providerAgentPDA);
+      expect(providerAgent.disputesWon.toNumber()).to.equal(1);
+      const buyer = await program.account.agent.fetch(buyerAgentPDA);
+      expect(buyer.disputesLost.toNumber()).to.equal(1);
+    });
+
+    it("records evidence from either party", async () => {
+      const [paymentPDA] = findPaymentPDA(buyerAgentPDA, svcPDA, 0);
+      const hash = Array.from({ length: 32 }, (_, i) => i);
+      await program.methods
+        .submitEvidence(hash, "ipfs://bafy-labels-sample")
+        .accounts({
+          dispute: findDisputePDA(paymentPDA),
+          agent: buyerAgentPDA,
+          authority: buyerKp.publicKey,
+        })
+        .signers([buyerKp])
+        .rpc();
+
+      try {
+        await program.methods
+          .submitEvidence(hash, "ipfs://not-a-party")
+          .accounts({
+            dispute: findDisputePDA(paymentPDA),
+            agent: outsiderAgentPDA,
+            authority: outsiderKp.publicKey,
+          })
+          .signers([outsiderKp])
+          .rpc();
+        expect.fail("Should have thrown NotPaymentParty");
+      } catch (err: any) {
+        expect(err.toString()).to.include("NotPaymentParty");
+      }
+
+      const dispute = await program.account.dispute.fetch(
+        findDisputePDA(paymentPDA)
+      );
+      expect(dispute.evidence.length).to.equal(1);
+      expect(dispute.evidence[0].hash).to.deep.equal(hash);
+      expect(dispute.evidence[0].uri).to.equal("ipfs://bafy-labels-sample");
+    });
+
+    it("lets only the arbiter split the escrow", async () => {
+      const [paymentPDA] = findPaymentPDA(buyerAgentPDA, svcPDA, 0);
+
+      try {
+        await program.methods
+          .resolveDispute(new anchor.BN(0))
+          .accounts({
+            dispute: findDisputePDA(paymentPDA),
+            payment: paymentPDA,
+            escrow: findEscrowPDA(paymentPDA)[0],
+            payerAgent: buyerAgentPDA,
+            receiverAgent: providerAgentPDA,
+            payerAuthority: buyerKp.publicKey,
+            receiverAuthority: providerKp.publicKey,
+            listing: svcPDA,
+            arbiterAccount: null,
+            arbiter: providerKp.publicKey,
+            referrerAgent: null,
+            referrerAuthority: null,
+          })
+          .signers([providerKp])
+          .rpc();
+        expect.fail("Should have thrown NotAssignedArbiter");
+      } catch (err: any) {
+        expect(err.toString()).to.include("NotAssignedArbiter");
+      }
+
+      try {
+        await resolve(paymentPDA, price + 1);
+        expect.fail("Should have thrown InvalidDisputeSplit");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidDisputeSplit");
+      }
+
+      const buyerBefore = await provider.connection.getBalance(buyerKp.publicKey);
+      const providerBefore = await provider.connection.getBalance(
+        providerKp.publicKey
+      );
+      await resolve(paymentPDA, 600_000);
+
+      expect(
+        (await provider.connection.getBalance(buyerKp.publicKey)) - buyerBefore
+      ).to.equal(600_000);
+      expect(
+        (await provider.connection.getBalance(providerKp.publicKey)) -
+          providerBefore
+      ).to.equal(400_000);
+
+      const dispute = await program.account.dispute.fetch(
+        findDisputePDA(paymentPDA)
+      );
+      expect(dispute.status).to.deep.equal({ resolved: {} });
+      expect(dispute.receiverAmount.toNumber()).to.equal(400_000);
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ released: {} });
+
+      const buyer = await program.account.agent.fetch(buyerAgentPDA);
+      expect(buyer.disputes.toNumber()).to.equal(2);
+      expect(buyer.disputesWon.toNumber()).to.equal(0);
+      expect(buyer.disputesLost.toNumber()).to.equal(1);
+      const svc = await program.account.service.fetch(svcPDA);
+      expect(svc.openOrders.toNumber()).to.equal(0);
+    });
+
+    it("caps evidence per party", async () => {
+      const paymentPDA = await pay();
+      await openDispute(paymentPDA, buyerKp, buyerAgentPDA);
+      const hash = Array.from({ length: 32 }, () => 7);
+      const submit = (kp: Keypair, agentPDA: PublicKey, uri: string) =>
+        program.methods
+          .submitEvidence(hash, uri)
+          .accounts({
+            dispute: findDisputePDA(paymentPDA),
+            agent: agentPDA,
+            authority: kp.publicKey,
+          })
+          .signers([kp])
+          .rpc();
+
+      for (let i = 0; i < 8; i++) {
+        await submit(buyerKp, buyerAgentPDA, `ipfs://buyer-${i}`);
+      }
+      try {
+        await submit(buyerKp, buyerAgentPDA, "ipfs://buyer-8");
+        expect.fail("Should have thrown EvidenceLimitReached");
+      } catch (err: any) {
+        expect(err.toString()).to.include("EvidenceLimitReached");
+      }
+
+      // A party flooding the record does not shut the other one out
+      await submit(providerKp, providerAgentPDA, "ipfs://provider-0");
+      const dispute = await program.account.dispute.fetch(
+        findDisputePDA(paymentPDA)
+      );
+      expect(dispute.evidence.length).to.equal(9);
+    });
+  });
+
+  describe("arbiter registry", () => {
+    let providerKp: Keypair;
+    let buyerKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let buyerAgentPDA: PublicKey;
+    let svcPDA: PublicKey;
+    let arbiterKps: Keypair[];
+    let arbiterPDAs: PublicKey[];
+    const POOL_ID = 7001;
+    const price = 1_000_000;
+    const minStake = 100_000_000;
+    const appealWindow = 2;
+    const voteWindow = 8;
+
+    function findArbiterPoolPDA(poolId: number): PublicKey {
+      const idBuf = Buffer.alloc(2);
+      idBuf.writeUInt16LE(poolId);
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("arbiter_pool"), idBuf],
+        program.programId
+      )[0];
+    }
+
+    function findArbiterPDA(pool: PublicKey, wallet: PublicKey): PublicKey {
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("arbiter"), pool.toBuffer(), wallet.toBuffer()],
+        program.programId
+      )[0];
+    }
+
+    function findDisputePDA(paymentPDA: PublicKey): PublicKey {
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("dispute"), paymentPDA.toBuffer()],
+        program.programId
+      )[0];
+    }
+
+    function createPool(poolId: number, panelSize: number, signer?: Keypair) {
+      const builder = program.methods
+        .createArbiterPool(
+          poolId,
+          "General",
+          new anchor.BN(minStake),
+          300,
+          panelSize,
+          new anchor.BN(appealWindow),
+          5000,
+          new anchor.BN(voteWindow)
+        )
+        .accounts({
+          arbiterPool: findArbiterPoolPDA(poolId),
+          protocol: findProtocolPDA()[0],
+          authority: signer ? signer.publicKey : authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        });
+      return signer ? builder.signers([signer]).rpc() : builder.rpc();
+    }
+
+    function registerArbiter(kp: Keypair, stake: number) {
+      const pool = findArbiterPoolPDA(POOL_ID);
+      return program.methods
+        .registerArbiter(new anchor.BN(stake))
+        .accounts({
+          arbiter: findArbiterPDA(pool, kp.publicKey),
+          arbiterPool: pool,
+          authority: kp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([kp])
+        .rpc();
+    }
+
+    async function payAndDispute(): Promise<PublicKey> {
+      const svc = await program.account.service.fetch(svcPDA);
+      const [paymentPDA] = findPaymentPDA(
+        buyerAgentPDA,
+        svcPDA,
+        svc.totalOrders.toNumber()
+      );
+      await program.methods
+        .payForService(
+          new anchor.BN(price),
+          new anchor.BN(1),
+          "Audit a contract",
+          [],
+          new anchor.BN(3600)
+        )
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: svcPDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+
+      await program.methods
+        .openDispute("Audit missed the reentrancy bug")
+        .accounts({
+          dispute: findDisputePDA(paymentPDA),
+          payment: paymentPDA,
+          agent: buyerAgentPDA,
+          listing: svcPDA,
+          arbiterPool: findArbiterPoolPDA(POOL_ID),
+          protocol: findProtocolPDA()[0],
+          authority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+      await drawPanel(paymentPDA);
+      return paymentPDA;
+    }
+
+    // Waits for the slot the dispute committed to, then draws from its hash
+    async function drawPanel(paymentPDA: PublicKey) {
+      const disputePDA = findDisputePDA(paymentPDA);
+      const { drawSlot } = await program.account.dispute.fetch(disputePDA);
+      while ((await provider.connection.getSlot()) <= drawSlot.toNumber() + 1) {
+        await new Promise((resolve) => setTimeout(resolve, 400));
+      }
+      const pool = await program.account.arbiterPool.fetch(
+        findArbiterPoolPDA(POOL_ID)
+      );
+      return program.methods
+        .drawDisputePanel()
+        .accounts({
+          dispute: disputePDA,
+          arbiterPool: findArbiterPoolPDA(POOL_ID),
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+        })
+        .remainingAccounts(
+          pool.arbiters.map((pubkey) => ({
+            pubkey,
+            isWritable: true,
+            isSigner: false,
+          }))
+        )
+        .rpc();
+    }
+
+    function releaseCase(paymentPDA: PublicKey, i: number) {
+      return program.methods
+        .releaseArbiterCase()
+        .accounts({
+          dispute: findDisputePDA(paymentPDA),
+          arbiter: arbiterPDAs[i],
+        })
+        .rpc();
+    }
+
+    function payoutAccounts(paymentPDA: PublicKey) {
+      return {
+        dispute: findDisputePDA(paymentPDA),
+        payment: paymentPDA,
+        escrow: findEscrowPDA(paymentPDA)[0],
+        payerAgent: buyerAgentPDA,
+        receiverAgent: providerAgentPDA,
+        payerAuthority: buyerKp.publicKey,
+        receiverAuthority: providerKp.publicKey,
+        listing: svcPDA,
+        referrerAgent: null,
+        referrerAuthority: null,
+      };
+    }
+
+    function vote(paymentPDA: PublicKey, i: number, payerAmount: number) {
+      return program.methods
+        .resolveDispute(new anchor.BN(payerAmount))
+        .accounts({
+          ...payoutAccounts(paymentPDA),
+          arbiterAccount: arbiterPDAs[i],
+          arbiter: arbiterKps[i].publicKey,
+        })
+        .signers([arbiterKps[i]])
+        .rpc();
+    }
+
+    function voterAccounts(indexes: number[]) {
+      return indexes.map((i) => ({
+        pubkey: arbiterPDAs[i],
+        isWritable: true,
+        isSigner: false,
+      }));
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      buyerKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(providerKp, "ArbProvider");
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "ArbBuyer");
+      arbiterKps = [];
+      for (let i = 0; i < 3; i++) {
+        arbiterKps.push(await createFundedKeypair());
+      }
+      arbiterPDAs = arbiterKps.map((kp) =>
+        findArbiterPDA(findArbiterPoolPDA(POOL_ID), kp.publicKey)
+      );
+
+      [svcPDA] = findServicePDA(providerAgentPDA, "arb-svc");
+      await program.methods
+        .createService(
+          "arb-svc",
+          "Contract audit",
+          "Disputes go to a staked panel",
+          new anchor.BN(price),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: svcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+    });
+
+    it("only lets the protocol authority create valid pools", async () => {
+      try {
+        await createPool(POOL_ID, 3, providerKp);
+        expect.fail("Should have thrown Unauthorized");
+      } catch (err: any) {
+        expect(err.toString()).to.include("Unauthorized");
+      }
+      try {
+        await createPool(POOL_ID, 2);
+        expect.fail("Should have thrown InvalidArbiterPool");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidArbiterPool");
+      }
+
+      await createPool(POOL_ID, 3);
+      const pool = await program.account.arbiterPool.fetch(
+        findArbiterPoolPDA(POOL_ID)
+      );
+      expect(pool.panelSize).to.equal(3);
+      expect(pool.feeBps).to.equal(300);
+    });
+
+    it("registers staked arbiters", async () => {
+      try {
+        await registerArbiter(arbiterKps[0], minStake - 1);
+        expect.fail("Should have thrown InsufficientArbiterStake");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InsufficientArbiterStake");
+      }
+
+      for (const kp of arbiterKps) {
+        await registerArbiter(kp, minStake);
+      }
+      const pool = await program.account.arbiterPool.fetch(
+        findArbiterPoolPDA(POOL_ID)
+      );
+      expect(pool.arbiters.map((a) => a.toBase58())).to.deep.equal(
+        arbiterPDAs.map((a) => a.toBase58())
+      );
+      const arbiter = await program.account.arbiter.fetch(arbiterPDAs[0]);
+      expect(arbiter.stake.toNumber()).to.equal(minStake);
+    });
+
+    it("assigns a panel from the service's pool", async () => {
+      await program.methods
+        .setArbiterPool()
+        .accounts({
+          service: svcPDA,
+          arbiterPool: findArbiterPoolPDA(POOL_ID),
+          authority: providerKp.publicKey,
+        })
+        .signers([providerKp])
+        .rpc();
+
+      const paymentPDA = await payAndDispute();
+      const dispute = await program.account.dispute.fetch(
+        findDisputePDA(paymentPDA)
+      );
+      // Panel size equals pool size, so every arbiter is drawn
+      expect(dispute.arbiters.map((a) => a.toBase58()).sort()).to.deep.equal(
+        arbiterPDAs.map((a) => a.toBase58()).sort()
+      );
+      expect(dispute.fee.toNumber()).to.equal(30_000);
+      expect(dispute.voteDeadline.toNumber()).to.be.greaterThan(0);
+      for (const arbiterPDA of arbiterPDAs) {
+        const arbiter = await program.account.arbiter.fetch(arbiterPDA);
+        expect(arbiter.openCases).to.equal(1);
+      }
+
+      try {
+        await drawPanel(paymentPDA);
+        expect.fail("Should have thrown PanelAlreadyDrawn");
+      } catch (err: any) {
+        expect(err.toString()).to.include("PanelAlreadyDrawn");
+      }
+    });
+
+    it("rules by panel majority and pays each voter a fee share", async () => {
+      const [paymentPDA] = findPaymentPDA(buyerAgentPDA, svcPDA, 0);
+      await vote(paymentPDA, 0, 300_000);
+
+      try {
+        await vote(paymentPDA, 0, 300_000);
+        expect.fail("Should have thrown AlreadyVoted");
+      } catch (err: any) {
+        expect(err.toString()).to.include("AlreadyVoted");
+      }
+
+      await vote(paymentPDA, 1, 500_000);
+      const dispute = await program.account.dispute.fetch(
+        findDisputePDA(paymentPDA)
+      );
+      expect(dispute.status).to.deep.equal({ ruled: {} });
+      expect(dispute.payerAmount.toNumber()).to.equal(300_000);
+      expect(dispute.feePaid.toNumber()).to.equal(20_000);
+      const arbiter = await program.account.arbiter.fetch(arbiterPDAs[0]);
+      expect(arbiter.feesEarned.toNumber()).to.equal(10_000);
+
+      try {
+        await program.methods
+          .finalizeDispute()
+          .accounts(payoutAccounts(paymentPDA))
+          .rpc();
+        expect.fail("Should have thrown AppealWindowOpen");
+      } catch (err: any) {
+        expect(err.toString()).to.include("AppealWindowOpen");
+      }
+    });
+
+    it("slashes arbiters overturned on appeal", async () => {
+      const [paymentPDA] = findPaymentPDA(buyerAgentPDA, svcPDA, 0);
+      await program.methods
+        .appealDispute()
+        .accounts({
+          dispute: findDisputePDA(paymentPDA),
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+        })
+        .remainingAccounts(voterAccounts([0, 1]))
+        .signers([providerKp])
+        .rpc();
+
+      // Stake stays locked while the appeal is pending
+      await new Promise((resolve) =>
+        setTimeout(resolve, appealWindow * 1000 + 500)
+      );
+      try {
+        await program.methods
+          .withdrawArbiter()
+          .accounts({
+            arbiter: arbiterPDAs[0],
+            arbiterPool: findArbiterPoolPDA(POOL_ID),
+            authority: arbiterKps[0].publicKey,
+          })
+          .signers([arbiterKps[0]])
+          .rpc();
+        expect.fail("Should have thrown ArbiterStakeLocked");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ArbiterStakeLocked");
+      }
+
+      const buyerBefore = await provider.connection.getBalance(buyerKp.publicKey);
+      const providerBefore = await provider.connection.getBalance(
+        providerKp.publicKey
+      );
+      await program.methods
+        .resolveAppeal(new anchor.BN(500_000))
+        .accounts({
+          ...payoutAccounts(paymentPDA),
+          protocol: findProtocolPDA()[0],
+          treasury: authority.publicKey,
+          authority: authority.publicKey,
+        })
+        .remainingAccounts(voterAccounts([0, 1]))
+        .rpc();
+
+      // The unpaid third fee share goes back to the payer
+      expect(
+        (await provider.connection.getBalance(buyerKp.publicKey)) - buyerBefore
+      ).to.equal(510_000);
+      expect(
+        (await provider.connection.getBalance(providerKp.publicKey)) -
+          providerBefore
+      ).to.equal(470_000);
+
+      const overturned = await program.account.arbiter.fetch(arbiterPDAs[0]);
+      expect(overturned.stake.toNumber()).to.equal(minStake / 2);
+      expect(overturned.casesOverturned.toNumber()).to.equal(1);
+      const upheld = await program.account.arbiter.fetch(arbiterPDAs[1]);
+      expect(upheld.stake.toNumber()).to.equal(minStake);
+      expect(upheld.openAppeals.toNumber()).to.equal(0);
+
+      const dispute = await program.account.dispute.fetch(
+        findDisputePDA(paymentPDA)
+      );
+      expect(dispute.status).to.deep.equal({ resolved: {} });
+      expect(dispute.appealedBy.toBase58()).to.equal(
+        providerAgentPDA.toBase58()
+      );
+    });
+
+    it("refunds the payer and slashes non-voters after the vote deadline", async () => {
+      const paymentPDA = await payAndDispute();
+      await vote(paymentPDA, 0, 0);
+
+      const { arbiters } = await program.account.dispute.fetch(
+        findDisputePDA(paymentPDA)
+      );
+      const expire = () =>
+        program.methods
+          .expireDispute()
+          .accounts({
+            dispute: findDisputePDA(paymentPDA),
+            payment: paymentPDA,
+            escrow: findEscrowPDA(paymentPDA)[0],
+            payerAgent: buyerAgentPDA,
+            payerAuthority: buyerKp.publicKey,
+            listing: svcPDA,
+            protocol: findProtocolPDA()[0],
+            treasury: authority.publicKey,
+          })
+          .remainingAccounts(
+            arbiters.map((pubkey) => ({
+              pubkey,
+              isWritable: true,
+              isSigner: false,
+            }))
+          )
+          .rpc();
+      try {
+        await expire();
+        expect.fail("Should have thrown VoteWindowOpen");
+      } catch (err: any) {
+        expect(err.toString()).to.include("VoteWindowOpen");
+      }
+
+      await new Promise((resolve) =>
+        setTimeout(resolve, voteWindow * 1000 + 1000)
+      );
+      try {
+        await vote(paymentPDA, 1, 0);
+        expect.fail("Should have thrown VoteWindowClosed");
+      } catch (err: any) {
+        expect(err.toString()).to.include("VoteWindowClosed");
+      }
+
+      const buyerBefore = await provider.connection.getBalance(buyerKp.publicKey);
+      await expire();
+      // Everything but the one fee share already paid goes back
+      expect(
+        (await provider.connection.getBalance(buyerKp.publicKey)) - buyerBefore
+      ).to.equal(price - 10_000);
+      const dispute = await program.account.dispute.fetch(
+        findDisputePDA(paymentPDA)
+      );
+      expect(dispute.status).to.deep.equal({ expired: {} });
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ refunded: {} });
+
+      const voter = await program.account.arbiter.fetch(arbiterPDAs[0]);
+      expect(voter.stake.toNumber()).to.equal(minStake / 2);
+      for (const i of [1, 2]) {
+        const missed = await program.account.arbiter.fetch(arbiterPDAs[i]);
+        expect(missed.stake.toNumber()).to.equal(minStake / 2);
+        // Still locked by the first (appealed) dispute until it is released
+        expect(missed.openCases).to.equal(1);
+      }
+    });
+
+    it("pays out an unappealed ruling after the window, then frees stake", async () => {
+      const paymentPDA = await payAndDispute();
+      await vote(paymentPDA, 1, 0);
+      await vote(paymentPDA, 2, 0);
+
+      await new Promise((resolve) =>
+        setTimeout(resolve, appealWindow * 1000 + 500)
+      );
+      await program.methods
+        .finalizeDispute()
+        .accounts(payoutAccounts(paymentPDA))
+        .rpc();
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ released: {} });
+
+      const withdraw = () =>
+        program.methods
+          .withdrawArbiter()
+          .accounts({
+            arbiter: arbiterPDAs[2],
+            arbiterPool: findArbiterPoolPDA(POOL_ID),
+            authority: arbiterKps[2].publicKey,
+          })
+          .signers([arbiterKps[2]])
+          .rpc();
+      // Drawn for the first dispute and this one; each must be released
+      try {
+        await withdraw();
+        expect.fail("Should have thrown ArbiterStakeLocked");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ArbiterStakeLocked");
+      }
+      const [firstPaymentPDA] = findPaymentPDA(buyerAgentPDA, svcPDA, 0);
+      await releaseCase(firstPaymentPDA, 2);
+      await releaseCase(paymentPDA, 2);
+      try {
+        await releaseCase(paymentPDA, 2);
+        expect.fail("Should have thrown ArbiterAlreadyReleased");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ArbiterAlreadyReleased");
+      }
+      expect(
+        (await program.account.arbiter.fetch(arbiterPDAs[2])).openCases
+      ).to.equal(0);
+
+      await withdraw();
+      expect(await provider.connection.getAccountInfo(arbiterPDAs[2])).to.be
+        .null;
+      const pool = await program.account.arbiterPool.fetch(
+        findArbiterPoolPDA(POOL_ID)
+      );
+      expect(pool.arbiters.length).to.equal(2);
+    });
+
+    it("never draws the parties and falls back to the pool authority", async () => {
+      // The buyer joins the pool: three arbiters, but only two independent ones
+      await registerArbiter(buyerKp, minStake);
+      const paymentPDA = await payAndDispute();
+
+      const dispute = await program.account.dispute.fetch(
+        findDisputePDA(paymentPDA)
+      );
+      expect(dispute.pool).to.be.null;
+      expect(dispute.arbiters.map((a) => a.toBase58())).to.deep.equal([
+        authority.publicKey.toBase58(),
+      ]);
+      expect(dispute.appealWindowSeconds.toNumber()).to.equal(0);
+      const buyerArbiter = await program.account.arbiter.fetch(
+        findArbiterPDA(findArbiterPoolPDA(POOL_ID), buyerKp.publicKey)
+      );
+      expect(buyerArbiter.openCases).to.equal(0);
+    });
+  });
+
+  describe("milestone escrow", () => {
+    let providerKp: Keypair;
+    let buyerKp: Keypair;
+    let outsiderKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let buyerAgentPDA: PublicKey;
+    let svcPDA: PublicKey;
+    const price = 900_000;
+
+    async function pay(): Promise<PublicKey> {
+      const svc = await program.account.service.fetch(svcPDA);
+      const [paymentPDA] = findPaymentPDA(
+        buyerAgentPDA,
+        svcPDA,
+        svc.totalOrders.toNumber()
+      );
+      await program.methods
+        .payForService(
+          new anchor.BN(price),
+          new anchor.BN(1),
+          "Build and deploy a trading bot",
+          [],
+          new anchor.BN(3600)
+        )
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: svcPDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+      return paymentPDA;
+    }
+
+    function setMilestones(
+      paymentPDA: PublicKey,
+      milestones: [number, number][]
+    ) {
+      return program.methods
+        .setMilestones(
+          milestones.map(([amount, deadline]) => ({
+            amount: new anchor.BN(amount),
+            deadline: new anchor.BN(deadline),
+          }))
+        )
+        .accounts({
+          payment: paymentPDA,
+          payerAgent: buyerAgentPDA,
+          service: svcPDA,
+          authority: buyerKp.publicKey,
+        })
+        .signers([buyerKp])
+        .rpc();
+    }
+
+    function releaseMilestone(paymentPDA: PublicKey, index: number) {
+      return program.methods
+        .releaseMilestone(index)
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: svcPDA,
+          receiverAuthority: providerKp.publicKey,
+          referrerAgent: null,
+          referrerAuthority: null,
+          authority: buyerKp.publicKey,
+        })
+        .signers([buyerKp])
+        .rpc();
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      buyerKp = await createFundedKeypair();
+      outsiderKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(providerKp, "MilestoneProvider");
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "MilestoneBuyer");
+
+      [svcPDA] = findServicePDA(providerAgentPDA, "milestone-svc");
+      await program.methods
+        .createService(
+          "milestone-svc",
+          "Trading bot",
+          "Design, build and deploy",
+          new anchor.BN(price),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: svcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+    });
+
+    it("rejects milestones that don't add up to the payment", async () => {
+      const paymentPDA = await pay();
+      try {
+        await setMilestones(paymentPDA, [
+          [300_000, 0],
+          [300_000, 0],
+        ]);
+        expect.fail("Should have thrown InvalidMilestones");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidMilestones");
+      }
+    });
+
+    it("rejects past, out-of-order or post-timeout deadlines and late splits", async () => {
+      const paymentPDA = await pay();
+      const now = Math.floor(Date.now() / 1000);
+      for (const milestones of [
+        [
+          [500_000, now - 10],
+          [400_000, 0],
+        ],
+        [
+          [500_000, now + 600],
+          [400_000, now + 300],
+        ],
+        [
+          [500_000, now + 300],
+          [400_000, now + 7200],
+        ],
+      ] as [number, number][][]) {
+        try {
+          await setMilestones(paymentPDA, milestones);
+          expect.fail("Should have thrown InvalidMilestones");
+        } catch (err: any) {
+          expect(err.toString()).to.include("InvalidMilestones");
+        }
+      }
+
+      await program.methods
+        .acknowledgePayment()
+        .accounts({
+          payment: paymentPDA,
+          receiverAgent: providerAgentPDA,
+          authority: providerKp.publicKey,
+        })
+        .signers([providerKp])
+        .rpc();
+      try {
+        await setMilestones(paymentPDA, [
+          [500_000, 0],
+          [400_000, 0],
+        ]);
+        expect.fail("Should have thrown AlreadyAcknowledged");
+      } catch (err: any) {
+        expect(err.toString()).to.include("AlreadyAcknowledged");
+      }
+    });
+
+    it("releases milestones one at a time and completes on the last", async () => {
+      const paymentPDA = await pay();
+      await setMilestones(paymentPDA, [
+        [200_000, 0],
+        [300_000, 0],
+        [400_000, 0],
+      ]);
+
+      const before = await provider.connection.getBalance(providerKp.publicKey);
+      await releaseMilestone(paymentPDA, 1);
+      expect(
+        (await provider.connection.getBalance(providerKp.publicKey)) - before
+      ).to.equal(300_000);
+
+      let payment = await program.account.payment.fetch(paymentPDA);
+      expect(payment.releasedAmount.toNumber()).to.equal(300_000);
+      expect(payment.milestones[1].releasedAt.toNumber()).to.be.greaterThan(0);
+      expect(payment.status).to.deep.equal({ escrowed: {} });
+
+      try {
+        await releaseMilestone(paymentPDA, 1);
+        expect.fail("Should have thrown MilestoneReleased");
+      } catch (err: any) {
+        expect(err.toString()).to.include("MilestoneReleased");
+      }
+
+      await releaseMilestone(paymentPDA, 0);
+      await releaseMilestone(paymentPDA, 2);
+      payment = await program.account.payment.fetch(paymentPDA);
+      expect(payment.status).to.deep.equal({ released: {} });
+      expect(payment.releasedAmount.toNumber()).to.equal(price);
+      expect(
+        (await provider.connection.getBalance(providerKp.publicKey)) - before
+      ).to.equal(price);
+    });
+
+    it("refunds only the unreleased remainder once a deadline is missed", async () => {
+      const paymentPDA = await pay();
+      const deadline = Math.floor(Date.now() / 1000) + 2;
+      await setMilestones(paymentPDA, [
+        [500_000, deadline],
+        [400_000, deadline],
+      ]);
+      await releaseMilestone(paymentPDA, 0);
+      await new Promise((resolve) => setTimeout(resolve, 4000));
+
+      const before = await provider.connection.getBalance(buyerKp.publicKey);
+      await program.methods
+        .refundPayment()
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          service: svcPDA,
+          payerAuthority: buyerKp.publicKey,
+          authority: outsiderKp.publicKey,
+        })
+        .signers([outsiderKp])
+        .rpc();
+
+      expect(
+        (await provider.connection.getBalance(buyerKp.publicKey)) - before
+      ).to.equal(400_000);
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ refunded: {} });
+    });
+  });
+
+  describe("payment settlement", () => {
+    let providerKp: Keypair;
+    let buyerKp: Keypair;
+    let outsiderKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let buyerAgentPDA: PublicKey;
+    let svcPDA: PublicKey;
+    const price = 1_000_000;
+
+    async function pay(): Promise<PublicKey> {
+      const svc = await program.account.service.fetch(svcPDA);
+      const [paymentPDA] = findPaymentPDA(
+        buyerAgentPDA,
+        svcPDA,
+        svc.totalOrders.toNumber()
+      );
+      await program.methods
+        .payForService(
+          new anchor.BN(price),
+          new anchor.BN(1),
+          "Migrate a data pipeline",
+          [],
+          new anchor.BN(3600)
+        )
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: svcPDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+      return paymentPDA;
+    }
+
+    function settle(
+      paymentPDA: PublicKey,
+      payerAmount: number,
+      receiverKp = providerKp
+    ) {
+      return program.methods
+        .settlePayment(new anchor.BN(payerAmount))
+        .accounts({
+          payment: paymentPDA,
+          dispute: null,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          listing: svcPDA,
+          referrerAgent: null,
+          referrerAuthority: null,
+          payerAuthority: buyerKp.publicKey,
+          receiverAuthority: receiverKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp, receiverKp])
+        .rpc();
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      buyerKp = await createFundedKeypair();
+      outsiderKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(providerKp, "SettleProvider");
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "SettleBuyer");
+
+      [svcPDA] = findServicePDA(providerAgentPDA, "settle-svc");
+      await program.methods
+        .createService(
+          "settle-svc",
+          "Pipeline migration",
+          "Move ETL jobs to a new warehouse",
+          new anchor.BN(price),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: svcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+    });
+
+    it("requires the receiver authority to co-sign", async () => {
+      const paymentPDA = await pay();
+      try {
+        await settle(paymentPDA, 400_000, outsiderKp);
+        expect.fail("Should have thrown ProviderMismatch");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ProviderMismatch");
+      }
+    });
+
+    it("rejects a payer share above the escrow", async () => {
+      const paymentPDA = await pay();
+      try {
+        await settle(paymentPDA, price + 1);
+        expect.fail("Should have thrown InvalidSettlement");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidSettlement");
+      }
+    });
+
+    it("splits the escrow 40/60 and updates both agents", async () => {
+      const paymentPDA = await pay();
+      const buyerBefore = await provider.connection.getBalance(buyerKp.publicKey);
+      const providerBefore = await provider.connection.getBalance(
+        providerKp.publicKey
+      );
+      const buyerAgentBefore = await program.account.agent.fetch(buyerAgentPDA);
+      const providerAgentBefore = await program.account.agent.fetch(
+        providerAgentPDA
+      );
+
+      const rent = await provider.connection.getBalance(paymentPDA);
+      await settle(paymentPDA, 400_000);
+
+      // The test wallet pays the transaction fee, so both sides see the exact split;
+      // the payer also gets the closed payment's rent back
+      expect(
+        (await provider.connection.getBalance(buyerKp.publicKey)) - buyerBefore
+      ).to.equal(400_000 + rent);
+      expect(
+        (await provider.connection.getBalance(providerKp.publicKey)) -
+          providerBefore
+      ).to.equal(600_000);
+      expect(
+        await provider.connection.getBalance(findEscrowPDA(paymentPDA)[0])
+      ).to.equal(0);
+
+      expect(await program.account.payment.fetchNullable(paymentPDA)).to.be
+        .null;
+      const svc = await program.account.service.fetch(svcPDA);
+      expect(svc.totalRevenue.toNumber()).to.equal(600_000);
+      const buyerAgent = await program.account.agent.fetch(buyerAgentPDA);
+      const providerAgent = await program.account.agent.fetch(providerAgentPDA);
+      expect(
+        buyerAgentBefore.totalSpent.sub(buyerAgent.totalSpent).toNumber()
+      ).to.equal(400_000);
+      expect(
+        providerAgent.totalEarned.sub(providerAgentBefore.totalEarned).toNumber()
+      ).to.equal(600_000);
+      expect(
+        providerAgent.servicesCompleted.toNumber() -
+          providerAgentBefore.servicesCompleted.toNumber()
+      ).to.equal(1);
+    });
+  });
+
+  describe("conditional payments", () => {
+    let providerKp: Keypair;
+    let buyerKp: Keypair;
+    let validatorKp: Keypair;
+    let issuerKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let buyerAgentPDA: PublicKey;
+    let svcPDA: PublicKey;
+    const price = 500_000;
+
+    async function pay(conditions: any[]): Promise<PublicKey> {
+      const svc = await program.account.service.fetch(svcPDA);
+      const [paymentPDA] = findPaymentPDA(
+        buyerAgentPDA,
+        svcPDA,
+        svc.totalOrders.toNumber()
+      );
+      await program.methods
+        .payForService(
+          new anchor.BN(price),
+          new anchor.BN(1),
+          "Audit a smart contract",
+          conditions,
+          new anchor.BN(3600)
+        )
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: svcPDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+      return paymentPDA;
+    }
+
+    function execute(
+      paymentPDA: PublicKey,
+      witnesses: { pubkey: PublicKey; isSigner: boolean }[] = [],
+      preimages: Buffer[] = [],
+      signers: Keypair[] = []
+    ) {
+      return program.methods
+        .executeConditionalRelease(preimages)
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: svcPDA,
+          payerAuthority: buyerKp.publicKey,
+          receiverAuthority: providerKp.publicKey,
+          referrerAgent: null,
+          referrerAuthority: null,
+        })
+        .remainingAccounts(
+          witnesses.map(({ pubkey, isSigner }) => ({
+            pubkey,
+            isSigner,
+            isWritable: false,
+          }))
+        )
+        .signers(signers)
+        .rpc();
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      buyerKp = await createFundedKeypair();
+      validatorKp = await createFundedKeypair();
+      issuerKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(providerKp, "ConditionalProvider");
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "ConditionalBuyer");
+
+      [svcPDA] = findServicePDA(providerAgentPDA, "conditional-svc");
+      await program.methods
+        .createService(
+          "conditional-svc",
+          "Contract audit",
+          "Security review of a Solana program",
+          new anchor.BN(price),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: svcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+    });
+
+    it("releases once the preimage is revealed and the validator signs", async () => {
+      const preimage = Buffer.from("audit-report-key");
+      const hash = [...createHash("sha256").update(preimage).digest()];
+      const paymentPDA = await pay([
+        { hashPreimage: { hash } },
+        { validatorSignature: { validator: validatorKp.publicKey } },
+      ]);
+      const validator = { pubkey: validatorKp.publicKey, isSigner: true };
+
+      try {
+        await execute(paymentPDA, [validator], [Buffer.from("wrong")], [
+          validatorKp,
+        ]);
+        expect.fail("Should have thrown ConditionsPending");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ConditionsPending");
+      }
+
+      try {
+        await execute(paymentPDA, [
+          { pubkey: buyerKp.publicKey, isSigner: false },
+        ]);
+        expect.fail("Should have thrown ConditionWitnessMismatch");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ConditionWitnessMismatch");
+      }
+
+      const before = await provider.connection.getBalance(providerKp.publicKey);
+      await execute(paymentPDA, [validator], [preimage], [validatorKp]);
+      expect(
+        (await provider.connection.getBalance(providerKp.publicKey)) - before
+      ).to.equal(price);
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ released: {} });
+    });
+
+    it("releases when the receiver holds the required attestation", async () => {
+      const paymentPDA = await pay([
+        { attestationPresent: { issuer: issuerKp.publicKey } },
+      ]);
+      const [attestationPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("attestation"),
+          issuerKp.publicKey.toBuffer(),
+          providerAgentPDA.toBuffer(),
+        ],
+        program.programId
+      );
+      await program.methods
+        .issueAttestation(new anchor.BN(0))
+        .accounts({
+          attestation: attestationPDA,
+          subjectAgent: providerAgentPDA,
+          issuer: issuerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([issuerKp])
+        .rpc();
+
+      await execute(paymentPDA, [{ pubkey: attestationPDA, isSigner: false }]);
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ released: {} });
+    });
+
+    it("refunds the payer once a deadline passes with conditions unmet", async () => {
+      const paymentPDA = await pay([
+        { validatorSignature: { validator: validatorKp.publicKey } },
+        {
+          deadline: {
+            at: new anchor.BN(Math.floor(Date.now() / 1000) + 2),
+          },
+        },
+      ]);
+      const validator = { pubkey: validatorKp.publicKey, isSigner: false };
+
+      try {
+        await execute(paymentPDA, [validator]);
+        expect.fail("Should have thrown ConditionsPending");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ConditionsPending");
+      }
+
+      await new Promise((resolve) => setTimeout(resolve, 4000));
+      const before = await provider.connection.getBalance(buyerKp.publicKey);
+      await execute(paymentPDA, [validator]);
+      expect(
+        (await provider.connection.getBalance(buyerKp.publicKey)) - before
+      ).to.equal(price);
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ refunded: {} });
+    });
+
+    it("lets the receiver claim a hashlocked payment with every preimage", async () => {
+      const keys = [Buffer.from("result-decryption-key"), Buffer.from("logs-key")];
+      const paymentPDA = await pay(
+        keys.map((key) => ({
+          hashPreimage: { hash: [...createHash("sha256").update(key).digest()] },
+        }))
+      );
+      const claim = (preimages: Buffer[]) =>
+        program.methods
+          .claimWithPreimage(preimages)
+          .accounts({
+            payment: paymentPDA,
+            escrow: findEscrowPDA(paymentPDA)[0],
+            payerAgent: buyerAgentPDA,
+            receiverAgent: providerAgentPDA,
+            service: svcPDA,
+            payerAuthority: buyerKp.publicKey,
+            referrerAgent: null,
+            referrerAuthority: null,
+            receiverAuthority: providerKp.publicKey,
+          })
+          .signers([providerKp])
+          .rpc();
+
+      // Every hashlock needs its preimage, in condition order
+      for (const preimages of [
+        [Buffer.from("not-the-key"), keys[1]],
+        [keys[0]],
+        [keys[1], keys[0]],
+      ]) {
+        try {
+          await claim(preimages);
+          expect.fail("Should have thrown PreimageMismatch");
+        } catch (err: any) {
+          expect(err.toString()).to.include("PreimageMismatch");
+        }
+      }
+
+      const before = await provider.connection.getBalance(providerKp.publicKey);
+      await claim(keys);
+      // The test wallet pays the transaction fee
+      expect(
+        (await provider.connection.getBalance(providerKp.publicKey)) - before
+      ).to.equal(price);
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ released: {} });
+    });
+
+    it("rejects oracle witnesses that aren't Pyth price updates", async () => {
+      const paymentPDA = await pay([
+        {
+          oraclePrice: {
+            feedId: Array(32).fill(7),
+            price: new anchor.BN(150_00000000),
+            exponent: -8,
+            above: true,
+            at: new anchor.BN(0),
+            maxAgeSeconds: 60,
+            maxConfBps: 100,
+          },
+        },
+      ]);
+      try {
+        await execute(paymentPDA, [{ pubkey: svcPDA, isSigner: false }]);
+        expect.fail("Should have thrown InvalidOracle");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidOracle");
+      }
+    });
+
+    it("releases on a mocked Pyth price published after `at`", async () => {
+      // Loaded by Anchor.toml from tests/fixtures/pyth-price-update.json:
+      // feed [7; 32] at 160.00000000 (exponent -8), published at 1_700_000_000
+      const priceUpdate = new PublicKey(
+        "BUvrRtBWt8jJ7MoVkt2aB7TbmnUMrBN88GQUXYWwkiDB"
+      );
+      const oraclePrice = (maxAgeSeconds: number) => ({
+        oraclePrice: {
+          feedId: Array(32).fill(7),
+          price: new anchor.BN(150_00000000),
+          exponent: -8,
+          above: true,
+          at: new anchor.BN(1_699_999_990),
+          maxAgeSeconds,
+          maxConfBps: 100,
+        },
+      });
+
+      for (const maxAgeSeconds of [0, 61]) {
+        try {
+          await pay([oraclePrice(maxAgeSeconds)]);
+          expect.fail("Should have thrown InvalidOracleCondition");
+        } catch (err: any) {
+          expect(err.toString()).to.include("InvalidOracleCondition");
+        }
+      }
+
+      const paymentPDA = await pay([oraclePrice(30)]);
+      const before = await provider.connection.getBalance(providerKp.publicKey);
+      await execute(paymentPDA, [{ pubkey: priceUpdate, isSigner: false }]);
+      expect(
+        (await provider.connection.getBalance(providerKp.publicKey)) - before
+      ).to.equal(price);
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ released: {} });
+    });
+
+    it("rejects a deadline as the only condition", async () => {
+      try {
+        await pay([
+          {
+            deadline: {
+              at: new anchor.BN(Math.floor(Date.now() / 1000) + 60),
+            },
+          },
+        ]);
+        expect.fail("Should have thrown DeadlineOnlyConditions");
+      } catch (err: any) {
+        expect(err.toString()).to.include("DeadlineOnlyConditions");
+      }
+    });
+
+    it("holds a timelocked payment until its time", async () => {
+      const paymentPDA = await pay([
+        {
+          notBefore: {
+            at: new anchor.BN(Math.floor(Date.now() / 1000) + 2),
+          },
+        },
+      ]);
+      try {
+        await execute(paymentPDA);
+        expect.fail("Should have thrown ConditionsPending");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ConditionsPending");
+      }
+
+      await new Promise((resolve) => setTimeout(resolve, 4000));
+      await execute(paymentPDA);
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ released: {} });
+    });
+
+    it("rejects payments without conditions", async () => {
+      const paymentPDA = await pay([]);
+      try {
+        await execute(paymentPDA);
+        expect.fail("Should have thrown NoConditions");
+      } catch (err: any) {
+        expect(err.toString()).to.include("NoConditions");
+      }
+    });
+  });
+
+  describe("delivery receipts", () => {
+    let providerKp: Keypair;
+    let buyerKp: Keypair;
+    let sessionKp: Keypair;
+    let outsiderKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let buyerAgentPDA: PublicKey;
+    let svcPDA: PublicKey;
+    let sessionKeyPDA: PublicKey;
+    const price = 700_000;
+
+    async function pay(): Promise<PublicKey> {
+      const svc = await program.account.service.fetch(svcPDA);
+      const [paymentPDA] = findPaymentPDA(
+        buyerAgentPDA,
+        svcPDA,
+        svc.totalOrders.toNumber()
+      );
+      await program.methods
+        .payForService(
+          new anchor.BN(price),
+          new anchor.BN(1),
+          "Render a product video",
+          [],
+          new anchor.BN(3600)
+        )
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: svcPDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+      return paymentPDA;
+    }
+
+    function receiptMessage(paymentPDA: PublicKey): Buffer {
+      return Buffer.concat([
+        Buffer.from("solagent:delivery-receipt:"),
+        program.programId.toBuffer(),
+        paymentPDA.toBuffer(),
+      ]);
+    }
+
+    function release(
+      paymentPDA: PublicKey,
+      signer: Keypair | null,
+      sessionKey: PublicKey | null = null,
+      signedPayment = paymentPDA
+    ) {
+      const builder = program.methods
+        .releaseWithReceipt()
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: svcPDA,
+          payerAuthority: buyerKp.publicKey,
+          referrerAgent: null,
+          referrerAuthority: null,
+          sessionKey,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          receiverAuthority: providerKp.publicKey,
+        })
+        .signers([providerKp]);
+      if (signer) {
+        builder.preInstructions([
+          Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: signer.secretKey,
+            message: receiptMessage(signedPayment),
+          }),
+        ]);
+      }
+      return builder.rpc();
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      buyerKp = await createFundedKeypair();
+      sessionKp = Keypair.generate();
+      outsiderKp = Keypair.generate();
+      providerAgentPDA = await registerAgentHelper(providerKp, "ReceiptProvider");
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "ReceiptBuyer");
+
+      [svcPDA] = findServicePDA(providerAgentPDA, "receipt-svc");
+      await program.methods
+        .createService(
+          "receipt-svc",
+          "Video rendering",
+          "Render a 30s product video",
+          new anchor.BN(price),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: svcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+
+      [sessionKeyPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("session_key"),
+          buyerAgentPDA.toBuffer(),
+          sessionKp.publicKey.toBuffer(),
+        ],
+        program.programId
+      );
+      await program.methods
+        .createSessionKey(sessionKp.publicKey, new anchor.BN(0))
+        .accounts({
+          sessionKey: sessionKeyPDA,
+          agent: buyerAgentPDA,
+          authority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+    });
+
+    it("releases on a receipt signed by the payer authority", async () => {
+      const paymentPDA = await pay();
+      const before = await provider.connection.getBalance(providerKp.publicKey);
+      await release(paymentPDA, buyerKp);
+
+      // The test wallet pays the transaction fee
+      expect(
+        (await provider.connection.getBalance(providerKp.publicKey)) - before
+      ).to.equal(price);
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ released: {} });
+    });
+
+    it("releases on a receipt signed by a payer session key", async () => {
+      const paymentPDA = await pay();
+      await release(paymentPDA, sessionKp, sessionKeyPDA);
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ released: {} });
+    });
+
+    it("rejects missing, foreign and replayed receipts", async () => {
+      const paymentPDA = await pay();
+      const otherPaymentPDA = await pay();
+
+      for (const [signer, signedPayment] of [
+        [null, paymentPDA],
+        [outsiderKp, paymentPDA],
+        [buyerKp, otherPaymentPDA],
+      ] as [Keypair | null, PublicKey][]) {
+        try {
+          await release(paymentPDA, signer, null, signedPayment);
+          expect.fail("Should have thrown InvalidReceipt");
+        } catch (err: any) {
+          expect(err.toString()).to.include("InvalidReceipt");
+        }
+      }
+    });
+  });
+
+  describe("timeout policy", () => {
+    let providerKp: Keypair;
+    let buyerKp: Keypair;
+    let providerAgentPDA: PublicKey;
+    let buyerAgentPDA: PublicKey;
+    let svcPDA: PublicKey;
+    const price = 600_000;
+    const challengeSeconds = 2;
+
+    async function pay(
+      timeoutSeconds = 3600,
+      conditions: any[] = []
+    ): Promise<PublicKey> {
+      const svc = await program.account.service.fetch(svcPDA);
+      const [paymentPDA] = findPaymentPDA(
+        buyerAgentPDA,
+        svcPDA,
+        svc.totalOrders.toNumber()
+      );
+      await program.methods
+        .payForService(
+          new anchor.BN(price),
+          new anchor.BN(1),
+          "Transcribe a podcast episode",
+          conditions,
+          new anchor.BN(timeoutSeconds)
+        )
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: svcPDA,
+          coupon: null,
+          referrerAgent: null,
+          buyerAttestation: null,
+          payerAuthority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+      return paymentPDA;
+    }
+
+    function markDelivered(paymentPDA: PublicKey) {
+      return program.methods
+        .markDelivered()
+        .accounts({
+          payment: paymentPDA,
+          receiverAgent: providerAgentPDA,
+          authority: providerKp.publicKey,
+        })
+        .signers([providerKp])
+        .rpc();
+    }
+
+    function releaseOnTimeout(paymentPDA: PublicKey) {
+      return program.methods
+        .releaseOnTimeout()
+        .accounts({
+          payment: paymentPDA,
+          escrow: findEscrowPDA(paymentPDA)[0],
+          payerAgent: buyerAgentPDA,
+          receiverAgent: providerAgentPDA,
+          service: svcPDA,
+          payerAuthority: buyerKp.publicKey,
+          referrerAgent: null,
+          referrerAuthority: null,
+          receiverAuthority: providerKp.publicKey,
+        })
+        .signers([providerKp])
+        .rpc();
+    }
+
+    function findDisputePDA(paymentPDA: PublicKey): PublicKey {
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("dispute"), paymentPDA.toBuffer()],
+        program.programId
+      )[0];
+    }
+
+    function openDispute(paymentPDA: PublicKey) {
+      return program.methods
+        .openDispute("Transcript is missing the second half")
+        .accounts({
+          dispute: findDisputePDA(paymentPDA),
+          payment: paymentPDA,
+          agent: buyerAgentPDA,
+          listing: svcPDA,
+          arbiterPool: null,
+          protocol: findProtocolPDA()[0],
+          authority: buyerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyerKp])
+        .rpc();
+    }
+
+    function setPolicy(policy: any) {
+      return program.methods
+        .setTimeoutPolicy(policy)
+        .accounts({ service: svcPDA, authority: providerKp.publicKey })
+        .signers([providerKp])
+        .rpc();
+    }
+
+    before(async () => {
+      providerKp = await createFundedKeypair();
+      buyerKp = await createFundedKeypair();
+      providerAgentPDA = await registerAgentHelper(providerKp, "TimeoutProvider");
+      buyerAgentPDA = await registerAgentHelper(buyerKp, "TimeoutBuyer");
+
+      [svcPDA] = findServicePDA(providerAgentPDA, "timeout-svc");
+      await program.methods
+        .createService(
+          "timeout-svc",
+          "Transcription",
+          "Transcribe an hour of audio",
+          new anchor.BN(price),
+          { fixed: {} },
+          []
+        )
+        .accounts({
+          service: svcPDA,
+          agent: providerAgentPDA,
+          authority: providerKp.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([providerKp])
+        .rpc();
+    });
+
+    it("refunds on timeout by default and cannot auto-release", async () => {
+      const svc = await program.account.service.fetch(svcPDA);
+      expect(svc.timeoutPolicy).to.deep.equal({ refundOnTimeout: {} });
+
+      const paymentPDA = await pay();
+      await markDelivered(paymentPDA);
+      try {
+        await releaseOnTimeout(paymentPDA);
+        expect.fail("Should have thrown NoAutoRelease");
+      } catch (err: any) {
+        expect(err.toString()).to.include("NoAutoRelease");
+      }
+    });
+
+    it("rejects a non-positive challenge window", async () => {
+      try {
+        await setPolicy({
+          releaseAfterDelivery: { challengeSeconds: new anchor.BN(0) },
+        });
+        expect.fail("Should have thrown InvalidTimeoutPolicy");
+      } catch (err: any) {
+        expect(err.toString()).to.include("InvalidTimeoutPolicy");
+      }
+    });
+
+    it("releases a delivered order once the challenge window passes", async () => {
+      await setPolicy({
+        releaseAfterDelivery: {
+          challengeSeconds: new anchor.BN(challengeSeconds),
+        },
+      });
+      const paymentPDA = await pay();
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).timeoutPolicy
+      ).to.deep.equal({
+        releaseAfterDelivery: {
+          challengeSeconds: new anchor.BN(challengeSeconds),
+        },
+      });
+
+      // The window only starts once the provider marks the order delivered
+      try {
+        await releaseOnTimeout(paymentPDA);
+        expect.fail("Should have thrown NoAutoRelease");
+      } catch (err: any) {
+        expect(err.toString()).to.include("NoAutoRelease");
+      }
+      await markDelivered(paymentPDA);
+
+      try {
+        await program.methods
+          .refundPayment()
+          .accounts({
+            payment: paymentPDA,
+            escrow: findEscrowPDA(paymentPDA)[0],
+            payerAgent: buyerAgentPDA,
+            service: svcPDA,
+            payerAuthority: buyerKp.publicKey,
+            authority: buyerKp.publicKey,
+          })
+          .signers([buyerKp])
+          .rpc();
+        expect.fail("Should have thrown DeliveredReleasePending");
+      } catch (err: any) {
+        expect(err.toString()).to.include("DeliveredReleasePending");
+      }
+      try {
+        await releaseOnTimeout(paymentPDA);
+        expect.fail("Should have thrown ChallengeWindowOpen");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ChallengeWindowOpen");
+      }
+
+      await new Promise((resolve) =>
+        setTimeout(resolve, (challengeSeconds + 2) * 1000)
+      );
+      try {
+        await openDispute(paymentPDA);
+        expect.fail("Should have thrown ChallengeWindowClosed");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ChallengeWindowClosed");
+      }
+
+      const before = await provider.connection.getBalance(providerKp.publicKey);
+      await releaseOnTimeout(paymentPDA);
+
+      // The test wallet pays the transaction fee
+      expect(
+        (await provider.connection.getBalance(providerKp.publicKey)) - before
+      ).to.equal(price);
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).status
+      ).to.deep.equal({ released: {} });
+    });
+
+    it("lets the payer dispute within the challenge window", async () => {
+      const paymentPDA = await pay();
+      await markDelivered(paymentPDA);
+      await openDispute(paymentPDA);
+
+      try {
+        await releaseOnTimeout(paymentPDA);
+        expect.fail("Should have thrown PaymentNotEscrowed");
+      } catch (err: any) {
+        expect(err.toString()).to.include("PaymentNotEscrowed");
+      }
+    });
+
+    it("rejects marking delivered after the payment timed out", async () => {
+      const paymentPDA = await pay(1);
+      await new Promise((resolve) => setTimeout(resolve, 3000));
+
+      // The payer's timeout refund stays available
+      try {
+        await markDelivered(paymentPDA);
+        expect.fail("Should have thrown DeliveryAfterTimeout");
+      } catch (err: any) {
+        expect(err.toString()).to.include("DeliveryAfterTimeout");
+      }
+      expect(
+        (await program.account.payment.fetch(paymentPDA)).deliveredAt.toNumber()
+      ).to.equal(0);
+    });
+
+    it("rejects milestones on an order that releases after delivery", async () => {
+      const paymentPDA = await pay();
+      try {
+        await program.methods
+          .setMilestones([
+            { amount: new anchor.BN(price), deadline: new anchor.BN(0) },
+          ])
+          .accounts({
+            payment: paymentPDA,
+            payerAgent: buyerAgentPDA,
+            service: svcPDA,
+            authority: buyerKp.publicKey,
+          })
+          .signers([buyerKp])
+          .rpc();
+        expect.fail("Should have thrown MilestonesWithAutoRelease");
+      } catch (err: any) {
+        expect(err.toString()).to.include("MilestonesWithAutoRelease");
+      }
+    });
+
+    it("rejects release conditions on an order that releases after delivery", async () => {
+      const now = Math.floor(Date.now() / 1000);
+      try {
+        await pay(3600, [{ notBefore: { at: new anchor.BN(now) } }]);
+        expect.fail("Should have thrown ConditionsWithAutoRelease");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ConditionsWithAutoRelease");
+      }
+    });
+
+    it("refuses to migrate a service that already has the current layout", async () => {
+      try {
+        await program.methods
+          .migrateService()
+          .accounts({
+            service: svcPDA,
+            authority: providerKp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([providerKp])
+          .rpc();
+        expect.fail("Should have thrown ServiceUpToDate");
+      } catch (err: any) {
+        expect(err.toString()).to.include("ServiceUpToDate");
+      }
+    });
+
+    it("refuses to migrate a payment that already has the current layout", async () => {
+      const paymentPDA = await pay();
+      try {
+        await program.methods
+          .migratePayment()
+          .accounts({
+            payment: paymentPDA,
+            payer: buyerKp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([buyerKp])
+          .rpc();
+        expect.fail("Should have thrown PaymentUpToDate");
+      } catch (err: any) {
+        expect(err.toString()).to.include("PaymentUpToDate");
+      }
+    });
+
+    it("refuses to migrate an agent that already has the current layout", async () => {
+      try {
+        await program.methods
+          .migrateAgent()
+          .accounts({
+            agent: buyerAgentPDA,
+            authority: buyerKp.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([buyerKp])
+          .rpc();
+        expect.fail("Should have thrown AgentUpToDate");
+      } catch (err: any) {
+        expect(err.toString()).to.include("AgentUpToDate");
+      }
+    });
+  });
 });